@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use biosynth_core::audit::{current_user, row_deltas, AuditAccess, AuditEvent};
+use biosynth_core::stats::StatsStore;
+
+/// The database a command touches, and how.
+pub struct AuditTarget {
+    pub command: &'static str,
+    pub sqlite: PathBuf,
+    pub access: AuditAccess,
+}
+
+/// Runs `command`, then appends an entry to the target database's audit log with the row-count
+/// changes it caused. Nothing is logged if the database does not exist afterwards.
+///
+/// Auditing never changes the command's outcome: a failure to read or write the log is reported
+/// as a warning and the command's own result is returned.
+pub fn audited<F>(target: Option<AuditTarget>, command: F) -> Result<()>
+where
+    F: FnOnce() -> Result<()>,
+{
+    let Some(target) = target else {
+        return command();
+    };
+    let before = if target.sqlite.exists() {
+        match StatsStore::connect(&target.sqlite).and_then(|store| store.table_counts()) {
+            Ok(counts) => counts,
+            Err(err) => {
+                eprintln!(
+                    "⚠️  Skipping the audit log entry for {:?}: {}",
+                    target.sqlite, err
+                );
+                return command();
+            }
+        }
+    } else {
+        Default::default()
+    };
+
+    let result = command();
+    if target.sqlite.exists() {
+        if let Err(err) = record(&target, &before, result.is_ok()) {
+            eprintln!("⚠️  {:#}", err);
+        }
+    }
+    result
+}
+
+fn record(target: &AuditTarget, before: &BTreeMap<String, i64>, succeeded: bool) -> Result<()> {
+    let context = || format!("Record audit log entry in {:?}", target.sqlite);
+    let store = StatsStore::connect(&target.sqlite).with_context(context)?;
+    let event = AuditEvent {
+        user: current_user(),
+        command: target.command.to_string(),
+        args: flag_names(std::env::args_os().skip(1)),
+        access: target.access,
+        succeeded,
+        row_deltas: row_deltas(before, &store.table_counts().with_context(context)?),
+    };
+    store.append_audit(&event).with_context(context)?;
+    Ok(())
+}
+
+/// The flags a command was given, without their values. Values and positional arguments are
+/// mostly paths to participant files, which do not belong in a log kept next to the aggregates.
+fn flag_names(args: impl Iterator<Item = OsString>) -> Vec<String> {
+    args.map(|arg| arg.to_string_lossy().into_owned())
+        .take_while(|arg| arg != "--")
+        .filter(|arg| arg.starts_with('-') && arg.len() > 1)
+        .map(|arg| match arg.split_once('=') {
+            Some((flag, _)) => flag.to_string(),
+            None if !arg.starts_with("--") => arg.chars().take(2).collect(),
+            None => arg,
+        })
+        .collect()
+}
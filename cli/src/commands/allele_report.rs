@@ -1,23 +1,194 @@
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Write;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
 use chrono::Utc;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use rusqlite::Connection;
+use serde::Serialize;
 
-use crate::download::ensure_reference_db;
-use crate::stats::StatsStore;
-use crate::AlleleReportArgs;
+use crate::output::{self, status};
+use crate::{AlleleReportArgs, GlobalArgs};
+use biosynth_core::buffers;
+use biosynth_core::policy::ReleasedColumn;
+use biosynth_core::privacy::{LaplaceNoise, Suppression};
 
-pub fn run_allele_report(args: AlleleReportArgs) -> Result<()> {
+/// `--output-format json` result. `written` is false when `--no-clobber` kept an existing report.
+#[derive(Debug, Default, Serialize)]
+struct AlleleReportOutput {
+    output: PathBuf,
+    written: bool,
+    formats: i64,
+    rows: i64,
+    /// Rows withheld by `--min-count`.
+    suppressed: usize,
+    /// `table.column` names the export policy redacted.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    redacted: Vec<String>,
+}
+
+/// What the report releases, for the export policy.
+const REPORT_COLUMNS: &[ReleasedColumn<'static>] =
+    &[("formats", Some("name")), ("rsid_reference", Some("rsid"))];
+
+/// Placeholder for cells the export policy redacts.
+const REDACTED: &str = "[redacted]";
+
+/// Lower bounds of the count ranges `--redact` publishes; rows below the first are left out.
+const REDACT_BUCKETS: &[u64] = &[10, 50, 100, 500, 1000];
+
+/// Disclosure controls applied to each observation count before it is written.
+struct Disclosure {
+    /// The `--epsilon` budget the whole report spends.
+    epsilon: Option<f64>,
+    /// Noise for one row: every file can be counted in every row, so the rows split the budget.
+    noise: Option<LaplaceNoise>,
+    suppression: Option<Suppression>,
+    /// `table.column` names rendered as redacted under the export policy.
+    redacted: Vec<String>,
+    /// `--redact`: publish counts as ranges from [`REDACT_BUCKETS`].
+    bucketed: bool,
+    rng: StdRng,
+}
+
+impl Disclosure {
+    /// The count to publish, or `None` when the row must be withheld.
+    fn release(&mut self, count: i64) -> Option<i64> {
+        let mut count = count.max(0) as u64;
+        if let Some(noise) = &self.noise {
+            count = noise.noisy_count(count, &mut self.rng);
+        }
+        match &self.suppression {
+            Some(suppression) if !suppression.allows(count) => None,
+            _ => Some(count as i64),
+        }
+    }
+
+    /// How a released count is shown, with the value the table sorts it by.
+    fn count_cell(&self, count: i64) -> (String, i64) {
+        if !self.bucketed {
+            return (count.to_string(), count);
+        }
+        let count = count.max(0) as u64;
+        let Some(index) = REDACT_BUCKETS.iter().rposition(|bound| count >= *bound) else {
+            return (format!("<{}", REDACT_BUCKETS[0]), 0);
+        };
+        let lower = REDACT_BUCKETS[index];
+        let label = match REDACT_BUCKETS.get(index + 1) {
+            Some(upper) => format!("{}–{}", lower, upper - 1),
+            None => format!("{}+", lower),
+        };
+        (label, lower as i64)
+    }
+
+    /// A report-wide total, bucketed under `--redact`.
+    fn total(&self, total: i64) -> String {
+        self.count_cell(total).0
+    }
+
+    /// `value`, unless the export policy redacts `column`.
+    fn cell(&self, column: &str, value: String) -> String {
+        if self.redacted.iter().any(|redacted| redacted == column) {
+            REDACTED.to_string()
+        } else {
+            value
+        }
+    }
+
+    fn note(&self) -> String {
+        let mut note = String::new();
+        if let (Some(epsilon), Some(noise)) = (self.epsilon, &self.noise) {
+            note.push_str(&format!(
+                "<br/>\n    Observations include Laplace noise (&epsilon; = <strong>{}</strong>, \
+                 {} per row); totals count the rows shown",
+                epsilon,
+                noise.epsilon()
+            ));
+        }
+        if let Some(suppression) = &self.suppression {
+            note.push_str(&format!(
+                "<br/>\n    Rows observed in fewer than <strong>{}</strong> files are withheld",
+                suppression.min_count()
+            ));
+        }
+        if !self.redacted.is_empty() {
+            note.push_str(&format!(
+                "<br/>\n    Redacted by export policy: <strong>{}</strong>",
+                html_escape(&self.redacted.join(", "))
+            ));
+        }
+        if self.bucketed {
+            note.push_str("<br/>\n    Counts and totals are shown as ranges");
+        }
+        note
+    }
+}
+
+pub fn run_allele_report(args: AlleleReportArgs, global: &GlobalArgs) -> Result<()> {
     if args.output.extension().is_none() {
         anyhow::bail!("--output must include a filename (e.g. report.html)");
     }
+    let mut disclosure = Disclosure {
+        epsilon: args.epsilon,
+        noise: args.epsilon.map(LaplaceNoise::new).transpose()?,
+        suppression: args.min_count.map(Suppression::new),
+        redacted: Vec::new(),
+        bucketed: args.redact,
+        rng: StdRng::from_entropy(),
+    };
+    if args.redact {
+        let requested = disclosure
+            .suppression
+            .map_or(0, |suppression| suppression.min_count());
+        disclosure.suppression = Some(Suppression::new(requested.max(REDACT_BUCKETS[0])));
+    }
+    if let Some(policy) = global.export_policy()? {
+        policy.check_noise("allele-report", args.epsilon)?;
+        disclosure.redacted = policy.redactions("allele-report", REPORT_COLUMNS)?;
+        if let Some(min_count) = policy.min_count {
+            let requested = disclosure
+                .suppression
+                .map_or(0, |suppression| suppression.min_count());
+            disclosure.suppression = Some(Suppression::new(min_count.max(requested)));
+        }
+    }
+    if !args.overwrite.policy().should_write(&args.output)? {
+        status!(
+            global,
+            "⏭️  {} already exists; skipping report",
+            args.output.display()
+        );
+        return output::emit(
+            global,
+            "allele-report",
+            &AlleleReportOutput {
+                output: args.output,
+                ..AlleleReportOutput::default()
+            },
+        );
+    }
 
-    let sqlite_path = ensure_reference_db(Some(&args.sqlite))?;
-    let store = StatsStore::connect(&sqlite_path)?;
+    let reference = global.reference_db(args.sqlite.as_ref())?;
+    let sqlite_path = reference.path;
+    let store = global.stats_store(&sqlite_path)?;
+    if let Some(enforced) = store.aggregate_only()? {
+        let min_count = disclosure
+            .suppression
+            .map_or(0, |suppression| suppression.min_count());
+        disclosure.suppression = Some(Suppression::new(min_count.max(enforced.min_count())));
+    }
     let conn = store.open_connection()?;
-    let summary = FormatSummary::gather(&conn)?;
+    let reference_rows: i64 =
+        conn.query_row("SELECT COUNT(*) FROM rsid_reference", [], |row| row.get(0))?;
+    disclosure.noise = disclosure
+        .noise
+        .map(|noise| noise.split(reference_rows as usize));
+    let observed = store.rsid_file_counts()?;
+    let (rows, suppressed) = released_rows(&conn, &observed, &mut disclosure)?;
+    let summary = FormatSummary::of(&rows);
 
     if let Some(parent) = args.output.parent() {
         if !parent.as_os_str().is_empty() {
@@ -26,20 +197,44 @@ pub fn run_allele_report(args: AlleleReportArgs) -> Result<()> {
         }
     }
 
-    let mut file = File::create(&args.output)
-        .with_context(|| format!("Create report file {:?}", args.output))?;
-    write_header(&mut file, &summary, &args)?;
-    write_table_rows(&mut file, &conn)?;
+    let mut file = buffers::writer(
+        File::create(&args.output)
+            .with_context(|| format!("Create report file {:?}", args.output))?,
+    );
+    write_header(&mut file, &summary, &sqlite_path, &disclosure)?;
+    write_table_rows(&mut file, &rows, &disclosure)?;
     write_footer(&mut file)?;
     file.flush()?;
 
-    println!(
+    status!(
+        global,
         "🧾 RSID coverage report written to {} ({} formats; {} format/rsid rows)",
         args.output.display(),
         summary.unique_formats,
         summary.total_rows
     );
-    Ok(())
+    if suppressed > 0 {
+        status!(global, "🔒 Withheld {} rows below --min-count", suppressed);
+    }
+    if !disclosure.redacted.is_empty() {
+        status!(
+            global,
+            "🚫 Export policy redacted {}",
+            disclosure.redacted.join(", ")
+        );
+    }
+    output::emit(
+        global,
+        "allele-report",
+        &AlleleReportOutput {
+            output: args.output,
+            written: true,
+            formats: summary.unique_formats,
+            rows: summary.total_rows,
+            suppressed,
+            redacted: disclosure.redacted,
+        },
+    )
 }
 
 struct FormatSummary {
@@ -50,40 +245,39 @@ struct FormatSummary {
 }
 
 impl FormatSummary {
-    fn gather(conn: &Connection) -> Result<Self> {
-        let unique_formats: i64 = conn
-            .query_row(
-                "SELECT COALESCE(COUNT(DISTINCT format_id), 0) FROM rsid_reference",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0);
-        let unique_rsids: i64 = conn
-            .query_row(
-                "SELECT COALESCE(COUNT(*), 0) FROM rsid_reference",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0);
-        let total_rows: i64 = conn
-            .query_row(
-                "SELECT COALESCE(COUNT(*), 0) FROM rsid_reference",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0);
-        Ok(Self {
-            unique_formats,
-            unique_rsids,
-            total_rows,
+    /// Totals of the released rows, so they disclose nothing the rows do not.
+    fn of(rows: &[ReportRow]) -> Self {
+        let formats: HashSet<&str> = rows.iter().map(|row| row.format.as_str()).collect();
+        let rsids: HashSet<i64> = rows.iter().map(|row| row.rsid).collect();
+        Self {
+            unique_formats: formats.len() as i64,
+            unique_rsids: rsids.len() as i64,
+            total_rows: rows.len() as i64,
             generated_at: Utc::now().to_rfc3339(),
-        })
+        }
     }
 }
 
-fn write_header(file: &mut File, summary: &FormatSummary, args: &AlleleReportArgs) -> Result<()> {
-    let source = html_escape(args.sqlite.display().to_string().as_str());
+/// A format/rsid row and its released observation count.
+struct ReportRow {
+    format: String,
+    rsid: i64,
+    count: i64,
+}
+
+fn write_header(
+    file: &mut impl Write,
+    summary: &FormatSummary,
+    sqlite_path: &Path,
+    disclosure: &Disclosure,
+) -> Result<()> {
+    let source = if disclosure.bucketed {
+        "withheld".to_string()
+    } else {
+        html_escape(sqlite_path.display().to_string().as_str())
+    };
     let generated_at = html_escape(&summary.generated_at);
+    let privacy = disclosure.note();
     writeln!(
         file,
         r#"<!DOCTYPE html>
@@ -136,7 +330,7 @@ fn write_header(file: &mut File, summary: &FormatSummary, args: &AlleleReportArg
     Generated at: <strong>{generated_at}</strong><br/>
     Formats tracked: <strong>{formats}</strong>,
     Unique rsids: <strong>{unique_rsids}</strong>,
-    Format/rsid rows: <strong>{total_rows}</strong>
+    Format/rsid rows: <strong>{total_rows}</strong>{privacy}
   </div>
   <table id="rsid-table">
     <thead>
@@ -149,42 +343,69 @@ fn write_header(file: &mut File, summary: &FormatSummary, args: &AlleleReportArg
     <tbody>
 "#,
         formats = summary.unique_formats,
-        unique_rsids = summary.unique_rsids,
-        total_rows = summary.total_rows
+        unique_rsids = html_escape(&disclosure.total(summary.unique_rsids)),
+        total_rows = html_escape(&disclosure.total(summary.total_rows))
     )
     .context("write report header")?;
     Ok(())
 }
 
-fn write_table_rows(file: &mut File, conn: &Connection) -> Result<()> {
+/// One released row per reference rsid, its observations being the source files that called
+/// it, and how many rows were withheld.
+fn released_rows(
+    conn: &Connection,
+    observed: &HashMap<i64, u64>,
+    disclosure: &mut Disclosure,
+) -> Result<(Vec<ReportRow>, usize)> {
     let mut stmt = conn.prepare(
-        "SELECT f.name as format, rr.rsid, 1 as count
+        "SELECT f.name as format, rr.rsid
          FROM rsid_reference rr
          JOIN formats f ON f.id = rr.format_id
          ORDER BY f.name ASC, rr.rsid ASC",
     )?;
     let mut rows = stmt.query([])?;
-    let mut has_rows = false;
+    let mut released = Vec::new();
+    let mut suppressed = 0usize;
     while let Some(row) = rows.next()? {
-        has_rows = true;
         let format: String = row.get(0)?;
         let rsid: i64 = row.get(1)?;
-        let count: i64 = row.get(2)?;
+        let files = observed.get(&rsid).copied().unwrap_or(0);
+        let Some(count) = disclosure.release(files as i64) else {
+            suppressed += 1;
+            continue;
+        };
+        released.push(ReportRow {
+            format,
+            rsid,
+            count,
+        });
+    }
+    Ok((released, suppressed))
+}
+
+fn write_table_rows(
+    file: &mut impl Write,
+    rows: &[ReportRow],
+    disclosure: &Disclosure,
+) -> Result<()> {
+    for row in rows {
+        let (count, sort_value) = disclosure.count_cell(row.count);
         writeln!(
             file,
             r#"      <tr>
         <td>{format}</td>
-        <td>rs{rsid}</td>
-        <td class="count" data-sort-value="{count}">{count}</td>
+        <td>{rsid}</td>
+        <td class="count" data-sort-value="{sort_value}">{count}</td>
       </tr>"#,
-            format = html_escape(&format),
-            rsid = rsid,
-            count = count
+            format = html_escape(&disclosure.cell("formats.name", row.format.clone())),
+            rsid = disclosure.cell("rsid_reference.rsid", format!("rs{}", row.rsid)),
+            sort_value = sort_value,
+            count = html_escape(&count)
         )
         .context("write report row")?;
     }
 
-    if !has_rows {
+    if rows.is_empty() {
         writeln!(
             file,
             r#"      <tr><td colspan="3" class="empty">No rsid data available</td></tr>"#
@@ -193,7 +414,7 @@ fn write_table_rows(file: &mut File, conn: &Connection) -> Result<()> {
     Ok(())
 }
 
-fn write_footer(file: &mut File) -> Result<()> {
+fn write_footer(file: &mut impl Write) -> Result<()> {
     writeln!(
         file,
         r#"    </tbody>
@@ -0,0 +1,170 @@
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use anyhow::{bail, Result};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::output::{self, status};
+use crate::util::{build_thread_pool, collect_input_files};
+use crate::{BenchArgs, GlobalArgs};
+use biosynth_core::genotype::ParseOptions;
+use biosynth_core::stats::process_input;
+use biosynth_core::synthetic::write_rows;
+
+/// One benchmark run; a list of these is the `--output-format json` result.
+#[derive(Debug, Serialize)]
+struct BenchResult {
+    name: &'static str,
+    threads: usize,
+    items: String,
+    rows: usize,
+    seconds: f64,
+    rows_per_sec: f64,
+}
+
+impl BenchResult {
+    fn new(name: &'static str, threads: usize, items: String, rows: usize, start: Instant) -> Self {
+        let seconds = start.elapsed().as_secs_f64();
+        let rows_per_sec = if seconds > 0.0 {
+            rows as f64 / seconds
+        } else {
+            0.0
+        };
+        Self {
+            name,
+            threads,
+            items,
+            rows,
+            seconds,
+            rows_per_sec,
+        }
+    }
+}
+
+pub fn run_bench(args: BenchArgs, global: &GlobalArgs) -> Result<()> {
+    if args.thread_counts.is_empty() || args.thread_counts.contains(&0) {
+        bail!("--thread-counts must list one or more positive thread counts");
+    }
+    if args.synthetic_files == 0 {
+        bail!("--synthetic-files must be at least 1");
+    }
+
+    let mut results = Vec::new();
+
+    if !args.inputs.is_empty() {
+        let mut files = collect_input_files(&args.inputs)?;
+        if let Some(max) = args.max_files {
+            files.truncate(max);
+        }
+        if files.is_empty() {
+            bail!("No genotype files discovered in the provided inputs");
+        }
+        status!(
+            global,
+            "⏱️ Benchmarking parse throughput on {} files",
+            files.len()
+        );
+        for &threads in &args.thread_counts {
+            let pool = build_thread_pool(Some(threads))?;
+            let options = ParseOptions {
+                threads,
+                ..global.parse_options()
+            };
+            let start = Instant::now();
+            let rows = pool.install(|| {
+                files
+                    .par_iter()
+                    .map(|path| {
+                        process_input(path, &options, |_, _| Ok(()))
+                            .map(|parsed| parsed.summary.variant_count)
+                    })
+                    .collect::<biosynth_core::Result<Vec<_>>>()
+            })?;
+            results.push(BenchResult::new(
+                "parse",
+                threads,
+                format!("{} files", files.len()),
+                rows.iter().sum(),
+                start,
+            ));
+        }
+    }
+
+    if !args.skip_synthetic {
+        let reference = global.reference_db(args.sqlite.as_ref())?;
+        let sqlite_path = reference.path;
+        let store = global.stats_backend(&sqlite_path)?;
+        let references = store.all_references(args.limit)?;
+        if references.is_empty() {
+            bail!(
+                "No reference rows found in {}",
+                sqlite_path.to_string_lossy()
+            );
+        }
+        status!(
+            global,
+            "⏱️ Benchmarking synthetic generation on {} reference rows",
+            references.len()
+        );
+        for &threads in &args.thread_counts {
+            let pool = build_thread_pool(Some(threads))?;
+            let start = Instant::now();
+            let rows = AtomicUsize::new(0);
+            pool.install(|| {
+                (0..args.synthetic_files)
+                    .into_par_iter()
+                    .try_for_each(|idx| {
+                        let mut rng = StdRng::seed_from_u64(idx as u64);
+                        let written =
+                            write_rows(&mut io::sink(), &references, &[], 0.01, None, &mut rng)?;
+                        rows.fetch_add(written, Ordering::Relaxed);
+                        Ok::<_, anyhow::Error>(())
+                    })
+            })?;
+            results.push(BenchResult::new(
+                "synthetic",
+                threads,
+                format!("{} files", args.synthetic_files),
+                rows.into_inner(),
+                start,
+            ));
+        }
+    }
+
+    if results.is_empty() {
+        bail!("Nothing to benchmark: provide --input paths or drop --skip-synthetic");
+    }
+
+    print_results(&results, global);
+    output::emit(global, "bench", &results)
+}
+
+fn print_results(results: &[BenchResult], global: &GlobalArgs) {
+    status!(global, "");
+    status!(
+        global,
+        "{:<10} {:>7} {:>12} {:>12} {:>10} {:>14}",
+        "benchmark",
+        "threads",
+        "items",
+        "rows",
+        "seconds",
+        "rows/sec"
+    );
+    for result in results {
+        status!(
+            global,
+            "{:<10} {:>7} {:>12} {:>12} {:>10.3} {:>14.0}",
+            result.name,
+            result.threads,
+            result.items,
+            result.rows,
+            result.seconds,
+            result.rows_per_sec
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use biosynth_core::download::COMPRESSED_SUFFIX;
+use biosynth_core::stats::is_fetched_reference;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::output::{self, status};
+use crate::util::format_bytes;
+use crate::{CleanArgs, GlobalArgs};
+
+const CACHE_DIR: &str = "cache";
+const TEMP_EXTENSIONS: &[&str] = &["part", "etag", "tmp"];
+const DATABASE_SUFFIX: &str = ".sqlite";
+const SIDECAR_SUFFIXES: &[&str] = &["-wal", "-shm"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Category {
+    Temp,
+    Cache,
+    Reference,
+}
+
+impl Category {
+    fn label(self) -> &'static str {
+        match self {
+            Category::Temp => "temp",
+            Category::Cache => "cache",
+            Category::Reference => "reference",
+        }
+    }
+}
+
+/// `--output-format json` result.
+#[derive(Debug, Default, Serialize)]
+struct CleanOutput {
+    dry_run: bool,
+    files: Vec<CleanedFile>,
+    bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct CleanedFile {
+    path: PathBuf,
+    category: Category,
+    bytes: u64,
+}
+
+pub fn run_clean(args: CleanArgs, global: &GlobalArgs) -> Result<()> {
+    let data_dir = &global.data_dir;
+    if !data_dir.exists() {
+        status!(
+            global,
+            "🧹 Nothing to clean: {} does not exist",
+            data_dir.display()
+        );
+        return output::emit(global, "clean", &CleanOutput::default());
+    }
+
+    let mut targets: Vec<(PathBuf, u64, Category)> = Vec::new();
+    let mut kept = HashSet::new();
+    for entry in WalkDir::new(data_dir).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Some(category) = classify(data_dir, entry.path()) else {
+            continue;
+        };
+        if category == Category::Reference {
+            if !args.references {
+                continue;
+            }
+            // Only databases fetch-reference wrote are removable; one that `bvs genostats` has
+            // ingested into holds observations that cannot be downloaded again.
+            let database = database_path(entry.path());
+            if !is_fetched_reference(&database) {
+                if kept.insert(database.clone()) {
+                    status!(
+                        global,
+                        "   kept {} (not a fetched reference, or it holds ingested data)",
+                        database.display()
+                    );
+                }
+                continue;
+            }
+        }
+        let size = entry.metadata().map(|meta| meta.len()).unwrap_or(0);
+        targets.push((entry.path().to_path_buf(), size, category));
+    }
+
+    if targets.is_empty() {
+        status!(global, "🧹 Nothing to clean under {}", data_dir.display());
+        return output::emit(global, "clean", &CleanOutput::default());
+    }
+
+    let mut reclaimed = 0u64;
+    for (path, size, category) in &targets {
+        if args.dry_run {
+            status!(
+                global,
+                "   would remove [{}] {} ({})",
+                category.label(),
+                path.display(),
+                format_bytes(*size)
+            );
+        } else {
+            fs::remove_file(path).with_context(|| format!("Remove {:?}", path))?;
+            status!(
+                global,
+                "   removed [{}] {} ({})",
+                category.label(),
+                path.display(),
+                format_bytes(*size)
+            );
+        }
+        reclaimed += size;
+    }
+
+    if !args.dry_run {
+        let cache_dir = data_dir.join(CACHE_DIR);
+        if cache_dir.is_dir() {
+            remove_empty_dirs(&cache_dir);
+        }
+    }
+
+    let (verb, outcome) = if args.dry_run {
+        ("Would remove", "reclaimable")
+    } else {
+        ("Removed", "reclaimed")
+    };
+    status!(
+        global,
+        "🧹 {} {} files, {} {}",
+        verb,
+        targets.len(),
+        format_bytes(reclaimed),
+        outcome
+    );
+    output::emit(
+        global,
+        "clean",
+        &CleanOutput {
+            dry_run: args.dry_run,
+            files: targets
+                .into_iter()
+                .map(|(path, bytes, category)| CleanedFile {
+                    path,
+                    category,
+                    bytes,
+                })
+                .collect(),
+            bytes: reclaimed,
+        },
+    )
+}
+
+fn classify(data_dir: &Path, path: &Path) -> Option<Category> {
+    if path.starts_with(data_dir.join(CACHE_DIR)) {
+        return Some(Category::Cache);
+    }
+    let name = path.file_name()?.to_str()?;
+    if path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| TEMP_EXTENSIONS.contains(&ext))
+    {
+        return Some(Category::Temp);
+    }
+    if name.ends_with(&format!("{}{}", DATABASE_SUFFIX, COMPRESSED_SUFFIX)) {
+        return Some(Category::Reference);
+    }
+    let database = SIDECAR_SUFFIXES
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(name);
+    database
+        .ends_with(DATABASE_SUFFIX)
+        .then_some(Category::Reference)
+}
+
+/// The database a reference file belongs to: itself, or the database of a WAL/SHM sidecar or
+/// compressed download.
+fn database_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    match SIDECAR_SUFFIXES
+        .iter()
+        .chain([&COMPRESSED_SUFFIX])
+        .find_map(|suffix| name.strip_suffix(suffix))
+    {
+        Some(database) => path.with_file_name(database),
+        None => path.to_path_buf(),
+    }
+}
+
+fn remove_empty_dirs(dir: &Path) {
+    for entry in WalkDir::new(dir)
+        .contents_first(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        if entry.file_type().is_dir() {
+            // Only empty directories are removed; anything else is left in place.
+            let _ = fs::remove_dir(entry.path());
+        }
+    }
+}
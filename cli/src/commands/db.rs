@@ -0,0 +1,141 @@
+use anyhow::Result;
+use biosynth_core::audit::AuditEntry;
+use biosynth_core::download::ensure_reference_db;
+use biosynth_core::policy::ReleasedColumn;
+use biosynth_core::stats::IndexTuning;
+use chrono::DateTime;
+
+use crate::output::{self, status};
+use crate::{DbArgs, DbAuditArgs, DbCommand, DbTuneArgs, GlobalArgs};
+
+pub fn run_db(args: DbArgs, global: &GlobalArgs) -> Result<()> {
+    match args.command {
+        DbCommand::Audit(args) => run_db_audit(args, global),
+        DbCommand::Tune(args) => run_db_tune(args, global),
+    }
+}
+
+fn run_db_audit(args: DbAuditArgs, global: &GlobalArgs) -> Result<()> {
+    let sqlite_path = ensure_reference_db(&global.sqlite_path(args.sqlite.as_ref()))?;
+    let mut entries = global.stats_store(&sqlite_path)?.audit_log(args.limit)?;
+    if let Some(policy) = global.export_policy()? {
+        policy.require("db audit", AUDIT_REQUIRED_COLUMNS)?;
+        let redacted = policy.redactions("db audit", AUDIT_REDACTABLE_COLUMNS)?;
+        for entry in &mut entries {
+            redact(entry, &redacted);
+        }
+        if !redacted.is_empty() {
+            status!(global, "🚫 Export policy redacted {}", redacted.join(", "));
+        }
+    }
+
+    if entries.is_empty() {
+        status!(global, "📜 No audit entries in {}", sqlite_path.display());
+    }
+    for entry in &entries {
+        status!(global, "{}", describe(entry));
+    }
+    output::emit(global, "db audit", &entries)
+}
+
+fn run_db_tune(args: DbTuneArgs, global: &GlobalArgs) -> Result<()> {
+    let sqlite_path = ensure_reference_db(&global.sqlite_path(args.sqlite.as_ref()))?;
+    let tuned = global
+        .stats_store(&sqlite_path)?
+        .tune_indexes(!args.dry_run)?;
+    for tuning in &tuned {
+        status!(global, "{}", describe_tuning(tuning));
+        status!(global, "   plan: {}", tuning.plan_before.join("; "));
+        if let Some(plan) = tuning.plan_after.as_ref().filter(|_| tuning.created) {
+            status!(global, "   now:  {}", plan.join("; "));
+        }
+    }
+    output::emit(global, "db tune", &tuned)
+}
+
+fn describe_tuning(tuning: &IndexTuning) -> String {
+    let before = format_seconds(tuning.before_seconds);
+    match tuning.after_seconds {
+        Some(after) if tuning.created => format!(
+            "⚡ {}: {} → {} with new index {}",
+            tuning.pattern,
+            before,
+            format_seconds(after),
+            tuning.index
+        ),
+        Some(after) => format!(
+            "✅ {}: {} → {}; already covered by {}",
+            tuning.pattern,
+            before,
+            format_seconds(after),
+            tuning.index
+        ),
+        None if tuning.existed => format!(
+            "✅ {}: {}; covered by {}",
+            tuning.pattern, before, tuning.index
+        ),
+        None => format!(
+            "🔍 {}: {}; would create {}",
+            tuning.pattern, before, tuning.index
+        ),
+    }
+}
+
+fn format_seconds(seconds: f64) -> String {
+    format!("{:.1} ms", seconds * 1000.0)
+}
+
+/// Audit columns every listing needs.
+const AUDIT_REQUIRED_COLUMNS: &[ReleasedColumn<'static>] = &[
+    ("audit_log", Some("id")),
+    ("audit_log", Some("timestamp")),
+    ("audit_log", Some("command")),
+    ("audit_log", Some("access")),
+    ("audit_log", Some("succeeded")),
+];
+
+/// Audit columns that are blanked when the export policy does not allow them.
+const AUDIT_REDACTABLE_COLUMNS: &[ReleasedColumn<'static>] = &[
+    ("audit_log", Some("user")),
+    ("audit_log", Some("args")),
+    ("audit_log", Some("row_deltas")),
+];
+
+fn redact(entry: &mut AuditEntry, redacted: &[String]) {
+    for column in redacted {
+        match column.as_str() {
+            "audit_log.user" => entry.event.user = None,
+            "audit_log.args" => entry.event.args.clear(),
+            "audit_log.row_deltas" => entry.event.row_deltas.clear(),
+            _ => {}
+        }
+    }
+}
+
+fn describe(entry: &AuditEntry) -> String {
+    let event = &entry.event;
+    let when = DateTime::from_timestamp(entry.timestamp, 0)
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_else(|| entry.timestamp.to_string());
+    let deltas = if event.row_deltas.is_empty() {
+        "no row changes".to_string()
+    } else {
+        event
+            .row_deltas
+            .iter()
+            .map(|(table, delta)| format!("{} {:+}", table, delta))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    format!(
+        "{} #{} {} {} {} [{}] {}; {}",
+        if event.succeeded { "✅" } else { "❌" },
+        entry.id,
+        when,
+        event.user.as_deref().unwrap_or("-"),
+        event.access,
+        event.command,
+        event.args.join(" "),
+        deltas
+    )
+}
@@ -0,0 +1,214 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+use crate::exit::DownloadFailed;
+use crate::output::{self, status};
+use crate::progress::DownloadBar;
+use crate::util::format_bytes;
+use crate::{FetchReferenceArgs, GlobalArgs};
+use biosynth_core::download::{
+    check_reference, ensure_reference_db, fetch_reference, list_reference_versions, ChecksumStatus,
+    HttpConfig, ReferenceCheck, ReferenceSource, DEFAULT_REFERENCE_VERSION,
+    UNRELEASED_REFERENCE_VERSION,
+};
+
+/// `--output-format json` result for `--list`.
+#[derive(Debug, Serialize)]
+struct ReferenceVersions {
+    versions: Vec<String>,
+}
+
+/// `--output-format json` result for a download.
+#[derive(Debug, Serialize)]
+struct DownloadedReference {
+    version: String,
+    path: PathBuf,
+    sha256: String,
+    verified: bool,
+    resumed_bytes: u64,
+    compressed_bytes: Option<u64>,
+}
+
+/// `--output-format json` result for `--check`.
+#[derive(Debug, Serialize)]
+struct CheckedReference {
+    path: PathBuf,
+    local_version: Option<String>,
+    remote_version: String,
+    status: CheckStatus,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum CheckStatus {
+    UpToDate,
+    UpdateAvailable,
+    Unknown,
+}
+
+pub fn run_fetch_reference(args: FetchReferenceArgs, global: &GlobalArgs) -> Result<()> {
+    let source = global.reference_source().context(DownloadFailed)?;
+    if args.list {
+        let versions =
+            list_reference_versions(&source, &global.http_config()).context(DownloadFailed)?;
+        status!(global, "📦 Available reference versions:");
+        for version in &versions {
+            status!(global, "   - {}", version);
+        }
+        return output::emit(global, "fetch-reference", &ReferenceVersions { versions });
+    }
+
+    let dest = global.sqlite_path(args.dest.as_ref());
+    let requested = args
+        .version
+        .as_deref()
+        .or(global.reference_version.as_deref())
+        .unwrap_or(DEFAULT_REFERENCE_VERSION);
+    if args.check {
+        return check(&source, requested, &dest, global);
+    }
+    if dest.exists() && !args.force {
+        bail!(
+            "{} already exists; pass --force to replace it",
+            dest.display()
+        );
+    }
+
+    status!(
+        global,
+        "📥 Downloading reference database ({}) from {}...",
+        requested,
+        source
+    );
+    let http = HttpConfig {
+        connections: args.connections,
+        ..global.http_config()
+    };
+    let bar = DownloadBar::start(global);
+    let fetched = fetch_reference(&source, &http, requested, &dest, &mut |progress| {
+        bar.update(progress)
+    })
+    .context(DownloadFailed)?;
+    bar.finish();
+    if fetched.version != requested {
+        status!(global, "🏷️  {} is release {}", requested, fetched.version);
+    }
+    if fetched.resumed_bytes > 0 {
+        status!(
+            global,
+            "↩️  Resumed an interrupted download after {}",
+            format_bytes(fetched.resumed_bytes)
+        );
+    }
+    if let Some(compressed) = fetched.compressed_bytes {
+        status!(
+            global,
+            "🗜️  Fetched the zstd-compressed copy ({}) and decompressed it",
+            format_bytes(compressed)
+        );
+    }
+    match fetched.checksum {
+        ChecksumStatus::Verified => {
+            status!(global, "🔐 Checksum verified (sha256 {})", fetched.sha256)
+        }
+        ChecksumStatus::Unpublished => status!(
+            global,
+            "⚠️  {} publishes no checksum; the download could not be verified (sha256 {})",
+            fetched.version,
+            fetched.sha256
+        ),
+    }
+    if fetched.version == UNRELEASED_REFERENCE_VERSION {
+        status!(
+            global,
+            "⚠️  {} is not a release; its data can change, so runs against it may not be reproducible",
+            fetched.version
+        );
+    }
+    // Later opens refuse the reference if its rows stop matching this digest, and
+    // --reference-version checks the recorded release.
+    let store = global.stats_store(&dest)?;
+    store.seal_reference()?;
+    store.set_reference_version(&fetched.version)?;
+    store.set_reference_validators(&fetched.validators)?;
+    status!(global, "✅ Downloaded to {}", dest.display());
+    output::emit(
+        global,
+        "fetch-reference",
+        &DownloadedReference {
+            version: fetched.version,
+            path: dest,
+            sha256: fetched.sha256,
+            verified: fetched.checksum == ChecksumStatus::Verified,
+            resumed_bytes: fetched.resumed_bytes,
+            compressed_bytes: fetched.compressed_bytes,
+        },
+    )
+}
+
+/// Compares the reference at `dest` with the one published at `requested`.
+fn check(
+    source: &ReferenceSource,
+    requested: &str,
+    dest: &Path,
+    global: &GlobalArgs,
+) -> Result<()> {
+    let path = ensure_reference_db(dest)?;
+    let store = global.stats_store(&path)?;
+    let local_version = store.reference_version()?;
+    let validators = store.reference_validators()?;
+    status!(
+        global,
+        "🔎 Checking {} ({}) against {} on {}...",
+        path.display(),
+        local_version.as_deref().unwrap_or("unknown release"),
+        requested,
+        source
+    );
+    let checked = check_reference(
+        source,
+        &global.http_config(),
+        requested,
+        local_version.as_deref(),
+        &validators,
+    )
+    .context(DownloadFailed)?;
+    let status = match &checked {
+        ReferenceCheck::UpToDate { version } => {
+            status!(global, "✅ Up to date with {}", version);
+            CheckStatus::UpToDate
+        }
+        ReferenceCheck::UpdateAvailable { version } => {
+            status!(
+                global,
+                "🆕 {} differs from the local reference; run `bvs --reference-version {} \
+                 fetch-reference --force` to download it",
+                version,
+                version
+            );
+            CheckStatus::UpdateAvailable
+        }
+        ReferenceCheck::Unknown { version } => {
+            status!(
+                global,
+                "❓ {} recorded no download validators, so it cannot be compared with {}; \
+                 fetch it again to enable checks",
+                path.display(),
+                version
+            );
+            CheckStatus::Unknown
+        }
+    };
+    output::emit(
+        global,
+        "fetch-reference",
+        &CheckedReference {
+            path,
+            local_version,
+            remote_version: checked.version().to_string(),
+            status,
+        },
+    )
+}
@@ -1,24 +1,114 @@
 use std::fs::File;
-use std::io::Write;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
 use std::time::Instant;
 
 use anyhow::{bail, Context, Result};
-use indicatif::{ProgressBar, ProgressStyle};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use rayon::prelude::*;
-use rayon::ThreadPoolBuilder;
-use thiserror::Error;
+use serde::Serialize;
 
-use crate::genotype::process_file;
-use crate::stats::StatsStore;
-use crate::util::collect_input_files;
-use crate::GenostatsArgs;
+use crate::exit::{ParseFailuresExceeded, PartialSuccess};
+use crate::output::{self, status};
+use crate::progress::{Progress, ProgressEvent};
+use crate::util::{build_thread_pool, collect_input_files, maintain, resolve_thread_count};
+use crate::{GenostatsArgs, GlobalArgs};
 
-pub fn run_genostats(args: GenostatsArgs) -> Result<()> {
+use biosynth_core::archive::{read_entry, split_entry};
+use biosynth_core::buffers;
+use biosynth_core::final_report;
+use biosynth_core::genotype::{ColumnOverrides, HeaderAliases, ParseOptions};
+use biosynth_core::liftover::GenomeBuild;
+use biosynth_core::privacy::{LaplaceNoise, Suppression};
+use biosynth_core::qc::QcWarning;
+use biosynth_core::staging::StagingArea;
+use biosynth_core::stats::{
+    CommitInterval, Observations, ParsedSamples, Provenance, RecordedFiles, StatsStore,
+    SummaryReport,
+};
+use biosynth_core::vcf::{is_vcf, split_sample, validate_vcf, validate_vcf_file, VcfValidation};
+use biosynth_core::vendors::Vendor;
+use biosynth_core::BiosynthError;
+
+/// Share of checked calls at other coordinates than the reference above which a file is
+/// reported as likely being on another genome build.
+const BUILD_MISMATCH_RATE: f64 = 0.05;
+
+/// `--output-format json` result.
+#[derive(Debug, Serialize)]
+struct GenostatsOutput {
+    files: usize,
+    failures: Vec<FileFailure>,
+    /// Per-file counts of invalid calls, rows dropped by `--on-duplicate`, and those from the
+    /// checks that were enabled, such as `--fix-strand`, `--check-positions`, or rsid remapping.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    file_reports: Vec<FileReport>,
+    summary: SummaryReport,
+}
+
+#[derive(Debug, Serialize)]
+struct FileFailure {
+    path: PathBuf,
+    error: String,
+}
+
+#[derive(Debug, Serialize)]
+struct FileReport {
+    path: PathBuf,
+    variants: usize,
+    strand_corrections: usize,
+    rsid_remaps: usize,
+    position_checks: usize,
+    position_mismatches: usize,
+    /// `position_mismatches / position_checks`, or `None` if no call was checked.
+    position_mismatch_rate: Option<f64>,
+    duplicate_rows: usize,
+    invalid_genotypes: usize,
+    /// The vendor whose export format the header matches.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vendor: Option<Vendor>,
+    /// The build the header names, or else the one its sentinel calls agree with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    genome_build: Option<GenomeBuild>,
+    /// `header` or `sentinels`, for `genome_build`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    build_source: Option<&'static str>,
+    /// Whether the header and the sentinel calls name different builds.
+    build_conflict: bool,
+    mixed_build: bool,
+    /// Sex-chromosome and mitochondrial patterns the calls should not show.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    qc_warnings: Vec<QcWarning>,
+}
+
+pub fn run_genostats(args: GenostatsArgs, global: &GlobalArgs) -> Result<()> {
     if args.inputs.is_empty() {
         bail!("Provide at least one --input path");
     }
+    let noise = args.epsilon.map(LaplaceNoise::new).transpose()?;
+    let policy = global.export_policy()?;
+    let parse_options = ParseOptions {
+        columns: ColumnOverrides {
+            rsid: args.col_rsid.clone(),
+            chromosome: args.col_chrom.clone(),
+            position: args.col_pos.clone(),
+            genotype: args.col_genotype.clone(),
+        },
+        aliases: args
+            .aliases
+            .as_deref()
+            .map(HeaderAliases::load)
+            .transpose()?
+            .unwrap_or_default(),
+        ..global.parse_options()
+    };
+    if let Some(policy) = &policy {
+        SummaryReport::check_policy(policy, args.epsilon)?;
+    }
 
     let mut files = collect_input_files(&args.inputs)?;
     if let Some(max) = args.max_files {
@@ -29,48 +119,162 @@ pub fn run_genostats(args: GenostatsArgs) -> Result<()> {
         bail!("No genotype files discovered in the provided inputs");
     }
 
-    println!("🧬 Discovered {} candidate files", files.len());
+    status!(global, "🧬 Discovered {} candidate files", files.len());
+    // The samples of a Final Report or multi-sample VCF are parsed together, in one pass over
+    // the file.
+    let units: Vec<&[PathBuf]> = files.chunk_by(|a, b| same_file(a, b)).collect();
+    let pool = build_thread_pool(global.threads)?;
+    let mut validations = pool.install(|| validate_vcfs(&units));
 
-    let store = Arc::new(StatsStore::connect(&args.sqlite)?);
-    let pb = Arc::new(ProgressBar::new(files.len() as u64));
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner} {pos}/{len} [{wide_bar}] {msg}")
-            .expect("valid progress template")
-            .progress_chars("=>-"),
-    );
+    let sqlite_path = global.sqlite_path(args.sqlite.as_ref());
+    let store = if args.in_memory {
+        status!(
+            global,
+            "🧠 Aggregating in memory; {} is written when the run finishes",
+            sqlite_path.display()
+        );
+        StatsStore::connect_in_memory(&sqlite_path, global.sqlite_tuning())?
+    } else {
+        global.stats_store(&sqlite_path)?
+    };
+    let mut store = store
+        .with_batch_size(args.batch_size)?
+        .with_commit_interval(
+            args.commit_rows
+                .map_or(CommitInterval::Adaptive, CommitInterval::Fixed),
+        )?
+        .with_duplicate_policy(args.on_duplicate)
+        .with_parse_options(parse_options);
+    #[cfg(feature = "columnar")]
+    {
+        store = store.with_columnar_aggregation(args.columnar);
+    }
+    if let Some(file_map) = args.file_map.clone() {
+        store = store.with_file_map(file_map);
+    }
+    if args.consent_tag.is_some() || args.source_dataset.is_some() {
+        store = store.with_provenance(Provenance {
+            consent_tag: args.consent_tag.clone(),
+            source_dataset: args.source_dataset.clone(),
+        })?;
+    }
+    if args.secure_temp {
+        let staging = StagingArea::secure(None)?.with_threads(resolve_thread_count(global.threads));
+        status!(
+            global,
+            "🔐 Staging decompressed inputs in {}",
+            staging.path().display()
+        );
+        store = store.with_staging(staging);
+    }
+    if args.aggregate_only {
+        let min_count = args.min_count.expect("clap requires --min-count");
+        store.enable_aggregate_only(&Suppression::new(min_count))?;
+    }
+    if args.fix_strand {
+        let reference = store.strand_reference()?;
+        if reference.is_empty() {
+            bail!(
+                "--fix-strand needs reference alleles in {}; run `bvs fetch-reference` or `bvs reference-load` first",
+                sqlite_path.display()
+            );
+        }
+        status!(
+            global,
+            "🧭 Checking calls against the reference alleles of {} SNPs for strand flips",
+            reference.len()
+        );
+        store = store.with_strand_correction(reference);
+    }
+    if args.check_positions {
+        let reference = store.position_reference()?;
+        if reference.is_empty() {
+            bail!(
+                "--check-positions needs reference positions in {}; run `bvs fetch-reference` or `bvs reference-load` first",
+                sqlite_path.display()
+            );
+        }
+        status!(
+            global,
+            "📍 Checking calls against the positions of {} reference SNPs",
+            reference.len()
+        );
+        store = store.with_position_check(reference);
+    }
+    let sentinels = store.build_sentinels()?;
+    let fingerprinting = !sentinels.is_empty();
+    if fingerprinting {
+        status!(
+            global,
+            "🧬 Fingerprinting genome builds from {} sentinel rsids",
+            sentinels.len()
+        );
+        store = store.with_build_sentinels(sentinels);
+    }
+    let merges = store.rsid_merges()?;
+    let remapping = !merges.is_empty();
+    if remapping {
+        status!(
+            global,
+            "🔀 Remapping {} retired rsids to their current IDs",
+            merges.len()
+        );
+        store = store.with_rsid_merges(merges);
+    }
+    let enforced = store.aggregate_only()?;
+    if let Some(suppression) = &enforced {
+        if args.file_map.is_some() || args.skip_recorded_files {
+            bail!("--file-map and --skip-recorded-files are unavailable for an aggregate-only database");
+        }
+        status!(
+            global,
+            "🔒 Aggregate-only database; counts below {} are not stored",
+            suppression.min_count()
+        );
+    }
+    let min_count = args
+        .min_count
+        .max(enforced.map(|suppression| suppression.min_count()));
+    let recorded = if args.skip_recorded_files {
+        let recorded = store.recorded_files()?;
+        status!(
+            global,
+            "📇 Loaded {} recorded files to skip",
+            recorded.len()
+        );
+        Some(recorded)
+    } else {
+        None
+    };
+    let failures: Failures = Mutex::new(Vec::new());
 
-    let failures: Arc<Mutex<Vec<(PathBuf, String)>>> = Arc::new(Mutex::new(Vec::new()));
-
-    let threads = args.threads.max(1);
-    let pool = ThreadPoolBuilder::new()
-        .num_threads(threads)
-        .build()
-        .context("build rayon thread pool")?;
-
-    pool.install(|| {
-        files.par_iter().for_each(|path| {
-            let pb = pb.clone();
-            let store = store.clone();
-            let failures = failures.clone();
-            let skip_existing = args.skip_recorded_files;
-
-            let result = process_single_file(&store, path, skip_existing);
-            if let Err(err) = result {
-                if err.downcast_ref::<SkipFile>().is_none() {
-                    let mut guard = failures.lock().expect("poisoned failures mutex");
-                    guard.push((path.clone(), err.to_string()));
-                }
-            }
-            pb.inc(1);
+    let progress = Progress::start(
+        global,
+        "genostats",
+        files.len(),
+        pool.current_num_threads(),
+        true,
+    )?;
+
+    // Workers parse in parallel; this thread writes every file through one connection, in the
+    // order parsing started, so workers never contend for the database's write lock.
+    let (announce, announced) = mpsc::channel();
+    let (reports, checked) = thread::scope(|scope| {
+        scope.spawn(|| {
+            pool.install(|| {
+                units.par_iter().for_each_with(announce, |announce, paths| {
+                    parse_file(&store, paths, recorded.as_ref(), announce, &progress)
+                })
+            })
         });
-    });
+        write_files(&store, announced, &progress, &failures)
+    })?;
 
-    pb.finish_with_message("genotype parsing complete");
+    progress.finish("genotype parsing complete")?;
+    validations.extend(checked);
+    report_vcf_problems(global, validations);
 
-    let failures = Arc::try_unwrap(failures)
-        .map(|mutex| mutex.into_inner().unwrap_or_default())
-        .unwrap_or_else(|arc| arc.lock().expect("poisoned failures mutex").clone());
+    let failures = failures.into_inner().unwrap_or_default();
 
     if !failures.is_empty() {
         eprintln!("⚠️ Encountered {} errors:", failures.len());
@@ -78,60 +282,458 @@ pub fn run_genostats(args: GenostatsArgs) -> Result<()> {
             eprintln!("   - {:?}: {}", path, message);
         }
     }
+    if args.fix_strand {
+        report_counts(
+            global,
+            &reports,
+            "🧭 Corrected",
+            "strand-flipped calls",
+            |report| report.strand_corrections,
+        );
+    }
+    report_qc_warnings(&reports);
+    if reports.iter().any(|report| report.invalid_genotypes > 0) {
+        report_counts(
+            global,
+            &reports,
+            "🚧 Found",
+            "invalid genotype calls",
+            |report| report.invalid_genotypes,
+        );
+    }
+    if reports.iter().any(|report| report.duplicate_rows > 0) {
+        report_counts(
+            global,
+            &reports,
+            "🪞 Dropped",
+            "duplicate-rsid rows",
+            |report| report.duplicate_rows,
+        );
+    }
+    if args.check_positions {
+        report_position_mismatches(global, &reports);
+    }
+    if fingerprinting {
+        report_builds(global, &reports);
+    }
+    if remapping {
+        report_counts(
+            global,
+            &reports,
+            "🔀 Remapped",
+            "retired rsids",
+            |report| report.rsid_remaps,
+        );
+    }
+
+    maintain(&store, &args.maintenance, global)?;
+    if args.in_memory {
+        store.persist()?;
+        status!(
+            global,
+            "💾 Wrote the in-memory database to {}",
+            sqlite_path.display()
+        );
+    }
 
-    let summary = store.summary()?;
-    println!(
-        "✅ Stored stats for {} files ({} variants; {} skipped rows)",
-        summary.files_processed, summary.total_variants, summary.skipped_rows
+    let mut summary = store.summary()?;
+    if let Some(noise) = &noise {
+        summary.privatize(noise, &mut StdRng::from_entropy());
+    }
+    if let Some(min_count) = min_count {
+        summary.suppress(&Suppression::new(min_count));
+    }
+    if let Some(policy) = &policy {
+        summary.apply_policy(policy)?;
+        if !summary.redacted.is_empty() {
+            status!(
+                global,
+                "🚫 Export policy redacted {} from the summary",
+                summary.redacted.join(", ")
+            );
+        }
+    }
+    let skipped = match summary.skipped_rows {
+        Some(rows) => format!("{} skipped rows", rows),
+        None => "skipped rows withheld".to_string(),
+    };
+    status!(
+        global,
+        "✅ Stored stats for {} files ({} variants; {})",
+        summary.files_processed,
+        summary.total_variants,
+        skipped
     );
-    println!(
+    status!(
+        global,
         "📁 SQLite database ready at {}",
         summary.sqlite_path.display()
     );
 
     if let Some(summary_json) = args.summary_json {
         write_summary_json(&summary_json, &summary)?;
-        println!("📝 Summary JSON written to {}", summary_json.display());
+        status!(
+            global,
+            "📝 Summary JSON written to {}",
+            summary_json.display()
+        );
+    }
+
+    let failed = failures.len();
+    output::emit(
+        global,
+        "genostats",
+        &GenostatsOutput {
+            files: files.len(),
+            failures: failures
+                .into_iter()
+                .map(|(path, error)| FileFailure { path, error })
+                .collect(),
+            file_reports: reports,
+            summary,
+        },
+    )?;
+
+    if let Some(limit) = args.max_failures {
+        if failed > limit {
+            return Err(ParseFailuresExceeded {
+                failed,
+                total: files.len(),
+                limit,
+            }
+            .into());
+        }
+    }
+    if failed > 0 {
+        return Err(PartialSuccess {
+            failed,
+            total: files.len(),
+        }
+        .into());
     }
 
     Ok(())
 }
 
-fn write_summary_json(path: &PathBuf, summary: &crate::stats::SummaryReport) -> Result<()> {
+fn write_summary_json(path: &PathBuf, summary: &SummaryReport) -> Result<()> {
     if let Some(parent) = path.parent() {
         if !parent.exists() {
             std::fs::create_dir_all(parent).with_context(|| format!("Create {:?}", parent))?;
         }
     }
-    let mut file = File::create(path).with_context(|| format!("Create {:?}", path))?;
+    let mut file =
+        buffers::writer(File::create(path).with_context(|| format!("Create {:?}", path))?);
     serde_json::to_writer_pretty(&mut file, summary)?;
     file.write_all(b"\n")?;
+    file.flush()?;
     Ok(())
 }
 
-#[derive(Debug, Error)]
-#[error("skip file")]
-struct SkipFile;
+/// Batches a parsed file may have waiting for the writer before its worker blocks.
+const QUEUED_BATCHES: usize = 2;
+
+type Failures = Mutex<Vec<(PathBuf, String)>>;
+/// What checking each VCF input found.
+type Validations = Vec<(PathBuf, VcfValidation)>;
+
+/// Files a worker has started parsing as one unit, handed to the writer.
+struct QueuedFile {
+    paths: Vec<PathBuf>,
+    started: Instant,
+    chunks: Receiver<biosynth_core::Result<ParsedChunk>>,
+}
+
+enum ParsedChunk {
+    Observations(Observations),
+    Finished(ParsedSamples),
+}
+
+/// Whether `a` and `b` are samples of the same Final Report or multi-sample VCF.
+fn same_file(a: &Path, b: &Path) -> bool {
+    let file = |path| {
+        split_sample(path)
+            .or_else(|| final_report::split_sample(path))
+            .map(|(file, _)| file)
+    };
+    file(a).zip(file(b)).is_some_and(|(a, b)| a == b)
+}
 
-fn process_single_file(store: &StatsStore, path: &Path, skip_if_recorded: bool) -> Result<()> {
-    if skip_if_recorded && store.has_file(path)? {
-        return Err(SkipFile.into());
+fn parse_file(
+    store: &StatsStore,
+    paths: &[PathBuf],
+    recorded: Option<&RecordedFiles>,
+    announce: &Sender<QueuedFile>,
+    progress: &Progress,
+) {
+    let started = Instant::now();
+    let mut pending = Vec::new();
+    for path in paths {
+        progress.emit(ProgressEvent::Started { path: path.clone() });
+        if recorded.is_some_and(|recorded| recorded.contains(path)) {
+            progress.emit(ProgressEvent::Skipped { path: path.clone() });
+        } else {
+            pending.push(path.clone());
+        }
+    }
+    if pending.is_empty() {
+        return;
     }
 
-    let start = Instant::now();
-    let mut conn = store.open_connection()?;
-    let tx = conn.transaction()?;
-    let parsed = process_file(path, |variant, metadata| {
-        StatsStore::record_variant_in_tx(&tx, variant, metadata)
-    })?;
-    tx.commit()?;
-    store.record_file(
-        &conn,
-        &parsed.metadata,
-        &parsed.summary,
-        start.elapsed(),
-        path,
-    )?;
+    let (sender, chunks) = mpsc::sync_channel(QUEUED_BATCHES);
+    let queued = QueuedFile {
+        paths: pending.clone(),
+        started,
+        chunks,
+    };
+    if announce.send(queued).is_err() {
+        // The writer has stopped; its error is reported instead.
+        return;
+    }
+    let parsed = store.parse_sample_observations(&pending, |batch| {
+        sender
+            .send(Ok(ParsedChunk::Observations(batch)))
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe).into())
+    });
+    let _ = sender.send(parsed.map(ParsedChunk::Finished));
+}
 
-    Ok(())
+/// Validates the VCF inputs in parallel, except multi-sample ones, which are checked as
+/// their samples are parsed; call inside the command's thread pool. Read errors are left for
+/// parsing to report.
+fn validate_vcfs(units: &[&[PathBuf]]) -> Validations {
+    units
+        .par_iter()
+        .filter_map(|paths| match paths {
+            [path] if split_sample(path).is_none() && is_vcf(path) => {
+                Some((path.clone(), validate_vcf_input(path).ok()?))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Warns about VCF inputs that break the specification. They are still parsed, as far as
+/// their records allow.
+fn report_vcf_problems(global: &GlobalArgs, mut validations: Validations) {
+    if validations.is_empty() {
+        return;
+    }
+    let checked = validations.len();
+    validations.retain(|(_, validation)| !validation.is_valid());
+    validations.sort_by(|(a, _), (b, _)| a.cmp(b));
+    if validations.is_empty() {
+        status!(
+            global,
+            "📐 {} VCF inputs conform to the specification",
+            checked
+        );
+        return;
+    }
+    eprintln!(
+        "⚠️ {} of {} VCF inputs break the specification (see `bvs validate --vcf`):",
+        validations.len(),
+        checked
+    );
+    for (path, validation) in validations {
+        let first = &validation.problems[0];
+        eprintln!(
+            "   - {:?}: {} problems, first at line {}: {}",
+            path, validation.total_problems, first.line, first.message
+        );
+    }
+}
+
+fn validate_vcf_input(path: &Path) -> biosynth_core::Result<VcfValidation> {
+    match split_entry(path) {
+        Some((archive, name)) => validate_vcf(read_entry(archive, &name)?.as_slice()),
+        None => validate_vcf_file(path),
+    }
+}
+
+/// Writes every announced file, returning a report for each one written and what checking
+/// the multi-sample VCFs parsed in one pass found.
+fn write_files(
+    store: &StatsStore,
+    announced: Receiver<QueuedFile>,
+    progress: &Progress,
+    failures: &Failures,
+) -> Result<(Vec<FileReport>, Validations)> {
+    let mut writer = store.writer()?;
+    let mut reports = Vec::new();
+    let mut validations = Vec::new();
+    for queued in announced {
+        let result = writer.write_samples(&queued.paths, queued.started, |sink| {
+            for chunk in &queued.chunks {
+                match chunk? {
+                    ParsedChunk::Observations(batch) => sink(batch)?,
+                    ParsedChunk::Finished(parsed) => return Ok(parsed),
+                }
+            }
+            Err(BiosynthError::Parse(format!(
+                "Parsing {:?} stopped before the end of the file",
+                queued.paths[0]
+            )))
+        });
+        let parsed = match result {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                for path in &queued.paths {
+                    record_failure(progress, failures, path, err.to_string());
+                }
+                continue;
+            }
+        };
+        if let Some(validation) = parsed.vcf_validation {
+            let vcf = split_sample(&queued.paths[0]).map_or(&*queued.paths[0], |(vcf, _)| vcf);
+            validations.push((vcf.to_path_buf(), validation));
+        }
+        for (path, parsed) in queued.paths.into_iter().zip(parsed.files) {
+            let declared = parsed.metadata.declared_build();
+            let detected = parsed.summary.detected_build;
+            reports.push(FileReport {
+                path: path.clone(),
+                variants: parsed.summary.variant_count,
+                strand_corrections: parsed.summary.strand_corrections,
+                rsid_remaps: parsed.summary.rsid_remaps,
+                position_checks: parsed.summary.position_checks,
+                position_mismatches: parsed.summary.position_mismatches,
+                position_mismatch_rate: (parsed.summary.position_checks > 0).then(|| {
+                    parsed.summary.position_mismatches as f64
+                        / parsed.summary.position_checks as f64
+                }),
+                duplicate_rows: parsed.summary.duplicate_rows,
+                invalid_genotypes: parsed.summary.invalid_genotypes,
+                vendor: parsed.metadata.vendor,
+                genome_build: declared.or(detected),
+                build_source: match (declared, detected) {
+                    (Some(_), _) => Some("header"),
+                    (None, Some(_)) => Some("sentinels"),
+                    (None, None) => None,
+                },
+                build_conflict: declared.zip(detected).is_some_and(|(a, b)| a != b),
+                mixed_build: parsed.summary.mixed_build,
+                qc_warnings: parsed.summary.sex_chromosomes.warnings(),
+            });
+            progress.emit(ProgressEvent::Finished {
+                path,
+                rows: parsed.summary.variant_count,
+            })
+        }
+    }
+    writer.finish()?;
+    Ok((reports, validations))
+}
+
+/// Prints the total `count` across `reports`, then each file it was nonzero for.
+fn report_counts(
+    global: &GlobalArgs,
+    reports: &[FileReport],
+    action: &str,
+    what: &str,
+    count: impl Fn(&FileReport) -> usize,
+) {
+    let counted: Vec<&FileReport> = reports.iter().filter(|report| count(report) > 0).collect();
+    status!(
+        global,
+        "{} {} {} in {} of {} files",
+        action,
+        counted.iter().map(|report| count(report)).sum::<usize>(),
+        what,
+        counted.len(),
+        reports.len()
+    );
+    for report in counted {
+        status!(global, "   - {}: {}", report.path.display(), count(report));
+    }
+}
+
+/// Warns about each file whose calls show a [`QcWarning`] pattern.
+fn report_qc_warnings(reports: &[FileReport]) {
+    for report in reports
+        .iter()
+        .filter(|report| !report.qc_warnings.is_empty())
+    {
+        let warnings: Vec<String> = report.qc_warnings.iter().map(ToString::to_string).collect();
+        eprintln!(
+            "⚠️ {}: QC found {}",
+            report.path.display(),
+            warnings.join(", ")
+        );
+    }
+}
+
+/// Prints how many files resolved to each build, then warns about files mixing builds or
+/// whose header disagrees with their sentinel calls.
+fn report_builds(global: &GlobalArgs, reports: &[FileReport]) {
+    let count = |build| {
+        reports
+            .iter()
+            .filter(|report| report.genome_build == Some(build))
+            .count()
+    };
+    let (grch37, grch38) = (count(GenomeBuild::Grch37), count(GenomeBuild::Grch38));
+    status!(
+        global,
+        "🧬 Genome builds: {} GRCh37, {} GRCh38, {} unresolved",
+        grch37,
+        grch38,
+        reports.len() - grch37 - grch38
+    );
+    for report in reports {
+        if report.mixed_build {
+            eprintln!(
+                "⚠️ {}: sentinel calls agree with both GRCh37 and GRCh38; the file may mix builds",
+                report.path.display()
+            );
+        } else if report.build_conflict {
+            eprintln!(
+                "⚠️ {}: the header names {} but the sentinel calls disagree",
+                report.path.display(),
+                report
+                    .genome_build
+                    .expect("a conflict needs a declared build")
+            );
+        }
+    }
+}
+
+/// Prints the overall position mismatch rate, then each file whose rate suggests it was
+/// exported on another genome build than the reference.
+fn report_position_mismatches(global: &GlobalArgs, reports: &[FileReport]) {
+    let checks: usize = reports.iter().map(|report| report.position_checks).sum();
+    let mismatches: usize = reports
+        .iter()
+        .map(|report| report.position_mismatches)
+        .sum();
+    status!(
+        global,
+        "📍 {} of {} checked calls ({:.2}%) disagree with the reference positions",
+        mismatches,
+        checks,
+        100.0 * mismatches as f64 / checks.max(1) as f64
+    );
+    for report in reports {
+        if let Some(rate) = report
+            .position_mismatch_rate
+            .filter(|rate| *rate > BUILD_MISMATCH_RATE)
+        {
+            eprintln!(
+                "⚠️ {}: {:.1}% of {} checked calls disagree with the reference positions; is it on another genome build?",
+                report.path.display(),
+                100.0 * rate,
+                report.position_checks
+            );
+        }
+    }
+}
+
+fn record_failure(progress: &Progress, failures: &Failures, path: &Path, error: String) {
+    failures
+        .lock()
+        .expect("poisoned failures mutex")
+        .push((path.to_path_buf(), error.clone()));
+    progress.emit(ProgressEvent::Failed {
+        path: path.to_path_buf(),
+        error,
+    });
 }
@@ -0,0 +1,182 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+use crate::output::{self, status};
+
+use crate::{GlobalArgs, LiftArgs};
+use biosynth_core::buffers;
+use biosynth_core::genotype::process_file_with_options;
+use biosynth_core::liftover::{chain_download_url, chain_file_name, complement_genotype, ChainMap};
+
+const LIFTOVER_DIR: &str = "liftover";
+
+/// `--output-format json` result. `written` is false when `--no-clobber` kept an existing output.
+#[derive(Debug, Default, Serialize)]
+struct LiftOutput {
+    output: PathBuf,
+    from: String,
+    to: String,
+    written: bool,
+    lifted: usize,
+    unmapped: usize,
+    strand_flips: usize,
+    /// Calls complemented to match the reference with `--fix-strand`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    strand_corrections: Option<usize>,
+    skipped_rows: usize,
+}
+
+pub fn run_lift(args: LiftArgs, global: &GlobalArgs) -> Result<()> {
+    let chain_path = match &args.chain {
+        Some(path) => path.clone(),
+        None => global
+            .data_dir
+            .join(LIFTOVER_DIR)
+            .join(chain_file_name(args.from, args.to)?),
+    };
+    if !chain_path.exists() {
+        bail!(
+            "Chain file not found at {:?}; download it from {} or pass --chain",
+            chain_path,
+            chain_download_url(args.from, args.to)?
+        );
+    }
+    if !args.overwrite.policy().should_write(&args.output)? {
+        status!(
+            global,
+            "⏭️  {} already exists; skipping lift",
+            args.output.display()
+        );
+        return output::emit(
+            global,
+            "lift",
+            &LiftOutput {
+                output: args.output,
+                from: args.from.to_string(),
+                to: args.to.to_string(),
+                ..LiftOutput::default()
+            },
+        );
+    }
+
+    let chains = ChainMap::load(&chain_path)?;
+    let strand = if args.fix_strand {
+        let sqlite_path = global.sqlite_path(args.sqlite.as_ref());
+        let reference = global.stats_store(&sqlite_path)?.strand_reference()?;
+        if reference.is_empty() {
+            bail!(
+                "--fix-strand needs reference alleles in {}; run `bvs fetch-reference` or `bvs reference-load` first",
+                sqlite_path.display()
+            );
+        }
+        Some(reference)
+    } else {
+        None
+    };
+
+    if let Some(parent) = args.output.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Create directory {:?}", parent))?;
+        }
+    }
+    let file = File::create(&args.output).with_context(|| format!("Create {:?}", args.output))?;
+    let mut writer = buffers::writer(file);
+    writeln!(
+        writer,
+        "# Lifted from {} to {} by bvs lift ({})",
+        args.from,
+        args.to,
+        chain_path.file_name().unwrap_or_default().to_string_lossy()
+    )?;
+    writeln!(
+        writer,
+        "# Rows that could not be lifted are kept as comments: # unmapped (<reason>)"
+    )?;
+    writeln!(writer, "# rsid\tchromosome\tposition\tgenotype")?;
+
+    let mut lifted = 0usize;
+    let mut flipped = 0usize;
+    let mut unmapped = 0usize;
+    let mut corrected = 0usize;
+    let parsed = process_file_with_options(&args.input, &global.parse_options(), |record, _| {
+        match chains.lift(&record.chromosome, record.position) {
+            Ok(target) => {
+                let genotype = if target.reverse {
+                    flipped += 1;
+                    complement_genotype(&record.genotype)
+                } else {
+                    record.genotype.clone()
+                };
+                let genotype = match strand
+                    .as_ref()
+                    .and_then(|strand| strand.corrected_genotype(&record.rsid, &genotype))
+                {
+                    Some(fixed) => {
+                        corrected += 1;
+                        fixed
+                    }
+                    None => genotype,
+                };
+                writeln!(
+                    writer,
+                    "{}\t{}\t{}\t{}",
+                    record.rsid, target.chromosome, target.position, genotype
+                )?;
+                lifted += 1;
+            }
+            Err(reason) => {
+                writeln!(
+                    writer,
+                    "# unmapped ({})\t{}\t{}\t{}\t{}",
+                    reason.label(),
+                    record.rsid,
+                    record.chromosome,
+                    record.position,
+                    record.genotype
+                )?;
+                unmapped += 1;
+            }
+        }
+        Ok(())
+    })?;
+    writer.flush()?;
+
+    status!(
+        global,
+        "🧬 Lifted {} rows from {} to {} ({} unmapped, {} strand flips, {} skipped) → {}",
+        lifted,
+        args.from,
+        args.to,
+        unmapped,
+        flipped,
+        parsed.summary.skipped_rows,
+        args.output.display()
+    );
+    if strand.is_some() {
+        status!(
+            global,
+            "🧭 Corrected {} strand-flipped calls against the reference",
+            corrected
+        );
+    }
+    output::emit(
+        global,
+        "lift",
+        &LiftOutput {
+            output: args.output,
+            from: args.from.to_string(),
+            to: args.to.to_string(),
+            written: true,
+            lifted,
+            unmapped,
+            strand_flips: flipped,
+            strand_corrections: strand.is_some().then_some(corrected),
+            skipped_rows: parsed.summary.skipped_rows,
+        },
+    )
+}
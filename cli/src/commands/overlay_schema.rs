@@ -0,0 +1,18 @@
+use anyhow::Result;
+use biosynth_core::overlay::schema;
+
+use crate::manifest::write_manifest;
+use crate::output::status;
+use crate::{GlobalArgs, OverlaySchemaArgs};
+
+pub fn run_overlay_schema(args: OverlaySchemaArgs, global: &GlobalArgs) -> Result<()> {
+    let schema = schema();
+    match &args.output {
+        Some(path) => {
+            write_manifest(path, &schema)?;
+            status!(global, "📝 Overlay schema written to {}", path.display());
+        }
+        None => println!("{}", serde_json::to_string_pretty(&schema)?),
+    }
+    Ok(())
+}
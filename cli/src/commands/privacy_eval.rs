@@ -0,0 +1,105 @@
+use anyhow::{bail, Result};
+use biosynth_core::privacy_eval::{EvalOptions, PrivacyEvaluator, RiskLevel};
+
+use crate::output::{self, status};
+use crate::util::collect_input_files;
+use crate::{GlobalArgs, PrivacyEvalArgs};
+
+pub fn run_privacy_eval(args: PrivacyEvalArgs, global: &GlobalArgs) -> Result<()> {
+    if !(0.0..=1.0).contains(&args.identity_threshold) {
+        bail!("--identity-threshold must be between 0 and 1");
+    }
+    if !(0.0..=1.0).contains(&args.max_rare_allele_rate) {
+        bail!("--max-rare-allele-rate must be between 0 and 1");
+    }
+    let synthetic = collect_input_files(&args.inputs)?;
+    let real = if args.real.is_empty() {
+        Vec::new()
+    } else {
+        collect_input_files(&args.real)?
+    };
+    if synthetic.is_empty() {
+        bail!("No genotype files discovered in the provided inputs");
+    }
+
+    let reference = global.reference_db(args.sqlite.as_ref())?;
+    let sqlite_path = reference.path;
+    let store = global.stats_store(&sqlite_path)?;
+    let references = store.all_references(None)?;
+    let source_carriers = store.allele_carriers()?;
+    status!(
+        global,
+        "🔎 Evaluating {} synthetic files against {} reference variants",
+        synthetic.len(),
+        references.len()
+    );
+
+    if source_carriers.is_empty() {
+        status!(
+            global,
+            "⚠️  {:?} holds no allele observations; rare alleles cannot be checked",
+            sqlite_path
+        );
+    }
+
+    let mut evaluator = PrivacyEvaluator::new(
+        &references,
+        &source_carriers,
+        EvalOptions {
+            rare_carriers: args.rare_carriers,
+            max_rare_allele_rate: args.max_rare_allele_rate,
+            identity_threshold: args.identity_threshold,
+            sample_every: args.sample_every,
+        },
+    );
+    drop(references);
+    drop(source_carriers);
+    for path in &synthetic {
+        evaluator.add_synthetic(path)?;
+    }
+    for path in &real {
+        evaluator.add_real(path)?;
+    }
+    let report = evaluator.finish();
+
+    status!(
+        global,
+        "🧬 {} calls; {} unknown rsids; {} novel alleles",
+        report.calls,
+        report.unknown_rsids,
+        report.novel_alleles
+    );
+    status!(
+        global,
+        "🧪 {} of {} alternate alleles are rare in the source cohort ({:.1}%)",
+        report.rare_alleles,
+        report.alternate_alleles,
+        report.rare_allele_rate * 100.0
+    );
+    status!(
+        global,
+        "👯 Max synthetic identity {:.3}; {} near-duplicate pairs",
+        report.max_synthetic_identity,
+        report.near_duplicate_pairs
+    );
+    if let Some(real) = &report.real {
+        status!(
+            global,
+            "🧍 Closest real genome: max identity {:.3}, mean {:.3} across {} real files",
+            real.max_identity,
+            real.mean_closest_identity,
+            real.real_files
+        );
+    }
+    for finding in &report.findings {
+        status!(global, "⚠️  {}", finding);
+    }
+    let verdict = match report.risk {
+        RiskLevel::Low => "✅ Risk: low",
+        RiskLevel::Elevated => "🟠 Risk: elevated",
+        RiskLevel::High => "🛑 Risk: high",
+    };
+    status!(global, "{}", verdict);
+
+    output::emit(global, "privacy-eval", &report)
+}
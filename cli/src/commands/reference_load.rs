@@ -1,9 +1,15 @@
+use std::path::{Path, PathBuf};
+
 use anyhow::{Context, Result};
 use csv::ReaderBuilder;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use crate::stats::{ReferenceVariant, StatsStore};
-use crate::ReferenceLoadArgs;
+use crate::output::{self, status};
+use crate::util::maintain;
+use crate::{GlobalArgs, MaintenanceArgs, ReferenceLoadArgs};
+use biosynth_core::builds::BuildSentinels;
+use biosynth_core::merges::RsidMerges;
+use biosynth_core::stats::{DerivedReferences, ReferenceVariant};
 
 #[derive(Debug, Deserialize)]
 struct LookupRow {
@@ -17,21 +23,171 @@ struct LookupRow {
     status: String,
 }
 
-pub fn run_reference_load(args: ReferenceLoadArgs) -> Result<()> {
-    if !args.lookup.exists() {
-        anyhow::bail!("Lookup CSV not found: {:?}", args.lookup);
+/// `--output-format json` result.
+#[derive(Debug, Serialize)]
+struct ReferenceLoadOutput {
+    sqlite: PathBuf,
+    loaded: usize,
+    skipped: usize,
+}
+
+/// `--output-format json` result for `--merges`.
+#[derive(Debug, Serialize)]
+struct MergesOutput {
+    sqlite: PathBuf,
+    merges: usize,
+}
+
+/// `--output-format json` result for `--sentinels`.
+#[derive(Debug, Serialize)]
+struct SentinelsOutput {
+    sqlite: PathBuf,
+    sentinels: usize,
+}
+
+/// `--output-format json` result for `--from-observations`.
+#[derive(Debug, Serialize)]
+struct DerivedOutput {
+    sqlite: PathBuf,
+    #[serde(flatten)]
+    derived: DerivedReferences,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    consent_tags: Vec<String>,
+}
+
+pub fn run_reference_load(args: ReferenceLoadArgs, global: &GlobalArgs) -> Result<()> {
+    let sqlite_path = global.sqlite_path(args.sqlite.as_ref());
+    if let Some(merges) = &args.merges {
+        return load_merges(merges, sqlite_path, &args.maintenance, global);
+    }
+    if let Some(sentinels) = &args.sentinels {
+        return load_sentinels(sentinels, sqlite_path, &args.maintenance, global);
+    }
+    match &args.lookup {
+        Some(lookup) => load_lookup(lookup, sqlite_path, &args.maintenance, global),
+        None => derive_from_observations(
+            args.min_individuals,
+            args.consent_tags,
+            sqlite_path,
+            &args.maintenance,
+            global,
+        ),
+    }
+}
+
+fn derive_from_observations(
+    min_individuals: u64,
+    consent_tags: Vec<String>,
+    sqlite_path: PathBuf,
+    maintenance: &MaintenanceArgs,
+    global: &GlobalArgs,
+) -> Result<()> {
+    let mut store = global.stats_store(&sqlite_path)?;
+    if !consent_tags.is_empty() {
+        status!(
+            global,
+            "🪪 Using only files consented as {}",
+            consent_tags.join(", ")
+        );
+        store = store.with_consent_filter(consent_tags.clone());
     }
+    let derived = store.derive_references(min_individuals)?;
+    status!(
+        global,
+        "📚 Derived {} reference rows from observations into {} ({} already present)",
+        derived.derived,
+        sqlite_path.display(),
+        derived.existing
+    );
+    status!(
+        global,
+        "🔒 Excluded {} rsids and {} alleles seen in fewer than {} files",
+        derived.excluded_rsids,
+        derived.excluded_alleles,
+        derived.min_individuals
+    );
+    maintain(&store, maintenance, global)?;
+    output::emit(
+        global,
+        "reference-load",
+        &DerivedOutput {
+            sqlite: sqlite_path,
+            derived,
+            consent_tags,
+        },
+    )
+}
+
+fn load_merges(
+    path: &Path,
+    sqlite_path: PathBuf,
+    maintenance: &MaintenanceArgs,
+    global: &GlobalArgs,
+) -> Result<()> {
+    let merges = RsidMerges::load(path)?;
+    let store = global.stats_store(&sqlite_path)?;
+    let stored = store.store_rsid_merges(&merges)?;
+    status!(
+        global,
+        "🔀 Loaded {} rsid merges into {}",
+        stored,
+        sqlite_path.display()
+    );
+    maintain(&store, maintenance, global)?;
+    output::emit(
+        global,
+        "reference-load",
+        &MergesOutput {
+            sqlite: sqlite_path,
+            merges: stored,
+        },
+    )
+}
 
-    let store = StatsStore::connect(&args.sqlite)?;
+fn load_sentinels(
+    path: &Path,
+    sqlite_path: PathBuf,
+    maintenance: &MaintenanceArgs,
+    global: &GlobalArgs,
+) -> Result<()> {
+    let sentinels = BuildSentinels::load(path)?;
+    let store = global.stats_store(&sqlite_path)?;
+    let stored = store.store_build_sentinels(&sentinels)?;
+    status!(
+        global,
+        "🧬 Loaded {} build coordinates for {} sentinel rsids into {}",
+        stored,
+        sentinels.len(),
+        sqlite_path.display()
+    );
+    maintain(&store, maintenance, global)?;
+    output::emit(
+        global,
+        "reference-load",
+        &SentinelsOutput {
+            sqlite: sqlite_path,
+            sentinels: stored,
+        },
+    )
+}
+
+fn load_lookup(
+    lookup: &Path,
+    sqlite_path: PathBuf,
+    maintenance: &MaintenanceArgs,
+    global: &GlobalArgs,
+) -> Result<()> {
+    if !lookup.exists() {
+        anyhow::bail!("Lookup CSV not found: {:?}", lookup);
+    }
+
+    let store = global.stats_backend(&sqlite_path)?;
     let mut reader = ReaderBuilder::new()
         .trim(csv::Trim::All)
-        .from_path(&args.lookup)
-        .with_context(|| format!("Read lookup CSV {:?}", args.lookup))?;
-
-    let mut conn = store.open_connection()?;
-    let tx = conn.transaction()?;
+        .from_path(lookup)
+        .with_context(|| format!("Read lookup CSV {:?}", lookup))?;
 
-    let mut imported = 0usize;
+    let mut references = Vec::new();
     let mut skipped = 0usize;
 
     for row in reader.deserialize::<LookupRow>() {
@@ -58,16 +214,25 @@ pub fn run_reference_load(args: ReferenceLoadArgs) -> Result<()> {
             reference: row.reference,
             alternates: row.alt,
         };
-        StatsStore::upsert_reference_in_tx(&tx, &reference)?;
-        imported += 1;
+        references.push(reference);
     }
 
-    tx.commit()?;
-    println!(
+    let imported = store.upsert_references(&references)?;
+    status!(
+        global,
         "📚 Loaded {} reference rows into {} ({} skipped)",
         imported,
-        args.sqlite.display(),
+        sqlite_path.display(),
         skipped
     );
-    Ok(())
+    maintain(store.as_ref(), maintenance, global)?;
+    output::emit(
+        global,
+        "reference-load",
+        &ReferenceLoadOutput {
+            sqlite: sqlite_path,
+            loaded: imported,
+            skipped,
+        },
+    )
 }
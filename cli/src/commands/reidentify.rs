@@ -0,0 +1,21 @@
+use anyhow::Result;
+use biosynth_core::pseudonym::reidentify;
+
+use crate::output::{self, status};
+use crate::{GlobalArgs, ReidentifyArgs};
+
+pub fn run_reidentify(args: ReidentifyArgs, global: &GlobalArgs) -> Result<()> {
+    let resolved = reidentify(&args.lookup, &args.pseudonyms)?;
+    for (pseudonym, participant) in &resolved {
+        match participant {
+            Some(participant) => status!(global, "🔓 {} → {}", pseudonym, participant),
+            None => status!(
+                global,
+                "❓ {} is not in {}",
+                pseudonym,
+                args.lookup.display()
+            ),
+        }
+    }
+    output::emit(global, "reidentify", &resolved)
+}
@@ -0,0 +1,331 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::manifest::{write_manifest, ManifestFile};
+use crate::output::{self, status};
+use crate::util::{build_thread_pool, participant_hasher, REFERENCE_COLUMNS};
+use crate::{GlobalArgs, SimulateCohortArgs};
+use biosynth_core::overlay::OverlayDocument;
+use biosynth_core::synthetic::{overlay_specs, write_single_file, Sex};
+
+const SUPPORTED_FORMATS: &[&str] = &["dynamic_dna"];
+const MANIFEST_FILENAME: &str = "manifest.json";
+
+/// Cohort description read from `--spec`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CohortSpec {
+    /// Number of participants to generate.
+    size: usize,
+    /// Fraction of participants that are male (0-1).
+    #[serde(default = "default_sex_ratio")]
+    sex_ratio: f64,
+    /// Population labels with sampling weights and per-population ALT frequency.
+    #[serde(default)]
+    populations: Vec<PopulationSpec>,
+    /// Output formats assigned round-robin across participants.
+    #[serde(default = "default_formats")]
+    formats: Vec<String>,
+    /// Overlay variants file, resolved relative to the spec file.
+    #[serde(default)]
+    overlays: Option<PathBuf>,
+    /// ALT frequency used when a population does not set its own.
+    #[serde(default = "default_alt_frequency")]
+    alt_frequency: f64,
+    /// Base RNG seed; one is drawn and recorded in the manifest when omitted.
+    #[serde(default)]
+    seed: Option<u64>,
+    /// Real participant identifiers the cohort stands in for, one per participant. Only their
+    /// pseudonyms are written, as participant IDs and in the manifest's copy of the spec.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    participant_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PopulationSpec {
+    name: String,
+    #[serde(default = "default_weight")]
+    weight: f64,
+    #[serde(default)]
+    alt_frequency: Option<f64>,
+}
+
+fn default_sex_ratio() -> f64 {
+    0.5
+}
+
+fn default_formats() -> Vec<String> {
+    vec![SUPPORTED_FORMATS[0].to_string()]
+}
+
+fn default_alt_frequency() -> f64 {
+    0.01
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Serialize)]
+struct CohortManifest {
+    generated_at: String,
+    sqlite: PathBuf,
+    /// Release the reference was fetched at, if recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reference_version: Option<String>,
+    reference_rows: usize,
+    seed: u64,
+    spec: CohortSpec,
+    /// Pseudonyms of the real participants overlay groups were taken from.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    overlay_participants: Vec<String>,
+    files: Vec<ParticipantEntry>,
+}
+
+/// `--output-format json` result: the manifest plus where it was written.
+#[derive(Debug, Serialize)]
+struct CohortOutput<'a> {
+    manifest_path: &'a Path,
+    #[serde(flatten)]
+    manifest: &'a CohortManifest,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ParticipantEntry {
+    id: String,
+    sex: Sex,
+    population: Option<String>,
+    format: String,
+    seed: u64,
+    alt_frequency: f64,
+    #[serde(flatten)]
+    file: ManifestFile,
+}
+
+pub fn run_simulate_cohort(args: SimulateCohortArgs, global: &GlobalArgs) -> Result<()> {
+    let raw_spec = std::fs::read_to_string(&args.spec)
+        .with_context(|| format!("Read cohort spec {:?}", args.spec))?;
+    let mut spec: CohortSpec = serde_json::from_str(&raw_spec)
+        .with_context(|| format!("Parse cohort spec {:?}", args.spec))?;
+    validate_spec(&spec)?;
+
+    let overlay_document = match &spec.overlays {
+        Some(path) => {
+            let resolved = resolve_relative(&args.spec, path);
+            let raw = std::fs::read_to_string(&resolved)
+                .with_context(|| format!("Read variants JSON file {:?}", resolved))?;
+            OverlayDocument::parse(&raw)?
+        }
+        None => OverlayDocument::default(),
+    };
+    let overlays = overlay_specs(&overlay_document)?;
+
+    // Real participant IDs are replaced before anything is planned or written.
+    let overlay_ids = overlay_document.participant_ids();
+    let mut overlay_participants = Vec::new();
+    if spec.participant_ids.is_some() || !overlay_ids.is_empty() {
+        let hasher = participant_hasher(global, args.id_lookup.clone())?;
+        if let Some(ids) = &spec.participant_ids {
+            spec.participant_ids = Some(hasher.pseudonymize(ids)?);
+        }
+        overlay_participants = hasher.pseudonymize(&overlay_ids)?;
+    }
+
+    if let Some(export_policy) = global.export_policy()? {
+        export_policy.require("simulate-cohort", REFERENCE_COLUMNS)?;
+    }
+
+    let reference = global.reference_db(args.sqlite.as_ref())?;
+    let sqlite_path = reference.path;
+    let store = global.stats_backend(&sqlite_path)?;
+    let references = store.all_references(args.limit)?;
+    if references.is_empty() {
+        bail!(
+            "No reference rows found in {}",
+            sqlite_path.to_string_lossy()
+        );
+    }
+    status!(
+        global,
+        "📚 Reference check passed: {} rows in {}",
+        references.len(),
+        sqlite_path.display()
+    );
+
+    let seed = *spec
+        .seed
+        .get_or_insert_with(|| StdRng::from_entropy().gen());
+    let participants = plan_participants(&spec, &args.output_dir, seed);
+
+    std::fs::create_dir_all(&args.output_dir)
+        .with_context(|| format!("Create directory {:?}", args.output_dir))?;
+
+    let pool = build_thread_pool(global.threads)?;
+
+    let participants = pool.install(|| {
+        participants
+            .into_par_iter()
+            .map(|mut entry| {
+                let rows = write_single_file(
+                    &entry.file.path,
+                    &references,
+                    &overlays,
+                    entry.alt_frequency,
+                    Some(entry.seed),
+                    Some(entry.sex),
+                )?;
+                entry.file = ManifestFile::record(&entry.file.path, rows)?;
+                Ok(entry)
+            })
+            .collect::<Result<Vec<_>>>()
+    })?;
+
+    let manifest = CohortManifest {
+        generated_at: Utc::now().to_rfc3339(),
+        sqlite: sqlite_path,
+        reference_version: reference.version,
+        reference_rows: references.len(),
+        seed,
+        spec,
+        overlay_participants,
+        files: participants,
+    };
+    let manifest_path = args.output_dir.join(MANIFEST_FILENAME);
+    write_manifest(&manifest_path, &manifest)?;
+
+    let total_rows: usize = manifest.files.iter().map(|entry| entry.file.rows).sum();
+    status!(
+        global,
+        "🧪 Simulated cohort of {} participants ({} total rows)",
+        manifest.files.len(),
+        total_rows
+    );
+    status!(global, "📝 Manifest written to {}", manifest_path.display());
+    output::emit(
+        global,
+        "simulate-cohort",
+        &CohortOutput {
+            manifest_path: &manifest_path,
+            manifest: &manifest,
+        },
+    )
+}
+
+fn validate_spec(spec: &CohortSpec) -> Result<()> {
+    if spec.size == 0 {
+        bail!("Cohort size must be at least 1");
+    }
+    if !(0.0..=1.0).contains(&spec.sex_ratio) {
+        bail!("sex_ratio must be between 0 and 1");
+    }
+    if !(0.0..=1.0).contains(&spec.alt_frequency) {
+        bail!("alt_frequency must be between 0 and 1");
+    }
+    for population in &spec.populations {
+        if population.weight <= 0.0 {
+            bail!("Population {} must have a positive weight", population.name);
+        }
+        if let Some(freq) = population.alt_frequency {
+            if !(0.0..=1.0).contains(&freq) {
+                bail!(
+                    "Population {} alt_frequency must be between 0 and 1",
+                    population.name
+                );
+            }
+        }
+    }
+    if spec.formats.is_empty() {
+        bail!("Cohort spec must list at least one format");
+    }
+    if let Some(ids) = &spec.participant_ids {
+        if ids.len() != spec.size {
+            bail!(
+                "participant_ids lists {} IDs for a cohort of size {}",
+                ids.len(),
+                spec.size
+            );
+        }
+        if ids.iter().any(|id| id.trim().is_empty()) {
+            bail!("participant_ids must not contain empty IDs");
+        }
+    }
+    for format in &spec.formats {
+        if !SUPPORTED_FORMATS.contains(&format.as_str()) {
+            bail!(
+                "Unsupported format {} (supported: {})",
+                format,
+                SUPPORTED_FORMATS.join(", ")
+            );
+        }
+    }
+    Ok(())
+}
+
+fn plan_participants(spec: &CohortSpec, output_dir: &Path, seed: u64) -> Vec<ParticipantEntry> {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let males = (spec.size as f64 * spec.sex_ratio).round() as usize;
+    let mut sexes: Vec<Sex> = (0..spec.size)
+        .map(|idx| if idx < males { Sex::Male } else { Sex::Female })
+        .collect();
+    sexes.shuffle(&mut rng);
+
+    let total_weight: f64 = spec.populations.iter().map(|p| p.weight).sum();
+
+    sexes
+        .into_iter()
+        .enumerate()
+        .map(|(idx, sex)| {
+            let population = if spec.populations.is_empty() {
+                None
+            } else {
+                let mut draw = rng.gen_range(0.0..total_weight);
+                let mut chosen = &spec.populations[spec.populations.len() - 1];
+                for candidate in &spec.populations {
+                    if draw < candidate.weight {
+                        chosen = candidate;
+                        break;
+                    }
+                    draw -= candidate.weight;
+                }
+                Some(chosen)
+            };
+            let format = spec.formats[idx % spec.formats.len()].clone();
+            let id = match &spec.participant_ids {
+                Some(ids) => ids[idx].clone(),
+                None => format!("P{:05}", idx + 1),
+            };
+            ParticipantEntry {
+                file: ManifestFile {
+                    path: output_dir.join(format!("{}_{}.txt", id, format)),
+                    rows: 0,
+                    sha256: String::new(),
+                },
+                id,
+                sex,
+                population: population.map(|p| p.name.clone()),
+                format,
+                seed: seed.wrapping_add(idx as u64 + 1),
+                alt_frequency: population
+                    .and_then(|p| p.alt_frequency)
+                    .unwrap_or(spec.alt_frequency),
+            }
+        })
+        .collect()
+}
+
+fn resolve_relative(spec_path: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    spec_path
+        .parent()
+        .map(|parent| parent.join(path))
+        .unwrap_or_else(|| path.to_path_buf())
+}
@@ -1,49 +1,26 @@
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::collections::HashSet;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::{anyhow, bail, Context, Result};
-use chrono::NaiveDate;
+use biosynth_core::formats::FormatRegistry;
+use biosynth_core::overlay::OverlayDocument;
+use biosynth_core::privacy::DpAccounting;
+use biosynth_core::stats::{ReferenceVariant, StatsBackend};
+use biosynth_core::synthetic::{overlay_specs, AlleleFrequencies, OverlaySpec, SyntheticGenerator};
+use chrono::{NaiveDate, Utc};
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
 use rayon::prelude::*;
-use rayon::ThreadPoolBuilder;
-use serde::Deserialize;
+use serde::Serialize;
 
-use crate::download::ensure_reference_db;
-use crate::stats::{ReferenceVariant, StatsStore};
-use crate::SyntheticArgs;
+use crate::manifest::{write_manifest, ManifestFile};
+use crate::output::{self, status, OutputFormat};
+use crate::progress::{Progress, ProgressEvent};
+use crate::util::{build_thread_pool, participant_hasher, REFERENCE_COLUMNS};
+use crate::{GlobalArgs, SyntheticArgs};
 
-const HEADER_TEXT: &str = r#"# This data file generated by Dynamic DNA (DDNA) Laboratories at: Thu Nov 7 16:03:14 2024
-#						
-# This file contains raw genetic data, including data that is not used in DDNA reports. 						
-# This data has undergone a general quality review however only a subset of markers have been 						
-# individually reviewed for accuracy. As such, this data is suitable only for research,  						
-# educational, and informational use and not for any medical or diagnostic use. 						
-# 						
-# Below is a text version of your data.  Fields are TAB-separated and						
-# each line corresponds to a single SNP.  For each SNP, we provide its identifier,  						
-# it chromosomal location realtive to Build 38 of the human reference genome, and the 						
-# genotype call oriented with respect to the plus strand on the human reference sequence.						
-# More information about Dynamic DNA Laboratories can be found at:						
-# https://dynamicdnalabs.com						
-# 						
-# More information on reference human assembly builds:						
-# https://www.ncbi.nlm.nih.gov/datasets/genome/GCF_000001405.40/						
-#						
-# rsid	chromosome	position	genotype	gs	baf	lrr
-"#;
-
-enum VariantKind {
-    Snp,
-    Mnv,
-    Insertion,
-    Deletion,
-}
-
-pub fn run_synthetic(args: SyntheticArgs) -> Result<()> {
+pub fn run_synthetic(args: SyntheticArgs, global: &GlobalArgs) -> Result<()> {
     if !(0.0..=1.0).contains(&args.alt_frequency) {
         bail!("--alt-frequency must be between 0 and 1");
     }
@@ -65,137 +42,239 @@ pub fn run_synthetic(args: SyntheticArgs) -> Result<()> {
         bail!("Day range must be between 1 and 31");
     }
 
-    let sqlite_path = ensure_reference_db(Some(&args.sqlite))?;
-    let store = StatsStore::connect(&sqlite_path)?;
-    let references = store.all_references(args.limit)?;
-    if references.is_empty() {
+    let registry = FormatRegistry::new();
+    let format = registry.get(&args.format).ok_or_else(|| {
+        anyhow!(
+            "Unknown --format {:?} (available: {})",
+            args.format,
+            registry.names().collect::<Vec<_>>().join(", ")
+        )
+    })?;
+
+    if let Some(export_policy) = global.export_policy()? {
+        export_policy.require("synthetic", REFERENCE_COLUMNS)?;
+        if args.dp_epsilon.is_some() {
+            export_policy.require(
+                "synthetic --dp-epsilon",
+                &[
+                    ("allele_observations", Some("allele")),
+                    ("allele_observations", Some("copies")),
+                ],
+            )?;
+            export_policy.check_noise("synthetic --dp-epsilon", args.dp_epsilon)?;
+        }
+    }
+
+    let reference = global.reference_db(args.sqlite.as_ref())?;
+    let sqlite_path = reference.path;
+    let store: Arc<dyn StatsBackend> = global.stats_backend(&sqlite_path)?.into();
+    let references = if args.stream_references {
+        None
+    } else {
+        Some(store.all_references(args.limit)?)
+    };
+    let has_references = match &references {
+        Some(references) => !references.is_empty(),
+        None => store.for_each_reference_chunk(Some(1), 1, &mut |_| Ok(()))? > 0,
+    };
+    if !has_references {
         bail!(
             "No reference rows found in {}",
             sqlite_path.to_string_lossy()
         );
     }
-    let references = Arc::new(references);
-
-    let overlays = load_overlay_specs(&args)?
-        .map(Arc::new)
-        .unwrap_or_else(|| Arc::new(Vec::new()));
+    let dp = match args.dp_epsilon {
+        Some(epsilon) => {
+            let mut store = global.stats_store(&sqlite_path)?;
+            if !args.consent_tags.is_empty() {
+                status!(
+                    global,
+                    "🪪 Using only files consented as {}",
+                    args.consent_tags.join(", ")
+                );
+                store = store.with_consent_filter(args.consent_tags.clone());
+            }
+            let copies = store.allele_copies()?;
+            if copies.is_empty() {
+                bail!("--dp-epsilon needs observations recorded by `bvs genostats`");
+            }
+            // Seeding the noise would let anyone with the seed subtract it again.
+            let (frequencies, accounting) = AlleleFrequencies::differentially_private(
+                references
+                    .as_deref()
+                    .expect("clap rejects --stream-references with --dp-epsilon"),
+                &copies,
+                epsilon,
+                &mut StdRng::from_entropy(),
+            )?;
+            status!(
+                global,
+                "🔐 DP frequencies for {} SNPs (ε = {} total, {:.3e} per variant, Laplace scale {:.1})",
+                accounting.variants,
+                accounting.epsilon,
+                accounting.per_variant_epsilon,
+                accounting.scale
+            );
+            Some((Arc::new(frequencies), accounting))
+        }
+        None => None,
+    };
+    let references: Option<Arc<[ReferenceVariant]>> = references.map(Into::into);
+    let overlay_document = load_overlay_document(&args)?.unwrap_or_default();
+    let overlays: Arc<[OverlaySpec]> = overlay_specs(&overlay_document)?.into();
+    // Real participant IDs never reach the manifest, only their pseudonyms.
+    let overlay_participants = match overlay_document.participant_ids() {
+        ids if ids.is_empty() => Vec::new(),
+        ids => participant_hasher(global, args.id_lookup.clone())?.pseudonymize(&ids)?,
+    };
 
-    let plans = build_file_plans(&output_template, &args)?;
+    let policy = args.overwrite.policy();
+    let mut plans = Vec::new();
+    let mut skipped = Vec::new();
+    for plan in build_file_plans(&output_template, &args)? {
+        if policy.should_write(&plan.path)? {
+            plans.push(plan);
+        } else {
+            skipped.push(plan.path);
+        }
+    }
 
-    let thread_count = args
-        .threads
-        .unwrap_or_else(|| rayon::current_num_threads().max(1));
-    let pool = ThreadPoolBuilder::new()
-        .num_threads(thread_count)
-        .build()
-        .context("build synthetic thread pool")?;
+    let pool = build_thread_pool(global.threads)?;
+
+    let progress = Progress::start(
+        global,
+        "synthetic",
+        plans.len() + skipped.len(),
+        pool.current_num_threads(),
+        false,
+    )?;
+    for path in &skipped {
+        progress.emit(ProgressEvent::Skipped { path: path.clone() });
+    }
 
-    let results: Vec<usize> = pool.install(|| {
+    let results = pool.install(|| {
         plans
             .par_iter()
             .map(|plan| {
-                write_single_file(
-                    &plan.path,
-                    references.as_ref(),
-                    overlays.as_ref(),
-                    args.alt_frequency,
-                    plan.seed,
-                )
+                progress.emit(ProgressEvent::Started {
+                    path: plan.path.clone(),
+                });
+                let builder = match &references {
+                    Some(references) => {
+                        SyntheticGenerator::builder().references(references.clone())
+                    }
+                    None => {
+                        SyntheticGenerator::builder().stream_references(store.clone(), args.limit)
+                    }
+                };
+                let mut builder = builder
+                    .overlays(overlays.clone())
+                    .format(format.clone())
+                    .alt_frequency(args.alt_frequency);
+                if let Some((frequencies, _)) = &dp {
+                    builder = builder.allele_frequencies(frequencies.clone());
+                }
+                if let Some(seed) = plan.seed {
+                    builder = builder.seed(seed);
+                }
+                if let Some(threads) = args.chunk_threads {
+                    builder = builder.chunk_threads(threads);
+                }
+                let result = builder
+                    .build()
+                    .and_then(|generator| generator.write_file(&plan.path));
+                progress.emit(match &result {
+                    Ok(rows) => ProgressEvent::Finished {
+                        path: plan.path.clone(),
+                        rows: *rows,
+                    },
+                    Err(err) => ProgressEvent::Failed {
+                        path: plan.path.clone(),
+                        error: err.to_string(),
+                    },
+                });
+                result
             })
-            .collect::<Result<Vec<_>>>()
-    })?;
+            .collect::<biosynth_core::Result<Vec<_>>>()
+    });
+    progress.finish("synthetic generation complete")?;
+    let results = results?;
 
     let total_rows: usize = results.iter().sum();
-    println!(
+    status!(
+        global,
         "🧪 Generated {} file(s), {} total rows (alt freq {:.2}%)",
         results.len(),
         total_rows,
         args.alt_frequency * 100.0
     );
-    Ok(())
-}
-
-fn write_single_file(
-    path: &PathBuf,
-    references: &[ReferenceVariant],
-    overlays: &[OverlaySpec],
-    alt_frequency: f64,
-    seed: Option<u64>,
-) -> Result<usize> {
-    if let Some(parent) = path.parent() {
-        if !parent.as_os_str().is_empty() {
-            std::fs::create_dir_all(parent)
-                .with_context(|| format!("Create directory {:?}", parent))?;
-        }
+    if !skipped.is_empty() {
+        status!(global, "⏭️  Skipped {} existing file(s)", skipped.len());
     }
-    let mut rng = match seed {
-        Some(seed) => StdRng::seed_from_u64(seed),
-        None => StdRng::from_entropy(),
-    };
-
-    let mut overlay_assignments = prepare_overlay_assignments(overlays, &mut rng)?;
 
-    let file = File::create(path).with_context(|| format!("Create {:?}", path))?;
-    let mut writer = BufWriter::new(file);
-    writer
-        .write_all(HEADER_TEXT.as_bytes())
-        .context("write header")?;
-
-    let mut written = 0usize;
-    for reference in references {
-        if let Some(assignment) = overlay_assignments.remove(&reference.rsid) {
-            write_overlay_row(&mut writer, &assignment, &mut rng)?;
-            written += 1;
-            continue;
-        }
-
-        let genotype = synthesize_genotype(reference, alt_frequency, &mut rng);
-        let gs = rng.gen_range(0.2..=1.0);
-        let baf = rng.gen_range(0.0..=1.0);
-        let lrr = rng.gen_range(-0.5..=0.5);
-        let rsid_label = format!("rs{}", reference.rsid);
-        writeln!(
-            writer,
-            "{}\t{}\t{}\t{}\t{:.4}\t{:.3}\t{:.4}",
-            rsid_label, reference.chromosome, reference.position, genotype, gs, baf, lrr
-        )
-        .with_context(|| format!("write row for rs{}", reference.rsid))?;
-        written += 1;
+    let json_output = global.output_format == OutputFormat::Json;
+    if args.manifest.is_none() && !json_output {
+        return Ok(());
     }
-
-    for assignment in overlay_assignments.into_values() {
-        write_overlay_row(&mut writer, &assignment, &mut rng)?;
-        written += 1;
+    let files = plans
+        .iter()
+        .zip(&results)
+        .map(|(plan, rows)| {
+            Ok(SyntheticManifestEntry {
+                seed: plan.seed,
+                file: ManifestFile::record(&plan.path, *rows)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let manifest = SyntheticManifest {
+        generated_at: Utc::now().to_rfc3339(),
+        sqlite: sqlite_path,
+        reference_version: reference.version,
+        alt_frequency: args.alt_frequency,
+        dp: dp.map(|(_, accounting)| accounting),
+        consent_tags: args.consent_tags,
+        overlay_participants,
+        files,
+        skipped,
+    };
+    if let Some(manifest_path) = &args.manifest {
+        write_manifest(manifest_path, &manifest)?;
+        status!(global, "📝 Manifest written to {}", manifest_path.display());
     }
+    output::emit(global, "synthetic", &manifest)
+}
 
-    writer.flush()?;
-    Ok(written)
+#[derive(Debug, Serialize)]
+struct SyntheticManifest {
+    generated_at: String,
+    sqlite: PathBuf,
+    /// Release the reference was fetched at, if recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reference_version: Option<String>,
+    alt_frequency: f64,
+    /// Privacy accounting when generated with `--dp-epsilon`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dp: Option<DpAccounting>,
+    /// Consent tags the observed frequencies were restricted to.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    consent_tags: Vec<String>,
+    /// Pseudonyms of the real participants overlay groups were taken from.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    overlay_participants: Vec<String>,
+    files: Vec<SyntheticManifestEntry>,
+    /// Outputs left in place by `--no-clobber`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    skipped: Vec<PathBuf>,
 }
 
-fn write_overlay_row(
-    writer: &mut BufWriter<File>,
-    assignment: &OverlayAssignment,
-    rng: &mut StdRng,
-) -> Result<()> {
-    let gs = rng.gen_range(0.2..=1.0);
-    let baf = rng.gen_range(0.0..=1.0);
-    let lrr = rng.gen_range(-0.5..=0.5);
-    let rsid_label = format!("rs{}", assignment.spec.rsid);
-    writeln!(
-        writer,
-        "{}\t{}\t{}\t{}\t{:.4}\t{:.3}\t{:.4}",
-        rsid_label,
-        assignment.spec.chromosome,
-        assignment.spec.position,
-        assignment.genotype,
-        gs,
-        baf,
-        lrr
-    )
-    .with_context(|| format!("write overlay row for {}", assignment.spec.rsid))
+#[derive(Debug, Serialize)]
+struct SyntheticManifestEntry {
+    seed: Option<u64>,
+    #[serde(flatten)]
+    file: ManifestFile,
 }
 
-fn load_overlay_specs(args: &SyntheticArgs) -> Result<Option<Vec<OverlaySpec>>> {
+fn load_overlay_document(args: &SyntheticArgs) -> Result<Option<OverlayDocument>> {
     let json_source = match (&args.variants_file, &args.variants_json) {
         (Some(_), Some(_)) => {
             bail!("Use only one of --variants-file or --variants-json");
@@ -212,94 +291,7 @@ fn load_overlay_specs(args: &SyntheticArgs) -> Result<Option<Vec<OverlaySpec>>>
         return Ok(None);
     };
 
-    let root: OverlayRoot =
-        serde_json::from_str(&raw_json).context("Parse variants JSON payload")?;
-    let mut specs = Vec::new();
-    for group in root.groups.values() {
-        for variant in &group.variants {
-            specs.push(OverlaySpec::from_raw(variant)?);
-        }
-    }
-    Ok(Some(specs))
-}
-
-fn prepare_overlay_assignments(
-    overlays: &[OverlaySpec],
-    rng: &mut StdRng,
-) -> Result<HashMap<i64, OverlayAssignment>> {
-    let mut assignments: HashMap<i64, OverlayAssignment> = HashMap::new();
-    for spec in overlays {
-        let genotype = spec.random_genotype(rng)?;
-        if assignments
-            .insert(
-                spec.rsid,
-                OverlayAssignment {
-                    spec: spec.clone(),
-                    genotype,
-                },
-            )
-            .is_some()
-        {
-            bail!("Duplicate overlay rsid detected: {}", spec.rsid);
-        }
-    }
-    Ok(assignments)
-}
-
-#[derive(Debug, Clone)]
-struct OverlaySpec {
-    rsid: i64,
-    chromosome: String,
-    position: i64,
-    genotype_options: Vec<String>,
-}
-
-impl OverlaySpec {
-    fn from_raw(raw: &OverlayVariantRaw) -> Result<Self> {
-        let rsid = raw
-            .rsid
-            .trim()
-            .trim_start_matches("rs")
-            .parse::<i64>()
-            .with_context(|| format!("parse overlay rsid {}", raw.rsid))?;
-        let genotype_options = if let Some(options) = &raw.genotypes {
-            if options.is_empty() {
-                bail!("Variant {} has empty genotype list", raw.rsid);
-            }
-            options.clone()
-        } else if let Some(reference) = &raw.reference {
-            let alternates = raw
-                .alternates
-                .clone()
-                .unwrap_or_else(|| vec![reference.clone()]);
-            generate_genotype_combinations(reference, &alternates)
-        } else {
-            bail!(
-                "Variant {} must specify either genotypes or reference/alternates",
-                raw.rsid
-            );
-        };
-
-        Ok(Self {
-            rsid,
-            chromosome: raw.chromosome.clone(),
-            position: raw.position,
-            genotype_options,
-        })
-    }
-
-    fn random_genotype(&self, rng: &mut StdRng) -> Result<String> {
-        if self.genotype_options.is_empty() {
-            bail!("Variant {} has no genotype options", self.rsid);
-        }
-        let idx = rng.gen_range(0..self.genotype_options.len());
-        Ok(self.genotype_options[idx].clone())
-    }
-}
-
-struct OverlayAssignment {
-    spec: OverlaySpec,
-    genotype: String,
+    Ok(Some(OverlayDocument::parse(&raw_json)?))
 }
 
 #[derive(Debug, Clone)]
@@ -407,111 +399,3 @@ fn fill_output_template(
     }
     result
 }
-
-#[derive(Debug, Deserialize)]
-struct OverlayRoot {
-    #[serde(flatten)]
-    groups: HashMap<String, OverlayGroup>,
-}
-
-#[derive(Debug, Deserialize)]
-struct OverlayGroup {
-    variants: Vec<OverlayVariantRaw>,
-}
-
-#[derive(Debug, Deserialize)]
-struct OverlayVariantRaw {
-    rsid: String,
-    chromosome: String,
-    position: i64,
-    #[serde(default)]
-    genotypes: Option<Vec<String>>,
-    reference: Option<String>,
-    alternates: Option<Vec<String>>,
-}
-
-fn generate_genotype_combinations(reference: &str, alternates: &[String]) -> Vec<String> {
-    let mut alleles = Vec::new();
-    alleles.push(reference.to_string());
-    for alt in alternates {
-        if !alt.is_empty() {
-            alleles.push(alt.clone());
-        }
-    }
-    alleles.sort();
-    alleles.dedup();
-
-    let mut combos = Vec::new();
-    for i in 0..alleles.len() {
-        for j in i..alleles.len() {
-            let combo = format!("{}{}", alleles[i], alleles[j]);
-            combos.push(combo);
-        }
-    }
-    combos
-}
-
-fn synthesize_genotype(
-    reference: &ReferenceVariant,
-    alt_frequency: f64,
-    rng: &mut StdRng,
-) -> String {
-    let alt_list = reference
-        .alternates
-        .split(',')
-        .map(|alt| alt.trim())
-        .filter(|alt| !alt.is_empty())
-        .collect::<Vec<_>>();
-    let kind = determine_variant_kind(reference, &alt_list);
-    let use_alt = !alt_list.is_empty() && rng.gen::<f64>() < alt_frequency;
-
-    match kind {
-        VariantKind::Snp | VariantKind::Mnv => {
-            let allele = if use_alt {
-                alt_list[rng.gen_range(0..alt_list.len())]
-            } else {
-                reference.reference.as_str()
-            };
-            let symbol = match kind {
-                VariantKind::Snp => allele.to_string(),
-                VariantKind::Mnv => allele
-                    .chars()
-                    .next()
-                    .map(|c| c.to_string())
-                    .unwrap_or_else(|| "?".into()),
-                _ => unreachable!(),
-            };
-            format!("{symbol}{symbol}")
-        }
-        VariantKind::Insertion | VariantKind::Deletion => {
-            let (ref_symbol, alt_symbol) = match kind {
-                VariantKind::Insertion => ("D", "I"),
-                VariantKind::Deletion => ("I", "D"),
-                _ => unreachable!(),
-            };
-            let symbol = if use_alt { alt_symbol } else { ref_symbol };
-            format!("{symbol}{symbol}")
-        }
-    }
-}
-
-fn determine_variant_kind(reference: &ReferenceVariant, alts: &[&str]) -> VariantKind {
-    let ref_len = reference.reference.len();
-    if alts.is_empty() {
-        return VariantKind::Snp;
-    }
-    if ref_len == 1 && alts.iter().all(|alt| alt.len() == 1) {
-        return VariantKind::Snp;
-    }
-    let first_alt_len = alts[0].len();
-    let all_alt_same_len = alts.iter().all(|alt| alt.len() == ref_len);
-    if all_alt_same_len && ref_len > 1 {
-        VariantKind::Mnv
-    } else if first_alt_len > ref_len {
-        VariantKind::Insertion
-    } else if first_alt_len < ref_len {
-        VariantKind::Deletion
-    } else {
-        VariantKind::Mnv
-    }
-}
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+use crate::output::{self, status};
+use crate::{GlobalArgs, ValidateArgs};
+use biosynth_core::buffers::{is_stdin, stdin_reader};
+use biosynth_core::vcf::{validate_vcf, validate_vcf_file, VcfValidation};
+
+/// `--output-format json` result.
+#[derive(Debug, Serialize)]
+struct ValidateOutput {
+    files: Vec<FileValidation>,
+}
+
+#[derive(Debug, Serialize)]
+struct FileValidation {
+    path: PathBuf,
+    #[serde(flatten)]
+    validation: VcfValidation,
+}
+
+pub fn run_validate(args: ValidateArgs, global: &GlobalArgs) -> Result<()> {
+    let mut files = Vec::with_capacity(args.vcf.len());
+    for path in args.vcf {
+        let validation = if is_stdin(&path) {
+            validate_vcf(stdin_reader().context("Read standard input")?)?
+        } else {
+            validate_vcf_file(&path)?
+        };
+        if validation.is_valid() {
+            status!(
+                global,
+                "✅ {}: {} records, {} samples",
+                path.display(),
+                validation.records,
+                validation.samples
+            );
+        } else {
+            eprintln!(
+                "❌ {}: {} problems",
+                path.display(),
+                validation.total_problems
+            );
+            for problem in &validation.problems {
+                eprintln!("   - line {}: {}", problem.line, problem.message);
+            }
+            let unlisted = validation.total_problems - validation.problems.len();
+            if unlisted > 0 {
+                eprintln!("   - … and {} more", unlisted);
+            }
+        }
+        files.push(FileValidation { path, validation });
+    }
+
+    let total = files.len();
+    let failed = files
+        .iter()
+        .filter(|file| !file.validation.is_valid())
+        .count();
+    output::emit(global, "validate", &ValidateOutput { files })?;
+    if failed > 0 {
+        bail!("{} of {} VCFs failed validation", failed, total);
+    }
+    Ok(())
+}
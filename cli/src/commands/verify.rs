@@ -0,0 +1,160 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Result};
+use rand::seq::index::sample;
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::manifest::{read_manifest_files, sha256_file, ManifestFile};
+use crate::output::{self, status};
+use crate::util::build_thread_pool;
+use crate::{GlobalArgs, VerifyArgs};
+use biosynth_core::genotype::{process_file_with_options, ParseOptions};
+
+enum FileStatus {
+    Ok,
+    Missing,
+    ChecksumMismatch { actual: String },
+    RowCountMismatch { actual: usize },
+    Unreadable(String),
+}
+
+/// `--output-format json` result.
+#[derive(Debug, Serialize)]
+struct VerifyOutput {
+    files: usize,
+    spot_checked: usize,
+    problems: Vec<VerifyProblem>,
+}
+
+#[derive(Debug, Serialize)]
+struct VerifyProblem {
+    path: PathBuf,
+    problem: String,
+}
+
+pub fn run_verify(args: VerifyArgs, global: &GlobalArgs) -> Result<()> {
+    let manifest = read_manifest_files(&args.manifest)?;
+    if manifest.files.is_empty() {
+        bail!("Manifest {:?} lists no files", args.manifest);
+    }
+
+    let total = manifest.files.len();
+    let spot_count = if args.spot_check == 0 {
+        total
+    } else {
+        args.spot_check.min(total)
+    };
+    let spot_checked: HashSet<usize> = sample(&mut rand::thread_rng(), total, spot_count)
+        .into_iter()
+        .collect();
+
+    let pool = build_thread_pool(global.threads)?;
+    let options = global.parse_options();
+    let statuses: Vec<FileStatus> = pool.install(|| {
+        manifest
+            .files
+            .par_iter()
+            .enumerate()
+            .map(|(idx, entry)| {
+                let path = resolve_path(&args.manifest, &entry.path);
+                verify_file(&path, entry, spot_checked.contains(&idx), &options)
+            })
+            .collect()
+    });
+
+    let mut problems = Vec::new();
+    for (entry, status) in manifest.files.iter().zip(&statuses) {
+        let message = match status {
+            FileStatus::Ok => continue,
+            FileStatus::Missing => "missing".to_string(),
+            FileStatus::ChecksumMismatch { actual } => {
+                format!(
+                    "checksum mismatch (expected {}, got {})",
+                    entry.sha256, actual
+                )
+            }
+            FileStatus::RowCountMismatch { actual } => {
+                format!(
+                    "row count mismatch (expected {}, got {})",
+                    entry.rows, actual
+                )
+            }
+            FileStatus::Unreadable(err) => format!("unreadable: {}", err),
+        };
+        eprintln!("   - {}: {}", entry.path.display(), message);
+        problems.push(VerifyProblem {
+            path: entry.path.clone(),
+            problem: message,
+        });
+    }
+
+    let failed = problems.len();
+    output::emit(
+        global,
+        "verify",
+        &VerifyOutput {
+            files: total,
+            spot_checked: spot_count,
+            problems,
+        },
+    )?;
+    if failed > 0 {
+        bail!("{} of {} files failed verification", failed, total);
+    }
+    status!(
+        global,
+        "✅ Verified {} files ({} re-parsed for row counts)",
+        total,
+        spot_count
+    );
+    Ok(())
+}
+
+fn verify_file(
+    path: &Path,
+    entry: &ManifestFile,
+    parse: bool,
+    options: &ParseOptions,
+) -> FileStatus {
+    if !path.exists() {
+        return FileStatus::Missing;
+    }
+    match sha256_file(path) {
+        Ok(actual) if actual != entry.sha256 => return FileStatus::ChecksumMismatch { actual },
+        Ok(_) => {}
+        Err(err) => return FileStatus::Unreadable(err.to_string()),
+    }
+    if parse {
+        match process_file_with_options(path, options, |_, _| Ok(())) {
+            Ok(parsed) if parsed.summary.variant_count != entry.rows => {
+                return FileStatus::RowCountMismatch {
+                    actual: parsed.summary.variant_count,
+                }
+            }
+            Ok(_) => {}
+            Err(err) => return FileStatus::Unreadable(err.to_string()),
+        }
+    }
+    FileStatus::Ok
+}
+
+/// Manifest paths are recorded as given on the command line. When a relative path does not
+/// resolve from the current directory, look for it next to the manifest instead.
+fn resolve_path(manifest: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() || path.exists() {
+        return path.to_path_buf();
+    }
+    let Some(dir) = manifest.parent() else {
+        return path.to_path_buf();
+    };
+    [
+        Some(dir.join(path)),
+        path.file_name().map(|name| dir.join(name)),
+    ]
+    .into_iter()
+    .flatten()
+    .find(|candidate| candidate.exists())
+    .unwrap_or_else(|| path.to_path_buf())
+}
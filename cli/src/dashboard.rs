@@ -0,0 +1,267 @@
+use std::collections::VecDeque;
+use std::io::{self, Stdout};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::cursor::{Hide, Show};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::widgets::{Block, Borders, Gauge, List, ListItem, Paragraph, Row, Table};
+use ratatui::{Frame, Terminal};
+
+use crate::progress::ProgressEvent;
+
+const REFRESH_INTERVAL: Duration = Duration::from_millis(200);
+const MAX_RECENT_ERRORS: usize = 8;
+
+/// Full-screen terminal dashboard rendered on a background thread while workers run.
+pub struct Dashboard {
+    state: Arc<Mutex<DashboardState>>,
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<io::Result<()>>,
+}
+
+struct DashboardState {
+    title: String,
+    total: usize,
+    completed: usize,
+    skipped: usize,
+    errors: usize,
+    rows: usize,
+    started: Instant,
+    workers: Vec<WorkerStatus>,
+    recent_errors: VecDeque<String>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct WorkerStatus {
+    current: Option<String>,
+    last: Option<String>,
+    files: usize,
+    rows: usize,
+}
+
+impl Dashboard {
+    pub fn start(title: &str, total: usize, workers: usize) -> Result<Self> {
+        let state = Arc::new(Mutex::new(DashboardState {
+            title: title.to_string(),
+            total,
+            completed: 0,
+            skipped: 0,
+            errors: 0,
+            rows: 0,
+            started: Instant::now(),
+            workers: vec![WorkerStatus::default(); workers.max(1)],
+            recent_errors: VecDeque::new(),
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen, Hide).context("enter dashboard screen")?;
+        let terminal =
+            Terminal::new(CrosstermBackend::new(stdout)).context("create dashboard terminal")?;
+
+        let handle = {
+            let state = state.clone();
+            let stop = stop.clone();
+            std::thread::spawn(move || render_loop(terminal, &state, &stop))
+        };
+
+        Ok(Self {
+            state,
+            stop,
+            handle,
+        })
+    }
+
+    pub fn record(&self, event: ProgressEvent) {
+        let worker = rayon::current_thread_index();
+        let mut state = self.state.lock().expect("poisoned dashboard state");
+        state.apply(worker, event);
+    }
+
+    pub fn finish(self) -> Result<()> {
+        self.stop.store(true, Ordering::SeqCst);
+        let result = self
+            .handle
+            .join()
+            .map_err(|_| anyhow!("dashboard render thread panicked"))?;
+        execute!(io::stdout(), LeaveAlternateScreen, Show).context("leave dashboard screen")?;
+        result.context("render dashboard")
+    }
+}
+
+impl DashboardState {
+    fn apply(&mut self, worker: Option<usize>, event: ProgressEvent) {
+        let slot = worker.and_then(|idx| self.workers.get_mut(idx));
+        match event {
+            ProgressEvent::Started { path } => {
+                if let Some(slot) = slot {
+                    slot.current = Some(path.display().to_string());
+                }
+            }
+            ProgressEvent::Finished { path, rows } => {
+                if let Some(slot) = slot {
+                    slot.current = None;
+                    slot.last = Some(path.display().to_string());
+                    slot.files += 1;
+                    slot.rows += rows;
+                }
+                self.completed += 1;
+                self.rows += rows;
+            }
+            ProgressEvent::Skipped { path } => {
+                if let Some(slot) = slot {
+                    slot.current = None;
+                    slot.last = Some(path.display().to_string());
+                }
+                self.completed += 1;
+                self.skipped += 1;
+            }
+            ProgressEvent::Failed { path, error } => {
+                if let Some(slot) = slot {
+                    slot.current = None;
+                }
+                self.completed += 1;
+                self.errors += 1;
+                if self.recent_errors.len() == MAX_RECENT_ERRORS {
+                    self.recent_errors.pop_front();
+                }
+                self.recent_errors
+                    .push_back(format!("{}: {}", path.display(), error));
+            }
+        }
+    }
+
+    fn eta(&self) -> Option<Duration> {
+        if self.completed == 0 || self.completed >= self.total {
+            return None;
+        }
+        let per_item = self.started.elapsed().as_secs_f64() / self.completed as f64;
+        Some(Duration::from_secs_f64(
+            per_item * (self.total - self.completed) as f64,
+        ))
+    }
+}
+
+fn render_loop(
+    mut terminal: Terminal<CrosstermBackend<Stdout>>,
+    state: &Mutex<DashboardState>,
+    stop: &AtomicBool,
+) -> io::Result<()> {
+    loop {
+        let finished = stop.load(Ordering::SeqCst);
+        {
+            let state = state.lock().expect("poisoned dashboard state");
+            terminal.draw(|frame| draw(frame, &state))?;
+        }
+        if finished {
+            return Ok(());
+        }
+        std::thread::sleep(REFRESH_INTERVAL);
+    }
+}
+
+fn draw(frame: &mut Frame<'_>, state: &DashboardState) {
+    let [gauge_area, stats_area, workers_area, errors_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(4),
+        Constraint::Min(4),
+        Constraint::Length(MAX_RECENT_ERRORS as u16 + 2),
+    ])
+    .areas(frame.area());
+
+    let ratio = if state.total == 0 {
+        1.0
+    } else {
+        state.completed as f64 / state.total as f64
+    };
+    let gauge = Gauge::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(state.title.as_str()),
+        )
+        .gauge_style(Style::default().fg(Color::Cyan))
+        .ratio(ratio.clamp(0.0, 1.0))
+        .label(format!("{}/{}", state.completed, state.total));
+    frame.render_widget(gauge, gauge_area);
+
+    let elapsed = state.started.elapsed().as_secs_f64();
+    let per_second = |count: usize| {
+        if elapsed > 0.0 {
+            count as f64 / elapsed
+        } else {
+            0.0
+        }
+    };
+    let eta = state
+        .eta()
+        .map(|eta| format!("{}s", eta.as_secs()))
+        .unwrap_or_else(|| "-".into());
+    let stats = Paragraph::new(vec![
+        format!(
+            "elapsed {:.0}s   eta {}   files/s {:.1}   rows/s {:.0}",
+            elapsed,
+            eta,
+            per_second(state.completed),
+            per_second(state.rows)
+        )
+        .into(),
+        format!(
+            "rows {}   skipped {}   errors {}",
+            state.rows, state.skipped, state.errors
+        )
+        .into(),
+    ])
+    .block(Block::default().borders(Borders::ALL).title("Throughput"));
+    frame.render_widget(stats, stats_area);
+
+    let rows = state.workers.iter().enumerate().map(|(idx, worker)| {
+        Row::new(vec![
+            idx.to_string(),
+            worker.files.to_string(),
+            worker.rows.to_string(),
+            match (&worker.current, &worker.last) {
+                (Some(current), _) => current.clone(),
+                (None, Some(last)) => format!("idle (last: {})", last),
+                (None, None) => "idle".into(),
+            },
+        ])
+    });
+    let workers = Table::new(
+        rows,
+        [
+            Constraint::Length(6),
+            Constraint::Length(8),
+            Constraint::Length(12),
+            Constraint::Fill(1),
+        ],
+    )
+    .header(
+        Row::new(vec!["worker", "files", "rows", "current"])
+            .style(Style::default().fg(Color::Yellow)),
+    )
+    .block(Block::default().borders(Borders::ALL).title("Workers"));
+    frame.render_widget(workers, workers_area);
+
+    let errors = List::new(
+        state
+            .recent_errors
+            .iter()
+            .map(|line| ListItem::new(line.as_str())),
+    )
+    .style(Style::default().fg(Color::Red))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Recent errors"),
+    );
+    frame.render_widget(errors, errors_area);
+}
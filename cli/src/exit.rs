@@ -0,0 +1,77 @@
+use thiserror::Error;
+
+/// Process exit codes returned by `bvs`, stable so orchestration systems can branch on them.
+///
+/// `2` is left to clap, which uses it for usage errors (unknown flags, bad values).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ExitCode {
+    /// Command completed without errors.
+    Success = 0,
+    /// Unclassified failure.
+    Failure = 1,
+    /// More input files failed to parse than `--max-failures` allows.
+    ParseFailures = 3,
+    /// The SQLite database could not be opened, queried, or written.
+    Database = 4,
+    /// Fetching reference data over the network failed.
+    Download = 5,
+    /// Command finished, but some inputs failed and were left out of the results.
+    PartialSuccess = 6,
+}
+
+impl ExitCode {
+    pub fn from_error(err: &anyhow::Error) -> Self {
+        if err.downcast_ref::<PartialSuccess>().is_some() {
+            return ExitCode::PartialSuccess;
+        }
+        if err.downcast_ref::<ParseFailuresExceeded>().is_some() {
+            return ExitCode::ParseFailures;
+        }
+        if err.downcast_ref::<DownloadFailed>().is_some() {
+            return ExitCode::Download;
+        }
+        #[cfg(feature = "download")]
+        if err.chain().any(|cause| cause.is::<reqwest::Error>()) {
+            return ExitCode::Download;
+        }
+        if err.chain().any(|cause| cause.is::<rusqlite::Error>()) {
+            return ExitCode::Database;
+        }
+        ExitCode::Failure
+    }
+}
+
+impl From<ExitCode> for std::process::ExitCode {
+    fn from(code: ExitCode) -> Self {
+        std::process::ExitCode::from(code as u8)
+    }
+}
+
+pub const EXIT_CODES_HELP: &str = "Exit codes:
+  0  success
+  1  unclassified failure
+  2  invalid command-line usage
+  3  parse failures exceeded --max-failures
+  4  database error
+  5  download error
+  6  partial success (some inputs failed)";
+
+#[derive(Debug, Error)]
+#[error("{failed} of {total} files failed to parse")]
+pub struct PartialSuccess {
+    pub failed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Error)]
+#[error("{failed} of {total} files failed to parse (limit {limit})")]
+pub struct ParseFailuresExceeded {
+    pub failed: usize,
+    pub total: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, Error)]
+#[error("Download reference database")]
+pub struct DownloadFailed;
@@ -1,52 +1,529 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{bail, Result};
+use biosynth_core::audit::AuditAccess;
+use biosynth_core::buffers::{self, DEFAULT_IO_BUFFER_SIZE};
+use biosynth_core::download::{
+    ensure_reference_db, DEFAULT_REFERENCE_VERSION, REFERENCE_DB_FILENAME,
+};
+#[cfg(feature = "download")]
+use biosynth_core::download::{HttpConfig, ReferenceSource, DEFAULT_CONNECTIONS};
+use biosynth_core::genotype::{ColumnRef, GenotypeValidation, ParseOptions};
+use biosynth_core::liftover::GenomeBuild;
+use biosynth_core::policy::ExportPolicy;
+use biosynth_core::stats::{
+    DuplicatePolicy, SqliteTuning, StatsBackend, StatsStore, TempStore, DEFAULT_BATCH_SIZE,
+};
 use clap::{ArgAction, Args, Parser, Subcommand};
 
-mod download;
-mod genotype;
-mod stats;
+use crate::audit::AuditTarget;
+use crate::exit::{ExitCode, EXIT_CODES_HELP};
+use crate::output::OutputFormat;
+use crate::util::{default_data_dir, parse_byte_size, OverwritePolicy};
+
+mod audit;
+#[cfg(feature = "tui")]
+mod dashboard;
+mod exit;
+mod manifest;
+mod output;
+mod progress;
 mod util;
 
+#[cfg(feature = "html-report")]
 use crate::commands::allele_report::run_allele_report;
+use crate::commands::bench::run_bench;
+use crate::commands::clean::run_clean;
+use crate::commands::db::run_db;
+#[cfg(feature = "download")]
+use crate::commands::fetch_reference::run_fetch_reference;
 use crate::commands::genostats::run_genostats;
+use crate::commands::lift::run_lift;
+use crate::commands::overlay_schema::run_overlay_schema;
+use crate::commands::privacy_eval::run_privacy_eval;
 use crate::commands::reference_load::run_reference_load;
+use crate::commands::reidentify::run_reidentify;
+use crate::commands::simulate_cohort::run_simulate_cohort;
 use crate::commands::synthetic::run_synthetic;
+use crate::commands::validate::run_validate;
+use crate::commands::verify::run_verify;
 
 mod commands {
+    #[cfg(feature = "html-report")]
     pub mod allele_report;
+    pub mod bench;
+    pub mod clean;
+    pub mod db;
+    #[cfg(feature = "download")]
+    pub mod fetch_reference;
     pub mod genostats;
+    pub mod lift;
+    pub mod overlay_schema;
+    pub mod privacy_eval;
     pub mod reference_load;
+    pub mod reidentify;
+    pub mod simulate_cohort;
     pub mod synthetic;
+    pub mod validate;
+    pub mod verify;
 }
 
 #[derive(Parser)]
-#[command(name = "bvs", version, about = "Synthetic Data Toolkit for BioVault", long_about = None)]
+#[command(
+    name = "bvs",
+    version,
+    about = "Synthetic Data Toolkit for BioVault",
+    long_about = None,
+    after_help = EXIT_CODES_HELP
+)]
 struct Cli {
+    #[command(flatten)]
+    global: GlobalArgs,
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Options shared by every subcommand.
+#[derive(Args, Clone, Debug)]
+pub struct GlobalArgs {
+    /// Directory holding reference databases and other bvs data. Defaults to ./data if it
+    /// exists, else the platform data directory ($XDG_DATA_HOME/biosynth on Linux,
+    /// ~/Library/Application Support on macOS).
+    #[arg(
+        long,
+        global = true,
+        env = "BVS_DATA_DIR",
+        default_value_os_t = default_data_dir(),
+        hide_default_value = true
+    )]
+    pub data_dir: PathBuf,
+    /// Number of worker threads, also used to split very large files (defaults to the available
+    /// parallelism).
+    #[arg(long, global = true, env = "BVS_THREADS")]
+    pub threads: Option<usize>,
+    /// Emit NDJSON progress events to this file (`-` for stderr).
+    #[arg(long, global = true, env = "BVS_PROGRESS_JSON", value_name = "PATH")]
+    pub progress_json: Option<PathBuf>,
+    /// Reference release (a git tag, `latest`, or `main`): fetch-reference downloads it, and
+    /// commands reading a reference refuse a database fetched at any other release.
+    #[arg(long, global = true, env = "BVS_REFERENCE_VERSION", value_name = "TAG")]
+    pub reference_version: Option<String>,
+    /// Mirror to fetch references from instead of GitHub, laid out like the repository
+    /// (`<URL>/<TAG>/data/genostats.sqlite`). Mirrors need an explicit --reference-version.
+    #[arg(long, global = true, env = "BVS_REFERENCE_URL", value_name = "URL")]
+    pub reference_url: Option<String>,
+    /// PEM file of extra CA certificates to trust for downloads, e.g. for a TLS-intercepting
+    /// proxy. Proxies themselves are read from HTTPS_PROXY, HTTP_PROXY and NO_PROXY.
+    #[arg(long, global = true, env = "BVS_CA_CERT", value_name = "PATH")]
+    pub ca_cert: Option<PathBuf>,
+    /// Fail instead of attempting any network access, for air-gapped environments.
+    #[arg(
+        long,
+        global = true,
+        env = "BVS_OFFLINE",
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    pub offline: bool,
+    /// Report results as human-readable text or as JSON on stdout.
+    #[arg(
+        long,
+        global = true,
+        env = "BVS_OUTPUT_FORMAT",
+        value_enum,
+        default_value_t = OutputFormat::Text
+    )]
+    pub output_format: OutputFormat,
+    /// Export policy limiting what reports and exports may release (defaults to policy.yaml in
+    /// the data directory, if present).
+    #[arg(long, global = true, env = "BVS_POLICY", value_name = "PATH")]
+    pub policy: Option<PathBuf>,
+    /// Buffer size for files bvs reads and writes, in bytes (K/M suffixes accepted); larger
+    /// buffers help on network filesystems.
+    #[arg(
+        long,
+        global = true,
+        env = "BVS_IO_BUFFER",
+        value_name = "BYTES",
+        value_parser = parse_byte_size,
+        default_value_t = DEFAULT_IO_BUFFER_SIZE
+    )]
+    pub io_buffer: usize,
+    /// What parsing does with a genotype call outside `ACGT`, `D`/`I`, `-` and `0`: keep the row
+    /// as a no-call (`lenient`), skip it (`strict`), or fail the file (`error`).
+    #[arg(
+        long,
+        global = true,
+        env = "BVS_GENOTYPE_VALIDATION",
+        value_name = "POLICY",
+        default_value = "strict"
+    )]
+    pub genotype_validation: GenotypeValidation,
+    /// Open stats databases with the SQLite bulk-load profile: a 256 MiB cache, 1 GiB of
+    /// memory-mapped reads, in-memory temp storage, 16 KiB pages for new databases, and fewer
+    /// WAL checkpoints. The `--sqlite-*` flags below override single settings.
+    #[arg(long, global = true)]
+    pub sqlite_bulk_load: bool,
+    /// SQLite page cache size: pages if positive, KiB if negative.
+    #[arg(
+        long,
+        global = true,
+        env = "BVS_SQLITE_CACHE_SIZE",
+        value_name = "PAGES",
+        allow_negative_numbers = true
+    )]
+    pub sqlite_cache_size: Option<i64>,
+    /// Bytes of each stats database SQLite may memory-map (0 disables it).
+    #[arg(
+        long,
+        global = true,
+        env = "BVS_SQLITE_MMAP_SIZE",
+        value_name = "BYTES"
+    )]
+    pub sqlite_mmap_size: Option<u64>,
+    /// Where SQLite keeps temporary tables and indices: default, file or memory.
+    #[arg(
+        long,
+        global = true,
+        env = "BVS_SQLITE_TEMP_STORE",
+        value_name = "STORE"
+    )]
+    pub sqlite_temp_store: Option<TempStore>,
+    /// Page size in bytes for newly created stats databases.
+    #[arg(
+        long,
+        global = true,
+        env = "BVS_SQLITE_PAGE_SIZE",
+        value_name = "BYTES"
+    )]
+    pub sqlite_page_size: Option<u32>,
+    /// WAL pages written before SQLite checkpoints automatically (0 disables it).
+    #[arg(
+        long,
+        global = true,
+        env = "BVS_SQLITE_WAL_AUTOCHECKPOINT",
+        value_name = "PAGES"
+    )]
+    pub sqlite_wal_autocheckpoint: Option<u32>,
+    /// Show a full-screen dashboard of worker activity instead of the progress bar.
+    #[cfg(feature = "tui")]
+    #[arg(long, global = true, action = ArgAction::SetTrue)]
+    pub dashboard: bool,
+}
+
+impl GlobalArgs {
+    /// Resolves a command's `--sqlite` flag, defaulting to the database in the data directory.
+    pub fn sqlite_path(&self, explicit: Option<&PathBuf>) -> PathBuf {
+        explicit
+            .cloned()
+            .unwrap_or_else(|| self.data_dir.join(REFERENCE_DB_FILENAME))
+    }
+
+    /// The SQLite pragmas selected by `--sqlite-bulk-load` and the `--sqlite-*` flags.
+    pub fn sqlite_tuning(&self) -> SqliteTuning {
+        let profile = if self.sqlite_bulk_load {
+            SqliteTuning::bulk_load()
+        } else {
+            SqliteTuning::default()
+        };
+        SqliteTuning {
+            cache_size: self.sqlite_cache_size.or(profile.cache_size),
+            mmap_size: self.sqlite_mmap_size.or(profile.mmap_size),
+            temp_store: self.sqlite_temp_store.or(profile.temp_store),
+            page_size: self.sqlite_page_size.or(profile.page_size),
+            wal_autocheckpoint: self
+                .sqlite_wal_autocheckpoint
+                .or(profile.wal_autocheckpoint),
+        }
+    }
+
+    /// The reference database for a command's `--sqlite` flag. It must exist and, with
+    /// `--reference-version` naming a release, have been fetched at that release.
+    pub fn reference_db(&self, explicit: Option<&PathBuf>) -> Result<ReferenceDb> {
+        let path = ensure_reference_db(&self.sqlite_path(explicit))?;
+        let version = self.stats_store(&path)?.reference_version()?;
+        let required = self
+            .reference_version
+            .as_deref()
+            .filter(|required| *required != DEFAULT_REFERENCE_VERSION);
+        if let Some(required) = required {
+            match &version {
+                Some(version) if version == required => {}
+                Some(version) => bail!(
+                    "{} holds reference release {}, not {}; run `bvs --reference-version {} \
+                     fetch-reference --force` to replace it",
+                    path.display(),
+                    version,
+                    required,
+                    required
+                ),
+                None => bail!(
+                    "{} records no reference release, so it cannot be checked against {}",
+                    path.display(),
+                    required
+                ),
+            }
+        }
+        Ok(ReferenceDb { path, version })
+    }
+
+    /// Where fetch-reference downloads from: `--reference-url`, or GitHub. Fails under
+    /// `--offline`.
+    #[cfg(feature = "download")]
+    pub fn reference_source(&self) -> Result<ReferenceSource> {
+        let source = ReferenceSource::new(self.reference_url.as_deref());
+        if self.offline {
+            bail!("--offline is set; refusing to contact {}", source);
+        }
+        Ok(source)
+    }
+
+    /// HTTP client settings from `--ca-cert`.
+    #[cfg(feature = "download")]
+    pub fn http_config(&self) -> HttpConfig {
+        HttpConfig {
+            ca_cert: self.ca_cert.clone(),
+            ..HttpConfig::default()
+        }
+    }
+
+    /// Connects to the stats database at `path` with [`sqlite_tuning`](Self::sqlite_tuning).
+    pub fn stats_store(&self, path: &Path) -> Result<StatsStore> {
+        Ok(StatsStore::connect_tuned(path, self.sqlite_tuning())?
+            .with_parse_options(self.parse_options()))
+    }
+
+    /// How genotype files are parsed: `--genotype-validation`, and `--threads` for splitting
+    /// one large file.
+    pub fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            validation: self.genotype_validation,
+            threads: util::resolve_thread_count(self.threads),
+            ..ParseOptions::default()
+        }
+    }
+
+    /// [`stats_store`](Self::stats_store) behind the backend interface.
+    pub fn stats_backend(&self, path: &Path) -> Result<Box<dyn StatsBackend>> {
+        Ok(Box::new(self.stats_store(path)?))
+    }
+
+    /// The export policy in force, from `--policy` or the data directory.
+    pub fn export_policy(&self) -> Result<Option<ExportPolicy>> {
+        Ok(ExportPolicy::discover(
+            self.policy.as_deref(),
+            &self.data_dir,
+        )?)
+    }
+}
+
+/// A reference database found by [`GlobalArgs::reference_db`].
+pub struct ReferenceDb {
+    pub path: PathBuf,
+    /// The release it was fetched at, if one was recorded.
+    pub version: Option<String>,
+}
+
+/// Database upkeep after commands that bulk-load a stats database.
+#[derive(Args, Clone, Debug)]
+pub struct MaintenanceArgs {
+    /// Skip refreshing query statistics (ANALYZE, PRAGMA optimize) after loading.
+    #[arg(long)]
+    pub no_optimize: bool,
+    /// Also reclaim free space after loading; rewrites the whole file unless the database
+    /// uses incremental auto-vacuum.
+    #[arg(long, conflicts_with = "no_optimize")]
+    pub vacuum: bool,
+}
+
+/// Existing-output handling shared by commands that write files.
+#[derive(Args, Clone, Debug)]
+pub struct OverwriteArgs {
+    /// Replace outputs that already exist.
+    #[arg(long, conflicts_with = "no_clobber")]
+    pub overwrite: bool,
+    /// Skip outputs that already exist instead of failing.
+    #[arg(long)]
+    pub no_clobber: bool,
+}
+
+impl OverwriteArgs {
+    pub fn policy(&self) -> OverwritePolicy {
+        if self.overwrite {
+            OverwritePolicy::Overwrite
+        } else if self.no_clobber {
+            OverwritePolicy::Skip
+        } else {
+            OverwritePolicy::Refuse
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Analyze genotype files and persist aggregated statistics.
+    #[command(visible_alias = "stats")]
     Genostats(GenostatsArgs),
     /// Export an HTML report of observed alleles per rsid.
+    #[cfg(feature = "html-report")]
     AlleleReport(AlleleReportArgs),
     /// Load reference allele lookup data into SQLite.
     ReferenceLoad(ReferenceLoadArgs),
     /// Generate a reference genotype file from stored data.
+    #[command(visible_alias = "gen")]
     Synthetic(SyntheticArgs),
+    /// Measure parse and synthetic generation throughput across thread counts.
+    Bench(BenchArgs),
+    /// Download a published reference database from GitHub, verifying its checksum.
+    #[cfg(feature = "download")]
+    FetchReference(FetchReferenceArgs),
+    /// Generate a whole synthetic cohort from a JSON spec, with a manifest.
+    SimulateCohort(SimulateCohortArgs),
+    /// Check generated files against the checksums and row counts in a manifest.
+    Verify(VerifyArgs),
+    /// Check VCFs for header, contig, sort-order, and GT problems that other tools reject.
+    Validate(ValidateArgs),
+    /// Remove temp artifacts, caches, and (optionally) reference databases from the data directory.
+    Clean(CleanArgs),
+    /// Lift a genotype file between GRCh37 and GRCh38 coordinates.
+    Lift(LiftArgs),
+    /// Print the JSON Schema for --variants-file overlay documents.
+    OverlaySchema(OverlaySchemaArgs),
+    /// Inspect a stats database.
+    Db(DbArgs),
+    /// Score a synthetic cohort for memorization and membership-inference risk.
+    PrivacyEval(PrivacyEvalArgs),
+    /// Resolve participant pseudonyms through an --id-lookup file.
+    Reidentify(ReidentifyArgs),
+}
+
+impl Commands {
+    /// The stats database this command reads or writes, for the audit log.
+    fn audit_target(&self, global: &GlobalArgs) -> Option<AuditTarget> {
+        let (command, sqlite, access) = match self {
+            Commands::Genostats(args) => ("genostats", args.sqlite.as_ref(), AuditAccess::Write),
+            #[cfg(feature = "html-report")]
+            Commands::AlleleReport(args) => {
+                ("allele-report", args.sqlite.as_ref(), AuditAccess::Read)
+            }
+            Commands::ReferenceLoad(args) => {
+                ("reference-load", args.sqlite.as_ref(), AuditAccess::Write)
+            }
+            Commands::Synthetic(args) => ("synthetic", args.sqlite.as_ref(), AuditAccess::Read),
+            Commands::Bench(args) => ("bench", args.sqlite.as_ref(), AuditAccess::Read),
+            #[cfg(feature = "download")]
+            Commands::FetchReference(args) if !args.list => {
+                let access = if args.check {
+                    AuditAccess::Read
+                } else {
+                    AuditAccess::Write
+                };
+                ("fetch-reference", args.dest.as_ref(), access)
+            }
+            Commands::SimulateCohort(args) => {
+                ("simulate-cohort", args.sqlite.as_ref(), AuditAccess::Read)
+            }
+            Commands::PrivacyEval(args) => {
+                ("privacy-eval", args.sqlite.as_ref(), AuditAccess::Read)
+            }
+            Commands::Lift(args) if args.fix_strand => {
+                ("lift", args.sqlite.as_ref(), AuditAccess::Read)
+            }
+            Commands::Db(DbArgs {
+                command: DbCommand::Audit(args),
+            }) => ("db audit", args.sqlite.as_ref(), AuditAccess::Read),
+            Commands::Db(DbArgs {
+                command: DbCommand::Tune(args),
+            }) => {
+                let access = if args.dry_run {
+                    AuditAccess::Read
+                } else {
+                    AuditAccess::Write
+                };
+                ("db tune", args.sqlite.as_ref(), access)
+            }
+            _ => return None,
+        };
+        Some(AuditTarget {
+            command,
+            sqlite: global.sqlite_path(sqlite),
+            access,
+        })
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct PrivacyEvalArgs {
+    /// Synthetic genotype files or directories to evaluate. Directories are scanned recursively.
+    #[arg(short = 'i', long = "input", required = true)]
+    pub inputs: Vec<PathBuf>,
+    /// Real genotype files or directories the synthetic files must not resemble.
+    #[arg(long)]
+    pub real: Vec<PathBuf>,
+    /// Path to the SQLite database the cohort was generated from. Defaults to <data-dir>/genostats.sqlite.
+    #[arg(long, env = "BVS_SQLITE")]
+    pub sqlite: Option<PathBuf>,
+    /// Alternate alleles carried by at most this many source files count as rare.
+    #[arg(long, default_value_t = 1)]
+    pub rare_carriers: usize,
+    /// Share (0-1) of synthetic alternate alleles that may be rare before risk is elevated.
+    #[arg(long, default_value_t = 0.01)]
+    pub max_rare_allele_rate: f64,
+    /// Genotype identity (0-1) at which two files count as near-duplicates.
+    #[arg(long, default_value_t = 0.95)]
+    pub identity_threshold: f64,
+    /// Compare identity on roughly one rsid in this many (raise for very large cohorts).
+    #[arg(long, default_value_t = 1)]
+    pub sample_every: u64,
+}
+
+#[derive(Args, Clone)]
+pub struct DbArgs {
+    #[command(subcommand)]
+    pub command: DbCommand,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum DbCommand {
+    /// List the append-only log of commands that read or wrote the database.
+    Audit(DbAuditArgs),
+    /// Time the queries reports and synthetic generation run, adding the covering indexes
+    /// they lack.
+    Tune(DbTuneArgs),
+}
+
+#[derive(Args, Clone)]
+pub struct DbAuditArgs {
+    /// Path to the SQLite database. Defaults to <data-dir>/genostats.sqlite.
+    #[arg(long, env = "BVS_SQLITE")]
+    pub sqlite: Option<PathBuf>,
+    /// Show only the most recent N entries.
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Args, Clone)]
+pub struct DbTuneArgs {
+    /// Path to the SQLite database. Defaults to <data-dir>/genostats.sqlite.
+    #[arg(long, env = "BVS_SQLITE")]
+    pub sqlite: Option<PathBuf>,
+    /// Report query plans and timings without creating any index.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub dry_run: bool,
 }
 
 #[derive(Args, Clone)]
 pub struct GenostatsArgs {
-    /// Input file or directory paths to process. Directories are scanned recursively.
+    /// Input file or directory paths to process. Directories are scanned recursively for
+    /// `.txt`, `.tsv`, `.csv`, `.vcf`, and `.jsonl` files, gzipped or not, and `.xlsx`
+    /// workbooks (and `.bcf` files, with feature `bcf`); `.zip` archives are read in memory for
+    /// the genotype files inside them, and a PLINK `.bed` next to its `.bim` and `.fam`, or an
+    /// Illumina Final Report with several samples, counts as one input per sample. `-` reads one
+    /// file from standard input.
     #[arg(short = 'i', long = "input")]
     pub inputs: Vec<PathBuf>,
-    /// Path to the SQLite database used to store aggregated stats.
-    #[arg(long, default_value = "data/genostats.sqlite")]
-    pub sqlite: PathBuf,
+    /// Path to the SQLite database used to store aggregated stats. Defaults to <data-dir>/genostats.sqlite.
+    #[arg(long, env = "BVS_SQLITE")]
+    pub sqlite: Option<PathBuf>,
     /// Optional JSON file to dump a summary report.
     #[arg(long)]
     pub summary_json: Option<PathBuf>,
@@ -56,60 +533,190 @@ pub struct GenostatsArgs {
     /// Skip files already recorded in the SQLite database.
     #[arg(long, action = ArgAction::SetTrue)]
     pub skip_recorded_files: bool,
-    /// Number of worker threads to use when parsing files.
-    #[arg(long, default_value = "16")]
-    pub threads: usize,
+    /// Fail with a parse-failure exit code when more than this many files fail to parse.
+    #[arg(long)]
+    pub max_failures: Option<usize>,
+    /// Release the summary's file counts under this total privacy budget (differential
+    /// privacy), split across them; skipped rows are then withheld.
+    #[arg(long)]
+    pub epsilon: Option<f64>,
+    /// Withhold any reported cell observed in fewer than this many source files.
+    #[arg(long, value_name = "K")]
+    pub min_count: Option<u64>,
+    /// Append `file_id<TAB>path` for each ingested file to this local TSV. The database itself
+    /// only stores salted hashes of input paths.
+    #[arg(long, value_name = "PATH")]
+    pub file_map: Option<PathBuf>,
+    /// Permanently switch the database to aggregate-only mode: no per-file records are kept and
+    /// every later report is suppressed at --min-count. Counts below --min-count at the end of
+    /// a run are never written to the database and are not carried over to later runs.
+    #[arg(long, requires = "min_count", conflicts_with_all = ["file_map", "skip_recorded_files"])]
+    pub aggregate_only: bool,
+    /// Decompress `.gz` inputs into a private (0700) temp directory and overwrite staged copies
+    /// with zeros before deleting them.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub secure_temp: bool,
+    /// Consent under which these inputs may be used, recorded with every file so exports can
+    /// be restricted to it.
+    #[arg(long, value_name = "TAG")]
+    pub consent_tag: Option<String>,
+    /// Identifier of the dataset these inputs came from, recorded with every file.
+    #[arg(long, value_name = "ID")]
+    pub source_dataset: Option<String>,
+    /// Complement calls that only match the stored reference alleles on the opposite strand
+    /// before counting them, reporting how many were corrected in each file.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub fix_strand: bool,
+    /// Compare each call's chromosome and position with the stored reference SNPs, reporting
+    /// each file's mismatch rate and warning about files that look like another genome build.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub check_positions: bool,
+    /// Which row to count when a file repeats an rsid: `first`, `last`, `skip` (none of them),
+    /// or `error` (fail the file). Dropped rows are reported per file.
+    #[arg(long, value_name = "POLICY", default_value = "first")]
+    pub on_duplicate: DuplicatePolicy,
+    /// Read rsids from this column, by header name or 1-based position, instead of the one the
+    /// header aliases pick.
+    #[arg(long, value_name = "COLUMN")]
+    pub col_rsid: Option<ColumnRef>,
+    /// Read chromosomes from this column (header name or 1-based position).
+    #[arg(long, value_name = "COLUMN")]
+    pub col_chrom: Option<ColumnRef>,
+    /// Read positions from this column (header name or 1-based position).
+    #[arg(long, value_name = "COLUMN")]
+    pub col_pos: Option<ColumnRef>,
+    /// Read genotype calls from this column (header name or 1-based position).
+    #[arg(long, value_name = "COLUMN")]
+    pub col_genotype: Option<ColumnRef>,
+    /// TOML (`.toml`) or JSON file of extra header names per field, such as
+    /// `genotype = ["Callz"]`, recognized on top of the built-in aliases.
+    #[arg(long, value_name = "PATH")]
+    pub aliases: Option<PathBuf>,
+    /// Calls buffered per file before their observations are written as multi-row inserts.
+    #[arg(long, value_name = "ROWS", env = "BVS_BATCH_SIZE", default_value_t = DEFAULT_BATCH_SIZE)]
+    pub batch_size: usize,
+    /// Commit after this many calls instead of adapting the interval to commit latency.
+    #[arg(long, value_name = "ROWS", env = "BVS_COMMIT_ROWS")]
+    pub commit_rows: Option<usize>,
+    /// Aggregate in an in-memory database and write it to --sqlite once at the end; faster for
+    /// one-shot batch jobs when the database fits in RAM. Nothing is saved if the run fails.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub in_memory: bool,
+    /// Hold observations as Arrow record batches and write one aggregated row per rsid and
+    /// allele at each commit instead of upserting every call. Suits very large corpora.
+    #[cfg(feature = "columnar")]
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub columnar: bool,
+    #[command(flatten)]
+    pub maintenance: MaintenanceArgs,
 }
 
+#[cfg(feature = "html-report")]
 #[derive(Args, Clone)]
 pub struct AlleleReportArgs {
-    /// Path to the SQLite database created by `bvs genostats` (uses data/genostats.sqlite in production).
-    #[arg(long, default_value = "data/genostats.sqlite")]
-    pub sqlite: PathBuf,
+    /// Path to the SQLite database created by `bvs genostats`. Defaults to <data-dir>/genostats.sqlite.
+    #[arg(long, env = "BVS_SQLITE")]
+    pub sqlite: Option<PathBuf>,
     /// Output path for the generated HTML report.
     #[arg(long)]
     pub output: PathBuf,
+    /// Add Laplace noise to observation counts, spending this total privacy budget across all
+    /// rows (differential privacy).
+    #[arg(long)]
+    pub epsilon: Option<f64>,
+    /// Leave out any format/rsid row observed in fewer than this many source files.
+    #[arg(long, value_name = "K")]
+    pub min_count: Option<u64>,
+    /// Write a version safe to share with external partners: counts become ranges, rows
+    /// observed in fewer than 10 files are left out, and the source path and exact totals are
+    /// omitted.
+    #[arg(long)]
+    pub redact: bool,
+    #[command(flatten)]
+    pub overwrite: OverwriteArgs,
 }
 
 #[derive(Args, Clone)]
 pub struct ReferenceLoadArgs {
-    /// Path to the SQLite database created by `bvs genostats`.
-    #[arg(long, default_value = "data/genostats.sqlite")]
-    pub sqlite: PathBuf,
+    /// Path to the SQLite database created by `bvs genostats`. Defaults to <data-dir>/genostats.sqlite.
+    #[arg(long, env = "BVS_SQLITE")]
+    pub sqlite: Option<PathBuf>,
     /// CSV produced by `scripts/extract_reference_variants.py`.
-    #[arg(long)]
-    pub lookup: PathBuf,
+    #[arg(long, required_unless_present_any = ["from_observations", "merges", "sentinels"])]
+    pub lookup: Option<PathBuf>,
+    /// Load dbSNP rsid merges instead, from `RsMergeArch.bcp` (optionally gzipped) or a
+    /// `retired<TAB>current` list; `bvs genostats` then counts retired rsids as current ones.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["lookup", "from_observations"])]
+    pub merges: Option<PathBuf>,
+    /// Load genome-build sentinels instead, from a curated `rsid,build,chromosome,position`
+    /// CSV; `bvs genostats` then fingerprints each file's build from where it places them.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["lookup", "from_observations", "merges"]
+    )]
+    pub sentinels: Option<PathBuf>,
+    /// Derive reference rows from the observations `bvs genostats` recorded instead of a CSV.
+    #[arg(long, conflicts_with = "lookup")]
+    pub from_observations: bool,
+    /// With --from-observations, leave out variants seen in fewer than this many files.
+    #[arg(long, value_name = "N", default_value_t = 5)]
+    pub min_individuals: u64,
+    /// With --from-observations, only use files ingested with this consent tag (repeatable).
+    #[arg(
+        long = "consent-tag",
+        value_name = "TAG",
+        requires = "from_observations"
+    )]
+    pub consent_tags: Vec<String>,
+    #[command(flatten)]
+    pub maintenance: MaintenanceArgs,
 }
 
 #[derive(Args, Clone)]
 pub struct SyntheticArgs {
-    /// Path to the SQLite database containing rsid_reference data (uses data/genostats.sqlite in production).
-    #[arg(long, default_value = "data/genostats.sqlite")]
-    pub sqlite: PathBuf,
+    /// Path to the SQLite database containing rsid_reference data. Defaults to <data-dir>/genostats.sqlite.
+    #[arg(long, env = "BVS_SQLITE")]
+    pub sqlite: Option<PathBuf>,
     /// Output file to write
     #[arg(long)]
     pub output: PathBuf,
     /// Probability of substituting a random ALT allele instead of the reference.
     #[arg(long, default_value = "0.01")]
     pub alt_frequency: f64,
+    /// Sample SNP genotypes from observed allele frequencies released under this total
+    /// differential-privacy budget. The noise is never seeded, even with --seed.
+    #[arg(long, value_name = "EPSILON")]
+    pub dp_epsilon: Option<f64>,
+    /// With --dp-epsilon, only use files ingested with this consent tag (repeatable).
+    #[arg(long = "consent-tag", value_name = "TAG", requires = "dp_epsilon")]
+    pub consent_tags: Vec<String>,
     /// Optional RNG seed for reproducible output.
     #[arg(long)]
     pub seed: Option<u64>,
     /// Limit the number of rows emitted (defaults to all).
     #[arg(long)]
     pub limit: Option<usize>,
+    /// Read references from the database in chunks for every file instead of loading them all
+    /// up front; for reference tables too large to hold in memory.
+    #[arg(long, conflicts_with = "dp_epsilon")]
+    pub stream_references: bool,
+    /// Generate each file in chromosome chunks on this many threads and stitch them together in
+    /// order; for single very large outputs. Seeded output differs from unchunked generation.
+    #[arg(long, value_name = "N", conflicts_with = "stream_references")]
+    pub chunk_threads: Option<usize>,
     /// Number of files to generate in parallel.
     #[arg(long, default_value = "1")]
     pub count: usize,
-    /// Override number of worker threads for synthetic generation.
-    #[arg(long)]
-    pub threads: Option<usize>,
     /// Optional JSON file describing overlay variants to force/include.
     #[arg(long = "variants-file")]
     pub variants_file: Option<PathBuf>,
     /// Inline JSON describing overlay variants (use instead of --variants-file).
     #[arg(long = "variants-json")]
     pub variants_json: Option<String>,
+    /// Output format to write (`dynamic_dna`).
+    #[arg(long, default_value = "dynamic_dna")]
+    pub format: String,
     /// Minimum random participant ID (inclusive) when using {id} placeholder.
     #[arg(long, default_value_t = 100000)]
     pub id_min: u32,
@@ -134,15 +741,200 @@ pub struct SyntheticArgs {
     /// Date format string used for {date} placeholder (chrono format).
     #[arg(long, default_value = "%m-%d-%Y")]
     pub date_format: String,
+    /// Write a JSON manifest with per-file row counts and SHA-256 checksums.
+    #[arg(long)]
+    pub manifest: Option<PathBuf>,
+    /// Append `pseudonym<TAB>participant` for real participant IDs in the overlay to this local
+    /// TSV, for authorized re-identification with `bvs reidentify`.
+    #[arg(long, value_name = "PATH")]
+    pub id_lookup: Option<PathBuf>,
+    #[command(flatten)]
+    pub overwrite: OverwriteArgs,
+}
+
+#[derive(Args, Clone)]
+pub struct BenchArgs {
+    /// Sample corpus of genotype files or directories to parse. Parse benchmark is skipped when omitted.
+    #[arg(short = 'i', long = "input")]
+    pub inputs: Vec<PathBuf>,
+    /// Limit the number of corpus files parsed per run.
+    #[arg(long)]
+    pub max_files: Option<usize>,
+    /// Path to the SQLite database containing rsid_reference data for the synthetic benchmark. Defaults to <data-dir>/genostats.sqlite.
+    #[arg(long, env = "BVS_SQLITE")]
+    pub sqlite: Option<PathBuf>,
+    /// Limit the number of reference rows generated per synthetic file.
+    #[arg(long)]
+    pub limit: Option<usize>,
+    /// Number of synthetic files generated per run (written to a null sink).
+    #[arg(long, default_value = "8")]
+    pub synthetic_files: usize,
+    /// Skip the synthetic generation benchmark.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub skip_synthetic: bool,
+    /// Comma-separated thread counts to compare.
+    #[arg(long, value_delimiter = ',', default_value = "1,2,4,8")]
+    pub thread_counts: Vec<usize>,
+}
+
+#[cfg(feature = "download")]
+#[derive(Args, Clone)]
+pub struct FetchReferenceArgs {
+    /// Former name of the global --reference-version.
+    #[arg(long, hide = true)]
+    pub version: Option<String>,
+    /// Destination path for the downloaded SQLite database. Defaults to <data-dir>/genostats.sqlite.
+    #[arg(long)]
+    pub dest: Option<PathBuf>,
+    /// List available reference versions instead of downloading.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub list: bool,
+    /// Report whether a newer reference than the one at --dest is published, using conditional
+    /// requests instead of downloading it.
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["list", "force"])]
+    pub check: bool,
+    /// Replace the destination file if it already exists.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub force: bool,
+    /// Download large files as up to this many concurrent range requests (1 for a single stream).
+    #[arg(long, default_value_t = DEFAULT_CONNECTIONS,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=32))]
+    pub connections: usize,
+}
+
+#[derive(Args, Clone)]
+pub struct SimulateCohortArgs {
+    /// JSON cohort spec (size, sex_ratio, populations, formats, overlays, alt_frequency, seed).
+    #[arg(long)]
+    pub spec: PathBuf,
+    /// Directory that receives the generated files and manifest.json.
+    #[arg(long)]
+    pub output_dir: PathBuf,
+    /// Path to the SQLite database containing rsid_reference data. Defaults to <data-dir>/genostats.sqlite.
+    #[arg(long, env = "BVS_SQLITE")]
+    pub sqlite: Option<PathBuf>,
+    /// Limit the number of reference rows per participant (defaults to all).
+    #[arg(long)]
+    pub limit: Option<usize>,
+    /// Append `pseudonym<TAB>participant` for real participant IDs in the spec or overlays to
+    /// this local TSV, for authorized re-identification with `bvs reidentify`.
+    #[arg(long, value_name = "PATH")]
+    pub id_lookup: Option<PathBuf>,
+}
+
+#[derive(Args, Clone)]
+pub struct ReidentifyArgs {
+    /// Lookup TSV written by `--id-lookup`.
+    #[arg(long, value_name = "PATH")]
+    pub lookup: PathBuf,
+    /// Pseudonyms (`pid-…`) to resolve.
+    #[arg(required = true, value_name = "PSEUDONYM")]
+    pub pseudonyms: Vec<String>,
+}
+
+#[derive(Args, Clone)]
+pub struct VerifyArgs {
+    /// Manifest written by `bvs synthetic --manifest` or `bvs simulate-cohort`.
+    #[arg(long)]
+    pub manifest: PathBuf,
+    /// Number of files to re-parse and compare against recorded row counts (0 = all).
+    #[arg(long, default_value_t = 10)]
+    pub spot_check: usize,
+}
+
+#[derive(Args, Clone)]
+pub struct ValidateArgs {
+    /// VCF files (plain or gzipped) to validate; `-` reads one from standard input.
+    #[arg(long, required = true, num_args = 1.., value_name = "PATH")]
+    pub vcf: Vec<PathBuf>,
+}
+
+#[derive(Args, Clone)]
+pub struct CleanArgs {
+    /// Also remove downloaded reference databases (including WAL/SHM sidecars). Databases that
+    /// hold ingested observations, such as the default genostats database, are always kept.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub references: bool,
+    /// List what would be removed without deleting anything.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Clone)]
+pub struct LiftArgs {
+    /// Genotype file to lift.
+    #[arg(short = 'i', long = "input")]
+    pub input: PathBuf,
+    /// Output path for the lifted genotype file.
+    #[arg(long)]
+    pub output: PathBuf,
+    /// Build the input coordinates are on (grch37 or grch38).
+    #[arg(long, default_value = "grch37")]
+    pub from: GenomeBuild,
+    /// Build to lift coordinates to (grch37 or grch38).
+    #[arg(long, default_value = "grch38")]
+    pub to: GenomeBuild,
+    /// UCSC chain file (optionally gzipped). Defaults to <data-dir>/liftover/<from>To<To>.over.chain.gz.
+    #[arg(long)]
+    pub chain: Option<PathBuf>,
+    /// Complement lifted calls that only match the reference alleles in --sqlite on the
+    /// opposite strand.
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub fix_strand: bool,
+    /// Database holding the reference alleles for --fix-strand. Defaults to <data-dir>/genostats.sqlite.
+    #[arg(long, env = "BVS_SQLITE")]
+    pub sqlite: Option<PathBuf>,
+    #[command(flatten)]
+    pub overwrite: OverwriteArgs,
+}
+
+#[derive(Args, Clone)]
+pub struct OverlaySchemaArgs {
+    /// Write the schema to this file instead of stdout.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
 }
 
-fn main() -> Result<()> {
+fn main() -> std::process::ExitCode {
     let cli = Cli::parse();
+    let global = cli.global.clone();
+
+    match run(cli) {
+        Ok(()) => ExitCode::Success.into(),
+        Err(err) => {
+            eprintln!("Error: {:?}", err);
+            let code = ExitCode::from_error(&err);
+            output::emit_error(&global, &err, code);
+            code.into()
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let global = cli.global;
+    buffers::set_io_buffer_size(global.io_buffer);
+    let target = cli.command.audit_target(&global);
+    audit::audited(target, || dispatch(cli.command, &global))
+}
 
-    match cli.command {
-        Commands::Genostats(args) => run_genostats(args),
-        Commands::AlleleReport(args) => run_allele_report(args),
-        Commands::ReferenceLoad(args) => run_reference_load(args),
-        Commands::Synthetic(args) => run_synthetic(args),
+fn dispatch(command: Commands, global: &GlobalArgs) -> Result<()> {
+    match command {
+        Commands::Genostats(args) => run_genostats(args, global),
+        #[cfg(feature = "html-report")]
+        Commands::AlleleReport(args) => run_allele_report(args, global),
+        Commands::ReferenceLoad(args) => run_reference_load(args, global),
+        Commands::Synthetic(args) => run_synthetic(args, global),
+        Commands::Bench(args) => run_bench(args, global),
+        #[cfg(feature = "download")]
+        Commands::FetchReference(args) => run_fetch_reference(args, global),
+        Commands::SimulateCohort(args) => run_simulate_cohort(args, global),
+        Commands::Verify(args) => run_verify(args, global),
+        Commands::Validate(args) => run_validate(args, global),
+        Commands::Clean(args) => run_clean(args, global),
+        Commands::Lift(args) => run_lift(args, global),
+        Commands::OverlaySchema(args) => run_overlay_schema(args, global),
+        Commands::Db(args) => run_db(args, global),
+        Commands::PrivacyEval(args) => run_privacy_eval(args, global),
+        Commands::Reidentify(args) => run_reidentify(args, global),
     }
 }
@@ -0,0 +1,69 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use biosynth_core::buffers;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// One generated output as recorded in a generation manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub path: PathBuf,
+    pub rows: usize,
+    pub sha256: String,
+}
+
+impl ManifestFile {
+    /// Records a freshly written output, hashing its contents.
+    pub fn record(path: &Path, rows: usize) -> Result<Self> {
+        Ok(Self {
+            path: path.to_path_buf(),
+            rows,
+            sha256: sha256_file(path)?,
+        })
+    }
+}
+
+/// The subset of any manifest written by `synthetic` or `simulate-cohort` that `verify` needs.
+#[derive(Debug, Deserialize)]
+pub struct ManifestFiles {
+    pub files: Vec<ManifestFile>,
+}
+
+pub fn write_manifest<T: Serialize>(path: &Path, manifest: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            std::fs::create_dir_all(parent).with_context(|| format!("Create {:?}", parent))?;
+        }
+    }
+    let mut file =
+        buffers::writer(File::create(path).with_context(|| format!("Create {:?}", path))?);
+    serde_json::to_writer_pretty(&mut file, manifest)?;
+    file.write_all(b"\n")?;
+    file.flush()?;
+    Ok(())
+}
+
+pub fn read_manifest_files(path: &Path) -> Result<ManifestFiles> {
+    let file = File::open(path).with_context(|| format!("Open manifest {:?}", path))?;
+    serde_json::from_reader(buffers::reader(file))
+        .with_context(|| format!("Parse manifest {:?}", path))
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("Open {:?}", path))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .with_context(|| format!("Read {:?}", path))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -0,0 +1,57 @@
+use std::io::{self, Write};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::exit::ExitCode;
+use crate::GlobalArgs;
+
+/// How a command reports its result on stdout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable status lines.
+    #[default]
+    Text,
+    /// One JSON document per line; status lines move to stderr.
+    Json,
+}
+
+/// Prints a human status line. Under `--output-format json` it goes to stderr so stdout carries
+/// only JSON documents.
+macro_rules! status {
+    ($global:expr, $($arg:tt)*) => {
+        if $global.output_format == $crate::output::OutputFormat::Json {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+pub(crate) use status;
+
+/// Writes `{"command": ..., "result": ...}` to stdout when JSON output is selected.
+pub fn emit<T: Serialize>(global: &GlobalArgs, command: &str, result: &T) -> Result<()> {
+    if global.output_format != OutputFormat::Json {
+        return Ok(());
+    }
+    write_line(&json!({ "command": command, "result": result }))
+}
+
+/// Writes `{"error": ..., "exit_code": ...}` to stdout when JSON output is selected. Follows the
+/// result document for commands that report partial results before failing.
+pub fn emit_error(global: &GlobalArgs, err: &anyhow::Error, code: ExitCode) {
+    if global.output_format != OutputFormat::Json {
+        return;
+    }
+    let _ = write_line(&json!({ "error": format!("{:#}", err), "exit_code": code as u8 }));
+}
+
+fn write_line(value: &serde_json::Value) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    serde_json::to_writer(&mut stdout, value)?;
+    stdout.write_all(b"\n")?;
+    stdout.flush()?;
+    Ok(())
+}
@@ -0,0 +1,219 @@
+#[cfg(feature = "download")]
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use serde_json::json;
+
+use biosynth_core::buffers;
+#[cfg(feature = "download")]
+use biosynth_core::download::DownloadProgress;
+pub use biosynth_core::progress::ProgressEvent;
+
+#[cfg(feature = "tui")]
+use crate::dashboard::Dashboard;
+use crate::GlobalArgs;
+
+/// Fans progress events out to whichever displays are enabled for the run.
+pub struct Progress {
+    json: Option<JsonSink>,
+    bar: Option<ProgressBar>,
+    #[cfg(feature = "tui")]
+    dashboard: Option<Dashboard>,
+}
+
+impl Progress {
+    /// `show_bar` controls the default indicatif bar; it is replaced by the dashboard when
+    /// `--dashboard` is set. NDJSON events from `--progress-json` are emitted alongside either,
+    /// except that the bar is hidden when both would write to stderr.
+    #[cfg_attr(not(feature = "tui"), allow(unused_variables))]
+    pub fn start(
+        global: &GlobalArgs,
+        title: &str,
+        total: usize,
+        workers: usize,
+        show_bar: bool,
+    ) -> Result<Self> {
+        let json = match &global.progress_json {
+            Some(dest) => Some(JsonSink::open(dest, title)?),
+            None => None,
+        };
+        if let Some(sink) = &json {
+            sink.write(&json!({ "event": "run_started", "total": total, "workers": workers }));
+        }
+
+        #[cfg(feature = "tui")]
+        if global.dashboard {
+            return Ok(Self {
+                json,
+                bar: None,
+                dashboard: Some(Dashboard::start(title, total, workers)?),
+            });
+        }
+
+        let json_on_stderr = json.as_ref().is_some_and(|sink| sink.is_stderr);
+        let bar = (show_bar && !json_on_stderr).then(|| {
+            let bar = ProgressBar::new(total as u64);
+            bar.set_style(
+                ProgressStyle::default_bar()
+                    .template("{spinner} {pos}/{len} [{wide_bar}] {msg}")
+                    .expect("valid progress template")
+                    .progress_chars("=>-"),
+            );
+            bar
+        });
+        Ok(Self {
+            json,
+            bar,
+            #[cfg(feature = "tui")]
+            dashboard: None,
+        })
+    }
+
+    pub fn emit(&self, event: ProgressEvent) {
+        if let Some(sink) = &self.json {
+            sink.write(&event);
+        }
+        #[cfg(feature = "tui")]
+        if let Some(dashboard) = &self.dashboard {
+            dashboard.record(event);
+            return;
+        }
+        if let Some(bar) = &self.bar {
+            if !matches!(event, ProgressEvent::Started { .. }) {
+                bar.inc(1);
+            }
+        }
+    }
+
+    pub fn finish(self, message: &'static str) -> Result<()> {
+        if let Some(sink) = &self.json {
+            sink.write(&json!({ "event": "run_finished" }));
+        }
+        if let Some(bar) = self.bar {
+            bar.finish_with_message(message);
+        }
+        #[cfg(feature = "tui")]
+        if let Some(dashboard) = self.dashboard {
+            dashboard.finish()?;
+        }
+        Ok(())
+    }
+}
+
+/// Byte counts, speed and ETA for a download, or a spinner until the server reports its size.
+#[cfg(feature = "download")]
+pub struct DownloadBar {
+    bar: Option<ProgressBar>,
+    started: Cell<bool>,
+}
+
+#[cfg(feature = "download")]
+impl DownloadBar {
+    /// Hidden, like the file progress bar, when `--progress-json` writes to stderr.
+    pub fn start(global: &GlobalArgs) -> Self {
+        let json_on_stderr = global
+            .progress_json
+            .as_deref()
+            .is_some_and(|dest| dest == Path::new("-"));
+        let bar = (!json_on_stderr).then(|| {
+            let bar = ProgressBar::no_length();
+            bar.set_style(
+                ProgressStyle::default_spinner()
+                    .template("{spinner} {bytes} {binary_bytes_per_sec}")
+                    .expect("valid progress template"),
+            );
+            bar
+        });
+        Self {
+            bar,
+            started: Cell::new(false),
+        }
+    }
+
+    pub fn update(&self, progress: DownloadProgress) {
+        let Some(bar) = &self.bar else {
+            return;
+        };
+        if bar.length().is_none() {
+            if let Some(total) = progress.total {
+                bar.set_length(total);
+                bar.set_style(
+                    ProgressStyle::default_bar()
+                        .template(
+                            "{spinner} {bytes}/{total_bytes} [{wide_bar}] \
+                             {binary_bytes_per_sec}, ETA {eta}",
+                        )
+                        .expect("valid progress template")
+                        .progress_chars("=>-"),
+                );
+            }
+        }
+        bar.set_position(progress.downloaded);
+        if !self.started.replace(true) {
+            // The first report covers bytes kept from an interrupted run, which must not count
+            // towards the speed.
+            bar.reset_eta();
+        }
+    }
+
+    pub fn finish(self) {
+        if let Some(bar) = self.bar {
+            bar.finish();
+        }
+    }
+}
+
+/// Newline-delimited JSON writer for `--progress-json`; `-` selects stderr.
+struct JsonSink {
+    command: String,
+    is_stderr: bool,
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+#[derive(Serialize)]
+struct JsonRecord<'a, T: Serialize> {
+    timestamp: String,
+    command: &'a str,
+    #[serde(flatten)]
+    payload: &'a T,
+}
+
+impl JsonSink {
+    fn open(dest: &Path, command: &str) -> Result<Self> {
+        let is_stderr = dest == Path::new("-");
+        let writer: Box<dyn Write + Send> = if is_stderr {
+            Box::new(io::stderr())
+        } else {
+            let file =
+                File::create(dest).with_context(|| format!("Create progress log {:?}", dest))?;
+            Box::new(buffers::writer(file))
+        };
+        Ok(Self {
+            command: command.to_string(),
+            is_stderr,
+            writer: Mutex::new(writer),
+        })
+    }
+
+    /// Progress output is best-effort: a failed write must not abort the run.
+    fn write<T: Serialize>(&self, payload: &T) {
+        let record = JsonRecord {
+            timestamp: Utc::now().to_rfc3339(),
+            command: &self.command,
+            payload,
+        };
+        let Ok(line) = serde_json::to_string(&record) else {
+            return;
+        };
+        let mut writer = self.writer.lock().expect("poisoned progress writer");
+        let _ = writeln!(writer, "{}", line);
+        let _ = writer.flush();
+    }
+}
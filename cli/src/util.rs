@@ -1,9 +1,158 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
+use biosynth_core::archive::{genotype_entries, is_archive};
+use biosynth_core::buffers::is_stdin;
+use biosynth_core::download::DATA_DIR;
+use biosynth_core::final_report::{self, is_final_report};
+use biosynth_core::plink::{is_bed, sample_paths};
+use biosynth_core::policy::ReleasedColumn;
+use biosynth_core::pseudonym::{ParticipantHasher, SALT_FILENAME};
+use biosynth_core::stats::StatsBackend;
+use biosynth_core::vcf::{self, is_vcf};
+use directories::ProjectDirs;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use walkdir::WalkDir;
 
+use crate::output::status;
+use crate::{GlobalArgs, MaintenanceArgs};
+
+/// The `rsid_reference` columns generated genotypes are drawn from, for the export policy.
+pub const REFERENCE_COLUMNS: &[ReleasedColumn<'static>] = &[
+    ("rsid_reference", Some("rsid")),
+    ("rsid_reference", Some("chromosome")),
+    ("rsid_reference", Some("position")),
+    ("rsid_reference", Some("reference")),
+    ("rsid_reference", Some("alternates")),
+];
+
+/// The default `--data-dir`: `./data` when it exists, as in a checkout or an existing setup,
+/// so relative paths keep working there; otherwise the platform's per-user data directory, so
+/// runs from any working directory share one set of references.
+pub fn default_data_dir() -> PathBuf {
+    let local = PathBuf::from(DATA_DIR);
+    if local.is_dir() {
+        return local;
+    }
+    ProjectDirs::from("org", "OpenMined", "biosynth")
+        .map(|dirs| dirs.data_dir().to_path_buf())
+        .unwrap_or(local)
+}
+
+/// The project's participant pseudonymizer, salted per data directory, recording new
+/// pseudonyms in `lookup` if given.
+pub fn participant_hasher(
+    global: &GlobalArgs,
+    lookup: Option<PathBuf>,
+) -> Result<ParticipantHasher> {
+    let hasher = ParticipantHasher::load_or_create(&global.data_dir.join(SALT_FILENAME))?;
+    Ok(match lookup {
+        Some(lookup) => hasher.with_lookup(lookup),
+        None => hasher,
+    })
+}
+
+/// Resolves `--threads`, falling back to the available parallelism when unset or zero.
+pub fn resolve_thread_count(threads: Option<usize>) -> usize {
+    threads.filter(|&count| count > 0).unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|count| count.get())
+            .unwrap_or(1)
+    })
+}
+
+pub fn build_thread_pool(threads: Option<usize>) -> Result<ThreadPool> {
+    ThreadPoolBuilder::new()
+        .num_threads(resolve_thread_count(threads))
+        .build()
+        .context("build rayon thread pool")
+}
+
+/// How a command treats an output path that already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    Refuse,
+    Overwrite,
+    Skip,
+}
+
+impl OverwritePolicy {
+    /// Returns whether `path` should be written; errors when it exists and the policy refuses.
+    pub fn should_write(self, path: &Path) -> Result<bool> {
+        if !path.exists() {
+            return Ok(true);
+        }
+        match self {
+            OverwritePolicy::Refuse => bail!(
+                "{} already exists; pass --overwrite to replace it or --no-clobber to skip it",
+                path.display()
+            ),
+            OverwritePolicy::Overwrite => Ok(true),
+            OverwritePolicy::Skip => Ok(false),
+        }
+    }
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Parses a byte count such as `65536`, `256K` or `4M` (binary units).
+pub fn parse_byte_size(raw: &str) -> std::result::Result<usize, String> {
+    let raw = raw.trim();
+    let (digits, multiplier) = match raw.char_indices().last() {
+        Some((idx, 'k' | 'K')) => (&raw[..idx], 1024),
+        Some((idx, 'm' | 'M')) => (&raw[..idx], 1024 * 1024),
+        _ => (raw, 1),
+    };
+    let value: usize = digits
+        .parse()
+        .map_err(|_| format!("`{}` is not a byte size", raw))?;
+    match value.checked_mul(multiplier) {
+        Some(0) => Err("byte size must be greater than 0".into()),
+        Some(bytes) => Ok(bytes),
+        None => Err(format!("`{}` is too large", raw)),
+    }
+}
+
+/// Runs the upkeep `maintenance` asks for on a freshly loaded database.
+pub fn maintain(
+    store: &dyn StatsBackend,
+    maintenance: &MaintenanceArgs,
+    global: &GlobalArgs,
+) -> Result<()> {
+    if maintenance.no_optimize {
+        return Ok(());
+    }
+    store.optimize(maintenance.vacuum)?;
+    if maintenance.vacuum {
+        status!(
+            global,
+            "🧹 Refreshed query statistics and reclaimed free space"
+        );
+    } else {
+        status!(global, "🧹 Refreshed query statistics");
+    }
+    Ok(())
+}
+
+/// Genotype files under `inputs`. A `.zip` archive stands for the genotype files inside it,
+/// listed as `export.zip/genome.txt`, and a PLINK `.bed` for the samples of its fileset,
+/// listed as `cohort.bed/sample-1`, as do an Illumina Final Report with several sample
+/// blocks (`report.txt/sample-1`) and a VCF with several sample columns. `-` is kept as is,
+/// for standard input.
 pub fn collect_input_files(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
     if inputs.is_empty() {
         bail!("Provide at least one --input path");
@@ -11,8 +160,24 @@ pub fn collect_input_files(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
 
     let mut files = Vec::new();
     for input in inputs {
+        if is_stdin(input) {
+            files.push(input.clone());
+            continue;
+        }
+        if input.is_file() && is_archive(input) {
+            let entries = genotype_entries(&canonicalize_path(input)?)?;
+            if entries.is_empty() {
+                bail!("Archive {:?} holds no genotype files", input);
+            }
+            files.extend(entries);
+            continue;
+        }
+        if input.is_file() && is_bed(input) {
+            files.extend(sample_paths(&canonicalize_path(input)?)?);
+            continue;
+        }
         if input.is_file() {
-            files.push(canonicalize_path(input)?);
+            files.extend(per_sample_paths(&canonicalize_path(input)?)?);
             continue;
         }
 
@@ -22,8 +187,12 @@ pub fn collect_input_files(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
                     continue;
                 }
                 let path = entry.path();
-                if is_candidate_file(path) {
-                    files.push(canonicalize_path(path)?);
+                if is_archive(path) {
+                    files.extend(genotype_entries(&canonicalize_path(path)?)?);
+                } else if is_bed(path) {
+                    files.extend(sample_paths(&canonicalize_path(path)?)?);
+                } else if is_candidate_file(path) {
+                    files.extend(per_sample_paths(&canonicalize_path(path)?)?);
                 }
             }
             continue;
@@ -37,6 +206,21 @@ pub fn collect_input_files(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
     Ok(files)
 }
 
+/// The per-sample paths of a multi-sample VCF or Final Report, else just `path`.
+fn per_sample_paths(path: &Path) -> Result<Vec<PathBuf>> {
+    let samples = if is_vcf(path) {
+        vcf::sample_paths(path)?
+    } else if is_final_report(path) {
+        final_report::sample_paths(path)?
+    } else {
+        Vec::new()
+    };
+    if samples.len() > 1 {
+        return Ok(samples);
+    }
+    Ok(vec![path.to_path_buf()])
+}
+
 fn canonicalize_path<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
     let path_ref = path.as_ref();
     match fs::canonicalize(path_ref) {
@@ -47,8 +231,19 @@ fn canonicalize_path<P: AsRef<Path>>(path: P) -> Result<PathBuf> {
 
 fn is_candidate_file(path: &Path) -> bool {
     if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        if ext.eq_ignore_ascii_case("gz") {
+            // `genome.txt.gz` and `sample.vcf.gz` are staged and decompressed at ingest.
+            return path
+                .file_stem()
+                .map(Path::new)
+                .is_some_and(is_candidate_file);
+        }
         let ext_lower = ext.to_lowercase();
-        return matches!(ext_lower.as_str(), "txt" | "tsv" | "csv");
+        return match ext_lower.as_str() {
+            "txt" | "tsv" | "csv" | "vcf" | "jsonl" | "xlsx" => true,
+            "bcf" => cfg!(feature = "bcf"),
+            _ => false,
+        };
     }
     true
 }
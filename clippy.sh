@@ -1,10 +1,10 @@
 #!/usr/bin/env bash
 set -euo pipefail
 
-cd cli
+cd "$(dirname "${BASH_SOURCE[0]}")"
 
 # Enforce formatting
 cargo fmt --all
 
 # Lint everything (lib, bins, tests, benches, examples), treat warnings as errors
-cargo clippy --fix --allow-dirty --all-targets --all-features --no-deps -- -D warnings
+cargo clippy --fix --allow-dirty --workspace --all-targets --all-features --no-deps -- -D warnings
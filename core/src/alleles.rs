@@ -0,0 +1,127 @@
+//! Normalization of alleles and genotype calls, applied when files are parsed, when reference
+//! rows are loaded, and when synthetic rows are written, so the same variant compares equal
+//! however its source spelled it.
+
+/// IUPAC codes standing for more than one base.
+const AMBIGUITY_CODES: &[u8] = b"RYSWKMBDHV";
+/// Symbols of a valid genotype call: bases, `D`/`I` for deletions and insertions, and `-` or
+/// `0` for no-calls.
+const GENOTYPE_ALPHABET: &[u8] = b"ACGTDI-0";
+
+/// Uppercases `allele`, reads RNA `U` as `T`, and replaces IUPAC ambiguity codes with `N`.
+/// Symbols that are not bases, such as VCF's `*` and `.`, are kept as they are, and symbolic
+/// alleles (`<DEL>`) and breakends (`G]17:198982]`) are returned untouched.
+pub fn normalize_allele(allele: &str) -> String {
+    let allele = allele.trim();
+    if allele.contains(['<', '[', ']']) {
+        return allele.to_string();
+    }
+    allele
+        .chars()
+        .map(|symbol| match symbol.to_ascii_uppercase() {
+            'U' => 'T',
+            code if code.is_ascii() && AMBIGUITY_CODES.contains(&(code as u8)) => 'N',
+            symbol => symbol,
+        })
+        .collect()
+}
+
+/// Uppercases a genotype call, reads `U` as `T`, and drops allele separators, so `a/g`,
+/// `A|G`, and `A G` all become `AG`. `D` and `I` are deletion and insertion calls here, not
+/// ambiguity codes, so no other letter is rewritten.
+pub fn normalize_genotype(call: &str) -> String {
+    if call.bytes().all(|byte| GENOTYPE_ALPHABET.contains(&byte)) {
+        return call.to_string();
+    }
+    call.chars()
+        .filter(|symbol| !matches!(symbol, '/' | '|' | ' ' | '\t'))
+        .map(|symbol| match symbol.to_ascii_uppercase() {
+            'U' => 'T',
+            symbol => symbol,
+        })
+        .collect()
+}
+
+/// Whether a [normalized](normalize_genotype) call is non-empty and uses only `A`, `C`, `G`,
+/// `T`, `D`, `I`, `-`, and `0`.
+pub fn is_valid_genotype(call: &str) -> bool {
+    !call.is_empty() && call.bytes().all(|byte| GENOTYPE_ALPHABET.contains(&byte))
+}
+
+/// A variant's position and alleles after [`normalize_variant`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedVariant {
+    pub position: i64,
+    pub reference: String,
+    pub alternates: Vec<String>,
+}
+
+/// Normalizes every allele, drops empty and repeated alternates and any equal to the
+/// reference (keeping the rest in their order), then [`left_align`]s the result.
+pub fn normalize_variant<'a>(
+    position: i64,
+    reference: &str,
+    alternates: impl IntoIterator<Item = &'a str>,
+) -> NormalizedVariant {
+    let reference = normalize_allele(reference);
+    let mut ordered: Vec<String> = Vec::new();
+    for alternate in alternates.into_iter().map(normalize_allele) {
+        if !alternate.is_empty() && alternate != reference && !ordered.contains(&alternate) {
+            ordered.push(alternate);
+        }
+    }
+    left_align(NormalizedVariant {
+        position,
+        reference,
+        alternates: ordered,
+    })
+}
+
+/// Trims bases shared by the end, then by the start, of every allele, moving the position
+/// past any leading bases removed, while each allele keeps at least one base (the VCF anchor
+/// of an indel). Shifting an indel further left through a repeat would need the reference
+/// sequence, which biosynth does not hold, so alignment stops where the alleles end. Variants
+/// with symbolic alleles are returned unchanged.
+pub fn left_align(variant: NormalizedVariant) -> NormalizedVariant {
+    let is_sequence = |allele: &String| {
+        !allele.is_empty()
+            && allele
+                .bytes()
+                .all(|base| matches!(base, b'A' | b'C' | b'G' | b'T' | b'N'))
+    };
+    if variant.alternates.is_empty()
+        || !is_sequence(&variant.reference)
+        || !variant.alternates.iter().all(is_sequence)
+    {
+        return variant;
+    }
+    let shared = |alleles: &[&[u8]], base: fn(&[u8]) -> u8| {
+        alleles.iter().all(|allele| allele.len() > 1)
+            && alleles
+                .windows(2)
+                .all(|pair| base(pair[0]) == base(pair[1]))
+    };
+    let (mut start, mut end) = (0, 0);
+    while shared(&trimmed_alleles(&variant, start, end), |allele| {
+        allele[allele.len() - 1]
+    }) {
+        end += 1;
+    }
+    while shared(&trimmed_alleles(&variant, start, end), |allele| allele[0]) {
+        start += 1;
+    }
+    let trim = |allele: &String| allele[start..allele.len() - end].to_string();
+    NormalizedVariant {
+        position: variant.position + start as i64,
+        reference: trim(&variant.reference),
+        alternates: variant.alternates.iter().map(trim).collect(),
+    }
+}
+
+/// Every allele of `variant` without its first `start` and last `end` bases.
+fn trimmed_alleles(variant: &NormalizedVariant, start: usize, end: usize) -> Vec<&[u8]> {
+    std::iter::once(&variant.reference)
+        .chain(&variant.alternates)
+        .map(|allele| &allele.as_bytes()[start..allele.len() - end])
+        .collect()
+}
@@ -0,0 +1,102 @@
+//! Genotype files inside `.zip` archives, as 23andMe and other vendors deliver them.
+//!
+//! An archive entry is addressed as if the archive were a directory: `export.zip/genome.txt`.
+//! [`genotype_entries`] lists those paths for an archive, and [`read_entry`] inflates one
+//! into memory, so raw genotype data from an archive never touches the disk.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use zip::ZipArchive;
+
+use crate::buffers;
+use crate::error::{BiosynthError, Context, Result};
+
+/// Extensions of archive entries read as genotype files.
+const GENOTYPE_EXTENSIONS: [&str; 5] = ["txt", "tsv", "csv", "vcf", "jsonl"];
+/// Largest entry inflated into memory; consumer exports are tens of megabytes, so anything
+/// bigger is more likely a decompression bomb than a genotype file.
+const MAX_ENTRY_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Whether `path` is named like a zip archive.
+pub fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// Paths of the genotype files in `archive`, sorted: entries with a genotype file extension,
+/// leaving out directories and macOS resource forks (`__MACOSX/`, `._*`).
+pub fn genotype_entries(archive: &Path) -> Result<Vec<PathBuf>> {
+    let zip = open(archive)?;
+    let mut entries: Vec<PathBuf> = zip
+        .file_names()
+        .filter(|name| is_genotype_entry(name))
+        .map(|name| archive.join(name))
+        .collect();
+    entries.sort();
+    Ok(entries)
+}
+
+/// The archive and entry name a path from [`genotype_entries`] refers to; `None` for paths
+/// outside any archive.
+pub fn split_entry(path: &Path) -> Option<(&Path, String)> {
+    let archive = path
+        .ancestors()
+        .skip(1)
+        .find(|ancestor| is_archive(ancestor) && ancestor.is_file())?;
+    let name = path.strip_prefix(archive).ok()?;
+    let name = name
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Some((archive, name))
+}
+
+/// Inflates entry `name` of `archive` into memory.
+pub fn read_entry(archive: &Path, name: &str) -> Result<Vec<u8>> {
+    let mut zip = open(archive)?;
+    let entry = zip
+        .by_name(name)
+        .map_err(|err| zip_error(archive, &format!("Find entry {:?} in", name), err))?;
+    if entry.size() > MAX_ENTRY_BYTES {
+        return Err(BiosynthError::Parse(format!(
+            "Entry {:?} of {:?} inflates to {} bytes, more than the {} allowed",
+            name,
+            archive,
+            entry.size(),
+            MAX_ENTRY_BYTES
+        )));
+    }
+    let mut bytes = Vec::with_capacity(entry.size() as usize);
+    entry
+        .take(MAX_ENTRY_BYTES)
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("Inflate {:?} from {:?}", name, archive))?;
+    Ok(bytes)
+}
+
+fn open(archive: &Path) -> Result<ZipArchive<std::io::BufReader<File>>> {
+    let file = File::open(archive).with_context(|| format!("Failed to open {:?}", archive))?;
+    ZipArchive::new(buffers::reader(file)).map_err(|err| zip_error(archive, "Read archive", err))
+}
+
+fn is_genotype_entry(name: &str) -> bool {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    !name.ends_with('/')
+        && !name.starts_with("__MACOSX/")
+        && !file_name.starts_with("._")
+        && Path::new(file_name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| {
+                GENOTYPE_EXTENSIONS
+                    .iter()
+                    .any(|known| ext.eq_ignore_ascii_case(known))
+            })
+}
+
+fn zip_error(archive: &Path, action: &str, err: zip::result::ZipError) -> BiosynthError {
+    BiosynthError::Parse(format!("{} {:?}: {}", action, archive, err))
+}
@@ -0,0 +1,541 @@
+//! Tokio-compatible variants of parsing, downloading, and generation (feature `async`).
+
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use reqwest::header::{IF_RANGE, RANGE};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tokio::fs::{self, File};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader,
+};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+use crate::buffers;
+use crate::download::{
+    check_part, classify_check, classify_response, compressed_path, contiguous_prefix,
+    parse_checksum, strong_etag, transfer_validators, validated_url, Download, DownloadProgress,
+    FetchedReference, HttpConfig, PartPlan, PartialDownload, ReferenceCheck, ReferenceSource,
+    Release, RemoteValidators, Transfer, CHECKSUM_SUFFIX, COMPRESSED_SUFFIX,
+    DEFAULT_REFERENCE_VERSION, GITHUB_API_LATEST_RELEASE, GITHUB_API_TAGS,
+    UNRELEASED_REFERENCE_VERSION,
+};
+use crate::error::{BiosynthError, Context, Result};
+use crate::genotype::{
+    detect_delimiter, LineOutcome, LineParser, Lookahead, ParseOptions, ParseSummary, VariantRecord,
+};
+use crate::stats::ReferenceVariant;
+use crate::synthetic::{OverlaySpec, Sex};
+
+/// Async counterpart of [`GenotypeReader`](crate::genotype::GenotypeReader).
+///
+/// ```no_run
+/// # async fn run() -> biosynth_core::Result<()> {
+/// use biosynth_core::asynchronous::AsyncGenotypeReader;
+///
+/// let mut reader = AsyncGenotypeReader::open("sample.txt".as_ref()).await?;
+/// while let Some(record) = reader.next_record().await? {
+///     println!("{} {}", record.rsid, record.genotype);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncGenotypeReader<R: AsyncBufRead + Unpin> {
+    reader: R,
+    lookahead: Lookahead,
+    parser: LineParser,
+    summary: ParseSummary,
+    buffer: String,
+}
+
+impl AsyncGenotypeReader<BufReader<File>> {
+    pub async fn open(path: &Path) -> Result<Self> {
+        Self::open_with_options(path, &ParseOptions::default()).await
+    }
+
+    pub async fn open_with_options(path: &Path, options: &ParseOptions) -> Result<Self> {
+        let file = File::open(path)
+            .await
+            .with_context(|| format!("Failed to open {:?}", path))?;
+        if file.metadata().await.is_ok_and(|meta| meta.len() == 0) {
+            return Err(BiosynthError::Parse(format!("File {:?} is empty", path)));
+        }
+        let reader = BufReader::with_capacity(buffers::io_buffer_size(), file);
+        Self::from_reader_with_options(reader, options).await
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncGenotypeReader<R> {
+    pub async fn from_reader(reader: R) -> Result<Self> {
+        Self::from_reader_with_options(reader, &ParseOptions::default()).await
+    }
+
+    pub async fn from_reader_with_options(mut reader: R, options: &ParseOptions) -> Result<Self> {
+        let mut lookahead = Lookahead::default();
+        while !lookahead.is_full() {
+            if reader.read_line(lookahead.buffer()).await? == 0 {
+                break;
+            }
+            lookahead.end_line();
+        }
+        if lookahead.is_empty() {
+            return Err(BiosynthError::Parse("Genotype input is empty".into()));
+        }
+        let delimiter = options
+            .delimiter
+            .unwrap_or_else(|| detect_delimiter(lookahead.lines()));
+        Ok(Self {
+            reader,
+            lookahead,
+            parser: LineParser::new(delimiter, options),
+            summary: ParseSummary::default(),
+            buffer: String::new(),
+        })
+    }
+
+    /// Returns the next usable row, or `None` at end of input.
+    pub async fn next_record(&mut self) -> Result<Option<VariantRecord>> {
+        loop {
+            let line = match self.lookahead.next_line() {
+                Some(line) => line,
+                None => {
+                    self.buffer.clear();
+                    if self.reader.read_line(&mut self.buffer).await? == 0 {
+                        return Ok(None);
+                    }
+                    &self.buffer
+                }
+            };
+            match self.parser.parse_line(line)? {
+                LineOutcome::Parsed(record) => {
+                    self.summary.variant_count += 1;
+                    return Ok(Some(record));
+                }
+                LineOutcome::Skipped => self.summary.skipped_rows += 1,
+                LineOutcome::Ignored => {}
+            }
+        }
+    }
+
+    /// Counts for the rows consumed so far.
+    pub fn summary(&self) -> ParseSummary {
+        ParseSummary {
+            invalid_genotypes: self.parser.invalid_genotypes,
+            ..self.summary
+        }
+    }
+}
+
+/// Async counterpart of [`download::fetch_reference`](crate::download::fetch_reference),
+/// streaming the response to disk.
+pub async fn fetch_reference(
+    source: &ReferenceSource,
+    http: &HttpConfig,
+    version: &str,
+    dest: &Path,
+    on_progress: &mut (dyn FnMut(DownloadProgress) + Send),
+) -> Result<FetchedReference> {
+    if let Some(parent) = dest.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("Create directory {:?}", parent))?;
+        }
+    }
+    let client = http_client(http)?;
+    let version = resolve_reference_version(&client, source, version).await?;
+    let url = source.reference_url(&version);
+    let expected = fetch_checksum(&client, &format!("{}{}", url, CHECKSUM_SUFFIX)).await?;
+    let partial = PartialDownload::for_dest(dest);
+    let compressed = PartialDownload::for_dest(&compressed_path(dest));
+    let compressed_url = format!("{}{}", url, COMPRESSED_SUFFIX);
+    let connections = http.connections;
+    let download = match download_file(
+        &client,
+        &compressed_url,
+        &compressed,
+        connections,
+        on_progress,
+    )
+    .await?
+    {
+        Some(download) => {
+            let target = partial.clone();
+            tokio::task::spawn_blocking(move || download.decompressed(compressed, &target))
+                .await
+                .map_err(BiosynthError::TaskFailed)??
+        }
+        None => {
+            compressed.discard()?;
+            download_file(&client, &url, &partial, connections, on_progress)
+                .await?
+                .ok_or(BiosynthError::HttpStatus {
+                    status: reqwest::StatusCode::NOT_FOUND.as_u16(),
+                    url,
+                })?
+        }
+    };
+    partial.install(dest, expected.as_deref(), download, version)
+}
+
+/// Async counterpart of streaming `url` into `partial`, split across up to `connections`
+/// range requests when it is large; `None` if the server has no such file.
+async fn download_file(
+    client: &reqwest::Client,
+    url: &str,
+    partial: &PartialDownload,
+    connections: usize,
+    on_progress: &mut (dyn FnMut(DownloadProgress) + Send),
+) -> Result<Option<Download>> {
+    let resume = partial.resumable();
+    if resume.is_none() && connections > 1 {
+        let probe = client
+            .head(url)
+            .send()
+            .await
+            .with_context(|| format!("Download from {}", url))?;
+        if probe.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if let Some(plan) = PartPlan::new(probe.status(), probe.headers(), connections) {
+            return download_parts(client, url, partial, plan, on_progress)
+                .await
+                .map(Some);
+        }
+    }
+    let mut request = client.get(url);
+    if let Some((offset, etag)) = &resume {
+        request = request
+            .header(RANGE, format!("bytes={}-", offset))
+            .header(IF_RANGE, etag);
+    }
+    let mut response = request
+        .send()
+        .await
+        .with_context(|| format!("Download from {}", url))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    let validators = transfer_validators(response.headers(), resume.as_ref());
+    let offset = resume.map_or(0, |(offset, _)| offset);
+    let transfer = classify_response(response.status(), offset, url)?;
+    let mut progress = DownloadProgress::starting(&transfer, offset, response.content_length());
+    on_progress(progress);
+    let (file, mut hasher, resumed) = match transfer {
+        Transfer::Fresh => (
+            Some(partial.start(strong_etag(response.headers()))?),
+            Sha256::new(),
+            0,
+        ),
+        Transfer::Resumed => (
+            Some(partial.open_append()?),
+            hash_file(&partial.path).await?,
+            offset,
+        ),
+        Transfer::Complete => (None, hash_file(&partial.path).await?, offset),
+    };
+    if let Some(file) = file {
+        let mut file = File::from_std(file);
+        while let Some(chunk) = response.chunk().await.with_context(|| {
+            format!(
+                "Download from {} to {:?}; run again to resume",
+                url, partial.path
+            )
+        })? {
+            hasher.update(&chunk);
+            file.write_all(&chunk)
+                .await
+                .with_context(|| format!("Write to {:?}", partial.path))?;
+            progress.downloaded += chunk.len() as u64;
+            on_progress(progress);
+        }
+        file.sync_all()
+            .await
+            .with_context(|| format!("Write to {:?}", partial.path))?;
+    }
+    Ok(Some(Download {
+        sha256: format!("{:x}", hasher.finalize()),
+        resumed_bytes: resumed,
+        validators,
+        compressed_bytes: None,
+    }))
+}
+
+/// Async counterpart of fetching a [`PartPlan`]'s ranges concurrently, then hashing the file.
+async fn download_parts(
+    client: &reqwest::Client,
+    url: &str,
+    partial: &PartialDownload,
+    plan: PartPlan,
+    on_progress: &mut (dyn FnMut(DownloadProgress) + Send),
+) -> Result<Download> {
+    // As in the blocking version, the ETag is only recorded once the file holds no gaps.
+    let file = partial.start(None)?;
+    file.set_len(plan.len)
+        .with_context(|| format!("Write to {:?}", partial.path))?;
+    let ranges = plan.ranges();
+    let fetched: Arc<[AtomicU64]> = ranges.iter().map(|_| AtomicU64::new(0)).collect();
+    let abort = Arc::new(AtomicBool::new(false));
+    let (sender, mut received) = mpsc::unbounded_channel();
+    let mut workers = JoinSet::new();
+    for (index, &range) in ranges.iter().enumerate() {
+        let (client, url, partial) = (client.clone(), url.to_string(), partial.clone());
+        let (etag, fetched, abort) = (plan.etag.clone(), fetched.clone(), abort.clone());
+        let sender = sender.clone();
+        workers.spawn(async move {
+            let result = fetch_range(&client, &url, &partial, &etag, range, |bytes| {
+                fetched[index].fetch_add(bytes, Ordering::Relaxed);
+                let _ = sender.send(bytes);
+                !abort.load(Ordering::Relaxed)
+            })
+            .await;
+            if result.is_err() {
+                abort.store(true, Ordering::Relaxed);
+            }
+            result
+        });
+    }
+    drop(sender);
+    let mut progress = DownloadProgress {
+        downloaded: 0,
+        total: Some(plan.len),
+    };
+    on_progress(progress);
+    while let Some(bytes) = received.recv().await {
+        progress.downloaded += bytes;
+        on_progress(progress);
+    }
+    let mut result = Ok(());
+    while let Some(joined) = workers.join_next().await {
+        let outcome = joined
+            .map_err(BiosynthError::TaskFailed)
+            .and_then(|outcome| outcome);
+        if result.is_ok() {
+            result = outcome;
+        }
+    }
+    if let Err(err) = result {
+        let fetched: Vec<u64> = fetched.iter().map(|f| f.load(Ordering::Relaxed)).collect();
+        partial.keep_prefix(contiguous_prefix(&ranges, &fetched), &plan.etag)?;
+        return Err(err);
+    }
+    Ok(Download {
+        sha256: format!("{:x}", hash_file(&partial.path).await?.finalize()),
+        resumed_bytes: 0,
+        validators: plan.validators,
+        compressed_bytes: None,
+    })
+}
+
+/// Async counterpart of writing one inclusive byte `range` of `url` into `partial`.
+async fn fetch_range(
+    client: &reqwest::Client,
+    url: &str,
+    partial: &PartialDownload,
+    etag: &str,
+    (start, end): (u64, u64),
+    mut on_chunk: impl FnMut(u64) -> bool,
+) -> Result<()> {
+    let mut response = client
+        .get(url)
+        .header(RANGE, format!("bytes={}-{}", start, end))
+        .header(IF_RANGE, etag)
+        .send()
+        .await
+        .with_context(|| format!("Download from {}", url))?;
+    check_part(response.status(), url)?;
+    let mut file = File::from_std(partial.open_write()?);
+    file.seek(SeekFrom::Start(start))
+        .await
+        .with_context(|| format!("Write to {:?}", partial.path))?;
+    let mut remaining = end - start + 1;
+    while remaining > 0 {
+        let Some(chunk) = response.chunk().await.with_context(|| {
+            format!(
+                "Download from {} to {:?}; run again to resume",
+                url, partial.path
+            )
+        })?
+        else {
+            return Err(BiosynthError::Parse(format!(
+                "{} ended {} bytes short of the requested range",
+                url, remaining
+            )));
+        };
+        let chunk = &chunk[..chunk.len().min(remaining as usize)];
+        file.write_all(chunk)
+            .await
+            .with_context(|| format!("Write to {:?}", partial.path))?;
+        remaining -= chunk.len() as u64;
+        if !on_chunk(chunk.len() as u64) {
+            return Ok(());
+        }
+    }
+    file.sync_all()
+        .await
+        .with_context(|| format!("Write to {:?}", partial.path))
+}
+
+/// Async counterpart of [`download::check_reference`](crate::download::check_reference).
+pub async fn check_reference(
+    source: &ReferenceSource,
+    http: &HttpConfig,
+    version: &str,
+    local_version: Option<&str>,
+    validators: &RemoteValidators,
+) -> Result<ReferenceCheck> {
+    let client = http_client(http)?;
+    let version = resolve_reference_version(&client, source, version).await?;
+    if local_version.is_some_and(|local| local != version) {
+        return Ok(ReferenceCheck::UpdateAvailable { version });
+    }
+    if validators.is_empty() {
+        return Ok(ReferenceCheck::Unknown { version });
+    }
+    let url = validated_url(source, &version, validators);
+    let response = client
+        .head(&url)
+        .headers(validators.conditions())
+        .send()
+        .await
+        .with_context(|| format!("Check {}", url))?;
+    classify_check(response.status(), &url, version)
+}
+
+/// Async counterpart of resolving [`DEFAULT_REFERENCE_VERSION`] to the newest release's tag.
+async fn resolve_reference_version(
+    client: &reqwest::Client,
+    source: &ReferenceSource,
+    version: &str,
+) -> Result<String> {
+    if version != DEFAULT_REFERENCE_VERSION {
+        return Ok(version.to_string());
+    }
+    source.require_github("resolve `latest`")?;
+    let response = client
+        .get(GITHUB_API_LATEST_RELEASE)
+        .header(reqwest::header::USER_AGENT, "bvs")
+        .send()
+        .await
+        .with_context(|| format!("Find the latest release at {}", GITHUB_API_LATEST_RELEASE))?;
+    if !response.status().is_success() {
+        return Err(BiosynthError::HttpStatus {
+            status: response.status().as_u16(),
+            url: GITHUB_API_LATEST_RELEASE.to_string(),
+        });
+    }
+    let release: Release = response.json().await.with_context(|| {
+        format!(
+            "Parse the latest release from {}",
+            GITHUB_API_LATEST_RELEASE
+        )
+    })?;
+    Ok(release.tag_name)
+}
+
+/// A hasher fed with the contents of `path`.
+async fn hash_file(path: &Path) -> Result<Sha256> {
+    let mut file = File::open(path)
+        .await
+        .with_context(|| format!("Open {:?}", path))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .await
+            .with_context(|| format!("Read {:?}", path))?;
+        if read == 0 {
+            return Ok(hasher);
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+/// The checksum published at `url`, or `None` if there is none.
+async fn fetch_checksum(client: &reqwest::Client, url: &str) -> Result<Option<String>> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("Download from {}", url))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(BiosynthError::HttpStatus {
+            status: response.status().as_u16(),
+            url: url.to_string(),
+        });
+    }
+    let text = response
+        .text()
+        .await
+        .with_context(|| format!("Read response from {}", url))?;
+    parse_checksum(&text, url).map(Some)
+}
+
+/// Async counterpart of
+/// [`download::list_reference_versions`](crate::download::list_reference_versions).
+pub async fn list_reference_versions(
+    source: &ReferenceSource,
+    http: &HttpConfig,
+) -> Result<Vec<String>> {
+    #[derive(Deserialize)]
+    struct Tag {
+        name: String,
+    }
+
+    source.require_github("list reference versions")?;
+
+    let response = http_client(http)?
+        .get(GITHUB_API_TAGS)
+        .header(reqwest::header::USER_AGENT, "bvs")
+        .send()
+        .await
+        .with_context(|| format!("List tags from {}", GITHUB_API_TAGS))?;
+    if !response.status().is_success() {
+        return Err(BiosynthError::HttpStatus {
+            status: response.status().as_u16(),
+            url: GITHUB_API_TAGS.to_string(),
+        });
+    }
+    let tags: Vec<Tag> = response
+        .json()
+        .await
+        .with_context(|| format!("Parse tags from {}", GITHUB_API_TAGS))?;
+
+    let mut versions = vec![UNRELEASED_REFERENCE_VERSION.to_string()];
+    versions.extend(tags.into_iter().map(|tag| tag.name));
+    Ok(versions)
+}
+
+/// Generates a synthetic file on tokio's blocking pool so callers can await it from async
+/// code. See [`synthetic::write_single_file`](crate::synthetic::write_single_file).
+pub async fn write_single_file(
+    path: PathBuf,
+    references: Arc<[ReferenceVariant]>,
+    overlays: Arc<[OverlaySpec]>,
+    alt_frequency: f64,
+    seed: Option<u64>,
+    sex: Option<Sex>,
+) -> Result<usize> {
+    tokio::task::spawn_blocking(move || {
+        crate::synthetic::write_single_file(&path, &references, &overlays, alt_frequency, seed, sex)
+    })
+    .await
+    .map_err(BiosynthError::TaskFailed)?
+}
+
+fn http_client(http: &HttpConfig) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder().timeout(std::time::Duration::from_secs(300));
+    for certificate in http.root_certificates()? {
+        builder = builder.add_root_certificate(certificate);
+    }
+    builder.build().context("Build HTTP client")
+}
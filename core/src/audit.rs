@@ -0,0 +1,89 @@
+//! Append-only record of the commands that read or wrote a stats database, for compliance
+//! review. Entries live in the database's `audit_log` table, which rejects updates and deletes.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Whether a command only read the database or may have changed it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAccess {
+    Read,
+    Write,
+}
+
+/// One command's use of a database, as passed to [`StatsStore::append_audit`].
+///
+/// [`StatsStore::append_audit`]: crate::stats::StatsStore::append_audit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    /// Login name of the invoking user, if the environment reports one.
+    pub user: Option<String>,
+    pub command: String,
+    /// Names of the command-line flags given. Their values are left out, since they are mostly
+    /// paths to participant files.
+    pub args: Vec<String>,
+    pub access: AuditAccess,
+    pub succeeded: bool,
+    /// Change in row count per table; unchanged tables are left out.
+    pub row_deltas: BTreeMap<String, i64>,
+}
+
+/// An [`AuditEvent`] as stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// Seconds since the Unix epoch.
+    pub timestamp: i64,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+}
+
+impl AuditAccess {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAccess::Read => "read",
+            AuditAccess::Write => "write",
+        }
+    }
+
+    pub(crate) fn from_column(value: &str) -> Self {
+        match value {
+            "read" => AuditAccess::Read,
+            _ => AuditAccess::Write,
+        }
+    }
+}
+
+impl fmt::Display for AuditAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The invoking user's login name from `USER` or `USERNAME`.
+pub fn current_user() -> Option<String> {
+    ["USER", "USERNAME"]
+        .iter()
+        .find_map(|key| std::env::var(key).ok())
+        .filter(|user| !user.is_empty())
+}
+
+/// Per-table differences between two [`StatsStore::table_counts`] snapshots.
+///
+/// [`StatsStore::table_counts`]: crate::stats::StatsStore::table_counts
+pub fn row_deltas(
+    before: &BTreeMap<String, i64>,
+    after: &BTreeMap<String, i64>,
+) -> BTreeMap<String, i64> {
+    after
+        .iter()
+        .map(|(table, count)| {
+            let delta = count - before.get(table).copied().unwrap_or(0);
+            (table.clone(), delta)
+        })
+        .filter(|(_, delta)| *delta != 0)
+        .collect()
+}
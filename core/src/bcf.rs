@@ -0,0 +1,117 @@
+//! Binary BCF callsets as genotype input, decoded through htslib (feature `bcf`).
+//!
+//! Records are read the way the genotype parser reads a VCF: `ID` is the rsid and `GT` indexes
+//! into `REF` and `ALT`, so a single-sample callset can go straight into `bvs genostats`
+//! without converting it to VCF first. A BCF with several samples is refused rather than read
+//! as its first sample; converted to a [multi-sample VCF](crate::vcf), each sample is read.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use rust_htslib::bcf::{self, Read};
+
+use crate::alleles::normalize_genotype;
+use crate::error::{BiosynthError, Result};
+use crate::genotype::{
+    vcf_call, FileMetadata, ParseOptions, ParseSummary, ParsedFile, Rsid, VariantRecord,
+};
+
+/// Whether `path` is named like a BCF file.
+pub fn is_bcf(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("bcf"))
+}
+
+/// Parses a single-sample BCF file, calling `on_variant` for every record with an ID, a
+/// contig, and a call. Of `options`, only the genotype validation applies. A BCF with no
+/// sample, or more than one, is an error.
+pub fn process_file<F>(path: &Path, options: &ParseOptions, mut on_variant: F) -> Result<ParsedFile>
+where
+    F: FnMut(&VariantRecord, &FileMetadata) -> Result<()>,
+{
+    let mut reader = bcf::Reader::from_path(path).map_err(|err| htslib_error(path, err))?;
+    let samples = reader.header().sample_count();
+    if samples == 0 {
+        return Err(BiosynthError::Parse(format!(
+            "BCF {:?} has no sample columns",
+            path
+        )));
+    }
+    if samples > 1 {
+        return Err(BiosynthError::Parse(format!(
+            "BCF {:?} has {} samples, but only single-sample BCFs are read; convert it to a \
+             .vcf.gz with `bcftools view -Oz` to read each sample",
+            path, samples
+        )));
+    }
+    let validation = options.validation;
+    let metadata = FileMetadata::default();
+    let mut summary = ParseSummary::default();
+    let mut chromosome: Option<Arc<str>> = None;
+    for record in reader.records() {
+        let record = record.map_err(|err| htslib_error(path, err))?;
+        let id = String::from_utf8_lossy(&record.id()).into_owned();
+        let rsid = id.split(';').next().unwrap_or_default();
+        let contig = record
+            .rid()
+            .and_then(|rid| record.header().rid2name(rid).ok())
+            .and_then(|name| std::str::from_utf8(name).ok());
+        let Some(contig) = contig.filter(|_| !rsid.is_empty() && rsid != ".") else {
+            summary.skipped_rows += 1;
+            continue;
+        };
+        let alleles: Vec<String> = record
+            .alleles()
+            .iter()
+            .map(|allele| String::from_utf8_lossy(allele).into_owned())
+            .collect();
+        let Some((reference, alternates)) = alleles.split_first() else {
+            summary.skipped_rows += 1;
+            continue;
+        };
+        let alternates = if alternates.is_empty() {
+            ".".to_string()
+        } else {
+            alternates.join(",")
+        };
+        let gt = record
+            .genotypes()
+            .map_err(|err| htslib_error(path, err))?
+            .get(0)
+            .to_string();
+        let Some(call) = vcf_call(&gt, reference, &alternates) else {
+            summary.skipped_rows += 1;
+            continue;
+        };
+        let rsid = Rsid::parse(rsid);
+        let Some(genotype) = validation.check(
+            normalize_genotype(&call),
+            &rsid,
+            &mut summary.invalid_genotypes,
+        )?
+        else {
+            summary.skipped_rows += 1;
+            continue;
+        };
+        let chromosome = match &chromosome {
+            Some(known) if **known == *contig => Arc::clone(known),
+            _ => Arc::clone(chromosome.insert(contig.into())),
+        };
+        summary.variant_count += 1;
+        on_variant(
+            &VariantRecord {
+                rsid,
+                chromosome,
+                // htslib positions are 0-based.
+                position: record.pos() + 1,
+                genotype,
+            },
+            &metadata,
+        )?;
+    }
+    Ok(ParsedFile { metadata, summary })
+}
+
+fn htslib_error(path: &Path, err: rust_htslib::errors::Error) -> BiosynthError {
+    BiosynthError::Parse(format!("Read BCF {:?}: {}", path, err))
+}
@@ -0,0 +1,51 @@
+//! Buffer sizes for the files biosynth reads and writes itself. The standard library's 8 KiB
+//! means a system call (and, on network filesystems, a round trip) every few hundred rows, so
+//! biosynth defaults to [`DEFAULT_IO_BUFFER_SIZE`]; [`set_io_buffer_size`] changes it for the
+//! whole process.
+
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use flate2::bufread::MultiGzDecoder;
+
+pub const DEFAULT_IO_BUFFER_SIZE: usize = 1024 * 1024;
+/// The input path that stands for standard input, as in `bvs genostats -i -`.
+pub const STDIN_PATH: &str = "-";
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+static IO_BUFFER_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_IO_BUFFER_SIZE);
+
+/// Sets the capacity of every reader and writer opened afterwards.
+pub fn set_io_buffer_size(bytes: usize) {
+    IO_BUFFER_SIZE.store(bytes.max(1), Ordering::Relaxed);
+}
+
+pub fn io_buffer_size() -> usize {
+    IO_BUFFER_SIZE.load(Ordering::Relaxed)
+}
+
+/// `inner` behind a reader buffer of [`io_buffer_size`] bytes.
+pub fn reader<R: Read>(inner: R) -> BufReader<R> {
+    BufReader::with_capacity(io_buffer_size(), inner)
+}
+
+/// Whether `path` is [`STDIN_PATH`].
+pub fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == STDIN_PATH
+}
+
+/// Standard input behind a reader buffer, gunzipped if it starts with a gzip header, so
+/// `curl ... | bvs genostats -i -` works whether or not the download is compressed.
+pub fn stdin_reader() -> io::Result<Box<dyn BufRead>> {
+    let mut stdin = reader(io::stdin());
+    if stdin.fill_buf()?.starts_with(&GZIP_MAGIC) {
+        return Ok(Box::new(reader(MultiGzDecoder::new(stdin))));
+    }
+    Ok(Box::new(stdin))
+}
+
+/// `inner` behind a writer buffer of [`io_buffer_size`] bytes.
+pub fn writer<W: Write>(inner: W) -> BufWriter<W> {
+    BufWriter::with_capacity(io_buffer_size(), inner)
+}
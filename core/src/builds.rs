@@ -0,0 +1,181 @@
+//! Genome-build fingerprinting from sentinel rsids.
+//!
+//! A sentinel is an SNP whose coordinates differ between GRCh37 and GRCh38, so where a file
+//! places it says which build the file was exported on. [`BuildSentinels::load`] reads a
+//! curated `rsid,build,chromosome,position` list with one row per sentinel and build; a
+//! [`BuildFingerprint`] then tallies which build each sentinel call in a file agrees with,
+//! resolving the build of files whose header does not name one and flagging files that mix
+//! builds.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufRead;
+use std::path::Path;
+
+use crate::buffers;
+use crate::error::{BiosynthError, Context, Result};
+use crate::genotype::{chromosome_code, Rsid};
+use crate::liftover::GenomeBuild;
+
+/// Sentinel calls a file needs before its build is called.
+const MIN_SENTINEL_CALLS: usize = 5;
+/// Share of a file's sentinel calls agreeing with a second build above which the file is
+/// flagged as mixing builds.
+const MIXED_BUILD_SHARE: f64 = 0.1;
+
+/// Per-build coordinates of the sentinel rsids.
+#[derive(Debug, Clone, Default)]
+pub struct BuildSentinels {
+    coordinates: HashMap<i64, Vec<Sentinel>>,
+}
+
+/// Where one build places a sentinel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sentinel {
+    pub build: GenomeBuild,
+    pub chromosome: u8,
+    pub position: i64,
+}
+
+impl BuildSentinels {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Open sentinel file {:?}", path))?;
+        Self::parse(buffers::reader(file)).map_err(|err| match err {
+            BiosynthError::Parse(message) => {
+                BiosynthError::Parse(format!("Parse sentinel file {:?}: {}", path, message))
+            }
+            other => other,
+        })
+    }
+
+    /// Reads `rsid,build,chromosome,position` rows (comma- or tab-separated); blank lines,
+    /// `#` comments, and a header line are skipped.
+    pub fn parse<R: BufRead>(reader: R) -> Result<Self> {
+        let mut sentinels = Self::default();
+        let mut seen_rows = false;
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line?;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let delimiter = if trimmed.contains('\t') { '\t' } else { ',' };
+            let fields: Vec<&str> = trimmed.split(delimiter).map(str::trim).collect();
+            let parsed = match fields[..] {
+                [rsid, build, chromosome, position] => rsid_number(rsid)
+                    .zip(build.parse::<GenomeBuild>().ok())
+                    .zip(position.parse::<i64>().ok())
+                    .map(|((rsid, build), position)| (rsid, build, chromosome, position)),
+                _ => None,
+            };
+            match parsed {
+                Some((rsid, build, chromosome, position)) => {
+                    let chromosome = chromosome_code(chromosome).ok_or_else(|| {
+                        BiosynthError::Parse(format!(
+                            "line {}: unknown chromosome {:?}",
+                            idx + 1,
+                            chromosome
+                        ))
+                    })?;
+                    sentinels.insert(
+                        rsid,
+                        Sentinel {
+                            build,
+                            chromosome,
+                            position,
+                        },
+                    );
+                }
+                None if !seen_rows => {}
+                None => {
+                    return Err(BiosynthError::Parse(format!(
+                        "line {}: expected rsid, build, chromosome and position",
+                        idx + 1
+                    )))
+                }
+            }
+            seen_rows = true;
+        }
+        Ok(sentinels)
+    }
+
+    /// Records where `sentinel.build` places `rsid`, replacing any earlier coordinates for
+    /// that build.
+    pub fn insert(&mut self, rsid: i64, sentinel: Sentinel) {
+        let builds = self.coordinates.entry(rsid).or_default();
+        builds.retain(|known| known.build != sentinel.build);
+        builds.push(sentinel);
+    }
+
+    /// Sentinel rsids held.
+    pub fn len(&self) -> usize {
+        self.coordinates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coordinates.is_empty()
+    }
+
+    /// Every `(rsid, coordinates)` pair, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (i64, Sentinel)> + '_ {
+        self.coordinates
+            .iter()
+            .flat_map(|(rsid, builds)| builds.iter().map(move |sentinel| (*rsid, *sentinel)))
+    }
+
+    /// The one build placing `rsid` on `chromosome` at `position`; `None` for other rsids,
+    /// for coordinates no build uses, and for coordinates several builds share.
+    pub fn build_of(&self, rsid: &Rsid, chromosome: &str, position: i64) -> Option<GenomeBuild> {
+        let chromosome = chromosome_code(chromosome)?;
+        let mut matching = self
+            .coordinates
+            .get(&rsid.number()?)?
+            .iter()
+            .filter(|sentinel| sentinel.chromosome == chromosome && sentinel.position == position);
+        let build = matching.next()?.build;
+        matching.next().is_none().then_some(build)
+    }
+}
+
+/// Tally of the builds one file's sentinel calls agree with.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BuildFingerprint {
+    pub grch37: usize,
+    pub grch38: usize,
+}
+
+impl BuildFingerprint {
+    pub fn observe(&mut self, build: GenomeBuild) {
+        match build {
+            GenomeBuild::Grch37 => self.grch37 += 1,
+            GenomeBuild::Grch38 => self.grch38 += 1,
+        }
+    }
+
+    /// Sentinel calls tallied.
+    pub fn calls(&self) -> usize {
+        self.grch37 + self.grch38
+    }
+
+    /// The build most sentinel calls agree with, once there are enough of them.
+    pub fn build(&self) -> Option<GenomeBuild> {
+        if self.calls() < MIN_SENTINEL_CALLS {
+            return None;
+        }
+        Some(if self.grch38 >= self.grch37 {
+            GenomeBuild::Grch38
+        } else {
+            GenomeBuild::Grch37
+        })
+    }
+
+    /// Whether a substantial share of sentinel calls agree with each build.
+    pub fn is_mixed(&self) -> bool {
+        self.build().is_some()
+            && self.grch37.min(self.grch38) as f64 > MIXED_BUILD_SHARE * self.calls() as f64
+    }
+}
+
+fn rsid_number(field: &str) -> Option<i64> {
+    field.strip_prefix("rs").unwrap_or(field).parse().ok()
+}
@@ -0,0 +1,296 @@
+//! Columnar aggregation of genostats observations (feature `columnar`).
+//!
+//! [`ObservationBatches`] holds the per-call rows of many files as Arrow record batches and
+//! folds them with sort and partition kernels into one row per rsid and per allele, so a commit
+//! upserts each key once rather than once per call. Totals match row-by-row writing exactly,
+//! including the first call's locus winning for a new rsid.
+
+use std::sync::{Arc, LazyLock};
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::Int64Type;
+use arrow_array::{ArrayRef, Int64Array, RecordBatch, StringArray, UInt32Array};
+use arrow_ord::partition::partition;
+use arrow_ord::sort::{lexsort_to_indices, SortColumn};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+use arrow_select::concat::concat_batches;
+use arrow_select::take::take;
+use rusqlite::{Connection, ToSql};
+
+use crate::error::{Context, Result};
+use crate::stats::{value_rows, Observations, ROWS_PER_STATEMENT};
+
+static RSID_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(|| {
+    schema(&[
+        ("rsid", DataType::Int64),
+        ("chromosome", DataType::Utf8),
+        ("position", DataType::Int64),
+    ])
+});
+
+static ALLELE_SCHEMA: LazyLock<SchemaRef> = LazyLock::new(|| {
+    schema(&[
+        ("rsid", DataType::Int64),
+        ("allele", DataType::Utf8),
+        ("copies", DataType::Int64),
+    ])
+});
+
+/// Observation rows of many files, kept column-wise until [`aggregate`](Self::aggregate).
+#[derive(Debug, Default)]
+pub struct ObservationBatches {
+    rsids: Vec<RecordBatch>,
+    alleles: Vec<RecordBatch>,
+    calls: usize,
+}
+
+/// One row per `rsid` (`rsid`, `chromosome`, `position`, `files`) and per `(rsid, allele)`
+/// (`rsid`, `allele`, `carriers`, `copies`), ready to be added to the observation tables.
+#[derive(Debug)]
+pub struct AggregatedObservations {
+    pub rsids: RecordBatch,
+    pub alleles: RecordBatch,
+}
+
+impl ObservationBatches {
+    /// Appends the rows of `observations`.
+    pub fn push(&mut self, observations: &Observations) -> Result<()> {
+        if observations.is_empty() {
+            return Ok(());
+        }
+        let rsids = RecordBatch::try_new(
+            Arc::clone(&RSID_SCHEMA),
+            vec![
+                Arc::new(Int64Array::from_iter_values(
+                    observations.rsids.iter().map(|(rsid, _, _)| *rsid),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    observations
+                        .rsids
+                        .iter()
+                        .map(|(_, chromosome, _)| &**chromosome),
+                )),
+                Arc::new(Int64Array::from_iter_values(
+                    observations.rsids.iter().map(|(_, _, position)| *position),
+                )),
+            ],
+        )
+        .context("Build rsid observation batch")?;
+        let alleles = RecordBatch::try_new(
+            Arc::clone(&ALLELE_SCHEMA),
+            vec![
+                Arc::new(Int64Array::from_iter_values(
+                    observations.alleles.iter().map(|(rsid, _, _)| *rsid),
+                )),
+                Arc::new(StringArray::from_iter_values(
+                    observations.alleles.iter().map(|(_, allele, _)| allele),
+                )),
+                Arc::new(Int64Array::from_iter_values(
+                    observations.alleles.iter().map(|(_, _, copies)| *copies),
+                )),
+            ],
+        )
+        .context("Build allele observation batch")?;
+        self.rsids.push(rsids);
+        self.alleles.push(alleles);
+        self.calls += observations.len();
+        Ok(())
+    }
+
+    /// Appends every batch of `other`, e.g. one file's once it has been written.
+    pub fn append(&mut self, other: ObservationBatches) {
+        self.rsids.extend(other.rsids);
+        self.alleles.extend(other.alleles);
+        self.calls += other.calls;
+    }
+
+    /// Calls held.
+    pub fn len(&self) -> usize {
+        self.calls
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls == 0
+    }
+
+    /// Drops every held row.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Folds the held rows into one per key.
+    pub fn aggregate(&self) -> Result<AggregatedObservations> {
+        Ok(AggregatedObservations {
+            rsids: fold_rsids(&self.rsids).context("Aggregate rsid observations")?,
+            alleles: fold_alleles(&self.alleles).context("Aggregate allele observations")?,
+        })
+    }
+}
+
+impl AggregatedObservations {
+    /// Adds every row to the observation tables under `consent_tag`.
+    pub fn write(&self, conn: &Connection, consent_tag: &str) -> Result<()> {
+        let rsid = self.rsids.column(0).as_primitive::<Int64Type>().values();
+        let chromosome = self.rsids.column(1).as_string::<i32>();
+        let position = self.rsids.column(2).as_primitive::<Int64Type>().values();
+        let files = self.rsids.column(3).as_primitive::<Int64Type>().values();
+        for start in (0..self.rsids.num_rows()).step_by(ROWS_PER_STATEMENT) {
+            let rows = start..(start + ROWS_PER_STATEMENT).min(self.rsids.num_rows());
+            let values = value_rows(rows.len(), 4, |base| {
+                format!(
+                    "(?{}, ?1, ?{}, ?{}, ?{})",
+                    base,
+                    base + 1,
+                    base + 2,
+                    base + 3
+                )
+            });
+            let chromosomes: Vec<&str> = rows.clone().map(|row| chromosome.value(row)).collect();
+            let mut params: Vec<&dyn ToSql> = vec![&consent_tag];
+            for (row, chromosome) in rows.zip(&chromosomes) {
+                params.extend([
+                    &rsid[row] as &dyn ToSql,
+                    chromosome,
+                    &position[row],
+                    &files[row],
+                ]);
+            }
+            conn.prepare_cached(&format!(
+                "INSERT INTO rsid_observations (rsid, consent_tag, chromosome, position, files)
+                 VALUES {}
+                 ON CONFLICT(rsid, consent_tag) DO UPDATE SET files = files + excluded.files",
+                values
+            ))?
+            .execute(params.as_slice())?;
+        }
+
+        let rsid = self.alleles.column(0).as_primitive::<Int64Type>().values();
+        let allele = self.alleles.column(1).as_string::<i32>();
+        let carriers = self.alleles.column(2).as_primitive::<Int64Type>().values();
+        let copies = self.alleles.column(3).as_primitive::<Int64Type>().values();
+        for start in (0..self.alleles.num_rows()).step_by(ROWS_PER_STATEMENT) {
+            let rows = start..(start + ROWS_PER_STATEMENT).min(self.alleles.num_rows());
+            let values = value_rows(rows.len(), 4, |base| {
+                format!(
+                    "(?{}, ?1, ?{}, ?{}, ?{})",
+                    base,
+                    base + 1,
+                    base + 2,
+                    base + 3
+                )
+            });
+            let alleles: Vec<&str> = rows.clone().map(|row| allele.value(row)).collect();
+            let mut params: Vec<&dyn ToSql> = vec![&consent_tag];
+            for (row, allele) in rows.zip(&alleles) {
+                params.extend([
+                    &rsid[row] as &dyn ToSql,
+                    allele,
+                    &carriers[row],
+                    &copies[row],
+                ]);
+            }
+            conn.prepare_cached(&format!(
+                "INSERT INTO allele_observations (rsid, consent_tag, allele, carriers, copies)
+                 VALUES {}
+                 ON CONFLICT(rsid, consent_tag, allele) DO UPDATE SET
+                    carriers = carriers + excluded.carriers,
+                    copies = copies + excluded.copies",
+                values
+            ))?
+            .execute(params.as_slice())?;
+        }
+        Ok(())
+    }
+}
+
+/// Groups calls by rsid: the count becomes `files`, and the locus is the first call's, which
+/// is what row-by-row upserts would have kept.
+fn fold_rsids(batches: &[RecordBatch]) -> Result<RecordBatch, ArrowError> {
+    let batch = concat_batches(&RSID_SCHEMA, batches)?;
+    let arrival: ArrayRef = Arc::new(UInt32Array::from_iter_values(
+        0..u32::try_from(batch.num_rows()).map_err(overflow)?,
+    ));
+    let order = lexsort_to_indices(&[ascending(batch.column(0)), ascending(&arrival)], None)?;
+    let runs = partition(&[take(batch.column(0), &order, None)?])?.ranges();
+    let firsts = take(
+        &order,
+        &UInt32Array::from_iter_values(runs.iter().map(|run| run.start as u32)),
+        None,
+    )?;
+    let mut columns = batch
+        .columns()
+        .iter()
+        .map(|column| take(column, &firsts, None))
+        .collect::<Result<Vec<_>, _>>()?;
+    columns.push(Arc::new(Int64Array::from_iter_values(
+        runs.iter().map(|run| run.len() as i64),
+    )));
+    RecordBatch::try_new(
+        schema(&[
+            ("rsid", DataType::Int64),
+            ("chromosome", DataType::Utf8),
+            ("position", DataType::Int64),
+            ("files", DataType::Int64),
+        ]),
+        columns,
+    )
+}
+
+/// Groups allele rows by `(rsid, allele)`, counting carriers and summing copies.
+fn fold_alleles(batches: &[RecordBatch]) -> Result<RecordBatch, ArrowError> {
+    let batch = concat_batches(&ALLELE_SCHEMA, batches)?;
+    u32::try_from(batch.num_rows()).map_err(overflow)?;
+    let order = lexsort_to_indices(
+        &[ascending(batch.column(0)), ascending(batch.column(1))],
+        None,
+    )?;
+    let sorted = batch
+        .columns()
+        .iter()
+        .map(|column| take(column, &order, None))
+        .collect::<Result<Vec<_>, _>>()?;
+    let runs = partition(&sorted[..2])?.ranges();
+    let copies = sorted[2].as_primitive::<Int64Type>().values();
+    let firsts = UInt32Array::from_iter_values(runs.iter().map(|run| run.start as u32));
+    RecordBatch::try_new(
+        schema(&[
+            ("rsid", DataType::Int64),
+            ("allele", DataType::Utf8),
+            ("carriers", DataType::Int64),
+            ("copies", DataType::Int64),
+        ]),
+        vec![
+            take(&sorted[0], &firsts, None)?,
+            take(&sorted[1], &firsts, None)?,
+            Arc::new(Int64Array::from_iter_values(
+                runs.iter().map(|run| run.len() as i64),
+            )),
+            Arc::new(Int64Array::from_iter_values(
+                runs.iter()
+                    .map(|run| copies[run.clone()].iter().sum::<i64>()),
+            )),
+        ],
+    )
+}
+
+fn schema(fields: &[(&str, DataType)]) -> SchemaRef {
+    Arc::new(Schema::new(
+        fields
+            .iter()
+            .map(|(name, data_type)| Field::new(*name, data_type.clone(), false))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+fn ascending(column: &ArrayRef) -> SortColumn {
+    SortColumn {
+        values: Arc::clone(column),
+        options: None,
+    }
+}
+
+fn overflow<E>(_: E) -> ArrowError {
+    ArrowError::ComputeError(
+        "Too many observation rows to aggregate at once; commit more often".into(),
+    )
+}
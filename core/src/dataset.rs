@@ -0,0 +1,293 @@
+//! Read-only, typed queries over a genostats database, so callers never touch the schema.
+//!
+//! ```no_run
+//! use biosynth_core::dataset::Dataset;
+//!
+//! let dataset = Dataset::open("data/genostats.sqlite".as_ref())?;
+//! if let Some(variant) = dataset.reference(4680)? {
+//!     println!("rs4680 is {}:{}", variant.chromosome, variant.position);
+//! }
+//! let region = dataset.frequencies(&"22:19000000-20000000".parse()?)?;
+//! println!("{} variants", region.variant_count);
+//! # Ok::<(), biosynth_core::BiosynthError>(())
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::encryption::{apply_key, DatabaseKey};
+use crate::error::{BiosynthError, Context, Result};
+use crate::stats::ReferenceVariant;
+
+/// Handle to a genostats database opened read-only.
+#[derive(Debug)]
+pub struct Dataset {
+    conn: Connection,
+    path: PathBuf,
+}
+
+/// A chromosome, optionally narrowed to an inclusive 1-based position range.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    pub chromosome: String,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+}
+
+/// How often each allele appears among the reference variants in a region.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegionFrequencies {
+    pub region: Region,
+    pub variant_count: u64,
+    /// Reference alleles, most common first.
+    pub reference_alleles: Vec<AlleleFrequency>,
+    /// Alternate alleles, most common first; a variant with several ALTs counts once for each.
+    pub alternate_alleles: Vec<AlleleFrequency>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlleleFrequency {
+    pub allele: String,
+    pub count: u64,
+    /// `count` as a fraction of the region's variants.
+    pub frequency: f64,
+}
+
+/// How many reference rsids are attributed to one vendor format.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormatCoverage {
+    pub format: String,
+    pub genome_build: Option<String>,
+    pub rsids: u64,
+    /// `rsids` as a fraction of every rsid in the database.
+    pub fraction: f64,
+}
+
+impl Dataset {
+    /// Opens `path` read-only, unlocking it with the key from the environment if one is set.
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::open_with_key(path, DatabaseKey::from_env()?)
+    }
+
+    pub fn open_with_key(path: &Path, key: Option<DatabaseKey>) -> Result<Self> {
+        if !path.exists() {
+            return Err(BiosynthError::ReferenceMissing {
+                path: path.to_path_buf(),
+            });
+        }
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Open database at {:?}", path))?;
+        apply_key(&conn, key.as_ref(), path)?;
+        Ok(Self {
+            conn,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Looks up one variant by its numeric rsid (`4680` for `rs4680`).
+    pub fn reference(&self, rsid: i64) -> Result<Option<ReferenceVariant>> {
+        self.conn
+            .query_row(
+                "SELECT rsid, chromosome, position, reference, alternates
+                 FROM rsid_reference WHERE rsid = ?1",
+                [rsid],
+                reference_from_row,
+            )
+            .optional()
+            .with_context(|| format!("Look up rs{}", rsid))
+    }
+
+    /// Variants in `region`, ordered by position.
+    pub fn references_in(&self, region: &Region) -> Result<Vec<ReferenceVariant>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT rsid, chromosome, position, reference, alternates
+             FROM rsid_reference
+             WHERE chromosome = ?1 AND position BETWEEN ?2 AND ?3
+             ORDER BY position",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                region.chromosome,
+                region.start.unwrap_or(i64::MIN),
+                region.end.unwrap_or(i64::MAX)
+            ],
+            reference_from_row,
+        )?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .with_context(|| format!("Query variants in {}", region))
+    }
+
+    /// Reference and alternate allele frequencies across the variants in `region`.
+    pub fn frequencies(&self, region: &Region) -> Result<RegionFrequencies> {
+        let variants = self.references_in(region)?;
+        let mut reference = BTreeMap::new();
+        let mut alternate = BTreeMap::new();
+        for variant in &variants {
+            *reference.entry(variant.reference.clone()).or_insert(0u64) += 1;
+            for allele in variant.alternates.split(',').map(str::trim) {
+                if !allele.is_empty() {
+                    *alternate.entry(allele.to_string()).or_insert(0u64) += 1;
+                }
+            }
+        }
+        let total = variants.len() as u64;
+        Ok(RegionFrequencies {
+            region: region.clone(),
+            variant_count: total,
+            reference_alleles: ranked(reference, total),
+            alternate_alleles: ranked(alternate, total),
+        })
+    }
+
+    /// Coverage for the vendor format named `format` (e.g. `dynamic_dna`), or `None` if the
+    /// database has never seen it.
+    pub fn coverage(&self, format: &str) -> Result<Option<FormatCoverage>> {
+        let Some((id, genome_build)) = self
+            .conn
+            .query_row(
+                "SELECT id, genome_build FROM formats WHERE name = ?1",
+                [format],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?)),
+            )
+            .optional()
+            .with_context(|| format!("Look up format {:?}", format))?
+        else {
+            return Ok(None);
+        };
+        let (rsids, total): (i64, i64) = self
+            .conn
+            .query_row(
+                "SELECT COALESCE(SUM(format_id = ?1), 0), COUNT(*) FROM rsid_reference",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .with_context(|| format!("Count rsids for format {:?}", format))?;
+        Ok(Some(FormatCoverage {
+            format: format.to_string(),
+            genome_build,
+            rsids: rsids as u64,
+            fraction: if total > 0 {
+                rsids as f64 / total as f64
+            } else {
+                0.0
+            },
+        }))
+    }
+
+    /// Names of every format recorded in the database.
+    pub fn formats(&self) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name FROM formats ORDER BY name")?;
+        let names = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(names)
+    }
+}
+
+impl Region {
+    pub fn chromosome(chromosome: &str) -> Self {
+        Self {
+            chromosome: normalize_chromosome(chromosome),
+            start: None,
+            end: None,
+        }
+    }
+
+    pub fn span(chromosome: &str, start: i64, end: i64) -> Self {
+        Self {
+            chromosome: normalize_chromosome(chromosome),
+            start: Some(start),
+            end: Some(end),
+        }
+    }
+}
+
+/// Parses `7`, `chr7`, or `7:1000-2000` (commas in positions are ignored).
+impl FromStr for Region {
+    type Err = BiosynthError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let invalid = || {
+            BiosynthError::InvalidArgument(format!(
+                "Invalid region {:?} (expected CHROM or CHROM:START-END)",
+                value
+            ))
+        };
+        let Some((chromosome, range)) = value.trim().split_once(':') else {
+            if value.trim().is_empty() {
+                return Err(invalid());
+            }
+            return Ok(Region::chromosome(value.trim()));
+        };
+        let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+        let parse = |text: &str| text.trim().replace(',', "").parse::<i64>();
+        let (Ok(start), Ok(end)) = (parse(start), parse(end)) else {
+            return Err(invalid());
+        };
+        if chromosome.trim().is_empty() || start > end {
+            return Err(invalid());
+        }
+        Ok(Region::span(chromosome.trim(), start, end))
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) => write!(f, "{}:{}-{}", self.chromosome, start, end),
+            (Some(start), None) => write!(f, "{}:{}-", self.chromosome, start),
+            (None, Some(end)) => write!(f, "{}:-{}", self.chromosome, end),
+            (None, None) => write!(f, "{}", self.chromosome),
+        }
+    }
+}
+
+/// The database stores bare chromosome names (`1`, `X`, `MT`).
+fn normalize_chromosome(chromosome: &str) -> String {
+    let bare = chromosome
+        .strip_prefix("chr")
+        .or_else(|| chromosome.strip_prefix("CHR"))
+        .unwrap_or(chromosome);
+    match bare.to_ascii_uppercase().as_str() {
+        "M" => "MT".to_string(),
+        upper => upper.to_string(),
+    }
+}
+
+fn reference_from_row(row: &Row<'_>) -> rusqlite::Result<ReferenceVariant> {
+    Ok(ReferenceVariant {
+        rsid: row.get(0)?,
+        chromosome: row.get(1)?,
+        position: row.get(2)?,
+        reference: row.get(3)?,
+        alternates: row.get(4)?,
+    })
+}
+
+fn ranked(counts: BTreeMap<String, u64>, total: u64) -> Vec<AlleleFrequency> {
+    let mut alleles: Vec<AlleleFrequency> = counts
+        .into_iter()
+        .map(|(allele, count)| AlleleFrequency {
+            allele,
+            count,
+            frequency: if total > 0 {
+                count as f64 / total as f64
+            } else {
+                0.0
+            },
+        })
+        .collect();
+    alleles.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.allele.cmp(&b.allele)));
+    alleles
+}
@@ -0,0 +1,1081 @@
+//! Locating reference databases, and (feature `download`) fetching published ones over HTTPS.
+
+#[cfg(feature = "download")]
+use std::fs;
+#[cfg(feature = "download")]
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "download")]
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(feature = "download")]
+use std::sync::mpsc;
+#[cfg(feature = "download")]
+use std::thread;
+
+#[cfg(feature = "download")]
+use reqwest::blocking::Client;
+#[cfg(feature = "download")]
+use reqwest::header::{
+    HeaderMap, HeaderValue, ACCEPT_RANGES, CONTENT_LENGTH, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+    IF_RANGE, LAST_MODIFIED, RANGE,
+};
+#[cfg(feature = "download")]
+use reqwest::Certificate;
+#[cfg(feature = "download")]
+use reqwest::StatusCode;
+#[cfg(feature = "download")]
+use serde::Deserialize;
+#[cfg(feature = "download")]
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "download")]
+use crate::error::Context;
+use crate::error::{BiosynthError, Result};
+
+#[cfg(feature = "download")]
+pub(crate) const GITHUB_RAW_BASE: &str = "https://raw.githubusercontent.com/openmined/biosynth";
+#[cfg(feature = "download")]
+pub(crate) const GITHUB_API_TAGS: &str = "https://api.github.com/repos/openmined/biosynth/tags";
+#[cfg(feature = "download")]
+pub(crate) const GITHUB_API_LATEST_RELEASE: &str =
+    "https://api.github.com/repos/openmined/biosynth/releases/latest";
+/// Default directory for reference databases and other bvs data.
+pub const DATA_DIR: &str = "data";
+/// File name of the reference database within the data directory.
+pub const REFERENCE_DB_FILENAME: &str = "genostats.sqlite";
+/// Reference version fetched when none is requested: the newest tagged release.
+pub const DEFAULT_REFERENCE_VERSION: &str = "latest";
+/// The untagged tip of `main`, whose data can change under the same name.
+pub const UNRELEASED_REFERENCE_VERSION: &str = "main";
+/// Suffix of the checksum published next to each data file, in `sha256sum` format.
+pub const CHECKSUM_SUFFIX: &str = ".sha256";
+/// Suffix of the zstd-compressed copy published next to each reference database.
+pub const COMPRESSED_SUFFIX: &str = ".zst";
+/// Suffix of the file a download is written to until it is complete and verified.
+pub const PARTIAL_SUFFIX: &str = ".part";
+/// Concurrent range requests [`HttpConfig::default`] allows for one large download.
+pub const DEFAULT_CONNECTIONS: usize = 4;
+/// Smallest range worth its own connection; files under two of these download in one stream.
+#[cfg(feature = "download")]
+pub(crate) const MIN_PART_BYTES: u64 = 8 * 1024 * 1024;
+/// Suffix, after [`PARTIAL_SUFFIX`], of the file holding a partial download's ETag.
+#[cfg(feature = "download")]
+const VALIDATOR_SUFFIX: &str = ".etag";
+
+/// Where [`fetch_reference`] downloads reference databases from.
+#[cfg(feature = "download")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ReferenceSource {
+    /// The project's GitHub repository and its releases.
+    #[default]
+    GitHub,
+    /// A mirror laid out like the repository, serving
+    /// `<url>/<version>/data/genostats.sqlite` and its checksum. Mirrors cannot resolve
+    /// `latest` or list versions, so a tag must be named.
+    Mirror(String),
+}
+
+#[cfg(feature = "download")]
+impl ReferenceSource {
+    /// The mirror at `url`, or GitHub if there is none.
+    pub fn new(mirror: Option<&str>) -> Self {
+        match mirror {
+            Some(url) => ReferenceSource::Mirror(url.trim_end_matches('/').to_string()),
+            None => ReferenceSource::GitHub,
+        }
+    }
+
+    /// URL of the reference database published at `version`.
+    pub(crate) fn reference_url(&self, version: &str) -> String {
+        let base = match self {
+            ReferenceSource::GitHub => GITHUB_RAW_BASE,
+            ReferenceSource::Mirror(url) => url,
+        };
+        format!(
+            "{}/{}/{}/{}",
+            base, version, DATA_DIR, REFERENCE_DB_FILENAME
+        )
+    }
+
+    /// Fails for mirrors, which have no release API to `what` with.
+    pub(crate) fn require_github(&self, what: &str) -> Result<()> {
+        match self {
+            ReferenceSource::GitHub => Ok(()),
+            ReferenceSource::Mirror(url) => Err(BiosynthError::InvalidArgument(format!(
+                "Cannot {} from the mirror at {}, which has no release API; name a release tag",
+                what, url
+            ))),
+        }
+    }
+}
+
+#[cfg(feature = "download")]
+impl std::fmt::Display for ReferenceSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReferenceSource::GitHub => f.write_str("GitHub"),
+            ReferenceSource::Mirror(url) => f.write_str(url),
+        }
+    }
+}
+
+/// HTTP client settings for downloads. Proxies are taken from the standard `HTTPS_PROXY`,
+/// `HTTP_PROXY` and `NO_PROXY` environment variables.
+#[cfg(feature = "download")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpConfig {
+    /// PEM file of CA certificates to trust besides the built-in roots, such as the one a
+    /// TLS-intercepting proxy signs with.
+    pub ca_cert: Option<PathBuf>,
+    /// Range requests a large download is split into, fetched concurrently. 1 downloads in a
+    /// single stream.
+    pub connections: usize,
+}
+
+#[cfg(feature = "download")]
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            ca_cert: None,
+            connections: DEFAULT_CONNECTIONS,
+        }
+    }
+}
+
+#[cfg(feature = "download")]
+impl HttpConfig {
+    /// The certificates in [`ca_cert`](Self::ca_cert), if one is set.
+    pub(crate) fn root_certificates(&self) -> Result<Vec<Certificate>> {
+        let Some(path) = &self.ca_cert else {
+            return Ok(Vec::new());
+        };
+        let pem =
+            fs::read(path).with_context(|| format!("Read CA certificates from {:?}", path))?;
+        let certificates = Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Parse CA certificates in {:?}", path))?;
+        if certificates.is_empty() {
+            return Err(BiosynthError::Parse(format!(
+                "{:?} holds no PEM certificates",
+                path
+            )));
+        }
+        Ok(certificates)
+    }
+}
+
+/// A reference database written by [`fetch_reference`].
+#[cfg(feature = "download")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchedReference {
+    /// SHA-256 of the downloaded file, as lowercase hex.
+    pub sha256: String,
+    /// Whether the download was checked against a published checksum.
+    pub checksum: ChecksumStatus,
+    /// Bytes kept from an earlier, interrupted download.
+    pub resumed_bytes: u64,
+    /// The release downloaded, with `latest` resolved to its tag.
+    pub version: String,
+    /// Validators the server sent with the download.
+    pub validators: RemoteValidators,
+    /// Size of the zstd-compressed copy that was downloaded instead of the database, if one
+    /// was published.
+    pub compressed_bytes: Option<u64>,
+}
+
+/// Whether a [fetched reference](FetchedReference) was checked against a published checksum.
+#[cfg(feature = "download")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumStatus {
+    /// The published checksum was found and matched.
+    Verified,
+    /// The release publishes no checksum, so nothing vouches for the download. Releases that
+    /// predate published checksums are fetched this way.
+    Unpublished,
+}
+
+/// How much of a download is on disk, reported as it streams in.
+#[cfg(feature = "download")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadProgress {
+    /// Bytes written so far, including any kept from an interrupted run.
+    pub downloaded: u64,
+    /// Size of the whole file, if the server reported one.
+    pub total: Option<u64>,
+}
+
+#[cfg(feature = "download")]
+impl DownloadProgress {
+    /// Progress before the body of a `transfer` resuming from `offset` is read; `body_len` is
+    /// the response's content length.
+    pub(crate) fn starting(transfer: &Transfer, offset: u64, body_len: Option<u64>) -> Self {
+        match transfer {
+            Transfer::Fresh => Self {
+                downloaded: 0,
+                total: body_len,
+            },
+            Transfer::Resumed => Self {
+                downloaded: offset,
+                total: body_len.map(|len| offset + len),
+            },
+            Transfer::Complete => Self {
+                downloaded: offset,
+                total: Some(offset),
+            },
+        }
+    }
+}
+
+/// HTTP validators of a downloaded reference, for later asking the server whether it changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteValidators {
+    /// The response's `ETag`.
+    pub etag: Option<String>,
+    /// The response's `Last-Modified` date.
+    pub last_modified: Option<String>,
+    /// Whether they belong to the zstd-compressed copy rather than the database itself.
+    pub compressed: bool,
+}
+
+impl RemoteValidators {
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    #[cfg(feature = "download")]
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+            compressed: false,
+        }
+    }
+
+    /// `If-None-Match` and `If-Modified-Since` headers making a request conditional on these.
+    #[cfg(feature = "download")]
+    pub(crate) fn conditions(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let pairs = [
+            (IF_NONE_MATCH, &self.etag),
+            (IF_MODIFIED_SINCE, &self.last_modified),
+        ];
+        for (name, value) in pairs {
+            if let Some(value) = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+                headers.insert(name, value);
+            }
+        }
+        headers
+    }
+}
+
+/// Whether a newer reference is published, as answered by [`check_reference`].
+#[cfg(feature = "download")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferenceCheck {
+    /// The published reference is the one held locally.
+    UpToDate { version: String },
+    /// A different release, or changed data under the same name, is published.
+    UpdateAvailable { version: String },
+    /// The local reference recorded no validators, so it cannot be compared without
+    /// downloading the published one.
+    Unknown { version: String },
+}
+
+#[cfg(feature = "download")]
+impl ReferenceCheck {
+    /// The published release compared against, with `latest` resolved to its tag.
+    pub fn version(&self) -> &str {
+        match self {
+            ReferenceCheck::UpToDate { version }
+            | ReferenceCheck::UpdateAvailable { version }
+            | ReferenceCheck::Unknown { version } => version,
+        }
+    }
+}
+
+/// Returns `path` if the reference database exists there.
+pub fn ensure_reference_db(path: &Path) -> Result<PathBuf> {
+    if path.exists() {
+        return Ok(path.to_path_buf());
+    }
+    Err(BiosynthError::ReferenceMissing {
+        path: path.to_path_buf(),
+    })
+}
+
+/// Downloads the reference database published at `version` (a git tag or `main`) from
+/// `source` to `dest`, checking it against the published checksum before replacing anything
+/// at `dest`. The zstd-compressed copy is downloaded and decompressed locally when one is
+/// published, and the database itself otherwise. An interrupted download is resumed where it
+/// stopped the next time this is called. `on_progress` is called as the file streams to disk.
+#[cfg(feature = "download")]
+pub fn fetch_reference(
+    source: &ReferenceSource,
+    http: &HttpConfig,
+    version: &str,
+    dest: &Path,
+    on_progress: &mut dyn FnMut(DownloadProgress),
+) -> Result<FetchedReference> {
+    if let Some(parent) = dest.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            fs::create_dir_all(parent).with_context(|| format!("Create directory {:?}", parent))?;
+        }
+    }
+    let client = http_client(http)?;
+    let version = resolve_reference_version(&client, source, version)?;
+    let url = source.reference_url(&version);
+    let expected = fetch_checksum(&client, &format!("{}{}", url, CHECKSUM_SUFFIX))?;
+    let partial = PartialDownload::for_dest(dest);
+    let compressed = PartialDownload::for_dest(&compressed_path(dest));
+    let compressed_url = format!("{}{}", url, COMPRESSED_SUFFIX);
+    let connections = http.connections;
+    let download = match download_file(
+        &client,
+        &compressed_url,
+        &compressed,
+        connections,
+        on_progress,
+    )? {
+        Some(download) => download.decompressed(compressed, &partial)?,
+        None => {
+            compressed.discard()?;
+            download_file(&client, &url, &partial, connections, on_progress)?.ok_or(
+                BiosynthError::HttpStatus {
+                    status: StatusCode::NOT_FOUND.as_u16(),
+                    url,
+                },
+            )?
+        }
+    };
+    partial.install(dest, expected.as_deref(), download, version)
+}
+
+/// Where the compressed copy of the database at `dest` is downloaded to.
+#[cfg(feature = "download")]
+pub(crate) fn compressed_path(dest: &Path) -> PathBuf {
+    let mut path = dest.as_os_str().to_owned();
+    path.push(COMPRESSED_SUFFIX);
+    PathBuf::from(path)
+}
+
+/// Asks `source` whether the reference published at `version` differs from a local one fetched
+/// at `local_version` with `validators`. The release is compared first; a matching release is
+/// checked with a conditional `HEAD` request, so nothing is downloaded.
+#[cfg(feature = "download")]
+pub fn check_reference(
+    source: &ReferenceSource,
+    http: &HttpConfig,
+    version: &str,
+    local_version: Option<&str>,
+    validators: &RemoteValidators,
+) -> Result<ReferenceCheck> {
+    let client = http_client(http)?;
+    let version = resolve_reference_version(&client, source, version)?;
+    if local_version.is_some_and(|local| local != version) {
+        return Ok(ReferenceCheck::UpdateAvailable { version });
+    }
+    if validators.is_empty() {
+        return Ok(ReferenceCheck::Unknown { version });
+    }
+    let url = validated_url(source, &version, validators);
+    let response = client
+        .head(&url)
+        .headers(validators.conditions())
+        .send()
+        .with_context(|| format!("Check {}", url))?;
+    classify_check(response.status(), &url, version)
+}
+
+/// URL of the artifact `validators` were recorded for: the database or its compressed copy.
+#[cfg(feature = "download")]
+pub(crate) fn validated_url(
+    source: &ReferenceSource,
+    version: &str,
+    validators: &RemoteValidators,
+) -> String {
+    let url = source.reference_url(version);
+    if validators.compressed {
+        format!("{}{}", url, COMPRESSED_SUFFIX)
+    } else {
+        url
+    }
+}
+
+/// The answer a conditional request for the reference at `url` gives.
+#[cfg(feature = "download")]
+pub(crate) fn classify_check(
+    status: StatusCode,
+    url: &str,
+    version: String,
+) -> Result<ReferenceCheck> {
+    match status {
+        StatusCode::NOT_MODIFIED => Ok(ReferenceCheck::UpToDate { version }),
+        status if status.is_success() => Ok(ReferenceCheck::UpdateAvailable { version }),
+        status => Err(BiosynthError::HttpStatus {
+            status: status.as_u16(),
+            url: url.to_string(),
+        }),
+    }
+}
+
+/// Lists downloadable reference versions: `main` followed by every release tag.
+#[cfg(feature = "download")]
+pub fn list_reference_versions(source: &ReferenceSource, http: &HttpConfig) -> Result<Vec<String>> {
+    source.require_github("list reference versions")?;
+    let tags = fetch_tag_names(http)?;
+    let mut versions = vec![UNRELEASED_REFERENCE_VERSION.to_string()];
+    versions.extend(tags);
+    Ok(versions)
+}
+
+/// `version`, with [`DEFAULT_REFERENCE_VERSION`] replaced by the newest release's tag.
+#[cfg(feature = "download")]
+fn resolve_reference_version(
+    client: &Client,
+    source: &ReferenceSource,
+    version: &str,
+) -> Result<String> {
+    if version != DEFAULT_REFERENCE_VERSION {
+        return Ok(version.to_string());
+    }
+    source.require_github("resolve `latest`")?;
+    let response = client
+        .get(GITHUB_API_LATEST_RELEASE)
+        .header(reqwest::header::USER_AGENT, "bvs")
+        .send()
+        .with_context(|| format!("Find the latest release at {}", GITHUB_API_LATEST_RELEASE))?;
+    if !response.status().is_success() {
+        return Err(BiosynthError::HttpStatus {
+            status: response.status().as_u16(),
+            url: GITHUB_API_LATEST_RELEASE.to_string(),
+        });
+    }
+    let release: Release = response.json().with_context(|| {
+        format!(
+            "Parse the latest release from {}",
+            GITHUB_API_LATEST_RELEASE
+        )
+    })?;
+    Ok(release.tag_name)
+}
+
+/// The part of a GitHub release that names its tag.
+#[cfg(feature = "download")]
+#[derive(Deserialize)]
+pub(crate) struct Release {
+    pub(crate) tag_name: String,
+}
+
+#[cfg(feature = "download")]
+fn fetch_tag_names(http: &HttpConfig) -> Result<Vec<String>> {
+    #[derive(Deserialize)]
+    struct Tag {
+        name: String,
+    }
+
+    let client = http_client(http)?;
+    let response = client
+        .get(GITHUB_API_TAGS)
+        .header(reqwest::header::USER_AGENT, "bvs")
+        .send()
+        .with_context(|| format!("List tags from {}", GITHUB_API_TAGS))?;
+
+    if !response.status().is_success() {
+        return Err(BiosynthError::HttpStatus {
+            status: response.status().as_u16(),
+            url: GITHUB_API_TAGS.to_string(),
+        });
+    }
+
+    let tags: Vec<Tag> = response
+        .json()
+        .with_context(|| format!("Parse tags from {}", GITHUB_API_TAGS))?;
+    Ok(tags.into_iter().map(|tag| tag.name).collect())
+}
+
+#[cfg(feature = "download")]
+fn http_client(http: &HttpConfig) -> Result<Client> {
+    let mut builder = Client::builder().timeout(std::time::Duration::from_secs(300));
+    for certificate in http.root_certificates()? {
+        builder = builder.add_root_certificate(certificate);
+    }
+    builder.build().context("Build HTTP client")
+}
+
+/// The checksum published at `url`, or `None` if there is none.
+#[cfg(feature = "download")]
+fn fetch_checksum(client: &Client, url: &str) -> Result<Option<String>> {
+    let response = client
+        .get(url)
+        .send()
+        .with_context(|| format!("Download from {}", url))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(BiosynthError::HttpStatus {
+            status: response.status().as_u16(),
+            url: url.to_string(),
+        });
+    }
+    let text = response
+        .text()
+        .with_context(|| format!("Read response from {}", url))?;
+    parse_checksum(&text, url).map(Some)
+}
+
+/// The digest from a `sha256sum` line: 64 hex digits, then optionally the file name.
+#[cfg(feature = "download")]
+pub(crate) fn parse_checksum(text: &str, source: &str) -> Result<String> {
+    match text.split_whitespace().next() {
+        Some(digest) if digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            Ok(digest.to_ascii_lowercase())
+        }
+        _ => Err(BiosynthError::Parse(format!(
+            "{} is not a SHA-256 checksum",
+            source
+        ))),
+    }
+}
+
+/// Streams `url` into `partial`, hashing as it goes, or returns `None` if the server has no
+/// such file. A partial download left by an interrupted run is resumed with a range request;
+/// a fresh one of a large file is split across up to `connections` concurrent range requests.
+#[cfg(feature = "download")]
+fn download_file(
+    client: &Client,
+    url: &str,
+    partial: &PartialDownload,
+    connections: usize,
+    on_progress: &mut dyn FnMut(DownloadProgress),
+) -> Result<Option<Download>> {
+    let resume = partial.resumable();
+    if resume.is_none() && connections > 1 {
+        let probe = client
+            .head(url)
+            .send()
+            .with_context(|| format!("Download from {}", url))?;
+        if probe.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if let Some(plan) = PartPlan::new(probe.status(), probe.headers(), connections) {
+            return download_parts(client, url, partial, plan, on_progress).map(Some);
+        }
+    }
+    let mut request = client.get(url);
+    if let Some((offset, etag)) = &resume {
+        request = request
+            .header(RANGE, format!("bytes={}-", offset))
+            .header(IF_RANGE, etag);
+    }
+    let mut response = request
+        .send()
+        .with_context(|| format!("Download from {}", url))?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    let validators = transfer_validators(response.headers(), resume.as_ref());
+    let offset = resume.map_or(0, |(offset, _)| offset);
+    let transfer = classify_response(response.status(), offset, url)?;
+    let progress = DownloadProgress::starting(&transfer, offset, response.content_length());
+    on_progress(progress);
+    let (file, mut hasher, resumed) = match transfer {
+        Transfer::Fresh => (
+            Some(partial.start(strong_etag(response.headers()))?),
+            Sha256::new(),
+            0,
+        ),
+        Transfer::Resumed => (Some(partial.open_append()?), partial.hash()?, offset),
+        Transfer::Complete => (None, partial.hash()?, offset),
+    };
+    if let Some(file) = file {
+        let mut hashing = HashingWriter {
+            inner: file,
+            hasher,
+            progress,
+            on_progress,
+        };
+        io::copy(&mut response, &mut hashing).with_context(|| {
+            format!(
+                "Download from {} to {:?}; run again to resume",
+                url, partial.path
+            )
+        })?;
+        hashing
+            .inner
+            .sync_all()
+            .with_context(|| format!("Write to {:?}", partial.path))?;
+        hasher = hashing.hasher;
+    }
+    Ok(Some(Download {
+        sha256: format!("{:x}", hasher.finalize()),
+        resumed_bytes: resumed,
+        validators,
+        compressed_bytes: None,
+    }))
+}
+
+/// Fetches `plan`'s ranges of `url` concurrently into `partial`, then hashes the assembled file.
+#[cfg(feature = "download")]
+fn download_parts(
+    client: &Client,
+    url: &str,
+    partial: &PartialDownload,
+    plan: PartPlan,
+    on_progress: &mut dyn FnMut(DownloadProgress),
+) -> Result<Download> {
+    // The ETag is recorded only once the file holds no gaps: a run killed midway leaves a
+    // partial download that cannot be resumed, rather than one with holes.
+    let file = partial.start(None)?;
+    file.set_len(plan.len)
+        .with_context(|| format!("Write to {:?}", partial.path))?;
+    let ranges = plan.ranges();
+    let fetched: Vec<AtomicU64> = ranges.iter().map(|_| AtomicU64::new(0)).collect();
+    let abort = AtomicBool::new(false);
+    let (sender, received) = mpsc::channel();
+    let result = thread::scope(|scope| {
+        let workers: Vec<_> = ranges
+            .iter()
+            .zip(&fetched)
+            .map(|(&range, fetched)| {
+                let sender = sender.clone();
+                let (abort, plan) = (&abort, &plan);
+                scope.spawn(move || {
+                    let result = fetch_range(client, url, partial, &plan.etag, range, |bytes| {
+                        fetched.fetch_add(bytes, Ordering::Relaxed);
+                        let _ = sender.send(bytes);
+                        !abort.load(Ordering::Relaxed)
+                    });
+                    if result.is_err() {
+                        abort.store(true, Ordering::Relaxed);
+                    }
+                    result
+                })
+            })
+            .collect();
+        drop(sender);
+        let mut progress = DownloadProgress {
+            downloaded: 0,
+            total: Some(plan.len),
+        };
+        on_progress(progress);
+        for bytes in received {
+            progress.downloaded += bytes;
+            on_progress(progress);
+        }
+        workers.into_iter().try_for_each(|worker| {
+            worker
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        })
+    });
+    if let Err(err) = result {
+        let fetched: Vec<u64> = fetched.iter().map(|f| f.load(Ordering::Relaxed)).collect();
+        partial.keep_prefix(contiguous_prefix(&ranges, &fetched), &plan.etag)?;
+        return Err(err);
+    }
+    Ok(Download {
+        sha256: format!("{:x}", partial.hash()?.finalize()),
+        resumed_bytes: 0,
+        validators: plan.validators,
+        compressed_bytes: None,
+    })
+}
+
+/// Writes the inclusive byte `range` of `url` into `partial` at its offset, if the file still
+/// has `etag`, calling `on_chunk` with each chunk's size; it stops early, without error, once
+/// `on_chunk` returns false.
+#[cfg(feature = "download")]
+fn fetch_range(
+    client: &Client,
+    url: &str,
+    partial: &PartialDownload,
+    etag: &str,
+    (start, end): (u64, u64),
+    mut on_chunk: impl FnMut(u64) -> bool,
+) -> Result<()> {
+    let mut response = client
+        .get(url)
+        .header(RANGE, format!("bytes={}-{}", start, end))
+        .header(IF_RANGE, etag)
+        .send()
+        .with_context(|| format!("Download from {}", url))?;
+    check_part(response.status(), url)?;
+    let mut file = partial.open_write()?;
+    file.seek(SeekFrom::Start(start))
+        .with_context(|| format!("Write to {:?}", partial.path))?;
+    let mut buffer = vec![0; 64 * 1024];
+    let mut remaining = end - start + 1;
+    while remaining > 0 {
+        let read = response.read(&mut buffer).with_context(|| {
+            format!(
+                "Download from {} to {:?}; run again to resume",
+                url, partial.path
+            )
+        })?;
+        if read == 0 {
+            return Err(BiosynthError::Parse(format!(
+                "{} ended {} bytes short of the requested range",
+                url, remaining
+            )));
+        }
+        let read = read.min(remaining as usize);
+        file.write_all(&buffer[..read])
+            .with_context(|| format!("Write to {:?}", partial.path))?;
+        remaining -= read as u64;
+        if !on_chunk(read as u64) {
+            return Ok(());
+        }
+    }
+    file.sync_all()
+        .with_context(|| format!("Write to {:?}", partial.path))
+}
+
+/// How a fresh download is split into concurrent range requests.
+#[cfg(feature = "download")]
+pub(crate) struct PartPlan {
+    /// Size of the whole file.
+    pub(crate) len: u64,
+    pub(crate) parts: u64,
+    /// Strong ETag making every range conditional on the same file.
+    pub(crate) etag: String,
+    pub(crate) validators: RemoteValidators,
+}
+
+#[cfg(feature = "download")]
+impl PartPlan {
+    /// A plan from the response to a `HEAD` probe, or `None` unless the server accepts byte
+    /// ranges, gives a strong ETag, and reports a file large enough for two parts.
+    pub(crate) fn new(status: StatusCode, headers: &HeaderMap, connections: usize) -> Option<Self> {
+        if !status.is_success() {
+            return None;
+        }
+        let ranges = headers.get(ACCEPT_RANGES)?.to_str().ok()?;
+        if !ranges.split(',').any(|unit| unit.trim() == "bytes") {
+            return None;
+        }
+        let etag = strong_etag(headers)?.to_string();
+        let len: u64 = headers.get(CONTENT_LENGTH)?.to_str().ok()?.parse().ok()?;
+        let parts = (connections as u64).min(len / MIN_PART_BYTES);
+        (parts > 1).then(|| Self {
+            len,
+            parts,
+            etag,
+            validators: RemoteValidators::from_headers(headers),
+        })
+    }
+
+    /// Inclusive byte ranges of similar size covering the file in order.
+    pub(crate) fn ranges(&self) -> Vec<(u64, u64)> {
+        let size = self.len.div_ceil(self.parts);
+        (0..self.parts)
+            .map(|part| part * size)
+            .take_while(|start| *start < self.len)
+            .map(|start| (start, (start + size).min(self.len) - 1))
+            .collect()
+    }
+}
+
+/// Fails unless a range request was answered with just that range; anything else means the
+/// server ignored the range or the file changed since it was probed.
+#[cfg(feature = "download")]
+pub(crate) fn check_part(status: StatusCode, url: &str) -> Result<()> {
+    match status {
+        StatusCode::PARTIAL_CONTENT => Ok(()),
+        status if status.is_success() => Err(BiosynthError::Parse(format!(
+            "{} changed or ignored a range request during the download; run again",
+            url
+        ))),
+        status => Err(BiosynthError::HttpStatus {
+            status: status.as_u16(),
+            url: url.to_string(),
+        }),
+    }
+}
+
+/// Bytes from the start of the file written without a gap, given how much of each range was
+/// fetched. Everything after them must be downloaded again.
+#[cfg(feature = "download")]
+pub(crate) fn contiguous_prefix(ranges: &[(u64, u64)], fetched: &[u64]) -> u64 {
+    let mut prefix = 0;
+    for (&(start, end), &fetched) in ranges.iter().zip(fetched) {
+        prefix = start + fetched;
+        if fetched < end - start + 1 {
+            break;
+        }
+    }
+    prefix
+}
+
+/// A file streamed into a [`PartialDownload`].
+#[cfg(feature = "download")]
+pub(crate) struct Download {
+    /// SHA-256 of the whole file, as lowercase hex.
+    pub(crate) sha256: String,
+    /// Bytes kept from an earlier, interrupted download.
+    pub(crate) resumed_bytes: u64,
+    pub(crate) validators: RemoteValidators,
+    /// Size of the compressed file this was decompressed from, if it was.
+    pub(crate) compressed_bytes: Option<u64>,
+}
+
+#[cfg(feature = "download")]
+impl Download {
+    /// This download of a zstd-compressed file, decompressed from `compressed` into `target`.
+    /// `compressed` is removed either way, since a corrupt archive must be fetched again.
+    pub(crate) fn decompressed(
+        self,
+        compressed: PartialDownload,
+        target: &PartialDownload,
+    ) -> Result<Self> {
+        let result = compressed.decompress_into(target);
+        compressed.discard()?;
+        if result.is_err() {
+            target.discard()?;
+        }
+        let (compressed_bytes, sha256) = result?;
+        Ok(Self {
+            sha256,
+            compressed_bytes: Some(compressed_bytes),
+            validators: RemoteValidators {
+                compressed: true,
+                ..self.validators
+            },
+            ..self
+        })
+    }
+}
+
+/// Validators of a download response, falling back to the ETag a resumed download was started
+/// under when the response carries none (as for `416 Range Not Satisfiable`).
+#[cfg(feature = "download")]
+pub(crate) fn transfer_validators(
+    headers: &HeaderMap,
+    resume: Option<&(u64, String)>,
+) -> RemoteValidators {
+    let mut validators = RemoteValidators::from_headers(headers);
+    if validators.etag.is_none() {
+        validators.etag = resume.map(|(_, etag)| etag.clone());
+    }
+    validators
+}
+
+/// What a response to a download request, ranged from `offset` when resuming, carries.
+#[cfg(feature = "download")]
+pub(crate) enum Transfer {
+    /// The whole file; anything downloaded earlier is stale.
+    Fresh,
+    /// The rest of the file after `offset`.
+    Resumed,
+    /// Nothing: the partial download already holds the whole file.
+    Complete,
+}
+
+#[cfg(feature = "download")]
+pub(crate) fn classify_response(status: StatusCode, offset: u64, url: &str) -> Result<Transfer> {
+    match status {
+        StatusCode::PARTIAL_CONTENT if offset > 0 => Ok(Transfer::Resumed),
+        StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => Ok(Transfer::Complete),
+        status if status.is_success() => Ok(Transfer::Fresh),
+        status => Err(BiosynthError::HttpStatus {
+            status: status.as_u16(),
+            url: url.to_string(),
+        }),
+    }
+}
+
+/// The response's ETag if it is strong; only strong validators can make a range conditional.
+#[cfg(feature = "download")]
+pub(crate) fn strong_etag(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .filter(|etag| !etag.starts_with("W/"))
+}
+
+/// The `<dest>.part` file a download is written to until it is complete and verified, with
+/// the ETag it was fetched under in `<dest>.part.etag`.
+#[cfg(feature = "download")]
+#[derive(Clone)]
+pub(crate) struct PartialDownload {
+    pub(crate) path: PathBuf,
+    validator: PathBuf,
+}
+
+#[cfg(feature = "download")]
+impl PartialDownload {
+    pub(crate) fn for_dest(dest: &Path) -> Self {
+        let mut path = dest.as_os_str().to_owned();
+        path.push(PARTIAL_SUFFIX);
+        let path = PathBuf::from(path);
+        let mut validator = path.as_os_str().to_owned();
+        validator.push(VALIDATOR_SUFFIX);
+        Self {
+            path,
+            validator: PathBuf::from(validator),
+        }
+    }
+
+    /// Bytes already downloaded and the ETag they were fetched under, if there are any and the
+    /// server gave an ETag to resume against.
+    pub(crate) fn resumable(&self) -> Option<(u64, String)> {
+        let len = fs::metadata(&self.path).ok()?.len();
+        let etag = fs::read_to_string(&self.validator).ok()?;
+        (len > 0 && !etag.is_empty()).then_some((len, etag))
+    }
+
+    /// Empties the partial download, recording the ETag of the response about to fill it.
+    pub(crate) fn start(&self, etag: Option<&str>) -> Result<fs::File> {
+        match etag {
+            Some(etag) => fs::write(&self.validator, etag)
+                .with_context(|| format!("Write {:?}", self.validator))?,
+            None => remove_if_present(&self.validator)?,
+        }
+        fs::File::create(&self.path).with_context(|| format!("Create {:?}", self.path))
+    }
+
+    pub(crate) fn open_write(&self) -> Result<fs::File> {
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&self.path)
+            .with_context(|| format!("Open {:?}", self.path))
+    }
+
+    /// Cuts the partial download back to its first `len` bytes, fetched under `etag`, so the
+    /// next attempt resumes after them.
+    pub(crate) fn keep_prefix(&self, len: u64, etag: &str) -> Result<()> {
+        self.open_write()?
+            .set_len(len)
+            .with_context(|| format!("Truncate {:?}", self.path))?;
+        fs::write(&self.validator, etag).with_context(|| format!("Write {:?}", self.validator))
+    }
+
+    pub(crate) fn open_append(&self) -> Result<fs::File> {
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Open {:?}", self.path))
+    }
+
+    /// Decompresses this finished zstd download into `target`, returning the compressed size
+    /// and the SHA-256 of the decompressed bytes.
+    pub(crate) fn decompress_into(&self, target: &PartialDownload) -> Result<(u64, String)> {
+        let file = fs::File::open(&self.path).with_context(|| format!("Open {:?}", self.path))?;
+        let compressed_bytes = file
+            .metadata()
+            .with_context(|| format!("Read {:?}", self.path))?
+            .len();
+        let mut decoder =
+            zstd::Decoder::new(file).with_context(|| format!("Decompress {:?}", self.path))?;
+        // Accept archives compressed with --long, whose windows exceed the default limit.
+        decoder
+            .window_log_max(31)
+            .with_context(|| format!("Decompress {:?}", self.path))?;
+        let mut hashing = HashingWriter {
+            inner: target.start(None)?,
+            hasher: Sha256::new(),
+            progress: DownloadProgress {
+                downloaded: 0,
+                total: None,
+            },
+            on_progress: &mut |_| {},
+        };
+        io::copy(&mut decoder, &mut hashing)
+            .with_context(|| format!("Decompress {:?} to {:?}", self.path, target.path))?;
+        hashing
+            .inner
+            .sync_all()
+            .with_context(|| format!("Write to {:?}", target.path))?;
+        Ok((compressed_bytes, format!("{:x}", hashing.hasher.finalize())))
+    }
+
+    /// Removes the partial download and its ETag.
+    pub(crate) fn discard(&self) -> Result<()> {
+        remove_if_present(&self.path)?;
+        remove_if_present(&self.validator)
+    }
+
+    /// A hasher fed with the bytes already downloaded.
+    fn hash(&self) -> Result<Sha256> {
+        let mut hasher = Sha256::new();
+        let mut file =
+            fs::File::open(&self.path).with_context(|| format!("Open {:?}", self.path))?;
+        io::copy(&mut file, &mut hasher).with_context(|| format!("Read {:?}", self.path))?;
+        Ok(hasher)
+    }
+
+    /// Moves the download to `dest` if its digest matches `expected`. A mismatching download
+    /// is deleted, so the next attempt starts over.
+    pub(crate) fn install(
+        self,
+        dest: &Path,
+        expected: Option<&str>,
+        download: Download,
+        version: String,
+    ) -> Result<FetchedReference> {
+        if let Some(expected) = expected {
+            if expected != download.sha256 {
+                self.discard()?;
+                return Err(BiosynthError::ChecksumMismatch {
+                    subject: format!("the download for {:?}", dest),
+                    expected: expected.to_string(),
+                    actual: download.sha256,
+                });
+            }
+        }
+        fs::rename(&self.path, dest)
+            .with_context(|| format!("Move {:?} to {:?}", self.path, dest))?;
+        remove_if_present(&self.validator)?;
+        Ok(FetchedReference {
+            sha256: download.sha256,
+            checksum: match expected {
+                Some(_) => ChecksumStatus::Verified,
+                None => ChecksumStatus::Unpublished,
+            },
+            resumed_bytes: download.resumed_bytes,
+            version,
+            validators: download.validators,
+            compressed_bytes: download.compressed_bytes,
+        })
+    }
+}
+
+#[cfg(feature = "download")]
+fn remove_if_present(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("Remove {:?}", path))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Hashes what it writes and reports each write to `on_progress`.
+#[cfg(feature = "download")]
+struct HashingWriter<'a, W> {
+    inner: W,
+    hasher: Sha256,
+    progress: DownloadProgress,
+    on_progress: &'a mut dyn FnMut(DownloadProgress),
+}
+
+#[cfg(feature = "download")]
+impl<W: Write> Write for HashingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.progress.downloaded += written as u64;
+        (self.on_progress)(self.progress);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,91 @@
+//! Keys for SQLCipher-encrypted stats databases (feature `sqlcipher`), so statistics derived
+//! from real genomes can sit on shared storage encrypted at rest.
+//!
+//! [`StatsStore::connect`](crate::stats::StatsStore::connect) and
+//! [`Dataset::open`](crate::dataset::Dataset::open) pick the key up from the environment:
+//! `BVS_DB_KEY` holds the passphrase itself, or `BVS_DB_KEY_FILE` names a file containing it.
+//! A new database opened with a key is created encrypted; an existing one must have been
+//! created with the same key.
+
+use std::fmt;
+use std::path::Path;
+
+use rusqlite::Connection;
+
+use crate::error::{BiosynthError, Context, Result};
+
+/// Environment variable holding the passphrase.
+pub const DB_KEY_ENV: &str = "BVS_DB_KEY";
+/// Environment variable naming a file that holds the passphrase.
+pub const DB_KEY_FILE_ENV: &str = "BVS_DB_KEY_FILE";
+
+/// A database passphrase. `Debug` never prints it.
+#[derive(Clone, PartialEq, Eq)]
+pub struct DatabaseKey(String);
+
+impl DatabaseKey {
+    pub fn new(passphrase: impl Into<String>) -> Result<Self> {
+        let passphrase = passphrase.into();
+        if passphrase.is_empty() {
+            return Err(BiosynthError::InvalidArgument(
+                "Database key must not be empty".into(),
+            ));
+        }
+        Ok(Self(passphrase))
+    }
+
+    /// Reads the passphrase from `path`, ignoring a trailing newline.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("Read database key file {:?}", path))?;
+        Self::new(raw.trim_end_matches(['\r', '\n']))
+    }
+
+    /// The key configured through `BVS_DB_KEY` or `BVS_DB_KEY_FILE`, if either is set.
+    pub fn from_env() -> Result<Option<Self>> {
+        let passphrase = std::env::var(DB_KEY_ENV).ok();
+        let key_file = std::env::var_os(DB_KEY_FILE_ENV);
+        match (passphrase, key_file) {
+            (Some(_), Some(_)) => Err(BiosynthError::InvalidArgument(format!(
+                "Set only one of {} or {}",
+                DB_KEY_ENV, DB_KEY_FILE_ENV
+            ))),
+            (Some(passphrase), None) => Self::new(passphrase).map(Some),
+            (None, Some(path)) => Self::from_file(Path::new(&path)).map(Some),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+impl fmt::Debug for DatabaseKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DatabaseKey(..)")
+    }
+}
+
+/// Unlocks `conn` with `key`; must run before any other statement on the connection.
+pub(crate) fn apply_key(conn: &Connection, key: Option<&DatabaseKey>, path: &Path) -> Result<()> {
+    let Some(key) = key else {
+        return Ok(());
+    };
+    if !cfg!(feature = "sqlcipher") {
+        return Err(BiosynthError::InvalidArgument(format!(
+            "A database key is configured but biosynth was built without the `sqlcipher` feature \
+             (unset {} / {})",
+            DB_KEY_ENV, DB_KEY_FILE_ENV
+        )));
+    }
+    conn.pragma_update(None, "key", &key.0)
+        .with_context(|| format!("Set key for {:?}", path))?;
+    // SQLCipher only checks the key on first access.
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+        row.get::<_, i64>(0)
+    })
+    .map_err(|_| {
+        BiosynthError::InvalidArgument(format!(
+            "Cannot unlock {:?}: wrong database key, or the database is not encrypted",
+            path
+        ))
+    })?;
+    Ok(())
+}
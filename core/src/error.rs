@@ -0,0 +1,185 @@
+//! Error type shared by every core module.
+
+use std::io;
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+pub type Result<T, E = BiosynthError> = std::result::Result<T, E>;
+
+/// Everything that can go wrong in biosynth-core. Underlying library errors are kept as the
+/// [`source`](std::error::Error::source), so `{:#}` in anyhow prints the full chain.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum BiosynthError {
+    /// Malformed input: genotype rows, chain files, or overlay documents.
+    #[error("{0}")]
+    Parse(String),
+
+    /// A value passed in by the caller was out of range or inconsistent.
+    #[error("{0}")]
+    InvalidArgument(String),
+
+    /// An export or report was refused by the [export policy](crate::policy).
+    #[cfg(feature = "stats")]
+    #[error("{0}")]
+    PolicyViolation(String),
+
+    /// The reference database does not exist at the expected path.
+    #[error(
+        "Reference database not found at {path:?}; run `bvs fetch-reference --dest {}` to download it",
+        path.display()
+    )]
+    ReferenceMissing { path: PathBuf },
+
+    /// Downloaded or stored data does not match its SHA-256 checksum, e.g. after truncation
+    /// or tampering.
+    #[error("Checksum mismatch for {subject}: expected {expected}, found {actual}")]
+    ChecksumMismatch {
+        subject: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("{context}")]
+    Io {
+        context: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("{context}")]
+    Json {
+        context: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// The database schema could not be created or configured.
+    #[cfg(feature = "stats")]
+    #[error("{context}")]
+    Schema {
+        context: String,
+        #[source]
+        source: rusqlite::Error,
+    },
+
+    #[cfg(feature = "stats")]
+    #[error("{context}")]
+    Database {
+        context: String,
+        #[source]
+        source: rusqlite::Error,
+    },
+
+    /// An Arrow kernel failed while aggregating observations.
+    #[cfg(feature = "columnar")]
+    #[error("{context}")]
+    Columnar {
+        context: String,
+        #[source]
+        source: arrow_schema::ArrowError,
+    },
+
+    #[cfg(feature = "download")]
+    #[error("{context}")]
+    Http {
+        context: String,
+        #[source]
+        source: reqwest::Error,
+    },
+
+    /// The server answered with a non-success status.
+    #[cfg(feature = "download")]
+    #[error("HTTP {status} for {url}")]
+    HttpStatus { status: u16, url: String },
+
+    /// A blocking task spawned onto tokio panicked or was cancelled.
+    #[cfg(feature = "async")]
+    #[error("Synthetic generation task panicked")]
+    TaskFailed(#[source] tokio::task::JoinError),
+}
+
+impl From<io::Error> for BiosynthError {
+    fn from(source: io::Error) -> Self {
+        source.into_error("I/O error".into())
+    }
+}
+
+#[cfg(feature = "stats")]
+impl From<rusqlite::Error> for BiosynthError {
+    fn from(source: rusqlite::Error) -> Self {
+        source.into_error("Database query failed".into())
+    }
+}
+
+/// Library errors that can be wrapped into a [`BiosynthError`] with a message.
+pub(crate) trait IntoBiosynthError {
+    fn into_error(self, context: String) -> BiosynthError;
+}
+
+impl IntoBiosynthError for io::Error {
+    fn into_error(self, context: String) -> BiosynthError {
+        BiosynthError::Io {
+            context,
+            source: self,
+        }
+    }
+}
+
+impl IntoBiosynthError for serde_json::Error {
+    fn into_error(self, context: String) -> BiosynthError {
+        BiosynthError::Json {
+            context,
+            source: self,
+        }
+    }
+}
+
+#[cfg(feature = "stats")]
+impl IntoBiosynthError for rusqlite::Error {
+    fn into_error(self, context: String) -> BiosynthError {
+        BiosynthError::Database {
+            context,
+            source: self,
+        }
+    }
+}
+
+#[cfg(feature = "columnar")]
+impl IntoBiosynthError for arrow_schema::ArrowError {
+    fn into_error(self, context: String) -> BiosynthError {
+        BiosynthError::Columnar {
+            context,
+            source: self,
+        }
+    }
+}
+
+#[cfg(feature = "download")]
+impl IntoBiosynthError for reqwest::Error {
+    fn into_error(self, context: String) -> BiosynthError {
+        BiosynthError::Http {
+            context,
+            source: self,
+        }
+    }
+}
+
+/// `anyhow::Context`-style helpers for the library errors above.
+pub(crate) trait Context<T> {
+    // Unused when only the parser and liftover are compiled in.
+    #[allow(dead_code)]
+    fn context(self, context: &str) -> Result<T>;
+    fn with_context<F: FnOnce() -> String>(self, context: F) -> Result<T>;
+}
+
+impl<T, E: IntoBiosynthError> Context<T> for std::result::Result<T, E> {
+    fn context(self, context: &str) -> Result<T> {
+        self.map_err(|err| err.into_error(context.to_string()))
+    }
+
+    fn with_context<F: FnOnce() -> String>(self, context: F) -> Result<T> {
+        self.map_err(|err| err.into_error(context()))
+    }
+}
@@ -0,0 +1,149 @@
+//! Illumina GenCall Final Reports, as GenomeStudio exports them for a lab's arrays.
+//!
+//! A report opens with a `[Header]` section of key/value lines and lists calls in its `[Data]`
+//! section, one row per sample and SNP, grouped into per-sample blocks by `Sample ID`. The
+//! genotype parser reads such a file as its first sample. For the others, each sample is
+//! addressed as if the report were a directory: `report.txt/sample-2` is the second sample
+//! block. [`sample_paths`] lists those paths, [`process_sample`] parses one, and
+//! [`process_samples`] parses several in one pass over the report.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, Read};
+use std::path::{Path, PathBuf};
+
+use crate::buffers;
+use crate::error::{BiosynthError, Context, Result};
+use crate::genotype::{
+    FileMetadata, GenotypeReader, ParseOptions, ParsedFile, ReportSection, VariantRecord,
+};
+
+const SAMPLE_PREFIX: &str = "sample-";
+/// Bytes read from the start of a file when checking for a `[Header]` line.
+const SNIFF_BYTES: u64 = 4096;
+
+/// Whether `path` is a file whose first non-empty line opens a Final Report's `[Header]`.
+pub fn is_final_report(path: &Path) -> bool {
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+    buffers::reader(file.take(SNIFF_BYTES))
+        .lines()
+        .map_while(|line| line.ok())
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(|line| ReportSection::parse(line.trim()) == Some(ReportSection::Header))
+}
+
+/// The `Sample ID`s of `report`'s `[Data]` section, in the order their blocks appear; empty
+/// if the section has no `Sample ID` column.
+pub fn sample_ids(report: &Path) -> Result<Vec<String>> {
+    let file = File::open(report).with_context(|| format!("Failed to open {:?}", report))?;
+    let mut in_data = false;
+    let mut column = None;
+    let mut seen = HashSet::new();
+    let mut ids = Vec::new();
+    for line in buffers::reader(file).lines() {
+        let line = line?;
+        let trimmed = line.trim();
+        if let Some(section) = ReportSection::parse(trimmed) {
+            in_data = section == ReportSection::Data;
+            column = None;
+            continue;
+        }
+        if !in_data || trimmed.is_empty() {
+            continue;
+        }
+        let delimiter = if trimmed.contains('\t') { '\t' } else { ',' };
+        let mut fields = trimmed.split(delimiter).map(str::trim);
+        let Some(idx) = column else {
+            column = fields.position(|name| name.eq_ignore_ascii_case("Sample ID"));
+            if column.is_none() {
+                return Ok(Vec::new());
+            }
+            continue;
+        };
+        if let Some(id) = fields.nth(idx).filter(|id| !id.is_empty()) {
+            if seen.insert(id.to_string()) {
+                ids.push(id.to_string());
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// One path per sample block of `report`.
+pub fn sample_paths(report: &Path) -> Result<Vec<PathBuf>> {
+    Ok((1..=sample_ids(report)?.len())
+        .map(|sample| report.join(format!("{}{}", SAMPLE_PREFIX, sample)))
+        .collect())
+}
+
+/// The report and 1-based sample number a path from [`sample_paths`] refers to; `None` for
+/// other paths.
+pub fn split_sample(path: &Path) -> Option<(&Path, usize)> {
+    let report = path
+        .parent()
+        .filter(|report| report.is_file() && is_final_report(report))?;
+    let sample = path
+        .file_name()?
+        .to_str()?
+        .strip_prefix(SAMPLE_PREFIX)?
+        .parse()
+        .ok()
+        .filter(|sample| *sample > 0)?;
+    Some((report, sample))
+}
+
+/// Parses the rows of sample block `sample` (1-based) of `report`, calling `on_variant` for
+/// every usable one.
+pub fn process_sample<F>(
+    report: &Path,
+    sample: usize,
+    options: &ParseOptions,
+    mut on_variant: F,
+) -> Result<ParsedFile>
+where
+    F: FnMut(&VariantRecord, &FileMetadata) -> Result<()>,
+{
+    let parsed = process_samples(report, &[sample], options, |_, record, metadata| {
+        on_variant(record, metadata)
+    })?;
+    Ok(parsed.into_iter().next().expect("one sample was read"))
+}
+
+/// Parses the rows of sample blocks `samples` (1-based) of `report` in one pass, sending each
+/// row to its `Sample ID`'s sample: `on_variant` is called with the sample's index in
+/// `samples`. Returns a [`ParsedFile`] per sample, in the order of `samples`.
+pub fn process_samples<F>(
+    report: &Path,
+    samples: &[usize],
+    options: &ParseOptions,
+    on_variant: F,
+) -> Result<Vec<ParsedFile>>
+where
+    F: FnMut(usize, &VariantRecord, &FileMetadata) -> Result<()>,
+{
+    if samples.contains(&0) {
+        return Err(BiosynthError::InvalidArgument(
+            "Final Report samples are numbered from 1".to_string(),
+        ));
+    }
+    let numbers: Vec<usize> = samples.iter().map(|sample| sample - 1).collect();
+    let parsed = GenotypeReader::open_with_options(report, options)?.drain_samples(
+        &numbers,
+        |_| {},
+        on_variant,
+    )?;
+    // Every row of a sample is either parsed or skipped, so one without rows is not there.
+    if let Some((sample, _)) = samples
+        .iter()
+        .zip(&parsed)
+        .find(|(_, parsed)| parsed.summary.variant_count + parsed.summary.skipped_rows == 0)
+    {
+        return Err(BiosynthError::InvalidArgument(format!(
+            "{:?} has no sample {}",
+            report, sample
+        )));
+    }
+    Ok(parsed)
+}
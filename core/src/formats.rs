@@ -0,0 +1,145 @@
+//! Output formats for synthetic generation (feature `synthetic`).
+//!
+//! A [`FormatWriter`] turns generated rows into bytes; a [`FormatRegistry`] looks writers up
+//! by name so embedders can add their own formats next to the built-in ones.
+//!
+//! ```no_run
+//! use std::io::Write;
+//! use std::sync::Arc;
+//!
+//! use biosynth_core::formats::{FormatRegistry, FormatWriter};
+//! use biosynth_core::synthetic::SyntheticRow;
+//!
+//! #[derive(Debug)]
+//! struct Csv;
+//!
+//! impl FormatWriter for Csv {
+//!     fn name(&self) -> &str {
+//!         "csv"
+//!     }
+//!
+//!     fn write_row(&self, out: &mut dyn Write, row: &SyntheticRow) -> biosynth_core::Result<()> {
+//!         writeln!(out, "rs{},{},{},{}", row.rsid, row.chromosome, row.position, row.genotype)?;
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut registry = FormatRegistry::new();
+//! registry.register(Arc::new(Csv));
+//! assert!(registry.get("csv").is_some());
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::io::Write;
+use std::sync::Arc;
+
+use crate::error::{Context, Result};
+use crate::synthetic::SyntheticRow;
+
+const DYNAMIC_DNA_HEADER: &str = r#"# This data file generated by Dynamic DNA (DDNA) Laboratories at: Thu Nov 7 16:03:14 2024
+#						
+# This file contains raw genetic data, including data that is not used in DDNA reports. 						
+# This data has undergone a general quality review however only a subset of markers have been 						
+# individually reviewed for accuracy. As such, this data is suitable only for research,  						
+# educational, and informational use and not for any medical or diagnostic use. 						
+# 						
+# Below is a text version of your data.  Fields are TAB-separated and						
+# each line corresponds to a single SNP.  For each SNP, we provide its identifier,  						
+# it chromosomal location realtive to Build 38 of the human reference genome, and the 						
+# genotype call oriented with respect to the plus strand on the human reference sequence.						
+# More information about Dynamic DNA Laboratories can be found at:						
+# https://dynamicdnalabs.com						
+# 						
+# More information on reference human assembly builds:						
+# https://www.ncbi.nlm.nih.gov/datasets/genome/GCF_000001405.40/						
+#						
+# rsid	chromosome	position	genotype	gs	baf	lrr
+"#;
+
+/// Writes generated rows in one output format. Only [`write_row`](Self::write_row) is
+/// required; the header, annotation, and footer hooks default to sensible no-ops.
+pub trait FormatWriter: Debug + Send + Sync {
+    /// Registry key, e.g. `dynamic_dna`.
+    fn name(&self) -> &str;
+
+    /// Called once before the first row.
+    fn write_header(&self, _out: &mut dyn Write) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_row(&self, out: &mut dyn Write, row: &SyntheticRow) -> Result<()>;
+
+    /// Writes a note attached to `row` by a [`RowAction::Annotate`](crate::synthetic::RowAction::Annotate)
+    /// hook, just before the row itself. Defaults to a `# <note>` comment line.
+    fn write_annotation(&self, out: &mut dyn Write, row: &SyntheticRow, note: &str) -> Result<()> {
+        writeln!(out, "# {}", note.replace('\n', " "))
+            .with_context(|| format!("write annotation for rs{}", row.rsid))
+    }
+
+    /// Called once after the last row with the number of rows written.
+    fn write_footer(&self, _out: &mut dyn Write, _rows: usize) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Dynamic DNA TSV export: rsid, chromosome, position, genotype, gs, baf, lrr.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DynamicDnaWriter;
+
+impl FormatWriter for DynamicDnaWriter {
+    fn name(&self) -> &str {
+        "dynamic_dna"
+    }
+
+    fn write_header(&self, out: &mut dyn Write) -> Result<()> {
+        out.write_all(DYNAMIC_DNA_HEADER.as_bytes())
+            .context("write header")
+    }
+
+    fn write_row(&self, out: &mut dyn Write, row: &SyntheticRow) -> Result<()> {
+        writeln!(
+            out,
+            "rs{}\t{}\t{}\t{}\t{:.4}\t{:.3}\t{:.4}",
+            row.rsid, row.chromosome, row.position, row.genotype, row.gs, row.baf, row.lrr
+        )
+        .with_context(|| format!("write row for rs{}", row.rsid))
+    }
+}
+
+/// Output formats keyed by [`FormatWriter::name`].
+#[derive(Debug, Clone)]
+pub struct FormatRegistry {
+    writers: BTreeMap<String, Arc<dyn FormatWriter>>,
+}
+
+impl FormatRegistry {
+    /// A registry holding the built-in formats.
+    pub fn new() -> Self {
+        let mut registry = Self {
+            writers: BTreeMap::new(),
+        };
+        registry.register(Arc::new(DynamicDnaWriter));
+        registry
+    }
+
+    /// Adds `writer`, replacing any format already registered under the same name.
+    pub fn register(&mut self, writer: Arc<dyn FormatWriter>) {
+        self.writers.insert(writer.name().to_string(), writer);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn FormatWriter>> {
+        self.writers.get(name).cloned()
+    }
+
+    /// Registered format names, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.writers.keys().map(String::as_str)
+    }
+}
+
+impl Default for FormatRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
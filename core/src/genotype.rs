@@ -0,0 +1,1945 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+#[cfg(feature = "mmap")]
+use rayon::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::alleles::{is_valid_genotype, normalize_genotype};
+use crate::buffers;
+use crate::error::{BiosynthError, Context, Result};
+use crate::liftover::GenomeBuild;
+use crate::qc::SexChromosomeCalls;
+use crate::vendors::{self, Vendor};
+
+pub(crate) const LOOKAHEAD_LINES: usize = 2048;
+/// Bytes of a mapped file each chunk holds when one file is split across threads.
+#[cfg(feature = "mmap")]
+const PARSE_CHUNK_BYTES: usize = 4 * 1024 * 1024;
+
+const COMMENT_PREFIXES: [&str; 2] = ["#", "//"];
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16_LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16_BE_BOM: &[u8] = b"\xFE\xFF";
+/// Distinct chromosome names a parser shares between rows; assemblies with more contigs than
+/// this allocate a name per row for the rest.
+const MAX_INTERNED_CHROMOSOMES: usize = 256;
+const RSID_ALIASES: &[&str] = &["rsid", "name", "snp", "snpname", "marker", "id"];
+const CHROM_ALIASES: &[&str] = &["chromosome", "chr", "chrom"];
+const POSITION_ALIASES: &[&str] = &[
+    "position",
+    "pos",
+    "coordinate",
+    "basepairposition",
+    "basepair",
+];
+const GENOTYPE_ALIASES: &[&str] = &[
+    "genotype",
+    "gt",
+    "result",
+    "results",
+    "result1",
+    "call",
+    "calls",
+    "yourcode",
+    "code",
+    "genotypevalue",
+    "variation",
+];
+const ALLELE1_ALIASES: &[&str] = &[
+    "allele1",
+    "allelea",
+    "allele_a",
+    "allele1top",
+    "allele1forward",
+    "allele1plus",
+];
+const ALLELE2_ALIASES: &[&str] = &[
+    "allele2",
+    "alleleb",
+    "allele_b",
+    "allele2top",
+    "allele2forward",
+    "allele2plus",
+];
+/// The column of an Illumina Final Report naming the sample a row belongs to.
+const SAMPLE_ALIASES: &[&str] = &["sampleid"];
+/// VCF columns read from each record; the sample columns follow `FORMAT`.
+const VCF_CHROM: usize = 0;
+const VCF_POS: usize = 1;
+const VCF_ID: usize = 2;
+const VCF_REF: usize = 3;
+const VCF_ALT: usize = 4;
+const VCF_FORMAT: usize = 8;
+const VCF_FIRST_SAMPLE: usize = 9;
+/// Header keys (normalized) that identify a person or an order; matched as substrings.
+const PII_KEY_FRAGMENTS: &[&str] = &[
+    "name",
+    "mail",
+    "order",
+    "kit",
+    "barcode",
+    "sample",
+    "customer",
+    "user",
+    "account",
+    "address",
+    "phone",
+    "birth",
+    "dob",
+    "patient",
+    "participant",
+    "donor",
+];
+/// Digits in a row at which a header value is treated as an identifier.
+const PII_DIGIT_RUN: usize = 6;
+const MAX_HEADER_KEY_LEN: usize = 64;
+const MAX_HEADER_VALUE_LEN: usize = 128;
+
+/// `key: value` pairs from a file's leading comment lines, with anything that could identify
+/// the person or order removed, and what the header says about the file's origin. Raw header
+/// text is never kept.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileMetadata {
+    pub header_fields: BTreeMap<String, String>,
+    /// Header fields dropped as potentially identifying.
+    pub scrubbed_fields: usize,
+    /// The [vendor](crate::vendors) whose export format the header matches.
+    #[serde(default)]
+    pub vendor: Option<Vendor>,
+    /// The vendor's format or chip version, where the header gives one.
+    #[serde(default)]
+    pub vendor_version: Option<String>,
+    /// The build the header comments name, if they name exactly one.
+    #[serde(default)]
+    pub genome_build: Option<GenomeBuild>,
+}
+
+impl FileMetadata {
+    /// Collects header fields from the comment lines before the first data row, or from the
+    /// `[Header]` section of an Illumina Final Report. A field is dropped when its key names
+    /// an identifier (name, email, order or kit ID, ...) or its value holds an email address or
+    /// a long digit run. The comments and the first other line's columns are
+    /// [fingerprinted](crate::vendors) for the vendor, version, and build.
+    pub fn from_header_lines<I>(lines: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut metadata = FileMetadata::default();
+        let mut comments = Vec::new();
+        let mut columns = Vec::new();
+        let mut section = None;
+        for line in lines {
+            let trimmed = line.as_ref().trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            if let Some(found) = ReportSection::parse(trimmed) {
+                section = Some(found);
+                continue;
+            }
+            let field = match section {
+                Some(ReportSection::Header) => trimmed.split_once(['\t', ',']),
+                Some(ReportSection::Other) => continue,
+                _ => {
+                    let Some(prefix) = COMMENT_PREFIXES
+                        .iter()
+                        .find(|prefix| trimmed.starts_with(**prefix))
+                    else {
+                        columns = trimmed
+                            .split(['\t', ','])
+                            .map(|field| normalize_name(field.trim().trim_matches('"')))
+                            .collect();
+                        break;
+                    };
+                    let content = trimmed.trim_start_matches(prefix).trim();
+                    comments.push(content.to_string());
+                    content.split_once([':', '='])
+                }
+            };
+            if let Some((key, value)) = field {
+                metadata.insert_field(key.trim(), value.trim().trim_end_matches([',', '\t']));
+            }
+        }
+        let comments: Vec<&str> = comments.iter().map(String::as_str).collect();
+        let fingerprint = vendors::fingerprint(&comments, &columns);
+        metadata.vendor = fingerprint.vendor;
+        metadata.vendor_version = fingerprint.version;
+        metadata.genome_build = fingerprint.genome_build;
+        metadata
+    }
+
+    fn insert_field(&mut self, key: &str, value: &str) {
+        if key.is_empty() || key.len() > MAX_HEADER_KEY_LEN {
+            return;
+        }
+        if is_identifying(key, value) {
+            self.scrubbed_fields += 1;
+            return;
+        }
+        let value: String = value.chars().take(MAX_HEADER_VALUE_LEN).collect();
+        self.header_fields.insert(key.to_string(), value);
+    }
+
+    /// The build the header names: [`genome_build`](Self::genome_build), or for metadata
+    /// stored before it existed, a header field such as `build: 37` or `reference: GRCh38`;
+    /// `None` if neither does, or fields name different builds.
+    pub fn declared_build(&self) -> Option<GenomeBuild> {
+        if self.genome_build.is_some() {
+            return self.genome_build;
+        }
+        let mut declared = self.header_fields.iter().filter_map(|(key, value)| {
+            vendors::named_build(&format!("{} {}", key, value).to_ascii_lowercase())
+        });
+        let build = declared.next()?;
+        declared.all(|other| other == build).then_some(build)
+    }
+}
+
+fn is_identifying(key: &str, value: &str) -> bool {
+    let key = normalize_name(key);
+    if PII_KEY_FRAGMENTS
+        .iter()
+        .any(|fragment| key.contains(fragment))
+        || key == "id"
+        || key.ends_with("id")
+    {
+        return true;
+    }
+    if value.contains('@') {
+        return true;
+    }
+    let mut run = 0;
+    value.chars().any(|ch| {
+        run = if ch.is_ascii_digit() { run + 1 } else { 0 };
+        run >= PII_DIGIT_RUN
+    })
+}
+
+/// One parsed genotype row.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariantRecord {
+    pub rsid: Rsid,
+    /// Shared by every row of a file that names the same chromosome.
+    pub chromosome: Arc<str>,
+    pub position: i64,
+    /// The call, or both alleles concatenated for split-allele formats, normalized by
+    /// [`normalize_genotype`].
+    pub genotype: String,
+}
+
+/// A row's rsid, parsed once while tokenizing. A dbSNP ID written canonically (`rs` and a
+/// number) is held as its number; anything else, such as a vendor ID like `i6019299`, keeps its
+/// text. Either way it displays exactly as written.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Rsid {
+    Rs(i64),
+    Other(Box<str>),
+}
+
+impl Rsid {
+    pub fn parse(raw: &str) -> Self {
+        let canonical = raw.strip_prefix("rs").filter(|digits| {
+            !digits.is_empty()
+                && digits.bytes().all(|byte| byte.is_ascii_digit())
+                && (digits.len() == 1 || !digits.starts_with('0'))
+        });
+        match canonical.map(str::parse) {
+            Some(Ok(number)) => Rsid::Rs(number),
+            _ => Rsid::Other(raw.into()),
+        }
+    }
+
+    /// The dbSNP number, including non-canonical spellings such as `rs0123`.
+    pub fn number(&self) -> Option<i64> {
+        match self {
+            Rsid::Rs(number) => Some(*number),
+            Rsid::Other(raw) => raw.trim().strip_prefix("rs")?.parse().ok(),
+        }
+    }
+}
+
+impl fmt::Display for Rsid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rsid::Rs(number) => write!(f, "rs{}", number),
+            Rsid::Other(raw) => f.write_str(raw),
+        }
+    }
+}
+
+impl Serialize for Rsid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rsid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        Ok(Rsid::parse(&String::deserialize(deserializer)?))
+    }
+}
+
+/// Row counts for a parsed file; rows missing an rsid, chromosome, position, or call are skipped.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct ParseSummary {
+    pub variant_count: usize,
+    pub skipped_rows: usize,
+    /// Calls complemented back onto the reference strand, when strand correction is on.
+    #[serde(default)]
+    pub strand_corrections: usize,
+    /// Retired rsids replaced by their current ID, when rsid merges are loaded.
+    #[serde(default)]
+    pub rsid_remaps: usize,
+    /// Calls whose coordinates were compared with a reference SNP's, when position checks are
+    /// on.
+    #[serde(default)]
+    pub position_checks: usize,
+    /// Checked calls on another chromosome or position than the reference's.
+    #[serde(default)]
+    pub position_mismatches: usize,
+    /// Rows left out for repeating an rsid, under the stats store's duplicate policy.
+    #[serde(default)]
+    pub duplicate_rows: usize,
+    /// Calls outside the genotype alphabet, handled as the [`GenotypeValidation`] says.
+    #[serde(default)]
+    pub invalid_genotypes: usize,
+    /// Calls at sentinel rsids placed where exactly one build puts them, when build sentinels
+    /// are loaded.
+    #[serde(default)]
+    pub sentinel_calls: usize,
+    /// The build most sentinel calls agree with, once there are enough of them.
+    #[serde(default)]
+    pub detected_build: Option<GenomeBuild>,
+    /// Whether a substantial share of sentinel calls agree with each build.
+    #[serde(default)]
+    pub mixed_build: bool,
+    /// X, Y, and MT call counts behind the file's [QC warnings](crate::qc), when the stats
+    /// store tallied them.
+    #[serde(default)]
+    pub sex_chromosomes: SexChromosomeCalls,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedFile {
+    pub metadata: FileMetadata,
+    pub summary: ParseSummary,
+}
+
+pub(crate) enum LineOutcome {
+    Parsed(VariantRecord),
+    Skipped,
+    Ignored,
+}
+
+/// Parses a genotype file with the default [`ParseOptions`], calling `on_variant` for every
+/// usable row; see [`process_file_with_options`].
+pub fn process_file<F>(path: &Path, on_variant: F) -> Result<ParsedFile>
+where
+    F: FnMut(&VariantRecord, &FileMetadata) -> Result<()>,
+{
+    process_file_with_options(path, &ParseOptions::default(), on_variant)
+}
+
+/// Parses a genotype file under `options`, calling `on_variant` for every usable row. The
+/// delimiter and column layout are detected from the first lines of the file, after a byte
+/// order mark is dropped and UTF-16 is transcoded to UTF-8. An `.xlsx` workbook is read
+/// as its [first worksheet](crate::xlsx); with feature `bcf`, `.bcf` files are decoded through
+/// htslib instead.
+pub fn process_file_with_options<F>(
+    path: &Path,
+    options: &ParseOptions,
+    on_variant: F,
+) -> Result<ParsedFile>
+where
+    F: FnMut(&VariantRecord, &FileMetadata) -> Result<()>,
+{
+    #[cfg(feature = "bcf")]
+    if crate::bcf::is_bcf(path) {
+        return crate::bcf::process_file(path, options, on_variant);
+    }
+    #[cfg(feature = "stats")]
+    if crate::xlsx::is_xlsx(path) {
+        return process_reader(
+            &crate::xlsx::first_sheet_text(path)?[..],
+            options,
+            on_variant,
+        );
+    }
+    #[cfg(feature = "mmap")]
+    if std::fs::metadata(path).is_ok_and(|meta| meta.len() >= MMAP_THRESHOLD) {
+        return process_mapped(path, options, on_variant);
+    }
+    let file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    if file.metadata().is_ok_and(|meta| meta.len() == 0) {
+        return Err(BiosynthError::Parse(format!("File {:?} is empty", path)));
+    }
+    process_reader(buffers::reader(file), options, on_variant)
+}
+
+/// Like [`process_file_with_options`], but over any buffered source, such as a file inflated
+/// into memory.
+pub fn process_reader<R, F>(
+    mut reader: R,
+    options: &ParseOptions,
+    on_variant: F,
+) -> Result<ParsedFile>
+where
+    R: BufRead,
+    F: FnMut(&VariantRecord, &FileMetadata) -> Result<()>,
+{
+    let start = reader.fill_buf()?;
+    let little_endian = if start.starts_with(UTF16_LE_BOM) {
+        true
+    } else if start.starts_with(UTF16_BE_BOM) {
+        false
+    } else {
+        return drain(
+            GenotypeReader::from_reader_with_options(reader, options)?,
+            on_variant,
+        );
+    };
+    reader.consume(UTF16_LE_BOM.len());
+    let decoded = buffers::reader(Utf16Decoder::new(reader, little_endian));
+    drain(
+        GenotypeReader::from_reader_with_options(decoded, options)?,
+        on_variant,
+    )
+}
+
+/// UTF-16 input, after its byte order mark, transcoded to UTF-8 as it is read. Unpaired
+/// surrogates and a trailing odd byte become U+FFFD, as in [`parse_bytes`].
+struct Utf16Decoder<R> {
+    inner: R,
+    little_endian: bool,
+    /// Bytes read but not yet decoded: an odd byte, or a high surrogate awaiting its pair.
+    carry: Vec<u8>,
+    decoded: String,
+    /// Bytes of `decoded` already handed out.
+    position: usize,
+}
+
+impl<R: BufRead> Utf16Decoder<R> {
+    fn new(inner: R, little_endian: bool) -> Self {
+        Self {
+            inner,
+            little_endian,
+            carry: Vec::new(),
+            decoded: String::new(),
+            position: 0,
+        }
+    }
+
+    /// Decodes the next chunk of input into `decoded`, which stays empty at the end of input.
+    fn refill(&mut self) -> io::Result<()> {
+        self.decoded.clear();
+        self.position = 0;
+        while self.decoded.is_empty() {
+            let mut bytes = std::mem::take(&mut self.carry);
+            let chunk = self.inner.fill_buf()?;
+            if chunk.is_empty() {
+                if !bytes.is_empty() {
+                    self.decoded.push(char::REPLACEMENT_CHARACTER);
+                }
+                return Ok(());
+            }
+            bytes.extend_from_slice(chunk);
+            let read = chunk.len();
+            self.inner.consume(read);
+
+            let mut whole = bytes.len() / 2 * 2;
+            let little_endian = self.little_endian;
+            let unit = |pair: &[u8]| {
+                if little_endian {
+                    u16::from_le_bytes([pair[0], pair[1]])
+                } else {
+                    u16::from_be_bytes([pair[0], pair[1]])
+                }
+            };
+            if whole > 0 && (0xD800..0xDC00).contains(&unit(&bytes[whole - 2..whole])) {
+                whole -= 2;
+            }
+            self.decoded = decode_utf16_lossy(bytes[..whole].chunks_exact(2).map(unit));
+            self.carry = bytes.split_off(whole);
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for Utf16Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.decoded.len() {
+            self.refill()?;
+        }
+        let pending = &self.decoded.as_bytes()[self.position..];
+        let count = pending.len().min(buf.len());
+        buf[..count].copy_from_slice(&pending[..count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+pub(crate) fn drain<R, F>(mut reader: GenotypeReader<R>, mut on_variant: F) -> Result<ParsedFile>
+where
+    R: BufRead,
+    F: FnMut(&VariantRecord, &FileMetadata) -> Result<()>,
+{
+    while let Some(record) = reader.next() {
+        on_variant(&record?, reader.metadata())?;
+    }
+    Ok(reader.into_parsed())
+}
+
+/// What parsers do with a call outside the genotype alphabet (`A`, `C`, `G`, `T`, `D`, `I`,
+/// `-`, `0`), such as `NN`, `?`, or an empty field. Either way the call is counted in
+/// [`ParseSummary::invalid_genotypes`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GenotypeValidation {
+    /// Keeps the row as a no-call (`--`).
+    Lenient,
+    /// Skips the row.
+    #[default]
+    Strict,
+    /// Fails the file.
+    Error,
+}
+
+impl GenotypeValidation {
+    /// `genotype` if it is in the alphabet; otherwise, counted in `invalid`, what this policy
+    /// makes of it: a no-call, `None` to skip the row, or an error naming `rsid`.
+    pub(crate) fn check(
+        self,
+        genotype: String,
+        rsid: &Rsid,
+        invalid: &mut usize,
+    ) -> Result<Option<String>> {
+        if is_valid_genotype(&genotype) {
+            return Ok(Some(genotype));
+        }
+        *invalid += 1;
+        match self {
+            GenotypeValidation::Lenient => Ok(Some("--".to_string())),
+            GenotypeValidation::Strict => Ok(None),
+            GenotypeValidation::Error => Err(BiosynthError::Parse(format!(
+                "Invalid genotype {:?} for {}",
+                genotype, rsid
+            ))),
+        }
+    }
+}
+
+impl FromStr for GenotypeValidation {
+    type Err = BiosynthError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "lenient" => Ok(GenotypeValidation::Lenient),
+            "strict" => Ok(GenotypeValidation::Strict),
+            "error" => Ok(GenotypeValidation::Error),
+            _ => Err(BiosynthError::InvalidArgument(format!(
+                "Unknown genotype validation {:?} (expected lenient, strict or error)",
+                value
+            ))),
+        }
+    }
+}
+
+/// A column named by its header or by its 1-based position, as `cut -f` counts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnRef {
+    Name(String),
+    Position(usize),
+}
+
+impl FromStr for ColumnRef {
+    type Err = BiosynthError;
+
+    /// Digits are a position; anything else is a header name.
+    fn from_str(value: &str) -> Result<Self> {
+        let value = value.trim();
+        match value.parse::<usize>() {
+            Ok(0) => Err(BiosynthError::InvalidArgument(
+                "Column positions start at 1".to_string(),
+            )),
+            Ok(position) => Ok(ColumnRef::Position(position)),
+            Err(_) if value.is_empty() => Err(BiosynthError::InvalidArgument(
+                "Empty column name".to_string(),
+            )),
+            Err(_) => Ok(ColumnRef::Name(value.to_string())),
+        }
+    }
+}
+
+/// Columns to read fields from instead of the ones the header aliases pick, for exports
+/// whose header the aliases do not recognize.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnOverrides {
+    pub rsid: Option<ColumnRef>,
+    pub chromosome: Option<ColumnRef>,
+    pub position: Option<ColumnRef>,
+    pub genotype: Option<ColumnRef>,
+}
+
+/// Header names recognized for each field on top of the built-in aliases, for lab formats
+/// the built-ins do not cover. Names are matched as headers are, ignoring case, spaces,
+/// hyphens, and underscores.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HeaderAliases {
+    pub rsid: Vec<String>,
+    pub chromosome: Vec<String>,
+    pub position: Vec<String>,
+    pub genotype: Vec<String>,
+    pub allele1: Vec<String>,
+    pub allele2: Vec<String>,
+    pub sample: Vec<String>,
+}
+
+impl HeaderAliases {
+    /// Reads aliases from a TOML file (`.toml`) or a JSON one (anything else), each field a
+    /// list of names:
+    ///
+    /// ```toml
+    /// rsid = ["Marker Ident"]
+    /// genotype = ["Callz"]
+    /// ```
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read aliases {:?}", path))?;
+        let is_toml = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+        let parsed = if is_toml {
+            toml::from_str(&raw).map_err(|err| err.message().to_string())
+        } else {
+            serde_json::from_str(&raw).map_err(|err| err.to_string())
+        };
+        parsed.map_err(|err| {
+            BiosynthError::InvalidArgument(format!("Invalid aliases {:?}: {}", path, err))
+        })
+    }
+}
+
+/// Files at least this large are parsed through a memory mapping by [`process_file`].
+#[cfg(feature = "mmap")]
+pub const MMAP_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// Like [`process_file_with_options`], but parses straight out of a read-only memory mapping
+/// of the file: line boundaries are found by scanning the mapping and rows are parsed in
+/// place, so no line is copied. Only for uncompressed files.
+///
+/// Once the column layout is known, the rest of the file is cut into line-aligned chunks,
+/// [`ParseOptions::threads`] at a time, parsed on the current rayon thread pool; a file parsed
+/// inside a pool's worker shares that pool rather than adding threads. `on_variant` still
+/// sees every row in file order.
+#[cfg(feature = "mmap")]
+pub fn process_mapped<F>(
+    path: &Path,
+    options: &ParseOptions,
+    mut on_variant: F,
+) -> Result<ParsedFile>
+where
+    F: FnMut(&VariantRecord, &FileMetadata) -> Result<()>,
+{
+    let file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    if file.metadata().is_ok_and(|meta| meta.len() == 0) {
+        return Err(BiosynthError::Parse(format!("File {:?} is empty", path)));
+    }
+    // SAFETY: the mapping is only read. Truncating the file while it is parsed would fault,
+    // as with any mapping; inputs are never written by biosynth itself.
+    let map = unsafe { memmap2::Mmap::map(&file) }
+        .with_context(|| format!("Failed to map {:?}", path))?;
+    #[cfg(unix)]
+    let _ = map.advise(memmap2::Advice::Sequential);
+    if map.starts_with(UTF16_LE_BOM) || map.starts_with(UTF16_BE_BOM) {
+        return process_reader(&map[..], options, on_variant);
+    }
+    let text = map.strip_prefix(UTF8_BOM).unwrap_or(&map);
+
+    let lookahead = mapped_lines(text)
+        .take(LOOKAHEAD_LINES)
+        .collect::<Result<Vec<_>>>()?;
+    let delimiter = options
+        .delimiter
+        .unwrap_or_else(|| detect_delimiter(&lookahead));
+    let mut parser = LineParser::new(delimiter, options);
+    let metadata = FileMetadata::from_header_lines(&lookahead);
+    drop(lookahead);
+
+    let mut summary = ParseSummary::default();
+    let mut rest: &[u8] = text;
+    // Header and comment lines change how later lines parse; data lines never do.
+    while !rest.is_empty() && !parser.columns_resolved() {
+        let line = take_chunk(&mut rest, 1);
+        match parser.parse_line(mapped_line(line)?)? {
+            LineOutcome::Parsed(record) => {
+                summary.variant_count += 1;
+                on_variant(&record, &metadata)?;
+            }
+            LineOutcome::Skipped => summary.skipped_rows += 1,
+            LineOutcome::Ignored => {}
+        }
+    }
+
+    summary.invalid_genotypes = parser.invalid_genotypes;
+
+    let threads = options.parse_threads();
+    while !rest.is_empty() {
+        let chunks: Vec<&[u8]> = (0..threads)
+            .map(|_| take_chunk(&mut rest, PARSE_CHUNK_BYTES))
+            .filter(|chunk| !chunk.is_empty())
+            .collect();
+        let parsed: Vec<ParsedChunk> = if let [chunk] = chunks[..] {
+            vec![parse_chunk(parser.fork(), chunk)]
+        } else {
+            chunks
+                .into_iter()
+                .map(|chunk| (parser.fork(), chunk))
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .map(|(parser, chunk)| parse_chunk(parser, chunk))
+                .collect()
+        };
+        for chunk in parsed {
+            for record in &chunk.records {
+                summary.variant_count += 1;
+                on_variant(record, &metadata)?;
+            }
+            summary.skipped_rows += chunk.skipped_rows;
+            summary.invalid_genotypes += chunk.invalid_genotypes;
+            if let Some(err) = chunk.error {
+                return Err(err);
+            }
+        }
+    }
+    Ok(ParsedFile { metadata, summary })
+}
+
+/// The rows of one chunk up to its first error, if any.
+#[cfg(feature = "mmap")]
+struct ParsedChunk {
+    records: Vec<VariantRecord>,
+    skipped_rows: usize,
+    invalid_genotypes: usize,
+    error: Option<BiosynthError>,
+}
+
+#[cfg(feature = "mmap")]
+fn parse_chunk(mut parser: LineParser, chunk: &[u8]) -> ParsedChunk {
+    let mut parsed = ParsedChunk {
+        records: Vec::new(),
+        skipped_rows: 0,
+        invalid_genotypes: 0,
+        error: None,
+    };
+    for line in mapped_lines(chunk) {
+        match line.and_then(|line| parser.parse_line(line)) {
+            Ok(LineOutcome::Parsed(record)) => parsed.records.push(record),
+            Ok(LineOutcome::Skipped) => parsed.skipped_rows += 1,
+            Ok(LineOutcome::Ignored) => {}
+            Err(err) => {
+                parsed.error = Some(err);
+                break;
+            }
+        }
+    }
+    parsed.invalid_genotypes = parser.invalid_genotypes;
+    parsed
+}
+
+/// Splits off the front of `bytes`: at least `size` bytes, extended to the end of a line.
+#[cfg(feature = "mmap")]
+fn take_chunk<'a>(bytes: &mut &'a [u8], size: usize) -> &'a [u8] {
+    let end = match bytes.get(size.max(1) - 1..) {
+        Some(tail) => memchr::memchr(b'\n', tail).map_or(bytes.len(), |idx| size.max(1) + idx),
+        None => bytes.len(),
+    };
+    let (chunk, rest) = bytes.split_at(end);
+    *bytes = rest;
+    chunk
+}
+
+/// Lines of `bytes`, each with its trailing newline as `read_line` would return it.
+#[cfg(feature = "mmap")]
+fn mapped_lines(bytes: &[u8]) -> impl Iterator<Item = Result<&str>> {
+    let mut rest = bytes;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let end = memchr::memchr(b'\n', rest).map_or(rest.len(), |idx| idx + 1);
+        let (line, tail) = rest.split_at(end);
+        rest = tail;
+        Some(mapped_line(line))
+    })
+}
+
+#[cfg(feature = "mmap")]
+fn mapped_line(line: &[u8]) -> Result<&str> {
+    std::str::from_utf8(line)
+        .map_err(|_| BiosynthError::Parse("Genotype input is not valid UTF-8".into()))
+}
+
+/// How genotype input is parsed, passed to each parse so concurrent parses in one process
+/// can differ.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Skip delimiter detection and split on this instead.
+    pub delimiter: Option<Delimiter>,
+    /// Stop [`parse_bytes`] after this many variants, so an oversized upload cannot exhaust
+    /// memory.
+    pub max_variants: Option<usize>,
+    /// What to do with calls outside the genotype alphabet.
+    pub validation: GenotypeValidation,
+    /// Columns to read fields from instead of the ones the header aliases pick.
+    pub columns: ColumnOverrides,
+    /// Header names recognized on top of the built-in aliases.
+    pub aliases: HeaderAliases,
+    /// Chunks [`process_mapped`] parses at once on the current rayon thread pool; 0 uses the
+    /// pool's size, 1 parses on the calling thread alone.
+    pub threads: usize,
+}
+
+impl ParseOptions {
+    #[cfg(feature = "mmap")]
+    fn parse_threads(&self) -> usize {
+        match self.threads {
+            0 => rayon::current_num_threads(),
+            threads => threads,
+        }
+    }
+}
+
+/// Text encoding detected by [`parse_bytes`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextEncoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    /// Not valid UTF-8; each byte was read as one Latin-1 character.
+    Latin1,
+}
+
+/// Everything [`parse_bytes`] found in an in-memory genotype file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedBytes {
+    pub records: Vec<VariantRecord>,
+    pub summary: ParseSummary,
+    pub metadata: FileMetadata,
+    pub encoding: TextEncoding,
+    pub delimiter: Delimiter,
+    /// True if parsing stopped at [`ParseOptions::max_variants`].
+    pub truncated: bool,
+}
+
+/// Parses a whole genotype file held in memory, with no filesystem access. Encoding,
+/// delimiter, and header are detected as for files, so this is the entry point for fuzz
+/// targets and for services handling uploads. Never panics on malformed input.
+///
+/// ```
+/// use biosynth_core::genotype::{parse_bytes, ParseOptions};
+///
+/// let parsed = parse_bytes(b"rsid,chromosome,position,genotype\nrs1,1,100,AG\n", ParseOptions::default())?;
+/// assert_eq!(parsed.records[0].genotype, "AG");
+/// # Ok::<(), biosynth_core::BiosynthError>(())
+/// ```
+pub fn parse_bytes(bytes: &[u8], options: ParseOptions) -> Result<ParsedBytes> {
+    let (text, encoding) = decode_text(bytes);
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.iter().all(|line| line.trim().is_empty()) {
+        return Err(BiosynthError::Parse("Genotype input is empty".into()));
+    }
+    let delimiter = options
+        .delimiter
+        .unwrap_or_else(|| detect_delimiter(&lines[..lines.len().min(LOOKAHEAD_LINES)]));
+
+    let mut parser = LineParser::new(delimiter, &options);
+    let mut records = Vec::new();
+    let mut summary = ParseSummary::default();
+    let mut truncated = false;
+    for &line in &lines {
+        if options.max_variants.is_some_and(|max| records.len() >= max) {
+            truncated = true;
+            break;
+        }
+        match parser.parse_line(line)? {
+            LineOutcome::Parsed(record) => records.push(record),
+            LineOutcome::Skipped => summary.skipped_rows += 1,
+            LineOutcome::Ignored => {}
+        }
+    }
+    summary.variant_count = records.len();
+    summary.invalid_genotypes = parser.invalid_genotypes;
+    Ok(ParsedBytes {
+        records,
+        summary,
+        metadata: FileMetadata::from_header_lines(&lines[..lines.len().min(LOOKAHEAD_LINES)]),
+        encoding,
+        delimiter,
+        truncated,
+    })
+}
+
+fn decode_text(bytes: &[u8]) -> (String, TextEncoding) {
+    if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+        return (
+            String::from_utf8_lossy(rest).into_owned(),
+            TextEncoding::Utf8Bom,
+        );
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16_LE_BOM) {
+        let units = rest
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]));
+        return (decode_utf16_lossy(units), TextEncoding::Utf16Le);
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16_BE_BOM) {
+        let units = rest
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]));
+        return (decode_utf16_lossy(units), TextEncoding::Utf16Be);
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_string(), TextEncoding::Utf8),
+        Err(_) => (
+            bytes.iter().map(|&byte| byte as char).collect(),
+            TextEncoding::Latin1,
+        ),
+    }
+}
+
+fn decode_utf16_lossy(units: impl Iterator<Item = u16>) -> String {
+    char::decode_utf16(units)
+        .map(|unit| unit.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Streaming parser yielding one [`VariantRecord`] per usable row. Iteration stops after the
+/// first error.
+///
+/// ```no_run
+/// use biosynth_core::genotype::GenotypeReader;
+///
+/// let y_calls = GenotypeReader::open("sample.txt".as_ref())?
+///     .filter_map(|record| record.ok())
+///     .filter(|record| &*record.chromosome == "Y")
+///     .count();
+/// # Ok::<(), biosynth_core::BiosynthError>(())
+/// ```
+pub struct GenotypeReader<R: BufRead> {
+    reader: R,
+    lookahead: Lookahead,
+    parser: LineParser,
+    metadata: FileMetadata,
+    summary: ParseSummary,
+    buffer: String,
+    done: bool,
+}
+
+impl GenotypeReader<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::open_with_options(path, &ParseOptions::default())
+    }
+
+    pub fn open_with_options(path: &Path, options: &ParseOptions) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+        if file.metadata().is_ok_and(|meta| meta.len() == 0) {
+            return Err(BiosynthError::Parse(format!("File {:?} is empty", path)));
+        }
+        Self::from_reader_with_options(buffers::reader(file), options)
+    }
+}
+
+impl<R: BufRead> GenotypeReader<R> {
+    /// Reads from any buffered source with the default [`ParseOptions`].
+    pub fn from_reader(reader: R) -> Result<Self> {
+        Self::from_reader_with_options(reader, &ParseOptions::default())
+    }
+
+    /// Reads from any buffered source; the layout is detected from the first lines, after a
+    /// UTF-8 byte order mark is dropped.
+    pub fn from_reader_with_options(mut reader: R, options: &ParseOptions) -> Result<Self> {
+        if reader.fill_buf()?.starts_with(UTF8_BOM) {
+            reader.consume(UTF8_BOM.len());
+        }
+        let mut lookahead = Lookahead::default();
+        while !lookahead.is_full() {
+            if reader.read_line(lookahead.buffer())? == 0 {
+                break;
+            }
+            lookahead.end_line();
+        }
+        if lookahead.is_empty() {
+            return Err(BiosynthError::Parse("Genotype input is empty".into()));
+        }
+
+        let delimiter = options
+            .delimiter
+            .unwrap_or_else(|| detect_delimiter(lookahead.lines()));
+        let metadata = FileMetadata::from_header_lines(lookahead.lines());
+        Ok(Self {
+            reader,
+            lookahead,
+            parser: LineParser::new(delimiter, options),
+            metadata,
+            summary: ParseSummary::default(),
+            buffer: String::new(),
+            done: false,
+        })
+    }
+
+    pub fn metadata(&self) -> &FileMetadata {
+        &self.metadata
+    }
+
+    /// Counts for the rows consumed so far.
+    pub fn summary(&self) -> ParseSummary {
+        ParseSummary {
+            invalid_genotypes: self.parser.invalid_genotypes,
+            ..self.summary
+        }
+    }
+
+    pub fn into_parsed(self) -> ParsedFile {
+        ParsedFile {
+            summary: self.summary(),
+            metadata: self.metadata,
+        }
+    }
+
+    /// Reads the calls of each of `samples` (0-based VCF sample columns, or Final Report
+    /// samples in order of first appearance) in one pass, handing `on_line` every line as read
+    /// and `on_variant` each call with its sample's index in `samples`. Returns a
+    /// [`ParsedFile`] per sample, in the order of `samples`.
+    pub(crate) fn drain_samples<L, F>(
+        mut self,
+        samples: &[usize],
+        mut on_line: L,
+        mut on_variant: F,
+    ) -> Result<Vec<ParsedFile>>
+    where
+        L: FnMut(&str),
+        F: FnMut(usize, &VariantRecord, &FileMetadata) -> Result<()>,
+    {
+        self.parser.select_samples(samples);
+        let mut summaries = vec![ParseSummary::default(); samples.len()];
+        loop {
+            let line = match self.lookahead.next_line() {
+                Some(line) => line,
+                None => {
+                    self.buffer.clear();
+                    if self.reader.read_line(&mut self.buffer)? == 0 {
+                        break;
+                    }
+                    &self.buffer
+                }
+            };
+            on_line(line);
+            let metadata = &self.metadata;
+            self.parser
+                .parse_samples(line, &mut |sample, outcome, invalid| {
+                    let summary = &mut summaries[sample];
+                    summary.invalid_genotypes += invalid;
+                    match outcome {
+                        LineOutcome::Parsed(record) => {
+                            summary.variant_count += 1;
+                            on_variant(sample, &record, metadata)
+                        }
+                        LineOutcome::Skipped => {
+                            summary.skipped_rows += 1;
+                            Ok(())
+                        }
+                        LineOutcome::Ignored => Ok(()),
+                    }
+                })?;
+        }
+        Ok(summaries
+            .into_iter()
+            .map(|summary| ParsedFile {
+                metadata: self.metadata.clone(),
+                summary,
+            })
+            .collect())
+    }
+}
+
+impl<R: BufRead> Iterator for GenotypeReader<R> {
+    type Item = Result<VariantRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let line = match self.lookahead.next_line() {
+                Some(line) => line,
+                None => {
+                    self.buffer.clear();
+                    match self.reader.read_line(&mut self.buffer) {
+                        Ok(0) => break,
+                        Ok(_) => &self.buffer,
+                        Err(err) => {
+                            self.done = true;
+                            return Some(Err(err.into()));
+                        }
+                    }
+                }
+            };
+            match self.parser.parse_line(line) {
+                Ok(LineOutcome::Parsed(record)) => {
+                    self.summary.variant_count += 1;
+                    return Some(Ok(record));
+                }
+                Ok(LineOutcome::Skipped) => self.summary.skipped_rows += 1,
+                Ok(LineOutcome::Ignored) => {}
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        self.done = true;
+        None
+    }
+}
+
+/// Field separator of a genotype file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Delimiter {
+    Tab,
+    Comma,
+    Space,
+}
+
+pub(crate) fn detect_delimiter<I>(lines: I) -> Delimiter
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    for line in lines {
+        let line = line.as_ref();
+        let trimmed = line.trim();
+        if trimmed.is_empty()
+            || COMMENT_PREFIXES
+                .iter()
+                .any(|prefix| trimmed.starts_with(prefix))
+        {
+            continue;
+        }
+        if line.contains('\t') {
+            return Delimiter::Tab;
+        }
+        if line.contains(',') {
+            return Delimiter::Comma;
+        }
+        let whitespace_fields = trimmed.split_whitespace().collect::<Vec<_>>();
+        if whitespace_fields.len() > 1 {
+            return Delimiter::Space;
+        }
+    }
+    Delimiter::Tab
+}
+
+/// The first [`LOOKAHEAD_LINES`] lines of an input, read into one buffer: layout detection
+/// borrows them and parsing then consumes them in place, so no line is copied.
+#[derive(Default)]
+pub(crate) struct Lookahead {
+    text: String,
+    /// Where each line ends in `text`, newline included.
+    ends: Vec<usize>,
+    /// Lines handed out by [`next_line`](Self::next_line) so far.
+    consumed: usize,
+}
+
+impl Lookahead {
+    /// The buffer to append the next line to; follow with [`end_line`](Self::end_line).
+    pub(crate) fn buffer(&mut self) -> &mut String {
+        &mut self.text
+    }
+
+    /// Marks the end of a line appended to [`buffer`](Self::buffer).
+    pub(crate) fn end_line(&mut self) {
+        self.ends.push(self.text.len());
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.ends.len() >= LOOKAHEAD_LINES
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// Every buffered line, consumed or not.
+    pub(crate) fn lines(&self) -> impl Iterator<Item = &str> {
+        (0..self.ends.len()).map(|idx| self.line(idx))
+    }
+
+    /// The next line not yet consumed.
+    pub(crate) fn next_line(&mut self) -> Option<&str> {
+        if self.consumed == self.ends.len() {
+            return None;
+        }
+        self.consumed += 1;
+        Some(self.line(self.consumed - 1))
+    }
+
+    fn line(&self, idx: usize) -> &str {
+        let start = idx.checked_sub(1).map_or(0, |prev| self.ends[prev]);
+        &self.text[start..self.ends[idx]]
+    }
+}
+
+pub(crate) struct LineParser {
+    delimiter: Delimiter,
+    /// Resolved once the header (or the first data row) has been seen.
+    columns: Option<Columns>,
+    /// Normalized names from a commented-out header line, used if no plain header follows.
+    comment_header: Option<Vec<String>>,
+    fields: Fields,
+    chromosomes: Vec<Arc<str>>,
+    validation: GenotypeValidation,
+    overrides: ColumnOverrides,
+    aliases: HeaderAliases,
+    /// Set by a `##fileformat=VCF` line; rows are then VCF records, read as the first sample's
+    /// calls unless [several samples](Self::parse_samples) are read.
+    vcf: bool,
+    /// Set by a line holding a JSON object; rows are then JSON Lines records.
+    json: bool,
+    /// The normalized keys of the last JSON record and where each field is among them.
+    json_columns: Option<(Vec<String>, Columns)>,
+    /// Set by a `[Header]` line: the Illumina Final Report section being read.
+    report: Option<ReportSection>,
+    /// The `Sample ID` whose rows a Final Report is read for, the first one seen, unless
+    /// [several samples](Self::select_samples) are read. Rows of other samples are ignored.
+    report_sample: Option<String>,
+    /// The samples [read together](Self::select_samples), by number (0-based): where each is
+    /// among them.
+    sample_slots: Vec<Option<usize>>,
+    /// While samples are read together, the number of each Final Report `Sample ID` seen, in
+    /// order of first appearance.
+    report_ids: Option<HashMap<String, usize>>,
+    /// Where the sample of the last Final Report row is among those read together.
+    report_slot: usize,
+    /// Calls outside the genotype alphabet seen so far.
+    pub(crate) invalid_genotypes: usize,
+}
+
+/// A bracketed section of an Illumina GenCall Final Report: key/value `[Header]` lines, the
+/// `[Data]` table (column header, then one row per sample and SNP, grouped by sample), or any
+/// other section, such as `[Controls]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReportSection {
+    Header,
+    Data,
+    Other,
+}
+
+impl ReportSection {
+    /// The section `line` (trimmed) opens; CSV reports pad it with trailing delimiters.
+    pub(crate) fn parse(line: &str) -> Option<Self> {
+        let name = line
+            .trim_end_matches([',', '\t'])
+            .strip_prefix('[')?
+            .strip_suffix(']')?;
+        Some(if name.eq_ignore_ascii_case("header") {
+            ReportSection::Header
+        } else if name.eq_ignore_ascii_case("data") {
+            ReportSection::Data
+        } else {
+            ReportSection::Other
+        })
+    }
+}
+
+impl LineParser {
+    pub(crate) fn new(delimiter: Delimiter, options: &ParseOptions) -> Self {
+        Self {
+            delimiter,
+            columns: None,
+            comment_header: None,
+            fields: Fields::default(),
+            chromosomes: Vec::new(),
+            validation: options.validation,
+            overrides: options.columns.clone(),
+            aliases: options.aliases.clone(),
+            vcf: false,
+            json: false,
+            json_columns: None,
+            report: None,
+            report_sample: None,
+            sample_slots: Vec::new(),
+            report_ids: None,
+            report_slot: 0,
+            invalid_genotypes: 0,
+        }
+    }
+
+    /// Reads the calls of each of `samples` through [`parse_samples`](Self::parse_samples):
+    /// VCF sample columns, or Final Report samples in order of first appearance (0-based).
+    pub(crate) fn select_samples(&mut self, samples: &[usize]) {
+        let slots = samples.iter().max().map_or(0, |max| max + 1);
+        self.sample_slots = vec![None; slots];
+        for (slot, sample) in samples.iter().enumerate() {
+            self.sample_slots[*sample] = Some(slot);
+        }
+        self.report_ids = Some(HashMap::new());
+    }
+
+    /// Whether the header (or first data row) has fixed the column layout, after which lines
+    /// parse independently of each other. VCF columns are fixed by the specification and
+    /// JSON Lines records name their own fields; a Final Report also needs its sample chosen.
+    #[cfg(feature = "mmap")]
+    fn columns_resolved(&self) -> bool {
+        (self.columns.is_some() && (self.report.is_none() || self.report_sample.is_some()))
+            || self.vcf
+            || self.json
+    }
+
+    /// A parser for lines after this one's, once [`columns_resolved`](Self::columns_resolved).
+    #[cfg(feature = "mmap")]
+    fn fork(&self) -> Self {
+        Self {
+            delimiter: self.delimiter,
+            columns: self.columns.clone(),
+            comment_header: None,
+            fields: Fields::default(),
+            chromosomes: Vec::new(),
+            validation: self.validation,
+            overrides: self.overrides.clone(),
+            aliases: self.aliases.clone(),
+            vcf: self.vcf,
+            json: self.json,
+            json_columns: self.json_columns.clone(),
+            report: self.report,
+            report_sample: self.report_sample.clone(),
+            sample_slots: Vec::new(),
+            report_ids: None,
+            report_slot: 0,
+            invalid_genotypes: 0,
+        }
+    }
+
+    pub(crate) fn parse_line(&mut self, line: &str) -> Result<LineOutcome> {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            return Ok(LineOutcome::Ignored);
+        }
+
+        if let Some(section) = ReportSection::parse(trimmed) {
+            if section == ReportSection::Header || self.report.is_some() {
+                self.report = Some(section);
+                self.columns = None;
+                return Ok(LineOutcome::Ignored);
+            }
+        }
+        if matches!(
+            self.report,
+            Some(ReportSection::Header | ReportSection::Other)
+        ) {
+            return Ok(LineOutcome::Ignored);
+        }
+
+        if let Some(prefix) = COMMENT_PREFIXES
+            .iter()
+            .find(|prefix| trimmed.starts_with(**prefix))
+        {
+            let candidate = trimmed.trim_start_matches(prefix).trim();
+            if candidate.is_empty() {
+                return Ok(LineOutcome::Ignored);
+            }
+            if candidate.starts_with("fileformat=VCF") {
+                self.vcf = true;
+            }
+            if self.vcf {
+                return Ok(LineOutcome::Ignored);
+            }
+            self.fields.split(candidate, self.delimiter);
+            if self.fields.looks_like_header(candidate, &self.aliases) {
+                self.comment_header = Some(self.fields.names(candidate));
+            }
+            return Ok(LineOutcome::Ignored);
+        }
+
+        if self.vcf {
+            return self.parse_vcf_record(line);
+        }
+        if self.json || (self.columns.is_none() && trimmed.starts_with('{')) {
+            self.json = true;
+            return self.parse_json_record(trimmed);
+        }
+        self.fields.split(line, self.delimiter);
+        if self.fields.is_empty() {
+            return Ok(LineOutcome::Ignored);
+        }
+
+        if self.columns.is_none() {
+            if self.fields.looks_like_header(line, &self.aliases)
+                || self.names_overridden_columns(line)
+            {
+                let header = self.fields.names(line);
+                self.columns = Some(Columns::resolve(&header, &self.aliases, &self.overrides)?);
+                return Ok(LineOutcome::Ignored);
+            }
+            let header = self
+                .comment_header
+                .take()
+                .unwrap_or_else(|| default_header(self.fields.len()));
+            self.columns = Some(Columns::resolve(&header, &self.aliases, &self.overrides)?);
+        }
+
+        let columns = self.columns.as_ref().expect("header must be set");
+        let fields = &self.fields;
+        if self.report.is_some() {
+            let sample = fields.lookup(line, &columns.sample).unwrap_or_default();
+            if let Some(ids) = &mut self.report_ids {
+                // Numbered as `final_report::sample_ids` lists them, which leaves out blank IDs.
+                let number = match ids.get(sample) {
+                    Some(number) => Some(*number),
+                    None if sample.is_empty() => None,
+                    None => {
+                        let number = ids.len();
+                        ids.insert(sample.to_string(), number);
+                        Some(number)
+                    }
+                };
+                match number.and_then(|number| self.sample_slots.get(number).copied().flatten()) {
+                    Some(slot) => self.report_slot = slot,
+                    None => return Ok(LineOutcome::Ignored),
+                }
+            } else {
+                match &self.report_sample {
+                    Some(selected) if selected != sample => return Ok(LineOutcome::Ignored),
+                    Some(_) => {}
+                    None => self.report_sample = Some(sample.to_string()),
+                }
+            }
+        }
+        let Some(rsid) = fields.lookup(line, &columns.rsid) else {
+            return Ok(LineOutcome::Skipped);
+        };
+        let Some(chromosome) = fields.lookup(line, &columns.chromosome) else {
+            return Ok(LineOutcome::Skipped);
+        };
+        let Some(position) = fields
+            .lookup(line, &columns.position)
+            .and_then(|value| value.parse::<i64>().ok())
+        else {
+            return Ok(LineOutcome::Skipped);
+        };
+
+        let genotype = match fields.lookup(line, &columns.genotype) {
+            Some(value) => normalize_genotype(value),
+            None => {
+                let allele1 = fields.lookup(line, &columns.allele1).unwrap_or_default();
+                let allele2 = fields.lookup(line, &columns.allele2).unwrap_or_default();
+                if allele1.is_empty() && allele2.is_empty() {
+                    return Ok(LineOutcome::Skipped);
+                }
+                normalize_genotype(&format!("{}{}", allele1, allele2))
+            }
+        };
+        let rsid = Rsid::parse(rsid);
+        let chromosome = intern(&mut self.chromosomes, chromosome);
+        self.record(rsid, chromosome, position, genotype)
+    }
+
+    /// Whether `line` holds every column the overrides give by name, which makes it the header
+    /// even when its first column is not an rsid alias.
+    fn names_overridden_columns(&self, line: &str) -> bool {
+        let names = self.fields.names(line);
+        let overrides = &self.overrides;
+        let mut wanted = [
+            &overrides.rsid,
+            &overrides.chromosome,
+            &overrides.position,
+            &overrides.genotype,
+        ]
+        .into_iter()
+        .filter_map(|column| match column {
+            Some(ColumnRef::Name(name)) => Some(normalize_name(name)),
+            _ => None,
+        })
+        .peekable();
+        wanted.peek().is_some() && wanted.all(|name| names.contains(&name))
+    }
+
+    /// Like [`parse_line`](Self::parse_line), for the [selected](Self::select_samples) samples:
+    /// a VCF record is split once and read for each sample column, and a Final Report row
+    /// goes to its `Sample ID`'s sample. `on_sample` is handed each call's index among the
+    /// samples, its outcome, and how many invalid calls it added; rows of other files go to
+    /// it as the first sample's.
+    pub(crate) fn parse_samples(
+        &mut self,
+        line: &str,
+        on_sample: &mut dyn FnMut(usize, LineOutcome, usize) -> Result<()>,
+    ) -> Result<()> {
+        let trimmed = line.trim();
+        let record = self.vcf
+            && !trimmed.is_empty()
+            && !COMMENT_PREFIXES
+                .iter()
+                .any(|prefix| trimmed.starts_with(prefix));
+        if !record {
+            let invalid = self.invalid_genotypes;
+            return match self.parse_line(line)? {
+                LineOutcome::Ignored => Ok(()),
+                outcome => on_sample(self.report_slot, outcome, self.invalid_genotypes - invalid),
+            };
+        }
+        self.fields.split(line, Delimiter::Tab);
+        for column in 0..self.sample_slots.len() {
+            let Some(slot) = self.sample_slots[column] else {
+                continue;
+            };
+            let invalid = self.invalid_genotypes;
+            let outcome = self.vcf_sample_call(line, column)?;
+            on_sample(slot, outcome, self.invalid_genotypes - invalid)?;
+        }
+        Ok(())
+    }
+
+    /// A VCF record as the first sample's call.
+    fn parse_vcf_record(&mut self, line: &str) -> Result<LineOutcome> {
+        self.fields.split(line, Delimiter::Tab);
+        self.vcf_sample_call(line, 0)
+    }
+
+    /// The call of sample column `column` (0-based) of the VCF record `line`, once split into
+    /// `fields`: `ID` is the rsid (the first, when several are listed) and `GT` indexes into
+    /// `REF` and `ALT`. Records without an ID, the sample, or a `GT` are skipped.
+    fn vcf_sample_call(&mut self, line: &str, column: usize) -> Result<LineOutcome> {
+        let fields = &self.fields;
+        let sample = VCF_FIRST_SAMPLE + column;
+        if fields.len() <= sample {
+            return Ok(LineOutcome::Skipped);
+        }
+        let rsid = fields
+            .get(line, VCF_ID)
+            .split(';')
+            .next()
+            .unwrap_or_default();
+        let chromosome = fields.get(line, VCF_CHROM);
+        if rsid.is_empty() || rsid == "." || chromosome.is_empty() {
+            return Ok(LineOutcome::Skipped);
+        }
+        let Ok(position) = fields.get(line, VCF_POS).parse::<i64>() else {
+            return Ok(LineOutcome::Skipped);
+        };
+        let Some(gt) = fields
+            .get(line, VCF_FORMAT)
+            .split(':')
+            .position(|key| key == "GT")
+            .and_then(|idx| fields.get(line, sample).split(':').nth(idx))
+        else {
+            return Ok(LineOutcome::Skipped);
+        };
+        let Some(call) = vcf_call(gt, fields.get(line, VCF_REF), fields.get(line, VCF_ALT)) else {
+            return Ok(LineOutcome::Skipped);
+        };
+        let rsid = Rsid::parse(rsid);
+        let chromosome = intern(&mut self.chromosomes, chromosome);
+        self.record(rsid, chromosome, position, normalize_genotype(&call))
+    }
+
+    /// A JSON Lines record: an object whose keys are matched against the same aliases as
+    /// column names, with string or number values. Lines that are not objects are skipped.
+    fn parse_json_record(&mut self, line: &str) -> Result<LineOutcome> {
+        let Ok(object) = serde_json::from_str::<Map<String, Value>>(line) else {
+            return Ok(LineOutcome::Skipped);
+        };
+        let keys: Vec<String> = object.keys().map(|key| normalize_name(key)).collect();
+        if self
+            .json_columns
+            .as_ref()
+            .is_none_or(|(known, _)| *known != keys)
+        {
+            let columns = Columns::resolve(&keys, &self.aliases, &self.overrides)?;
+            self.json_columns = Some((keys, columns));
+        }
+        let (_, columns) = self
+            .json_columns
+            .as_ref()
+            .expect("columns were just resolved");
+        let values: Vec<Option<String>> = object.values().map(json_text).collect();
+        let lookup = |candidates: &[Vec<usize>]| {
+            candidates
+                .iter()
+                .filter_map(|positions| positions.first())
+                .filter_map(|idx| values[*idx].as_deref())
+                .find(|value| !value.is_empty())
+        };
+        let (Some(rsid), Some(chromosome)) = (lookup(&columns.rsid), lookup(&columns.chromosome))
+        else {
+            return Ok(LineOutcome::Skipped);
+        };
+        let Some(position) = lookup(&columns.position).and_then(|value| value.parse::<i64>().ok())
+        else {
+            return Ok(LineOutcome::Skipped);
+        };
+        let genotype = match lookup(&columns.genotype) {
+            Some(value) => normalize_genotype(value),
+            None => {
+                let allele1 = lookup(&columns.allele1).unwrap_or_default();
+                let allele2 = lookup(&columns.allele2).unwrap_or_default();
+                if allele1.is_empty() && allele2.is_empty() {
+                    return Ok(LineOutcome::Skipped);
+                }
+                normalize_genotype(&format!("{}{}", allele1, allele2))
+            }
+        };
+        let rsid = Rsid::parse(rsid);
+        let chromosome = intern(&mut self.chromosomes, chromosome);
+        self.record(rsid, chromosome, position, genotype)
+    }
+
+    /// The parsed row, once its call has been checked against the genotype alphabet.
+    fn record(
+        &mut self,
+        rsid: Rsid,
+        chromosome: Arc<str>,
+        position: i64,
+        genotype: String,
+    ) -> Result<LineOutcome> {
+        let Some(genotype) = self
+            .validation
+            .check(genotype, &rsid, &mut self.invalid_genotypes)?
+        else {
+            return Ok(LineOutcome::Skipped);
+        };
+        Ok(LineOutcome::Parsed(VariantRecord {
+            rsid,
+            chromosome,
+            position,
+            genotype,
+        }))
+    }
+}
+
+/// The call a VCF `GT` names, written the way consumer exports write it: one letter per allele
+/// and `-` for a missing one. At indels, where consumer exports do not spell out the bases,
+/// alleles longer than the record's shortest are `I` and the shortest are `D`. `None` for
+/// allele indices past `ALT`, symbolic alleles such as `<DEL>` or `*`, and multi-base
+/// substitutions, which have no consumer-style call.
+pub(crate) fn vcf_call(gt: &str, reference: &str, alternates: &str) -> Option<String> {
+    let alleles: Vec<&str> = std::iter::once(reference)
+        .chain(alternates.split(',').filter(|allele| *allele != "."))
+        .collect();
+    let shortest = alleles.iter().map(|allele| allele.len()).min()?;
+    let indel = alleles.iter().any(|allele| allele.len() != shortest);
+    if !indel && shortest != 1 {
+        return None;
+    }
+    gt.split(['/', '|'])
+        .map(|index| {
+            if index == "." {
+                return Some('-');
+            }
+            let allele = alleles.get(index.parse::<usize>().ok()?)?;
+            if !allele.bytes().all(|base| base.is_ascii_alphabetic()) {
+                None
+            } else if !indel {
+                Some(char::from(allele.as_bytes()[0]))
+            } else if allele.len() > shortest {
+                Some('I')
+            } else {
+                Some('D')
+            }
+        })
+        .collect()
+}
+
+/// A JSON value as field text: strings trimmed, numbers as written, anything else absent.
+fn json_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.trim().to_string()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+/// Where each record field may be found: per alias, in the order aliases are tried, the header
+/// positions carrying that name from last to first (a repeated column's last occurrence wins).
+#[derive(Clone)]
+struct Columns {
+    rsid: Vec<Vec<usize>>,
+    chromosome: Vec<Vec<usize>>,
+    position: Vec<Vec<usize>>,
+    genotype: Vec<Vec<usize>>,
+    allele1: Vec<Vec<usize>>,
+    allele2: Vec<Vec<usize>>,
+    sample: Vec<Vec<usize>>,
+}
+
+impl Columns {
+    /// Resolves every alias, built-in then `extra`, against normalized header names, then
+    /// applies `overrides`; an override naming a column the header lacks is an error, not a
+    /// file of skipped rows.
+    fn resolve(
+        header: &[String],
+        extra: &HeaderAliases,
+        overrides: &ColumnOverrides,
+    ) -> Result<Self> {
+        let positions = |aliases: &[&str], extra: &[String]| -> Vec<Vec<usize>> {
+            let builtin: BTreeSet<String> =
+                aliases.iter().map(|alias| normalize_name(alias)).collect();
+            let extra: Vec<String> = extra
+                .iter()
+                .map(|alias| normalize_name(alias))
+                .filter(|name| !builtin.contains(name))
+                .collect();
+            builtin
+                .into_iter()
+                .chain(extra)
+                .map(|name| {
+                    header
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .filter(|(_, column)| **column == name)
+                        .map(|(idx, _)| idx)
+                        .collect::<Vec<_>>()
+                })
+                .filter(|positions| !positions.is_empty())
+                .collect()
+        };
+        let mut columns = Self {
+            rsid: positions(RSID_ALIASES, &extra.rsid),
+            chromosome: positions(CHROM_ALIASES, &extra.chromosome),
+            position: positions(POSITION_ALIASES, &extra.position),
+            genotype: positions(GENOTYPE_ALIASES, &extra.genotype),
+            allele1: positions(ALLELE1_ALIASES, &extra.allele1),
+            allele2: positions(ALLELE2_ALIASES, &extra.allele2),
+            sample: positions(SAMPLE_ALIASES, &extra.sample),
+        };
+        for (field, column, candidates) in [
+            ("rsid", &overrides.rsid, &mut columns.rsid),
+            ("chromosome", &overrides.chromosome, &mut columns.chromosome),
+            ("position", &overrides.position, &mut columns.position),
+            ("genotype", &overrides.genotype, &mut columns.genotype),
+        ] {
+            let position = match column {
+                None => continue,
+                Some(ColumnRef::Position(position)) => {
+                    position.checked_sub(1).ok_or_else(|| {
+                        BiosynthError::InvalidArgument(format!(
+                            "The {} column position must start at 1, not 0",
+                            field
+                        ))
+                    })?
+                }
+                Some(ColumnRef::Name(name)) => {
+                    let wanted = normalize_name(name);
+                    header
+                        .iter()
+                        .rposition(|column| *column == wanted)
+                        .ok_or_else(|| {
+                            BiosynthError::Parse(format!(
+                                "The {} column {:?} is not in the header",
+                                field, name
+                            ))
+                        })?
+                }
+            };
+            *candidates = vec![vec![position]];
+        }
+        Ok(columns)
+    }
+}
+
+/// Numbers chromosomes the way PLINK does, so `chrX`, `X`, and `23` compare equal.
+pub(crate) fn chromosome_code(chromosome: &str) -> Option<u8> {
+    let bare = match chromosome.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("chr") => &chromosome[3..],
+        _ => chromosome,
+    };
+    match bare.to_ascii_uppercase().as_str() {
+        "X" => Some(23),
+        "Y" => Some(24),
+        "XY" => Some(25),
+        "M" | "MT" => Some(26),
+        number => number.parse().ok().filter(|code| (1..=26).contains(code)),
+    }
+}
+
+/// The shared name for `chromosome`. Rows come grouped by chromosome, so the newest name
+/// usually matches first; past [`MAX_INTERNED_CHROMOSOMES`] names are no longer cached.
+fn intern(chromosomes: &mut Vec<Arc<str>>, chromosome: &str) -> Arc<str> {
+    if let Some(known) = chromosomes
+        .iter()
+        .rev()
+        .find(|known| ***known == *chromosome)
+    {
+        return Arc::clone(known);
+    }
+    let interned: Arc<str> = chromosome.into();
+    if chromosomes.len() < MAX_INTERNED_CHROMOSOMES {
+        chromosomes.push(Arc::clone(&interned));
+    }
+    interned
+}
+
+fn default_header(field_count: usize) -> Vec<String> {
+    let mut header: Vec<String> = ["rsid", "chromosome", "position", "genotype"]
+        .into_iter()
+        .take(field_count)
+        .map(str::to_string)
+        .collect();
+    for idx in 0..field_count.saturating_sub(header.len()) {
+        header.push(normalize_name(&format!("extra_{}", idx)));
+    }
+    header
+}
+
+/// Trimmed field boundaries of the line being parsed, reused from line to line.
+#[derive(Default)]
+struct Fields {
+    ranges: Vec<Range<usize>>,
+    /// Quoted CSV fields with their quoting removed; when `unquoted` is set, `ranges` index
+    /// into this buffer rather than the line.
+    buffer: String,
+    unquoted: bool,
+}
+
+impl Fields {
+    fn split(&mut self, line: &str, delimiter: Delimiter) {
+        self.ranges.clear();
+        self.unquoted = false;
+        match delimiter {
+            Delimiter::Tab => self.split_on(line, b'\t'),
+            Delimiter::Space => {
+                for field in line.split_whitespace() {
+                    let start = field.as_ptr() as usize - line.as_ptr() as usize;
+                    self.ranges.push(start..start + field.len());
+                }
+            }
+            Delimiter::Comma if memchr::memchr(b'"', line.as_bytes()).is_none() => {
+                self.split_on(line, b',')
+            }
+            Delimiter::Comma => self.split_quoted(line),
+        }
+    }
+
+    /// Splits on a single-byte delimiter.
+    fn split_on(&mut self, line: &str, delimiter: u8) {
+        let mut start = 0;
+        for idx in memchr::memchr_iter(delimiter, line.as_bytes()) {
+            self.ranges.push(trimmed(line, start..idx));
+            start = idx + 1;
+        }
+        self.ranges.push(trimmed(line, start..line.len()));
+    }
+
+    fn split_quoted(&mut self, line: &str) {
+        self.unquoted = true;
+        self.buffer.clear();
+        // Jump between quotes and commas; both are ASCII, so every index is a char boundary.
+        let bytes = line.as_bytes();
+        let mut in_quotes = false;
+        let mut start = 0;
+        let mut field_start = 0;
+        let mut special = memchr::memchr2_iter(b'"', b',', bytes);
+        while let Some(idx) = special.next() {
+            self.buffer.push_str(&line[start..idx]);
+            start = idx + 1;
+            match bytes[idx] {
+                b'"' if in_quotes && bytes.get(idx + 1) == Some(&b'"') => {
+                    self.buffer.push('"');
+                    special.next();
+                    start = idx + 2;
+                }
+                b'"' => in_quotes = !in_quotes,
+                _ if in_quotes => self.buffer.push(','),
+                _ => {
+                    self.ranges
+                        .push(trimmed(&self.buffer, field_start..self.buffer.len()));
+                    field_start = self.buffer.len();
+                }
+            }
+        }
+        self.buffer.push_str(&line[start..]);
+        self.ranges
+            .push(trimmed(&self.buffer, field_start..self.buffer.len()));
+    }
+
+    fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    fn get<'a>(&'a self, line: &'a str, idx: usize) -> &'a str {
+        let source = if self.unquoted { &self.buffer } else { line };
+        &source[self.ranges[idx].clone()]
+    }
+
+    /// The first non-empty value, comments stripped, among `candidates`.
+    fn lookup<'a>(&'a self, line: &'a str, candidates: &[Vec<usize>]) -> Option<&'a str> {
+        candidates
+            .iter()
+            .filter_map(|positions| positions.iter().find(|idx| **idx < self.len()))
+            .map(|idx| strip_inline_comment(self.get(line, *idx)))
+            .find(|value| !value.is_empty())
+    }
+
+    fn names(&self, line: &str) -> Vec<String> {
+        (0..self.len())
+            .map(|idx| normalize_name(self.get(line, idx)))
+            .collect()
+    }
+
+    /// Whether the first field is an rsid alias, built-in or one of `extra`.
+    fn looks_like_header(&self, line: &str, extra: &HeaderAliases) -> bool {
+        !self.is_empty() && {
+            let first = self.get(line, 0);
+            RSID_ALIASES
+                .iter()
+                .any(|alias| normalized_chars(first).eq(alias.chars()))
+                || extra
+                    .rsid
+                    .iter()
+                    .any(|alias| normalized_chars(first).eq(normalized_chars(alias)))
+        }
+    }
+}
+
+/// `range` of `text` without surrounding whitespace.
+fn trimmed(text: &str, range: Range<usize>) -> Range<usize> {
+    let field = &text[range.clone()];
+    let end = range.start + field.trim_end().len();
+    let start = range.start + (field.len() - field.trim_start().len());
+    start.min(end)..end
+}
+
+fn normalize_name(name: &str) -> String {
+    normalized_chars(name).collect()
+}
+
+fn normalized_chars(name: &str) -> impl Iterator<Item = char> + '_ {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '\t' | '-' | '_'))
+        .flat_map(|c| c.to_lowercase())
+}
+
+fn strip_inline_comment(value: &str) -> &str {
+    let mut trimmed = value.trim();
+    if let Some(idx) = trimmed.find('#') {
+        trimmed = &trimmed[..idx];
+    }
+    if let Some(idx) = trimmed.find("//") {
+        trimmed = &trimmed[..idx];
+    }
+    trimmed.trim()
+}
@@ -0,0 +1,121 @@
+//! Core functionality behind the `bvs` CLI, for embedding in other Rust services.
+//!
+//! - [`buffers`]: the buffer size used for every file biosynth reads or writes.
+//! - [`alleles`]: normalization of alleles and genotype calls, shared by every module.
+//! - [`archive`]: genotype files inside `.zip` archives, read into memory.
+//! - `bcf` (feature `bcf`): single-sample binary BCF callsets, read through htslib.
+//! - [`genotype`]: streaming parser for consumer genotype exports (23andMe-style TSV/CSV),
+//!   as a callback ([`process_file`]), an iterator ([`GenotypeReader`]), or from memory
+//!   ([`parse_bytes`](genotype::parse_bytes)).
+//! - [`final_report`]: Illumina GenCall Final Reports, split into their sample blocks.
+//! - [`stats`]: the SQLite store holding reference variants and per-format statistics.
+//! - [`columnar`]: Arrow-based aggregation of observations before they are written (feature
+//!   `columnar`).
+//! - [`builds`]: genome-build fingerprinting from sentinel rsids.
+//! - [`audit`]: the append-only log of commands that used a stats database.
+//! - [`privacy`]: differential-privacy noise for aggregate statistics.
+//! - [`policy`]: export policies limiting which tables and columns may be released.
+//! - [`privacy_eval`]: memorization and membership-inference risk metrics for synthetic output.
+//! - [`dataset`]: read-only typed queries (lookups, region frequencies, format coverage).
+//! - [`encryption`]: keys for SQLCipher-encrypted databases (feature `sqlcipher`).
+//! - [`download`]: locating reference databases, and fetching published ones (feature
+//!   `download`).
+//! - [`liftover`]: UCSC chain-file coordinate conversion between GRCh37 and GRCh38.
+//! - [`merges`]: dbSNP rsid merges, for remapping retired rsids to current ones.
+//! - [`synthetic`]: generation of synthetic genotype files from reference variants.
+//! - [`formats`]: pluggable output formats for generated files.
+//! - [`overlay`]: the overlay variants document, with validation and a JSON Schema.
+//! - [`plink`]: PLINK 1 binary filesets (`.bed`/`.bim`/`.fam`), read one sample at a time.
+//! - [`positions`]: checking parsed calls' coordinates against the reference.
+//! - [`qc`]: quality-control checks on one file's calls, such as sex-chromosome consistency.
+//! - [`pseudonym`]: salted pseudonyms for real participant identifiers.
+//! - [`progress`]: channel-based progress events for long-running operations.
+//! - [`staging`]: temporary copies of raw genotype data, with optional shredding.
+//! - [`strand`]: correcting calls reported on the opposite strand to the reference.
+//! - [`vcf`]: conformance checks for VCFs biosynth reads or writes.
+//! - [`vendors`]: recognizing which vendor exported a genotype file.
+//! - [`xlsx`]: the first worksheet of an Excel workbook as genotype input.
+//! - `asynchronous` (feature `async`): tokio-compatible variants of the above.
+//!
+//! Fallible functions return [`BiosynthError`], which callers can match on by kind.
+//!
+//! The parser (with the Final Report and VCF readers), liftover, rsid merges, QC checks,
+//! vendor detection, and progress events are unconditional; `download`, `stats`, and
+//! `synthetic` are default features, so `default-features = false` leaves a pure-Rust build
+//! suitable for wasm32.
+//! Embedders that only generate data should pick `features = ["synthetic"]`, which leaves out
+//! the HTTP and TLS stack.
+//!
+//! ```no_run
+//! use biosynth_core::{StatsStore, SyntheticGenerator};
+//!
+//! let store = StatsStore::connect("data/genostats.sqlite".as_ref())?;
+//! let generator = SyntheticGenerator::builder()
+//!     .references(store.all_references(Some(1000))?)
+//!     .seed(42)
+//!     .build()?;
+//! generator.write_file("out.txt".as_ref())?;
+//! # Ok::<(), biosynth_core::BiosynthError>(())
+//! ```
+
+pub mod alleles;
+#[cfg(feature = "stats")]
+pub mod archive;
+#[cfg(feature = "async")]
+pub mod asynchronous;
+#[cfg(feature = "stats")]
+pub mod audit;
+#[cfg(feature = "bcf")]
+pub mod bcf;
+pub mod buffers;
+#[cfg(feature = "stats")]
+pub mod builds;
+#[cfg(feature = "columnar")]
+pub mod columnar;
+#[cfg(feature = "stats")]
+pub mod dataset;
+pub mod download;
+#[cfg(feature = "stats")]
+pub mod encryption;
+pub mod error;
+pub mod final_report;
+#[cfg(feature = "synthetic")]
+pub mod formats;
+pub mod genotype;
+pub mod liftover;
+pub mod merges;
+#[cfg(feature = "synthetic")]
+pub mod overlay;
+#[cfg(feature = "stats")]
+pub mod plink;
+#[cfg(feature = "stats")]
+pub mod policy;
+#[cfg(feature = "stats")]
+pub mod positions;
+#[cfg(feature = "stats")]
+pub mod privacy;
+#[cfg(feature = "stats")]
+pub mod privacy_eval;
+pub mod progress;
+#[cfg(feature = "stats")]
+pub mod pseudonym;
+pub mod qc;
+#[cfg(feature = "stats")]
+pub mod staging;
+#[cfg(feature = "stats")]
+pub mod stats;
+#[cfg(feature = "stats")]
+pub mod strand;
+#[cfg(feature = "synthetic")]
+pub mod synthetic;
+pub mod vcf;
+pub mod vendors;
+#[cfg(feature = "stats")]
+pub mod xlsx;
+
+pub use error::{BiosynthError, Result};
+pub use genotype::{process_file, GenotypeReader, ParseSummary, ParsedFile, VariantRecord};
+#[cfg(feature = "stats")]
+pub use stats::{ReferenceVariant, StatsStore};
+#[cfg(feature = "synthetic")]
+pub use synthetic::SyntheticGenerator;
@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+use flate2::read::MultiGzDecoder;
+use serde::{Deserialize, Serialize};
+
+use crate::buffers;
+use crate::error::{BiosynthError, Context, Result};
+
+const UCSC_LIFTOVER_BASE: &str = "https://hgdownload.soe.ucsc.edu/goldenPath";
+
+/// A human reference assembly supported by liftover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GenomeBuild {
+    Grch37,
+    Grch38,
+}
+
+impl GenomeBuild {
+    fn ucsc_name(self) -> &'static str {
+        match self {
+            GenomeBuild::Grch37 => "hg19",
+            GenomeBuild::Grch38 => "hg38",
+        }
+    }
+}
+
+impl FromStr for GenomeBuild {
+    type Err = BiosynthError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "grch37" | "hg19" => Ok(GenomeBuild::Grch37),
+            "grch38" | "hg38" => Ok(GenomeBuild::Grch38),
+            _ => Err(BiosynthError::InvalidArgument(format!(
+                "Unknown genome build {:?} (expected grch37 or grch38)",
+                value
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for GenomeBuild {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenomeBuild::Grch37 => write!(f, "GRCh37"),
+            GenomeBuild::Grch38 => write!(f, "GRCh38"),
+        }
+    }
+}
+
+/// UCSC file name of the chain converting `from` coordinates to `to`.
+pub fn chain_file_name(from: GenomeBuild, to: GenomeBuild) -> Result<String> {
+    if from == to {
+        return Err(BiosynthError::InvalidArgument(format!(
+            "Source and target builds are both {}",
+            from
+        )));
+    }
+    let target = to.ucsc_name();
+    let mut capitalized = target[..1].to_uppercase();
+    capitalized.push_str(&target[1..]);
+    Ok(format!(
+        "{}To{}.over.chain.gz",
+        from.ucsc_name(),
+        capitalized
+    ))
+}
+
+/// Where UCSC publishes the chain for `from` → `to`.
+pub fn chain_download_url(from: GenomeBuild, to: GenomeBuild) -> Result<String> {
+    Ok(format!(
+        "{}/{}/liftOver/{}",
+        UCSC_LIFTOVER_BASE,
+        from.ucsc_name(),
+        chain_file_name(from, to)?
+    ))
+}
+
+/// A gap-free aligned block of a chain, in 0-based source coordinates.
+#[derive(Debug, Clone)]
+struct Block {
+    source_start: i64,
+    source_end: i64,
+    target: usize,
+    target_start: i64,
+    target_size: i64,
+    reverse: bool,
+}
+
+/// Where a source position lands in the target build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiftedPosition {
+    pub chromosome: String,
+    pub position: i64,
+    /// The target sequence is on the opposite strand, so alleles must be complemented.
+    pub reverse: bool,
+}
+
+/// Why a position could not be lifted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unmapped {
+    UnknownChromosome,
+    Deleted,
+    MultipleTargets,
+}
+
+impl Unmapped {
+    pub fn label(self) -> &'static str {
+        match self {
+            Unmapped::UnknownChromosome => "unknown chromosome",
+            Unmapped::Deleted => "deleted in target build",
+            Unmapped::MultipleTargets => "multiple targets",
+        }
+    }
+}
+
+/// Position index built from a UCSC chain file.
+pub struct ChainMap {
+    targets: Vec<String>,
+    blocks: HashMap<String, Vec<Block>>,
+    longest_block: HashMap<String, i64>,
+}
+
+impl ChainMap {
+    /// Loads a chain file; `.gz` files are decompressed on the fly.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Open chain file {:?}", path))?;
+        let reader: Box<dyn Read> = if path.extension().is_some_and(|ext| ext == "gz") {
+            Box::new(MultiGzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+        Self::parse(buffers::reader(reader)).map_err(|err| match err {
+            BiosynthError::Parse(message) => {
+                BiosynthError::Parse(format!("Parse chain file {:?}: {}", path, message))
+            }
+            other => other,
+        })
+    }
+
+    /// Parses chain data from any reader.
+    pub fn parse<R: BufRead>(reader: R) -> Result<Self> {
+        let mut map = ChainMap {
+            targets: Vec::new(),
+            blocks: HashMap::new(),
+            longest_block: HashMap::new(),
+        };
+        let mut target_ids: HashMap<String, usize> = HashMap::new();
+        // Cursor for the chain being read: (source chrom, source pos, target id, target pos, target size, reverse).
+        let mut current: Option<(String, i64, usize, i64, i64, bool)> = None;
+
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() || fields[0].starts_with('#') {
+                continue;
+            }
+            if fields[0] == "chain" {
+                if fields.len() < 12 {
+                    return Err(BiosynthError::Parse(format!(
+                        "line {}: malformed chain header",
+                        idx + 1
+                    )));
+                }
+                let target_name = fields[7].to_string();
+                let next_id = map.targets.len();
+                let target = *target_ids.entry(target_name.clone()).or_insert(next_id);
+                if target == next_id {
+                    map.targets.push(target_name);
+                }
+                current = Some((
+                    fields[2].to_string(),
+                    parse_coordinate(fields[5], idx)?,
+                    target,
+                    parse_coordinate(fields[10], idx)?,
+                    parse_coordinate(fields[8], idx)?,
+                    fields[9] == "-",
+                ));
+                continue;
+            }
+
+            let Some((source, source_pos, target, target_pos, target_size, reverse)) =
+                current.as_mut()
+            else {
+                return Err(BiosynthError::Parse(format!(
+                    "line {}: alignment data before chain header",
+                    idx + 1
+                )));
+            };
+            let size = parse_coordinate(fields[0], idx)?;
+            map.blocks.entry(source.clone()).or_default().push(Block {
+                source_start: *source_pos,
+                source_end: *source_pos + size,
+                target: *target,
+                target_start: *target_pos,
+                target_size: *target_size,
+                reverse: *reverse,
+            });
+            let longest = map.longest_block.entry(source.clone()).or_insert(0);
+            *longest = (*longest).max(size);
+            if fields.len() >= 3 {
+                *source_pos += size + parse_coordinate(fields[1], idx)?;
+                *target_pos += size + parse_coordinate(fields[2], idx)?;
+            } else {
+                current = None;
+            }
+        }
+
+        for blocks in map.blocks.values_mut() {
+            blocks.sort_by_key(|block| block.source_start);
+        }
+        Ok(map)
+    }
+
+    /// Lifts a 1-based position. Chromosome names may be given with or without the `chr`
+    /// prefix; the result uses the same style as the input.
+    pub fn lift(&self, chromosome: &str, position: i64) -> Result<LiftedPosition, Unmapped> {
+        let source = ucsc_chromosome(chromosome);
+        let blocks = self
+            .blocks
+            .get(&source)
+            .ok_or(Unmapped::UnknownChromosome)?;
+        let longest = self.longest_block.get(&source).copied().unwrap_or(0);
+        let offset = position - 1;
+
+        let end = blocks.partition_point(|block| block.source_start <= offset);
+        let mut hits = blocks[..end]
+            .iter()
+            .rev()
+            .take_while(|block| block.source_start + longest > offset)
+            .filter(|block| offset < block.source_end)
+            .map(|block| {
+                let delta = offset - block.source_start;
+                let target_offset = if block.reverse {
+                    block.target_size - 1 - (block.target_start + delta)
+                } else {
+                    block.target_start + delta
+                };
+                (block.target, target_offset + 1, block.reverse)
+            });
+
+        let (target, lifted, reverse) = hits.next().ok_or(Unmapped::Deleted)?;
+        if hits.any(|hit| hit != (target, lifted, reverse)) {
+            return Err(Unmapped::MultipleTargets);
+        }
+        Ok(LiftedPosition {
+            chromosome: restyle_chromosome(&self.targets[target], chromosome),
+            position: lifted,
+            reverse,
+        })
+    }
+}
+
+fn parse_coordinate(value: &str, idx: usize) -> Result<i64> {
+    value
+        .parse()
+        .map_err(|_| BiosynthError::Parse(format!("line {}: invalid number {:?}", idx + 1, value)))
+}
+
+/// Maps vendor chromosome labels (`1`, `X`, `23`, `MT`, `chr1`) onto UCSC names.
+fn ucsc_chromosome(chromosome: &str) -> String {
+    let bare = strip_chr_prefix(chromosome);
+    let name = match bare.to_ascii_uppercase().as_str() {
+        "23" | "X" => "X".to_string(),
+        "24" | "Y" => "Y".to_string(),
+        "26" | "M" | "MT" => "M".to_string(),
+        _ => bare.to_string(),
+    };
+    format!("chr{}", name)
+}
+
+fn restyle_chromosome(ucsc: &str, original: &str) -> String {
+    if strip_chr_prefix(original).len() != original.len() {
+        return ucsc.to_string();
+    }
+    match strip_chr_prefix(ucsc) {
+        "M" => "MT".to_string(),
+        bare => bare.to_string(),
+    }
+}
+
+fn strip_chr_prefix(chromosome: &str) -> &str {
+    match chromosome.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("chr") => &chromosome[3..],
+        _ => chromosome,
+    }
+}
+
+/// Complements each base of a genotype call for reverse-strand mappings.
+pub fn complement_genotype(genotype: &str) -> String {
+    genotype
+        .chars()
+        .map(|base| match base {
+            'A' => 'T',
+            'T' => 'A',
+            'C' => 'G',
+            'G' => 'C',
+            'a' => 't',
+            't' => 'a',
+            'c' => 'g',
+            'g' => 'c',
+            other => other,
+        })
+        .collect()
+}
@@ -0,0 +1,123 @@
+//! dbSNP rsid merges: retired rsids and the rsid each one now lives under.
+//!
+//! dbSNP retires an rsid when it merges two records, so older exports may call the same site
+//! by either ID. [`RsidMerges::load`] reads dbSNP's `RsMergeArch.bcp` (optionally gzipped) or
+//! any two-column `retired<TAB>current` (or comma-separated) list.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, Read};
+use std::path::Path;
+
+use flate2::read::MultiGzDecoder;
+
+use crate::buffers;
+use crate::error::{BiosynthError, Context, Result};
+use crate::genotype::Rsid;
+
+/// Merges followed before a chain is assumed to be a cycle.
+const MAX_MERGE_HOPS: usize = 32;
+/// Column of `rsCurrent` in `RsMergeArch.bcp`, which has at least this many columns.
+const MERGE_ARCH_CURRENT_COLUMN: usize = 6;
+
+/// Retired rsid numbers and the rsid each was merged into.
+#[derive(Debug, Clone, Default)]
+pub struct RsidMerges {
+    merged_into: HashMap<i64, i64>,
+}
+
+impl RsidMerges {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Open merge file {:?}", path))?;
+        let reader: Box<dyn Read> = if path.extension().is_some_and(|ext| ext == "gz") {
+            Box::new(MultiGzDecoder::new(file))
+        } else {
+            Box::new(file)
+        };
+        Self::parse(buffers::reader(reader)).map_err(|err| match err {
+            BiosynthError::Parse(message) => {
+                BiosynthError::Parse(format!("Parse merge file {:?}: {}", path, message))
+            }
+            other => other,
+        })
+    }
+
+    /// Reads one merge per line; blank lines, `#` comments, and a header line are skipped.
+    pub fn parse<R: BufRead>(reader: R) -> Result<Self> {
+        let mut merges = Self::default();
+        let mut seen_rows = false;
+        for (idx, line) in reader.lines().enumerate() {
+            let line = line?;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let delimiter = if trimmed.contains('\t') { '\t' } else { ',' };
+            let fields: Vec<&str> = trimmed.split(delimiter).map(str::trim).collect();
+            let current = match fields.len() {
+                0 | 1 => None,
+                len if len > MERGE_ARCH_CURRENT_COLUMN => fields.get(MERGE_ARCH_CURRENT_COLUMN),
+                _ => fields.get(1),
+            };
+            let parsed =
+                current.and_then(|current| Some((rsid_number(fields[0])?, rsid_number(current)?)));
+            match parsed {
+                Some((retired, current)) => merges.insert(retired, current),
+                None if !seen_rows => {}
+                None => {
+                    return Err(BiosynthError::Parse(format!(
+                        "line {}: expected a retired and a current rsid",
+                        idx + 1
+                    )))
+                }
+            }
+            seen_rows = true;
+        }
+        Ok(merges)
+    }
+
+    /// Records that `retired` was merged into `current`.
+    pub fn insert(&mut self, retired: i64, current: i64) {
+        if retired != current {
+            self.merged_into.insert(retired, current);
+        }
+    }
+
+    /// Retired rsids held.
+    pub fn len(&self) -> usize {
+        self.merged_into.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.merged_into.is_empty()
+    }
+
+    /// Every `(retired, merged into)` pair, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.merged_into
+            .iter()
+            .map(|(retired, current)| (*retired, *current))
+    }
+
+    /// The rsid `retired` lives under now, following merges of merged rsids; `None` if it was
+    /// never retired.
+    pub fn current(&self, retired: i64) -> Option<i64> {
+        let mut current = *self.merged_into.get(&retired)?;
+        for _ in 1..MAX_MERGE_HOPS {
+            match self.merged_into.get(&current) {
+                Some(next) if *next != retired => current = *next,
+                _ => break,
+            }
+        }
+        Some(current)
+    }
+
+    /// `rsid` under its current ID, if it was retired.
+    pub fn remap(&self, rsid: &Rsid) -> Option<Rsid> {
+        self.current(rsid.number()?).map(Rsid::Rs)
+    }
+}
+
+fn rsid_number(field: &str) -> Option<i64> {
+    field.strip_prefix("rs").unwrap_or(field).parse().ok()
+}
@@ -0,0 +1,264 @@
+//! The overlay document accepted by `bvs synthetic --variants-file`: named groups of variants
+//! forced into every generated file.
+//!
+//! ```json
+//! {
+//!   "HERC2": {
+//!     "description": "Eye colour",
+//!     "variants": [
+//!       { "rsid": "rs12913832", "chromosome": "15", "position": 28120472,
+//!         "reference": "A", "alternates": ["C", "G"] }
+//!     ]
+//!   }
+//! }
+//! ```
+//!
+//! Each variant gives either an explicit `genotypes` list or a `reference` (plus optional
+//! `alternates`) from which every diploid combination is drawn. [`schema`] returns the same
+//! rules as a JSON Schema for editors and external tooling.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::error::{BiosynthError, Result};
+
+/// A whole overlay file, keyed by group name.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct OverlayDocument {
+    pub groups: BTreeMap<String, OverlayGroup>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OverlayGroup {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The real participant these variants were taken from. bvs only ever records it as a
+    /// [pseudonym](crate::pseudonym).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub participant_id: Option<String>,
+    pub variants: Vec<OverlayVariant>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OverlayVariant {
+    /// `rs`-prefixed or bare numeric rsid.
+    pub rsid: String,
+    pub chromosome: String,
+    pub position: i64,
+    /// Genotypes to choose from; takes precedence over `reference`/`alternates`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub genotypes: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alternates: Option<Vec<String>>,
+}
+
+const GROUP_FIELDS: &[&str] = &["description", "participant_id", "variants"];
+const VARIANT_FIELDS: &[&str] = &[
+    "rsid",
+    "chromosome",
+    "position",
+    "genotypes",
+    "reference",
+    "alternates",
+];
+
+impl OverlayDocument {
+    /// Parses and validates an overlay document. Errors name the offending field, e.g.
+    /// `BRCA.variants[2].position: expected an integer, found "abc"`.
+    pub fn parse(raw_json: &str) -> Result<Self> {
+        let root: Value = serde_json::from_str(raw_json).map_err(|err| {
+            BiosynthError::Parse(format!("Overlay document is not valid JSON: {}", err))
+        })?;
+        validate(&root)?;
+        serde_json::from_value(root)
+            .map_err(|err| BiosynthError::Parse(format!("Invalid overlay document: {}", err)))
+    }
+
+    pub fn variants(&self) -> impl Iterator<Item = &OverlayVariant> {
+        self.groups.values().flat_map(|group| &group.variants)
+    }
+
+    /// Distinct real participant identifiers the groups reference.
+    pub fn participant_ids(&self) -> Vec<&str> {
+        let mut ids: Vec<&str> = self
+            .groups
+            .values()
+            .filter_map(|group| group.participant_id.as_deref())
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+}
+
+/// JSON Schema (draft 2020-12) describing [`OverlayDocument`].
+pub fn schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": "https://github.com/openmined/biosynth/overlay.schema.json",
+        "title": "biosynth overlay variants",
+        "description": "Named groups of variants forced into every generated file.",
+        "type": "object",
+        "additionalProperties": { "$ref": "#/$defs/group" },
+        "$defs": {
+            "group": {
+                "type": "object",
+                "required": ["variants"],
+                "additionalProperties": false,
+                "properties": {
+                    "description": { "type": "string" },
+                    "participant_id": { "type": "string", "minLength": 1 },
+                    "variants": { "type": "array", "items": { "$ref": "#/$defs/variant" } }
+                }
+            },
+            "variant": {
+                "type": "object",
+                "required": ["rsid", "chromosome", "position"],
+                "additionalProperties": false,
+                "anyOf": [{ "required": ["genotypes"] }, { "required": ["reference"] }],
+                "properties": {
+                    "rsid": { "type": "string", "pattern": "^(rs)?[0-9]+$" },
+                    "chromosome": { "type": "string", "minLength": 1 },
+                    "position": { "type": "integer" },
+                    "genotypes": {
+                        "type": "array",
+                        "minItems": 1,
+                        "items": { "type": "string", "minLength": 1 }
+                    },
+                    "reference": { "type": "string", "minLength": 1 },
+                    "alternates": { "type": "array", "items": { "type": "string" } }
+                }
+            }
+        }
+    })
+}
+
+fn validate(root: &Value) -> Result<()> {
+    let groups = expect_object(root, "overlay document")?;
+    for (name, group) in groups {
+        let fields = expect_object(group, name)?;
+        check_unknown(fields, GROUP_FIELDS, name)?;
+        if let Some(description) = fields.get("description") {
+            expect_string(description, &format!("{}.description", name))?;
+        }
+        if let Some(participant_id) = fields.get("participant_id") {
+            let path = format!("{}.participant_id", name);
+            if expect_string(participant_id, &path)?.trim().is_empty() {
+                return Err(invalid(&path, "must not be empty"));
+            }
+        }
+        let path = format!("{}.variants", name);
+        let variants = fields
+            .get("variants")
+            .ok_or_else(|| invalid(&path, "is required"))?
+            .as_array()
+            .ok_or_else(|| invalid(&path, "expected an array"))?;
+        for (idx, variant) in variants.iter().enumerate() {
+            validate_variant(variant, &format!("{}[{}]", path, idx))?;
+        }
+    }
+    Ok(())
+}
+
+fn validate_variant(variant: &Value, path: &str) -> Result<()> {
+    let fields = expect_object(variant, path)?;
+    check_unknown(fields, VARIANT_FIELDS, path)?;
+    let field = |name: &str| format!("{}.{}", path, name);
+    let required = |name: &str| {
+        fields
+            .get(name)
+            .ok_or_else(|| invalid(&field(name), "is required"))
+    };
+
+    let rsid = expect_string(required("rsid")?, &field("rsid"))?;
+    let digits = rsid.trim().trim_start_matches("rs");
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(invalid(
+            &field("rsid"),
+            &format!("expected rs<digits>, found {:?}", rsid),
+        ));
+    }
+    if expect_string(required("chromosome")?, &field("chromosome"))?.is_empty() {
+        return Err(invalid(&field("chromosome"), "must not be empty"));
+    }
+    let position = required("position")?;
+    if position.as_i64().is_none() {
+        return Err(invalid(
+            &field("position"),
+            &format!("expected an integer, found {}", position),
+        ));
+    }
+
+    if let Some(genotypes) = fields.get("genotypes") {
+        if expect_strings(genotypes, &field("genotypes"))? == 0 {
+            return Err(invalid(
+                &field("genotypes"),
+                "must list at least one genotype",
+            ));
+        }
+    }
+    if let Some(reference) = fields.get("reference") {
+        expect_string(reference, &field("reference"))?;
+    }
+    if let Some(alternates) = fields.get("alternates") {
+        expect_strings(alternates, &field("alternates"))?;
+    }
+    if !fields.contains_key("genotypes") && !fields.contains_key("reference") {
+        return Err(invalid(path, "must specify either genotypes or reference"));
+    }
+    Ok(())
+}
+
+fn expect_object<'a>(value: &'a Value, path: &str) -> Result<&'a Map<String, Value>> {
+    value
+        .as_object()
+        .ok_or_else(|| invalid(path, &format!("expected an object, found {}", kind(value))))
+}
+
+fn expect_string<'a>(value: &'a Value, path: &str) -> Result<&'a str> {
+    value
+        .as_str()
+        .ok_or_else(|| invalid(path, &format!("expected a string, found {}", value)))
+}
+
+fn expect_strings(value: &Value, path: &str) -> Result<usize> {
+    let items = value
+        .as_array()
+        .ok_or_else(|| invalid(path, &format!("expected an array, found {}", kind(value))))?;
+    for (idx, item) in items.iter().enumerate() {
+        expect_string(item, &format!("{}[{}]", path, idx))?;
+    }
+    Ok(items.len())
+}
+
+fn check_unknown(fields: &Map<String, Value>, known: &[&str], path: &str) -> Result<()> {
+    match fields.keys().find(|key| !known.contains(&key.as_str())) {
+        Some(key) => Err(invalid(
+            &format!("{}.{}", path, key),
+            &format!("unknown field (expected one of {})", known.join(", ")),
+        )),
+        None => Ok(()),
+    }
+}
+
+fn invalid(path: &str, message: &str) -> BiosynthError {
+    BiosynthError::Parse(format!("{}: {}", path, message))
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
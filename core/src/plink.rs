@@ -0,0 +1,218 @@
+//! PLINK 1 binary filesets (`.bed` genotypes, `.bim` variants, `.fam` samples) as genotype
+//! input.
+//!
+//! A fileset holds a whole cohort, while the rest of biosynth works one sample per file, so
+//! each sample is addressed as if the `.bed` were a directory: `cohort.bed/sample-3` is the
+//! third sample of the `.fam`. [`sample_paths`] lists those paths and [`process_sample`]
+//! parses one, reading the `.bed` through a memory mapping so each sample only touches its
+//! own bits of every variant.
+
+use std::fs::File;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::alleles::normalize_genotype;
+use crate::buffers;
+use crate::error::{BiosynthError, Context, Result};
+use crate::genotype::{FileMetadata, ParseOptions, ParseSummary, ParsedFile, Rsid, VariantRecord};
+
+/// Leading bytes of a variant-major `.bed`: two magic bytes, then mode `1`.
+const BED_MAGIC: [u8; 3] = [0x6c, 0x1b, 0x01];
+const SAMPLE_PREFIX: &str = "sample-";
+
+/// Whether `path` is named like a PLINK `.bed` file.
+pub fn is_bed(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("bed"))
+}
+
+/// The `.bim` and `.fam` files of the fileset `bed` belongs to, which share its stem.
+pub fn companions(bed: &Path) -> Result<(PathBuf, PathBuf)> {
+    let (bim, fam) = (bed.with_extension("bim"), bed.with_extension("fam"));
+    for companion in [&bim, &fam] {
+        if !companion.is_file() {
+            return Err(BiosynthError::InvalidArgument(format!(
+                "PLINK fileset {:?} is missing {:?}",
+                bed, companion
+            )));
+        }
+    }
+    Ok((bim, fam))
+}
+
+/// One path per sample of the fileset `bed` belongs to, in `.fam` order.
+pub fn sample_paths(bed: &Path) -> Result<Vec<PathBuf>> {
+    let (_, fam) = companions(bed)?;
+    Ok((1..=sample_count(&fam)?)
+        .map(|sample| bed.join(format!("{}{}", SAMPLE_PREFIX, sample)))
+        .collect())
+}
+
+/// The `.bed` and 1-based sample number a path from [`sample_paths`] refers to; `None` for
+/// other paths.
+pub fn split_sample(path: &Path) -> Option<(&Path, usize)> {
+    let bed = path.parent().filter(|bed| is_bed(bed) && bed.is_file())?;
+    let sample = path
+        .file_name()?
+        .to_str()?
+        .strip_prefix(SAMPLE_PREFIX)?
+        .parse()
+        .ok()
+        .filter(|sample| *sample > 0)?;
+    Some((bed, sample))
+}
+
+/// Parses sample `sample` (1-based) of the fileset `bed` belongs to, calling `on_variant` for
+/// every `.bim` variant with an ID, a placed chromosome, and a position. Calls are written as
+/// consumer exports write them: both alleles for SNPs, `I`/`D` for indels, `--` when missing.
+/// Of `options`, only the genotype validation applies.
+pub fn process_sample<F>(
+    bed: &Path,
+    sample: usize,
+    options: &ParseOptions,
+    mut on_variant: F,
+) -> Result<ParsedFile>
+where
+    F: FnMut(&VariantRecord, &FileMetadata) -> Result<()>,
+{
+    let (bim, fam) = companions(bed)?;
+    let samples = sample_count(&fam)?;
+    if sample == 0 || sample > samples {
+        return Err(BiosynthError::InvalidArgument(format!(
+            "{:?} has {} samples, not a sample {}",
+            fam, samples, sample
+        )));
+    }
+    let file = File::open(bed).with_context(|| format!("Failed to open {:?}", bed))?;
+    // SAFETY: the mapping is only read, as in `genotype::process_mapped`.
+    let map =
+        unsafe { memmap2::Mmap::map(&file) }.with_context(|| format!("Failed to map {:?}", bed))?;
+    if !map.starts_with(&BED_MAGIC) {
+        return Err(BiosynthError::Parse(format!(
+            "{:?} is not a variant-major PLINK .bed file",
+            bed
+        )));
+    }
+
+    let stride = samples.div_ceil(4);
+    let (byte, shift) = ((sample - 1) / 4, 2 * ((sample - 1) % 4));
+    let validation = options.validation;
+    let metadata = FileMetadata::default();
+    let mut summary = ParseSummary::default();
+    let mut chromosome: Option<Arc<str>> = None;
+    let mut variants = 0;
+    let reader =
+        buffers::reader(File::open(&bim).with_context(|| format!("Failed to open {:?}", bim))?);
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+        let [raw_chromosome, id, _, position, allele1, allele2] = fields[..] else {
+            return Err(BiosynthError::Parse(format!(
+                "{:?} line {}: expected 6 columns, found {}",
+                bim,
+                idx + 1,
+                fields.len()
+            )));
+        };
+        let offset = BED_MAGIC.len() + variants * stride;
+        variants += 1;
+        let Some(&packed) = map.get(offset + byte) else {
+            return Err(BiosynthError::Parse(format!(
+                "{:?} ends before variant {} of {:?}",
+                bed, variants, bim
+            )));
+        };
+        let name = chromosome_name(raw_chromosome);
+        let position = position.parse::<i64>().ok();
+        let (Some(position), Some((first, second))) = (position, allele_codes(allele1, allele2))
+        else {
+            summary.skipped_rows += 1;
+            continue;
+        };
+        if id == "." || name == "0" {
+            summary.skipped_rows += 1;
+            continue;
+        }
+        let rsid = Rsid::parse(id);
+        let genotype = match (packed >> shift) & 0b11 {
+            0b00 => format!("{}{}", first, first),
+            0b01 => "--".to_string(),
+            0b10 => format!("{}{}", first, second),
+            _ => format!("{}{}", second, second),
+        };
+        let genotype = normalize_genotype(&genotype);
+        let Some(genotype) = validation.check(genotype, &rsid, &mut summary.invalid_genotypes)?
+        else {
+            summary.skipped_rows += 1;
+            continue;
+        };
+        let chromosome = match &chromosome {
+            Some(known) if **known == *name => Arc::clone(known),
+            _ => Arc::clone(chromosome.insert(name.into())),
+        };
+        summary.variant_count += 1;
+        on_variant(
+            &VariantRecord {
+                rsid,
+                chromosome,
+                position,
+                genotype,
+            },
+            &metadata,
+        )?;
+    }
+    if map.len() != BED_MAGIC.len() + variants * stride {
+        return Err(BiosynthError::Parse(format!(
+            "{:?} holds {} bytes, but {} variants of {} samples need {}",
+            bed,
+            map.len(),
+            variants,
+            samples,
+            BED_MAGIC.len() + variants * stride
+        )));
+    }
+    Ok(ParsedFile { metadata, summary })
+}
+
+fn sample_count(fam: &Path) -> Result<usize> {
+    let reader =
+        buffers::reader(File::open(fam).with_context(|| format!("Failed to open {:?}", fam))?);
+    let mut samples = 0;
+    for line in reader.lines() {
+        if !line?.trim().is_empty() {
+            samples += 1;
+        }
+    }
+    Ok(samples)
+}
+
+/// PLINK's numeric codes for the sex chromosomes and mitochondria, as consumer exports name
+/// them; `0` stays `0` (unplaced).
+fn chromosome_name(raw: &str) -> &str {
+    match raw {
+        "23" => "X",
+        "24" => "Y",
+        "25" => "XY",
+        "26" => "MT",
+        other => other,
+    }
+}
+
+/// The letters a call uses for each allele: the base of a SNP allele (`0` when missing), or
+/// for indels `I` for the longer allele and `D` for the shorter. `None` for multi-base
+/// substitutions, which have no consumer-style call.
+fn allele_codes(allele1: &str, allele2: &str) -> Option<(char, char)> {
+    match (allele1.len(), allele2.len()) {
+        (1, 1) => Some((
+            char::from(allele1.as_bytes()[0]),
+            char::from(allele2.as_bytes()[0]),
+        )),
+        (first, second) if first > second => Some(('I', 'D')),
+        (first, second) if first < second => Some(('D', 'I')),
+        _ => None,
+    }
+}
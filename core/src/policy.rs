@@ -0,0 +1,153 @@
+//! Export policies: which tables and columns of a stats database may leave the machine, and at
+//! what aggregation level.
+//!
+//! A policy is a YAML document:
+//!
+//! ```yaml
+//! # Counts observed in fewer source files are withheld.
+//! min_count: 5
+//! # Noised releases may spend at most this budget; `require_noise` refuses exact counts.
+//! max_epsilon: 1.0
+//! require_noise: false
+//! # Tables that may be released, with the columns that may appear. An empty list allows
+//! # aggregate counts over the table only; "*" allows every column.
+//! tables:
+//!   formats: [name, genome_build]
+//!   rsid_reference: ["*"]
+//!   files: []
+//! ```
+//!
+//! Commands that export or report data describe what they release as a list of
+//! `(table, column)` pairs. Reading from an unlisted table is refused outright; disallowed
+//! columns are redacted where the output allows it and refused otherwise.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{BiosynthError, Context, Result};
+
+/// The file name a policy is looked up under in the data directory.
+pub const POLICY_FILENAME: &str = "policy.yaml";
+
+/// Column list entry that allows every column of a table.
+const ALL_COLUMNS: &str = "*";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExportPolicy {
+    /// Minimum number of source files behind any released count.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_count: Option<u64>,
+    /// Largest differential-privacy budget a single release may spend.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_epsilon: Option<f64>,
+    /// Refuse releases of exact (un-noised) counts.
+    #[serde(default)]
+    pub require_noise: bool,
+    /// Allowed columns per table; tables not listed may not be released at all.
+    #[serde(default)]
+    pub tables: BTreeMap<String, Vec<String>>,
+}
+
+/// A `(table, column)` a release reads; `column` is `None` for aggregate counts over the table.
+pub type ReleasedColumn<'a> = (&'a str, Option<&'a str>);
+
+impl ExportPolicy {
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("Read export policy {:?}", path))?;
+        let policy: Self = serde_yaml::from_str(&raw).map_err(|err| {
+            BiosynthError::Parse(format!("Invalid export policy {:?}: {}", path, err))
+        })?;
+        if let Some(epsilon) = policy.max_epsilon {
+            if !epsilon.is_finite() || epsilon <= 0.0 {
+                return Err(BiosynthError::Parse(format!(
+                    "Invalid export policy {:?}: max_epsilon must be a positive number",
+                    path
+                )));
+            }
+        }
+        Ok(policy)
+    }
+
+    /// Loads `explicit` if given, else `policy.yaml` in `data_dir` if it exists.
+    pub fn discover(explicit: Option<&Path>, data_dir: &Path) -> Result<Option<Self>> {
+        let path = match explicit {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let default: PathBuf = data_dir.join(POLICY_FILENAME);
+                if !default.exists() {
+                    return Ok(None);
+                }
+                default
+            }
+        };
+        Self::load(&path).map(Some)
+    }
+
+    pub fn allows_table(&self, table: &str) -> bool {
+        self.tables.contains_key(table)
+    }
+
+    pub fn allows_column(&self, table: &str, column: &str) -> bool {
+        self.tables.get(table).is_some_and(|columns| {
+            columns
+                .iter()
+                .any(|allowed| allowed == ALL_COLUMNS || allowed == column)
+        })
+    }
+
+    /// Checks a release that cannot redact: every table and column must be allowed.
+    pub fn require(&self, release: &str, columns: &[ReleasedColumn<'_>]) -> Result<()> {
+        let redacted = self.redactions(release, columns)?;
+        if redacted.is_empty() {
+            Ok(())
+        } else {
+            Err(BiosynthError::PolicyViolation(format!(
+                "{} would release {}, which the export policy does not allow",
+                release,
+                redacted.join(", ")
+            )))
+        }
+    }
+
+    /// Checks a release that can redact: errors if any table is not allowed, and returns the
+    /// `table.column` names that must be redacted.
+    pub fn redactions(&self, release: &str, columns: &[ReleasedColumn<'_>]) -> Result<Vec<String>> {
+        let mut redacted = Vec::new();
+        for (table, column) in columns {
+            if !self.allows_table(table) {
+                return Err(BiosynthError::PolicyViolation(format!(
+                    "{} reads table `{}`, which the export policy does not allow",
+                    release, table
+                )));
+            }
+            if let Some(column) = column {
+                let name = format!("{}.{}", table, column);
+                if !self.allows_column(table, column) && !redacted.contains(&name) {
+                    redacted.push(name);
+                }
+            }
+        }
+        Ok(redacted)
+    }
+
+    /// Checks the privacy budget a release spends; `None` means exact counts.
+    pub fn check_noise(&self, release: &str, epsilon: Option<f64>) -> Result<()> {
+        match (epsilon, self.max_epsilon) {
+            (None, _) if self.require_noise => Err(BiosynthError::PolicyViolation(format!(
+                "{} releases exact counts, but the export policy requires noise",
+                release
+            ))),
+            (Some(epsilon), Some(max)) if epsilon > max => {
+                Err(BiosynthError::PolicyViolation(format!(
+                    "{} spends epsilon {}, above the export policy's max_epsilon {}",
+                    release, epsilon, max
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Checking parsed calls' coordinates against the reference.
+//!
+//! A file whose rsids land on other chromosomes or positions than the reference says was
+//! usually exported on a different genome build, so the share of calls that disagree doubles
+//! as a build sanity check. Only SNPs are held: indel positions depend on how each source pads
+//! and aligns the alleles, and would disagree without anything being wrong.
+
+use std::collections::HashMap;
+
+use crate::genotype::{chromosome_code, Rsid};
+use crate::stats::ReferenceVariant;
+
+/// The chromosome and position of each SNP in a reference, for checking calls against.
+#[derive(Debug, Clone, Default)]
+pub struct PositionReference {
+    sites: HashMap<i64, (u8, i64)>,
+}
+
+impl PositionReference {
+    /// Adds the SNPs among `references` on the autosomes, `X`, `Y`, `XY`, and `MT`.
+    pub fn extend<'a>(&mut self, references: impl IntoIterator<Item = &'a ReferenceVariant>) {
+        for reference in references {
+            if reference.snp_alleles().is_none() {
+                continue;
+            }
+            if let Some(chromosome) = chromosome_code(&reference.chromosome) {
+                self.sites
+                    .insert(reference.rsid, (chromosome, reference.position));
+            }
+        }
+    }
+
+    /// SNPs held.
+    pub fn len(&self) -> usize {
+        self.sites.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sites.is_empty()
+    }
+
+    /// Whether a call at `rsid` on `chromosome` at `position` agrees with the reference;
+    /// `None` for rsids the reference does not hold and for unplaced calls (position `0` or an
+    /// unrecognized chromosome), which are not checked.
+    pub fn agrees(&self, rsid: &Rsid, chromosome: &str, position: i64) -> Option<bool> {
+        let expected = *self.sites.get(&rsid.number()?)?;
+        if position <= 0 {
+            return None;
+        }
+        Some(expected == (chromosome_code(chromosome)?, position))
+    }
+}
@@ -0,0 +1,146 @@
+//! Disclosure controls applied to aggregate statistics before they leave the machine.
+//!
+//! [`LaplaceNoise`] releases counts under ε-differential privacy: each count is perturbed with
+//! Laplace noise of scale `sensitivity / ε`, where sensitivity is how much one source file can
+//! change the count (1 for "files observing X"). [`Suppression`] withholds cells observed in
+//! too few source files, so rare observations cannot single anyone out. When both are used,
+//! suppress after noising so the threshold itself does not leak exact counts.
+
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{BiosynthError, Result};
+
+/// The Laplace mechanism for counts and frequencies.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LaplaceNoise {
+    epsilon: f64,
+    sensitivity: f64,
+}
+
+impl LaplaceNoise {
+    /// Noise for the privacy budget `epsilon`; smaller is more private. Sensitivity defaults to 1.
+    pub fn new(epsilon: f64) -> Result<Self> {
+        if !epsilon.is_finite() || epsilon <= 0.0 {
+            return Err(BiosynthError::InvalidArgument(format!(
+                "Epsilon must be a positive number, got {}",
+                epsilon
+            )));
+        }
+        Ok(Self {
+            epsilon,
+            sensitivity: 1.0,
+        })
+    }
+
+    /// Sets how much a single source file can change any one released count.
+    pub fn with_sensitivity(mut self, sensitivity: f64) -> Result<Self> {
+        if !sensitivity.is_finite() || sensitivity <= 0.0 {
+            return Err(BiosynthError::InvalidArgument(format!(
+                "Sensitivity must be a positive number, got {}",
+                sensitivity
+            )));
+        }
+        self.sensitivity = sensitivity;
+        Ok(self)
+    }
+
+    pub fn epsilon(&self) -> f64 {
+        self.epsilon
+    }
+
+    /// Noise for one of `releases` counts released from the same files: each gets
+    /// `epsilon / releases`, so together they spend this noise's budget.
+    pub fn split(&self, releases: usize) -> Self {
+        Self {
+            epsilon: self.epsilon / releases.max(1) as f64,
+            sensitivity: self.sensitivity,
+        }
+    }
+
+    /// The Laplace scale `b = sensitivity / epsilon`.
+    pub fn scale(&self) -> f64 {
+        self.sensitivity / self.epsilon
+    }
+
+    /// Draws one sample from Laplace(0, b).
+    pub fn sample(&self, rng: &mut dyn RngCore) -> f64 {
+        // Inverse CDF on u in (-0.5, 0.5); the open interval avoids ln(0).
+        let u: f64 = rng.gen_range(-0.5..0.5);
+        let magnitude = -(1.0 - 2.0 * u.abs()).max(f64::MIN_POSITIVE).ln();
+        self.scale() * magnitude * u.signum()
+    }
+
+    /// `count` plus noise, rounded and clamped at zero.
+    pub fn noisy_count(&self, count: u64, rng: &mut dyn RngCore) -> u64 {
+        (count as f64 + self.sample(rng)).round().max(0.0) as u64
+    }
+
+    /// `count / total` with noise added to the numerator, clamped to `[0, 1]`.
+    pub fn noisy_frequency(&self, count: u64, total: u64, rng: &mut dyn RngCore) -> f64 {
+        if total == 0 {
+            return 0.0;
+        }
+        ((count as f64 + self.sample(rng)) / total as f64).clamp(0.0, 1.0)
+    }
+}
+
+/// Budget accounting for a one-off DP release of per-variant allele counts.
+///
+/// Adding or removing one person changes the allele copies at each variant by at most 2 (one
+/// diploid call), so each variant's counts have L1 sensitivity 2. Every variant is released
+/// from the same people, so budgets compose sequentially: each gets `epsilon / variants`, and
+/// Laplace noise of scale `2 * variants / epsilon` is added to every count. Anything derived
+/// from the noisy counts afterwards, such as any number of synthetic files, is
+/// post-processing and spends no further budget.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DpAccounting {
+    /// Total budget spent on the release.
+    pub epsilon: f64,
+    pub variants: usize,
+    pub per_variant_epsilon: f64,
+    pub sensitivity: f64,
+    /// Laplace scale applied to each allele count.
+    pub scale: f64,
+}
+
+impl DpAccounting {
+    pub const ALLELE_COUNT_SENSITIVITY: f64 = 2.0;
+
+    /// Splits `epsilon` evenly over `variants` allele-count releases.
+    pub fn allele_counts(epsilon: f64, variants: usize) -> Result<(Self, LaplaceNoise)> {
+        let per_variant_epsilon = epsilon / variants.max(1) as f64;
+        LaplaceNoise::new(epsilon)?;
+        let noise = LaplaceNoise::new(per_variant_epsilon)?
+            .with_sensitivity(Self::ALLELE_COUNT_SENSITIVITY)?;
+        let accounting = Self {
+            epsilon,
+            variants,
+            per_variant_epsilon,
+            sensitivity: Self::ALLELE_COUNT_SENSITIVITY,
+            scale: noise.scale(),
+        };
+        Ok((accounting, noise))
+    }
+}
+
+/// Withholds any cell observed in fewer than `min_count` source files (k-anonymity).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Suppression {
+    min_count: u64,
+}
+
+impl Suppression {
+    pub fn new(min_count: u64) -> Self {
+        Self { min_count }
+    }
+
+    pub fn min_count(&self) -> u64 {
+        self.min_count
+    }
+
+    /// Whether a cell with `count` observations may be released.
+    pub fn allows(&self, count: u64) -> bool {
+        count >= self.min_count
+    }
+}
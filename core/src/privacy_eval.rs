@@ -0,0 +1,382 @@
+//! Proxy metrics for how much a synthetic cohort could reveal about real people, reported by
+//! `bvs privacy-eval` before synthetic data is distributed.
+//!
+//! None of these prove privacy; they flag the usual ways a generator leaks:
+//!
+//! - **Unknown rsids / novel alleles**: calls the reference statistics cannot explain, which can
+//!   only have come from real genomes (e.g. through an overlay).
+//! - **Rare alleles**: alternate alleles in the synthetic cohort that very few source files
+//!   carry (per the database's allele observations). Reproducing them is the kind of
+//!   observation that singles out an individual.
+//! - **Identity**: Jaccard similarity of two files' non-reference SNP calls, optionally on a
+//!   deterministic sample of rsids. Shared reference calls are ignored, since any two genomes
+//!   agree on almost all of them. Near-identical synthetic pairs suggest memorization; a
+//!   synthetic file close to a real one (`add_real`) is a direct membership-inference signal.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::genotype::{ParseOptions, Rsid};
+use crate::stats::{process_input, ReferenceVariant};
+
+/// Thresholds for [`PrivacyEvaluator`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct EvalOptions {
+    /// An alternate allele carried by at most this many source files is rare.
+    pub rare_carriers: usize,
+    /// Share (0-1) of synthetic alternate alleles that may be rare before risk is elevated.
+    pub max_rare_allele_rate: f64,
+    /// Two files at or above this identity count as near-duplicates.
+    pub identity_threshold: f64,
+    /// Compare identity on roughly one rsid in this many.
+    pub sample_every: u64,
+}
+
+impl Default for EvalOptions {
+    fn default() -> Self {
+        Self {
+            rare_carriers: 1,
+            max_rare_allele_rate: 0.01,
+            identity_threshold: 0.95,
+            sample_every: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskLevel {
+    Low,
+    Elevated,
+    High,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyReport {
+    pub synthetic_files: usize,
+    pub calls: u64,
+    /// Calls whose rsid is not in the reference.
+    pub unknown_rsids: u64,
+    /// SNP calls carrying an allele that is neither the reference nor a listed alternate.
+    pub novel_alleles: u64,
+    /// Distinct (rsid, alternate allele) pairs carried by any synthetic file.
+    pub alternate_alleles: u64,
+    /// Of those, pairs carried by at least one and at most [`EvalOptions::rare_carriers`]
+    /// source files.
+    pub rare_alleles: u64,
+    pub rare_allele_rate: f64,
+    /// Highest identity between two synthetic files.
+    pub max_synthetic_identity: f64,
+    /// Synthetic pairs at or above [`EvalOptions::identity_threshold`].
+    pub near_duplicate_pairs: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub real: Option<RealComparison>,
+    pub risk: RiskLevel,
+    /// Human-readable reasons behind `risk`.
+    pub findings: Vec<String>,
+}
+
+/// How close synthetic files come to the real genomes they must not reproduce.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RealComparison {
+    pub real_files: usize,
+    /// Highest identity between any synthetic and any real file.
+    pub max_identity: f64,
+    /// Mean, over synthetic files, of the identity to the closest real file.
+    pub mean_closest_identity: f64,
+    /// Synthetic files whose closest real file is at or above the identity threshold.
+    pub matches: usize,
+}
+
+/// Accumulates synthetic (and optionally real) files, then scores them with [`finish`].
+///
+/// [`finish`]: PrivacyEvaluator::finish
+pub struct PrivacyEvaluator {
+    options: EvalOptions,
+    /// SNP alleles (reference first) per rsid; `None` for indels and MNVs.
+    references: HashMap<i64, Option<Vec<char>>>,
+    /// Source files carrying each SNP allele, from the database's allele observations.
+    source_carriers: HashMap<(i64, char), u64>,
+    /// Synthetic files carrying each alternate SNP allele.
+    carriers: HashMap<(i64, char), usize>,
+    synthetic: Vec<Profile>,
+    real: Vec<Profile>,
+    calls: u64,
+    unknown_rsids: u64,
+    novel_alleles: u64,
+}
+
+/// Normalized non-reference genotypes on the sampled rsids of one file.
+type Profile = HashMap<i64, String>;
+
+impl PrivacyEvaluator {
+    /// `source_carriers` are the source cohort's carriers per rsid and allele, as returned by
+    /// [`StatsStore::allele_carriers`](crate::stats::StatsStore::allele_carriers).
+    pub fn new(
+        references: &[ReferenceVariant],
+        source_carriers: &HashMap<i64, HashMap<String, u64>>,
+        options: EvalOptions,
+    ) -> Self {
+        let references = references
+            .iter()
+            .map(|variant| (variant.rsid, variant.snp_alleles()))
+            .collect();
+        let source_carriers = source_carriers
+            .iter()
+            .flat_map(|(rsid, alleles)| {
+                alleles.iter().filter_map(move |(allele, carriers)| {
+                    let mut chars = allele.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(base), None) => Some(((*rsid, base.to_ascii_uppercase()), *carriers)),
+                        _ => None,
+                    }
+                })
+            })
+            .collect();
+        Self {
+            options,
+            references,
+            source_carriers,
+            carriers: HashMap::new(),
+            synthetic: Vec::new(),
+            real: Vec::new(),
+            calls: 0,
+            unknown_rsids: 0,
+            novel_alleles: 0,
+        }
+    }
+
+    /// Adds one generated file to the cohort under evaluation. `path` may be any input
+    /// [`process_input`] reads, such as one sample of a multi-sample VCF.
+    pub fn add_synthetic(&mut self, path: &Path) -> Result<()> {
+        let mut profile = Profile::new();
+        process_input(path, &ParseOptions::default(), |record, _| {
+            self.calls += 1;
+            let alleles = called_alleles(&record.genotype);
+            if let Some((rsid, genotype)) = self.profile_entry(&record.rsid, &alleles) {
+                profile.insert(rsid, genotype);
+            }
+
+            let Some(reference) = numeric_rsid(&record.rsid)
+                .and_then(|rsid| self.references.get(&rsid).map(|alleles| (rsid, alleles)))
+            else {
+                self.unknown_rsids += 1;
+                return Ok(());
+            };
+            let (rsid, Some(known)) = reference else {
+                return Ok(());
+            };
+            if alleles.iter().any(|allele| !known.contains(allele)) {
+                self.novel_alleles += 1;
+            }
+            let mut carried: Vec<char> = alleles
+                .into_iter()
+                .filter(|allele| *allele != known[0])
+                .collect();
+            carried.dedup();
+            for allele in carried {
+                *self.carriers.entry((rsid, allele)).or_insert(0) += 1;
+            }
+            Ok(())
+        })?;
+        self.synthetic.push(profile);
+        Ok(())
+    }
+
+    /// Adds a real genome the synthetic files are compared against, read like
+    /// [`add_synthetic`](Self::add_synthetic)'s.
+    pub fn add_real(&mut self, path: &Path) -> Result<()> {
+        let mut profile = Profile::new();
+        process_input(path, &ParseOptions::default(), |record, _| {
+            let alleles = called_alleles(&record.genotype);
+            if let Some((rsid, genotype)) = self.profile_entry(&record.rsid, &alleles) {
+                profile.insert(rsid, genotype);
+            }
+            Ok(())
+        })?;
+        self.real.push(profile);
+        Ok(())
+    }
+
+    pub fn finish(self) -> PrivacyReport {
+        let threshold = self.options.identity_threshold;
+        let mut max_synthetic_identity = 0.0f64;
+        let mut near_duplicate_pairs = 0;
+        for (idx, left) in self.synthetic.iter().enumerate() {
+            for right in &self.synthetic[idx + 1..] {
+                let identity = identity(left, right);
+                max_synthetic_identity = max_synthetic_identity.max(identity);
+                if identity >= threshold {
+                    near_duplicate_pairs += 1;
+                }
+            }
+        }
+
+        let real = (!self.real.is_empty()).then(|| {
+            let closest: Vec<f64> = self
+                .synthetic
+                .iter()
+                .map(|synthetic| {
+                    self.real
+                        .iter()
+                        .map(|real| identity(synthetic, real))
+                        .fold(0.0, f64::max)
+                })
+                .collect();
+            RealComparison {
+                real_files: self.real.len(),
+                max_identity: closest.iter().copied().fold(0.0, f64::max),
+                mean_closest_identity: if closest.is_empty() {
+                    0.0
+                } else {
+                    closest.iter().sum::<f64>() / closest.len() as f64
+                },
+                matches: closest.iter().filter(|value| **value >= threshold).count(),
+            }
+        });
+
+        let alternate_alleles = self.carriers.len() as u64;
+        let rare_carriers = 1..=self.options.rare_carriers as u64;
+        let rare_alleles = self
+            .carriers
+            .keys()
+            .filter(|key| {
+                self.source_carriers
+                    .get(key)
+                    .is_some_and(|carriers| rare_carriers.contains(carriers))
+            })
+            .count() as u64;
+        let rare_allele_rate = if alternate_alleles > 0 {
+            rare_alleles as f64 / alternate_alleles as f64
+        } else {
+            0.0
+        };
+
+        let mut findings = Vec::new();
+        let mut risk = RiskLevel::Low;
+        let mut flag = |level: RiskLevel, finding: String| {
+            risk = risk.max(level);
+            findings.push(finding);
+        };
+        if let Some(real) = &real {
+            if real.matches > 0 {
+                flag(
+                    RiskLevel::High,
+                    format!(
+                        "{} synthetic files are at least {:.0}% identical to a real genome",
+                        real.matches,
+                        threshold * 100.0
+                    ),
+                );
+            }
+        }
+        if self.unknown_rsids > 0 {
+            flag(
+                RiskLevel::High,
+                format!(
+                    "{} calls use rsids absent from the reference",
+                    self.unknown_rsids
+                ),
+            );
+        }
+        if self.novel_alleles > 0 {
+            flag(
+                RiskLevel::High,
+                format!(
+                    "{} calls carry alleles the reference does not list",
+                    self.novel_alleles
+                ),
+            );
+        }
+        if rare_allele_rate > self.options.max_rare_allele_rate {
+            flag(
+                RiskLevel::Elevated,
+                format!(
+                    "{:.1}% of alternate alleles are carried by at most {} source files (limit {:.1}%)",
+                    rare_allele_rate * 100.0,
+                    self.options.rare_carriers,
+                    self.options.max_rare_allele_rate * 100.0
+                ),
+            );
+        }
+        if near_duplicate_pairs > 0 {
+            flag(
+                RiskLevel::Elevated,
+                format!(
+                    "{} pairs of synthetic files are near-duplicates",
+                    near_duplicate_pairs
+                ),
+            );
+        }
+
+        PrivacyReport {
+            synthetic_files: self.synthetic.len(),
+            calls: self.calls,
+            unknown_rsids: self.unknown_rsids,
+            novel_alleles: self.novel_alleles,
+            alternate_alleles,
+            rare_alleles,
+            rare_allele_rate,
+            max_synthetic_identity,
+            near_duplicate_pairs,
+            real,
+            risk,
+            findings,
+        }
+    }
+
+    /// The profile entry for a call: sampled, a known SNP, and not homozygous reference.
+    fn profile_entry(&self, rsid: &Rsid, alleles: &[char]) -> Option<(i64, String)> {
+        let rsid = numeric_rsid(rsid)?;
+        // A multiplicative hash keeps the sample deterministic and shared by every file.
+        let sampled = (rsid as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32;
+        if !sampled.is_multiple_of(self.options.sample_every.max(1)) {
+            return None;
+        }
+        let known = self.references.get(&rsid)?.as_ref()?;
+        alleles
+            .iter()
+            .any(|allele| *allele != known[0])
+            .then(|| (rsid, normalize(alleles)))
+    }
+}
+
+/// The rsid's number, accepting bare numbers as well as `rs` IDs.
+fn numeric_rsid(rsid: &Rsid) -> Option<i64> {
+    rsid.number().or_else(|| match rsid {
+        Rsid::Other(raw) => raw.trim().parse().ok(),
+        Rsid::Rs(_) => None,
+    })
+}
+
+/// Alleles in a call, ignoring separators and no-call markers.
+fn called_alleles(genotype: &str) -> Vec<char> {
+    genotype
+        .chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+fn normalize(alleles: &[char]) -> String {
+    let mut sorted = alleles.to_vec();
+    sorted.sort_unstable();
+    sorted.into_iter().collect()
+}
+
+/// Jaccard similarity of two profiles' (rsid, genotype) calls.
+fn identity(left: &Profile, right: &Profile) -> f64 {
+    let matching = left
+        .iter()
+        .filter(|(rsid, genotype)| right.get(rsid) == Some(genotype))
+        .count();
+    let union = left.len() + right.len() - matching;
+    if union == 0 {
+        0.0
+    } else {
+        matching as f64 / union as f64
+    }
+}
@@ -0,0 +1,27 @@
+//! Progress reporting for long-running operations.
+//!
+//! Operations that work through several files accept an optional [`Sender`] and report one
+//! event per state change, so GUIs and servers can render progress without parsing stdout.
+//! Events are dropped silently once the receiver goes away.
+
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+
+use serde::{Deserialize, Serialize};
+
+/// A unit of progress, one file at a time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent {
+    Started { path: PathBuf },
+    Finished { path: PathBuf, rows: usize },
+    Skipped { path: PathBuf },
+    Failed { path: PathBuf, error: String },
+}
+
+#[cfg_attr(not(feature = "stats"), allow(dead_code))]
+pub(crate) fn emit(progress: Option<&Sender<ProgressEvent>>, event: ProgressEvent) {
+    if let Some(sender) = progress {
+        let _ = sender.send(event);
+    }
+}
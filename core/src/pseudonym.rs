@@ -0,0 +1,138 @@
+//! Salted pseudonyms for real participant identifiers referenced by overlays and cohort specs,
+//! so they never reach a manifest, file name, or database in the clear.
+//!
+//! Each project (data directory) keeps its own random salt in [`SALT_FILENAME`]; the same
+//! identifier always maps to the same pseudonym within a project and to unrelated ones across
+//! projects. Re-identification needs the lookup file written by
+//! [`ParticipantHasher::with_lookup`], which should stay with whoever is authorized to use it.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use crate::error::{BiosynthError, Context, Result};
+
+/// Name of the per-project salt file in the data directory.
+pub const SALT_FILENAME: &str = "participant_id.salt";
+/// Prefix marking a string as a pseudonym rather than a real identifier.
+pub const PSEUDONYM_PREFIX: &str = "pid-";
+
+/// Maps real participant identifiers to salted pseudonyms.
+#[derive(Debug, Clone)]
+pub struct ParticipantHasher {
+    salt: String,
+    lookup: Option<PathBuf>,
+}
+
+impl ParticipantHasher {
+    pub fn with_salt(salt: impl Into<String>) -> Result<Self> {
+        let salt = salt.into();
+        if salt.trim().is_empty() {
+            return Err(BiosynthError::InvalidArgument(
+                "Participant ID salt must not be empty".into(),
+            ));
+        }
+        Ok(Self { salt, lookup: None })
+    }
+
+    /// Reads the salt from `path`, generating one (readable only by the current user) if the
+    /// file does not exist yet.
+    pub fn load_or_create(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).with_context(|| format!("Create {:?}", parent))?;
+            }
+            let salt: [u8; 32] = rand::thread_rng().gen();
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            // Another process may have created it first; its salt wins.
+            if let Ok(mut file) = options.open(path) {
+                writeln!(file, "{}", hex(&salt))
+                    .with_context(|| format!("Write participant ID salt {:?}", path))?;
+            }
+        }
+        let salt = fs::read_to_string(path)
+            .with_context(|| format!("Read participant ID salt {:?}", path))?;
+        Self::with_salt(salt.trim())
+    }
+
+    /// Appends `pseudonym<TAB>identifier` to the local TSV at `path` for every identifier
+    /// pseudonymized, so authorized users can re-identify with [`reidentify`].
+    pub fn with_lookup(mut self, path: PathBuf) -> Self {
+        self.lookup = Some(path);
+        self
+    }
+
+    /// The pseudonym for `id`, without recording it.
+    pub fn pseudonym(&self, id: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.salt.as_bytes());
+        hasher.update(b"\0");
+        hasher.update(id.trim().as_bytes());
+        format!("{}{}", PSEUDONYM_PREFIX, hex(&hasher.finalize()[..16]))
+    }
+
+    /// Pseudonyms for `ids`, in order, recording new ones in the lookup file if one is set.
+    pub fn pseudonymize<S: AsRef<str>>(&self, ids: &[S]) -> Result<Vec<String>> {
+        let pseudonyms: Vec<String> = ids.iter().map(|id| self.pseudonym(id.as_ref())).collect();
+        let Some(lookup) = &self.lookup else {
+            return Ok(pseudonyms);
+        };
+        let mut known: HashSet<String> = read_lookup(lookup)?.into_keys().collect();
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options
+            .open(lookup)
+            .with_context(|| format!("Open participant lookup {:?}", lookup))?;
+        for (id, pseudonym) in ids.iter().zip(&pseudonyms) {
+            if known.insert(pseudonym.clone()) {
+                writeln!(file, "{}\t{}", pseudonym, id.as_ref().trim())
+                    .with_context(|| format!("Write participant lookup {:?}", lookup))?;
+            }
+        }
+        Ok(pseudonyms)
+    }
+}
+
+/// Resolves `pseudonyms` through a lookup file; unknown ones map to `None`.
+pub fn reidentify(
+    lookup: &Path,
+    pseudonyms: &[String],
+) -> Result<BTreeMap<String, Option<String>>> {
+    let mut entries = read_lookup(lookup)?;
+    Ok(pseudonyms
+        .iter()
+        .map(|pseudonym| (pseudonym.clone(), entries.remove(pseudonym)))
+        .collect())
+}
+
+fn read_lookup(path: &Path) -> Result<BTreeMap<String, String>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let raw =
+        fs::read_to_string(path).with_context(|| format!("Read participant lookup {:?}", path))?;
+    Ok(raw
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(pseudonym, id)| (pseudonym.to_string(), id.to_string()))
+        .collect())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
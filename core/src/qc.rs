@@ -0,0 +1,120 @@
+//! Quality-control checks on one file's calls.
+//!
+//! Sex chromosomes and mitochondria allow only some call patterns: Y is haploid, so a male
+//! sample (one with Y calls) has no heterozygous X calls outside the pseudoautosomal regions,
+//! and mitochondria are haploid too. A few violations are genotyping noise; many mean the file
+//! was mislabelled, merged from two samples, or mangled in export.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::genotype::chromosome_code;
+
+/// PLINK codes of the chromosomes checked.
+const X: u8 = 23;
+const Y: u8 = 24;
+const MT: u8 = 26;
+/// Violations a file needs, as a count and as a share of the chromosome's called calls,
+/// before it is flagged.
+const MIN_VIOLATIONS: usize = 3;
+const MAX_VIOLATION_SHARE: f64 = 0.02;
+/// Called Y calls that mark a sample as male.
+const MIN_Y_CALLS: usize = 20;
+/// Share of heterozygous X calls above which a male sample is flagged.
+const MAX_MALE_X_HETEROZYGOSITY: f64 = 0.05;
+
+/// A biologically impossible pattern in one file's calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QcWarning {
+    /// Heterozygous calls on Y, which is haploid.
+    HeterozygousY,
+    /// Substantial heterozygosity on X in a sample with Y calls.
+    HeterozygousXWithY,
+    /// Heterozygous calls on the haploid mitochondrial genome.
+    DiploidMt,
+}
+
+impl QcWarning {
+    pub const ALL: [QcWarning; 3] = [
+        QcWarning::HeterozygousY,
+        QcWarning::HeterozygousXWithY,
+        QcWarning::DiploidMt,
+    ];
+
+    /// The snake_case name reports and the stats database use.
+    pub fn name(self) -> &'static str {
+        match self {
+            QcWarning::HeterozygousY => "heterozygous_y",
+            QcWarning::HeterozygousXWithY => "heterozygous_x_with_y",
+            QcWarning::DiploidMt => "diploid_mt",
+        }
+    }
+}
+
+impl fmt::Display for QcWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QcWarning::HeterozygousY => write!(f, "heterozygous Y calls"),
+            QcWarning::HeterozygousXWithY => write!(f, "heterozygous X calls alongside Y calls"),
+            QcWarning::DiploidMt => write!(f, "diploid mitochondrial calls"),
+        }
+    }
+}
+
+/// Called (not no-call) X, Y, and MT calls in one file, and how many were heterozygous.
+/// Homozygous two-letter calls are how some vendors write haploid calls, so only calls with
+/// two different alleles count against Y and MT.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SexChromosomeCalls {
+    pub x_calls: usize,
+    pub x_heterozygous: usize,
+    pub y_calls: usize,
+    pub y_heterozygous: usize,
+    pub mt_calls: usize,
+    pub mt_heterozygous: usize,
+}
+
+impl SexChromosomeCalls {
+    /// Tallies one [normalized](crate::alleles::normalize_genotype) call; calls on other
+    /// chromosomes, including the pseudoautosomal `XY`, and no-calls are ignored.
+    pub fn observe(&mut self, chromosome: &str, genotype: &str) {
+        let (calls, heterozygous) = match chromosome_code(chromosome) {
+            Some(X) => (&mut self.x_calls, &mut self.x_heterozygous),
+            Some(Y) => (&mut self.y_calls, &mut self.y_heterozygous),
+            Some(MT) => (&mut self.mt_calls, &mut self.mt_heterozygous),
+            _ => return,
+        };
+        if genotype.is_empty() || genotype.contains(['-', '0']) {
+            return;
+        }
+        *calls += 1;
+        let mut alleles = genotype.chars();
+        let first = alleles.next();
+        if alleles.any(|allele| Some(allele) != first) {
+            *heterozygous += 1;
+        }
+    }
+
+    /// The patterns these calls show, in [`QcWarning::ALL`] order.
+    pub fn warnings(&self) -> Vec<QcWarning> {
+        let male = self.y_calls >= MIN_Y_CALLS;
+        QcWarning::ALL
+            .into_iter()
+            .filter(|warning| match warning {
+                QcWarning::HeterozygousY => violates(self.y_heterozygous, self.y_calls),
+                QcWarning::HeterozygousXWithY => {
+                    male && self.x_calls > 0
+                        && self.x_heterozygous as f64
+                            > MAX_MALE_X_HETEROZYGOSITY * self.x_calls as f64
+                }
+                QcWarning::DiploidMt => violates(self.mt_heterozygous, self.mt_calls),
+            })
+            .collect()
+    }
+}
+
+fn violates(violations: usize, calls: usize) -> bool {
+    violations >= MIN_VIOLATIONS && violations as f64 > MAX_VIOLATION_SHARE * calls as f64
+}
@@ -0,0 +1,298 @@
+//! Temporary copies of raw genotype data, such as decompressed `.gz` inputs.
+//!
+//! A [`StagingArea::secure`] area lives in a directory only the current user can open
+//! (`0700`, files `0600` on Unix), and every staged file is overwritten with zeros and synced
+//! before it is unlinked. Overwriting is best effort: copy-on-write filesystems, snapshots and
+//! SSD wear levelling can keep old blocks, so full-disk encryption remains the real control.
+//!
+//! BGZF inputs (block-gzipped, as written by `bgzip`) are decompressed in parallel on the
+//! current rayon thread pool, since each block is an independent gzip member; plain gzip falls
+//! back to a single stream.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, Read, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use flate2::read::{GzDecoder, MultiGzDecoder};
+use rayon::prelude::*;
+use tempfile::{Builder, TempDir};
+
+use crate::buffers;
+use crate::error::{Context, Result};
+
+/// BGZF blocks (at most 64 KiB compressed each) inflated as one run of a round.
+const BGZF_BLOCKS_PER_THREAD: usize = 64;
+
+/// A private directory holding staged copies; removed (and, if secure, shredded) on drop.
+#[derive(Debug)]
+pub struct StagingArea {
+    dir: TempDir,
+    secure: bool,
+    /// Runs of BGZF blocks inflated at once; 0 for the current rayon pool's size.
+    threads: usize,
+}
+
+/// One staged file. Dropping it deletes the copy, shredding it first in a secure area.
+#[derive(Debug)]
+pub struct StagedFile<'a> {
+    path: PathBuf,
+    secure: bool,
+    _area: PhantomData<&'a StagingArea>,
+}
+
+impl StagingArea {
+    /// A staging directory under the system temp directory with default permissions.
+    pub fn new() -> Result<Self> {
+        let dir = Builder::new()
+            .prefix("biosynth-")
+            .tempdir()
+            .context("Create staging directory")?;
+        Ok(Self {
+            dir,
+            secure: false,
+            threads: 0,
+        })
+    }
+
+    /// A staging directory under `base` (the system temp directory if `None`) that only the
+    /// current user can access, whose files are shredded on cleanup.
+    pub fn secure(base: Option<&Path>) -> Result<Self> {
+        let mut builder = Builder::new();
+        builder.prefix("biosynth-secure-");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            builder.permissions(fs::Permissions::from_mode(0o700));
+        }
+        let dir = match base {
+            Some(base) => builder.tempdir_in(base),
+            None => builder.tempdir(),
+        }
+        .context("Create secure staging directory")?;
+        Ok(Self {
+            dir,
+            secure: true,
+            threads: 0,
+        })
+    }
+
+    /// Runs of BGZF blocks inflated at once on the current rayon thread pool; 0 (the default)
+    /// uses the pool's size, 1 inflates on the calling thread alone. Decompressing inside a
+    /// pool's worker shares that pool, so this bounds the parallelism without adding threads.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+
+    /// Decompresses the gzip file `source` into the area, keeping the inner extension so
+    /// format detection still works.
+    pub fn decompress(&self, source: &Path) -> Result<StagedFile<'_>> {
+        let suffix = source
+            .file_stem()
+            .map(Path::new)
+            .and_then(Path::extension)
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_default();
+        let (file, temp_path) = Builder::new()
+            .prefix("staged-")
+            .suffix(&suffix)
+            .tempfile_in(self.path())
+            .context("Create staged file")?
+            .into_parts();
+        let path = temp_path
+            .keep()
+            .map_err(|err| err.error)
+            .context("Keep staged file")?;
+        let staged = StagedFile {
+            path,
+            secure: self.secure,
+            _area: PhantomData,
+        };
+
+        let input = File::open(source).with_context(|| format!("Failed to open {:?}", source))?;
+        let mut input = buffers::reader(input);
+        let mut output = buffers::writer(file);
+        let threads = match self.threads {
+            0 => rayon::current_num_threads(),
+            threads => threads,
+        };
+        let decompressed = match input.fill_buf() {
+            Ok(header) if threads > 1 && is_bgzf(header) => {
+                decompress_bgzf(&mut input, &mut output, threads)
+            }
+            Ok(_) => io::copy(&mut MultiGzDecoder::new(input), &mut output).map(drop),
+            Err(err) => Err(err),
+        };
+        decompressed
+            .and_then(|()| output.flush())
+            .with_context(|| format!("Decompress {:?}", source))?;
+        Ok(staged)
+    }
+}
+
+impl Drop for StagingArea {
+    fn drop(&mut self) {
+        if !self.secure {
+            return;
+        }
+        // Anything left behind (e.g. after a failed decompress) is shredded before `TempDir`
+        // removes the directory.
+        if let Ok(entries) = fs::read_dir(self.dir.path()) {
+            for entry in entries.flatten() {
+                let _ = shred(&entry.path());
+            }
+        }
+    }
+}
+
+impl StagedFile<'_> {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for StagedFile<'_> {
+    fn drop(&mut self) {
+        let _ = if self.secure {
+            shred(&self.path)
+        } else {
+            fs::remove_file(&self.path).map_err(Into::into)
+        };
+    }
+}
+
+/// Whether `header` starts a gzip member carrying the BGZF block-size (`BC`) extra field.
+fn is_bgzf(header: &[u8]) -> bool {
+    matches!(bgzf_block_size(header), Some(Some(_)))
+}
+
+/// The total size of the gzip member starting `header`: `None` if `header` is too short to
+/// tell, `Some(None)` if it is not a BGZF block.
+fn bgzf_block_size(header: &[u8]) -> Option<Option<usize>> {
+    const FEXTRA: u8 = 0x04;
+    if header.len() < 12 {
+        return None;
+    }
+    if header[..3] != [0x1f, 0x8b, 8] || header[3] & FEXTRA == 0 {
+        return Some(None);
+    }
+    let extra_len = u16::from_le_bytes([header[10], header[11]]) as usize;
+    let extra = header.get(12..12 + extra_len)?;
+    let mut fields = extra;
+    while fields.len() >= 4 {
+        let len = u16::from_le_bytes([fields[2], fields[3]]) as usize;
+        if fields[..2] == *b"BC" && len == 2 && fields.len() >= 6 {
+            return Some(Some(
+                u16::from_le_bytes([fields[4], fields[5]]) as usize + 1,
+            ));
+        }
+        fields = fields.get(4 + len..)?;
+    }
+    Some(None)
+}
+
+/// Reads the next whole BGZF block, or `None` at the end of the input.
+fn read_bgzf_block<R: Read>(input: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut block = vec![0u8; 12];
+    let mut filled = 0;
+    while filled < block.len() {
+        match input.read(&mut block[filled..])? {
+            0 if filled == 0 => return Ok(None),
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            read => filled += read,
+        }
+    }
+    let extra_len = u16::from_le_bytes([block[10], block[11]]) as usize;
+    block.resize(12 + extra_len, 0);
+    input.read_exact(&mut block[12..])?;
+    let size = match bgzf_block_size(&block) {
+        Some(Some(size)) if size >= block.len() => size,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "gzip member without a BGZF block size in a BGZF file",
+            ))
+        }
+    };
+    let header_len = block.len();
+    block.resize(size, 0);
+    input.read_exact(&mut block[header_len..])?;
+    Ok(Some(block))
+}
+
+/// Inflates BGZF blocks in rounds of `threads` runs of consecutive blocks, in parallel on the
+/// current rayon pool, and writes the results in input order.
+fn decompress_bgzf<R: Read, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    threads: usize,
+) -> io::Result<()> {
+    let round = threads * BGZF_BLOCKS_PER_THREAD;
+    loop {
+        let mut blocks = Vec::with_capacity(round);
+        while blocks.len() < round {
+            match read_bgzf_block(input)? {
+                Some(block) => blocks.push(block),
+                None => break,
+            }
+        }
+        if blocks.is_empty() {
+            return Ok(());
+        }
+        let finished = blocks.len() < round;
+        let inflated: Vec<io::Result<Vec<u8>>> = blocks
+            .par_chunks(BGZF_BLOCKS_PER_THREAD)
+            .map(inflate_blocks)
+            .collect();
+        for run in inflated {
+            output.write_all(&run?)?;
+        }
+        if finished {
+            return Ok(());
+        }
+    }
+}
+
+fn inflate_blocks(blocks: &[Vec<u8>]) -> io::Result<Vec<u8>> {
+    let mut inflated = Vec::new();
+    for block in blocks {
+        GzDecoder::new(block.as_slice()).read_to_end(&mut inflated)?;
+    }
+    Ok(inflated)
+}
+
+/// Whether `path` needs decompressing before it can be parsed.
+pub fn is_compressed(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+}
+
+/// Overwrites `path` with zeros, syncs it to disk, then deletes it.
+pub fn shred(path: &Path) -> Result<()> {
+    const CHUNK: usize = 64 * 1024;
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Open {:?} for shredding", path))?;
+    let mut remaining = file.metadata()?.len();
+    let zeros = [0u8; CHUNK];
+    while remaining > 0 {
+        let step = remaining.min(CHUNK as u64) as usize;
+        file.write_all(&zeros[..step])?;
+        remaining -= step as u64;
+    }
+    file.sync_all()
+        .with_context(|| format!("Sync {:?} after shredding", path))?;
+    drop(file);
+    fs::remove_file(path).with_context(|| format!("Remove {:?}", path))?;
+    Ok(())
+}
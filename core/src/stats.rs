@@ -0,0 +1,3134 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use rand::{Rng, RngCore};
+use rusqlite::backup::Progress;
+use rusqlite::types::ValueRef;
+use rusqlite::{
+    params, Connection, DatabaseName, ErrorCode, OpenFlags, OptionalExtension, ToSql, Transaction,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::alleles::{normalize_allele, normalize_variant};
+use crate::archive::{read_entry, split_entry};
+use crate::audit::{AuditAccess, AuditEntry, AuditEvent};
+use crate::buffers::{is_stdin, stdin_reader};
+use crate::builds::{BuildFingerprint, BuildSentinels, Sentinel};
+#[cfg(feature = "columnar")]
+use crate::columnar::ObservationBatches;
+use crate::download::RemoteValidators;
+use crate::encryption::{apply_key, DatabaseKey};
+use crate::error::{BiosynthError, Context, Result};
+use crate::final_report;
+use crate::genotype::{
+    process_file_with_options, process_reader, FileMetadata, ParseOptions, ParseSummary,
+    ParsedFile, Rsid, VariantRecord,
+};
+use crate::merges::RsidMerges;
+use crate::plink::{process_sample, split_sample};
+use crate::policy::{ExportPolicy, ReleasedColumn};
+use crate::positions::PositionReference;
+use crate::privacy::{LaplaceNoise, Suppression};
+use crate::progress::{emit, ProgressEvent};
+use crate::qc::{QcWarning, SexChromosomeCalls};
+use crate::staging::{is_compressed, StagingArea};
+use crate::strand::StrandReference;
+use crate::vcf::{self, VcfValidation};
+
+/// Where [`StatsStore::parse_observations`] reads a genotype file from.
+#[derive(Clone, Copy)]
+enum ParseSource<'a> {
+    File(&'a Path),
+    /// An archive entry, inflated into memory.
+    Memory(&'a [u8]),
+    /// One sample (1-based) of a PLINK fileset.
+    Plink(&'a Path, usize),
+    /// One sample block (1-based) of an Illumina Final Report.
+    Report(&'a Path, usize),
+    /// One sample column (1-based) of a multi-sample VCF.
+    Vcf(&'a Path, usize),
+}
+
+impl ParseSource<'_> {
+    fn process<F>(self, options: &ParseOptions, on_variant: F) -> Result<ParsedFile>
+    where
+        F: FnMut(&VariantRecord, &FileMetadata) -> Result<()>,
+    {
+        match self {
+            ParseSource::File(path) => process_file_with_options(path, options, on_variant),
+            ParseSource::Memory(bytes) => process_reader(bytes, options, on_variant),
+            ParseSource::Plink(bed, sample) => process_sample(bed, sample, options, on_variant),
+            ParseSource::Report(report, sample) => {
+                final_report::process_sample(report, sample, options, on_variant)
+            }
+            ParseSource::Vcf(vcf, sample) => vcf::process_sample(vcf, sample, options, on_variant),
+        }
+    }
+}
+
+/// A file whose samples [`StatsStore::parse_sample_observations`] reads in one pass.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SampleFile<'a> {
+    Report(&'a Path),
+    Vcf(&'a Path),
+}
+
+impl<'a> SampleFile<'a> {
+    /// The file and 1-based sample number a sample path refers to.
+    fn of(path: &'a Path) -> Option<(Self, usize)> {
+        if let Some((report, sample)) = final_report::split_sample(path) {
+            return Some((SampleFile::Report(report), sample));
+        }
+        vcf::split_sample(path).map(|(vcf, sample)| (SampleFile::Vcf(vcf), sample))
+    }
+
+    /// Parses `samples` in one pass; a VCF is checked along the way.
+    fn process<F>(
+        self,
+        samples: &[usize],
+        options: &ParseOptions,
+        on_variant: F,
+    ) -> Result<(Vec<ParsedFile>, Option<VcfValidation>)>
+    where
+        F: FnMut(usize, &VariantRecord, &FileMetadata) -> Result<()>,
+    {
+        match self {
+            SampleFile::Report(report) => Ok((
+                final_report::process_samples(report, samples, options, on_variant)?,
+                None,
+            )),
+            SampleFile::Vcf(vcf) => {
+                let (parsed, validation) = vcf::process_samples(vcf, samples, options, on_variant)?;
+                Ok((parsed, Some(validation)))
+            }
+        }
+    }
+}
+
+/// What [`StatsStore::parse_sample_observations`] parsed.
+#[derive(Debug, Clone)]
+pub struct ParsedSamples {
+    /// One per path, in order.
+    pub files: Vec<ParsedFile>,
+    /// What checking a multi-sample VCF found, when its samples were read together.
+    pub vcf_validation: Option<VcfValidation>,
+}
+
+/// Parses any path the CLI lists as a genotype input, calling `on_variant` for every usable
+/// row: a plain file, [standard input](crate::buffers::STDIN_PATH), an
+/// [archive entry](crate::archive), a `.gz` file (decompressed into a temporary staging
+/// area), or one sample of a [PLINK](crate::plink) fileset, [Final Report](crate::final_report),
+/// or multi-sample [VCF](crate::vcf).
+pub fn process_input<F>(path: &Path, options: &ParseOptions, on_variant: F) -> Result<ParsedFile>
+where
+    F: FnMut(&VariantRecord, &FileMetadata) -> Result<()>,
+{
+    with_source(path, None, options.threads, |source| {
+        source.process(options, on_variant)
+    })
+}
+
+/// Hands `parse` where `path` is read from: archive entries and standard input are read into
+/// memory, `.gz` files are decompressed into `staging` (a temporary area decompressing on
+/// `threads` if `None`), and sample paths resolve to their fileset, report, or VCF.
+fn with_source<T, F>(
+    path: &Path,
+    staging: Option<&StagingArea>,
+    threads: usize,
+    parse: F,
+) -> Result<T>
+where
+    F: FnOnce(ParseSource<'_>) -> Result<T>,
+{
+    let entry = if is_stdin(path) {
+        let mut bytes = Vec::new();
+        stdin_reader()
+            .and_then(|mut stdin| stdin.read_to_end(&mut bytes))
+            .context("Read standard input")?;
+        Some(bytes)
+    } else {
+        split_entry(path)
+            .map(|(archive, name)| read_entry(archive, &name))
+            .transpose()?
+    };
+    let default_staging;
+    let staged = if entry.is_none() && is_compressed(path) {
+        let staging = match staging {
+            Some(staging) => staging,
+            None => {
+                default_staging = StagingArea::new()?.with_threads(threads);
+                &default_staging
+            }
+        };
+        Some(staging.decompress(path)?)
+    } else {
+        None
+    };
+    let source = if let Some(bytes) = &entry {
+        ParseSource::Memory(bytes)
+    } else if let Some((bed, sample)) = split_sample(path) {
+        ParseSource::Plink(bed, sample)
+    } else if let Some((report, sample)) = final_report::split_sample(path) {
+        ParseSource::Report(report, sample)
+    } else if let Some((vcf, sample)) = vcf::split_sample(path) {
+        ParseSource::Vcf(vcf, sample)
+    } else {
+        ParseSource::File(staged.as_ref().map_or(path, |staged| staged.path()))
+    };
+    parse(source)
+}
+
+/// A row of the `rsid_reference` table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceVariant {
+    pub rsid: i64,
+    pub chromosome: String,
+    pub position: i64,
+    pub reference: String,
+    pub alternates: String,
+}
+
+impl ReferenceVariant {
+    /// This row with its alleles normalized and indels left-aligned; see
+    /// [`normalize_variant`].
+    pub fn normalized(&self) -> Self {
+        let normalized =
+            normalize_variant(self.position, &self.reference, self.alternates.split(','));
+        Self {
+            rsid: self.rsid,
+            chromosome: self.chromosome.clone(),
+            position: normalized.position,
+            reference: normalized.reference,
+            alternates: normalized.alternates.join(","),
+        }
+    }
+
+    /// Reference then alternate alleles, [normalized](normalize_allele), if every one is a
+    /// single base.
+    pub fn snp_alleles(&self) -> Option<Vec<char>> {
+        let mut alleles = Vec::new();
+        for allele in std::iter::once(self.reference.as_str())
+            .chain(self.alternates.split(','))
+            .map(normalize_allele)
+            .filter(|allele| !allele.is_empty())
+        {
+            let mut chars = allele.chars();
+            match (chars.next(), chars.next()) {
+                (Some(base), None) => alleles.push(base),
+                _ => return None,
+            }
+        }
+        alleles.dedup();
+        (!alleles.is_empty()).then_some(alleles)
+    }
+}
+
+/// Observation counts for one rsid across ingested files, from [`StatsStore::observed_variants`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObservedVariant {
+    pub rsid: i64,
+    pub chromosome: String,
+    pub position: i64,
+    /// Files with a call at this rsid.
+    pub files: u64,
+    /// Alleles seen in enough files, most common first.
+    pub alleles: Vec<ObservedAllele>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObservedAllele {
+    pub allele: String,
+    /// Files carrying at least one copy.
+    pub carriers: u64,
+    /// Copies as a fraction of every allele called at the rsid.
+    pub frequency: f64,
+}
+
+/// Outcome of [`StatsStore::derive_references`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DerivedReferences {
+    /// New `rsid_reference` rows.
+    pub derived: usize,
+    /// Observed rsids left out because they, or all of their alleles, were seen in too few files.
+    pub excluded_rsids: usize,
+    /// Alleles of frequently observed rsids dropped for the same reason.
+    pub excluded_alleles: usize,
+    /// Rsids already in the reference, which are never overwritten.
+    pub existing: usize,
+    /// The threshold applied, after any aggregate-only minimum.
+    pub min_individuals: u64,
+}
+
+/// Storage behind the genostats commands. [`StatsStore`] is the SQLite implementation;
+/// commands take `&dyn StatsBackend` so other stores (or test doubles) can stand in.
+pub trait StatsBackend: Send + Sync {
+    /// Whether `path` has already been ingested.
+    fn has_file(&self, path: &Path) -> Result<bool>;
+
+    /// Parses `path` and records its variants and file-level stats.
+    fn ingest_file(&self, path: &Path) -> Result<ParsedFile>;
+
+    /// Ingests `paths` in order, reporting each file to `progress`. A failed file does not stop
+    /// the rest; results are returned in input order.
+    fn ingest_files(
+        &self,
+        paths: &[PathBuf],
+        progress: Option<&Sender<ProgressEvent>>,
+    ) -> Vec<Result<ParsedFile>> {
+        paths
+            .iter()
+            .map(|path| {
+                emit(progress, ProgressEvent::Started { path: path.clone() });
+                let result = self.ingest_file(path);
+                emit(
+                    progress,
+                    match &result {
+                        Ok(parsed) => ProgressEvent::Finished {
+                            path: path.clone(),
+                            rows: parsed.summary.variant_count,
+                        },
+                        Err(err) => ProgressEvent::Failed {
+                            path: path.clone(),
+                            error: err.to_string(),
+                        },
+                    },
+                );
+                result
+            })
+            .collect()
+    }
+
+    /// Inserts or replaces reference rows, returning how many were written.
+    fn upsert_references(&self, references: &[ReferenceVariant]) -> Result<usize>;
+
+    fn summary(&self) -> Result<SummaryReport>;
+
+    /// Refreshes query statistics after a bulk load, and with `vacuum` also reclaims free
+    /// space. Stores without either do nothing.
+    fn optimize(&self, _vacuum: bool) -> Result<()> {
+        Ok(())
+    }
+
+    /// Reference rows ordered by chromosome and position.
+    fn all_references(&self, limit: Option<usize>) -> Result<Vec<ReferenceVariant>>;
+
+    /// The rows [`all_references`](Self::all_references) returns, in the same order, handed to
+    /// `on_chunk` at most `chunk_size` at a time; returns how many rows were streamed. The
+    /// default loads every row first; [`StatsStore`] reads them from a cursor instead.
+    fn for_each_reference_chunk(
+        &self,
+        limit: Option<usize>,
+        chunk_size: usize,
+        on_chunk: &mut dyn FnMut(Vec<ReferenceVariant>) -> Result<()>,
+    ) -> Result<usize> {
+        let references = self.all_references(limit)?;
+        for chunk in references.chunks(chunk_size.max(1)) {
+            on_chunk(chunk.to_vec())?;
+        }
+        Ok(references.len())
+    }
+}
+
+/// Opens the stats backend at `path`. Only SQLite is supported today.
+pub fn open_backend(path: &Path) -> Result<Box<dyn StatsBackend>> {
+    Ok(Box::new(StatsStore::connect(path)?))
+}
+
+/// `properties` key holding the salt for [`StatsStore::file_id`].
+const FILE_ID_SALT_KEY: &str = "file_id_salt";
+/// `properties` key holding the digest [`StatsStore::seal_reference`] records.
+const REFERENCE_SHA256_KEY: &str = "reference_sha256";
+/// `properties` key holding the release the reference was fetched at.
+const REFERENCE_VERSION_KEY: &str = "reference_version";
+/// `properties` key holding the ETag the reference was downloaded with.
+const REFERENCE_ETAG_KEY: &str = "reference_etag";
+/// `properties` key holding the `Last-Modified` date the reference was downloaded with.
+const REFERENCE_LAST_MODIFIED_KEY: &str = "reference_last_modified";
+/// `properties` key, present when the validators belong to the reference's compressed copy.
+const REFERENCE_COMPRESSED_KEY: &str = "reference_compressed";
+/// `properties` key holding the suppression threshold of an aggregate-only database.
+const AGGREGATE_ONLY_KEY: &str = "aggregate_only_min_count";
+/// Tables whose row counts [`StatsStore::table_counts`] reports.
+const AUDITED_TABLES: &[&str] = &[
+    "formats",
+    "rsid_reference",
+    "files",
+    "properties",
+    "rsid_observations",
+    "allele_observations",
+    "rsid_merges",
+    "build_sentinels",
+    "file_qc_warnings",
+    "file_formats",
+];
+/// Tables that are empty until a file is ingested.
+const INGESTED_TABLES: &[&str] = &["files", "rsid_observations", "allele_observations"];
+/// `properties` counters that replace the `files` table in aggregate-only mode, suffixed with
+/// `:<consent tag>` for tagged files.
+const FILES_INGESTED_KEY: &str = "files_ingested";
+const SKIPPED_ROWS_KEY: &str = "skipped_rows";
+/// Prefix of the aggregate-only counters of files flagged with each [`QcWarning`].
+const QC_WARNING_KEY_PREFIX: &str = "qc_warning:";
+/// In-memory tables a [`StatsWriter`] on an aggregate-only database writes a run to. As `TEMP`
+/// tables they shadow the observation tables of the same name on the writer's connection, so
+/// observations are written the same way in either mode; [`merge_pending`] moves the cells
+/// that reach the threshold into the database once the run finishes.
+const PENDING_SCHEMA: &str = "
+    PRAGMA temp_store = MEMORY;
+    CREATE TEMP TABLE rsid_observations (
+        rsid INTEGER NOT NULL,
+        consent_tag TEXT NOT NULL DEFAULT '',
+        chromosome TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        files INTEGER NOT NULL,
+        PRIMARY KEY (rsid, consent_tag)
+    );
+    CREATE TEMP TABLE allele_observations (
+        rsid INTEGER NOT NULL,
+        consent_tag TEXT NOT NULL DEFAULT '',
+        allele TEXT NOT NULL,
+        carriers INTEGER NOT NULL,
+        copies INTEGER NOT NULL,
+        PRIMARY KEY (rsid, consent_tag, allele)
+    );
+    CREATE TEMP TABLE pending_counters (
+        key TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );";
+/// The observation tables summed over the consent tags in `?1` (a JSON array), or over every
+/// tag when `?1` is NULL, as CTEs `o` (per rsid) and `a` (per allele).
+const CONSENTED_OBSERVATIONS: &str = "
+    WITH o AS (
+        SELECT rsid, MIN(chromosome) AS chromosome, MIN(position) AS position,
+               SUM(files) AS files
+        FROM rsid_observations
+        WHERE ?1 IS NULL OR consent_tag IN (SELECT value FROM json_each(?1))
+        GROUP BY rsid
+    ),
+    a AS (
+        SELECT rsid, allele, SUM(carriers) AS carriers, SUM(copies) AS copies
+        FROM allele_observations
+        WHERE ?1 IS NULL OR consent_tag IN (SELECT value FROM json_each(?1))
+        GROUP BY rsid, allele
+    )";
+
+/// Whether the database at `path` holds a fetched reference and nothing more: it records the
+/// release it was fetched at, and no file has been ingested into it. Nothing is written, and a
+/// database that cannot be read (e.g. encrypted under another key) does not count.
+pub fn is_fetched_reference(path: &Path) -> bool {
+    let inspect = || -> Result<bool> {
+        // Not read-only: closing a read-write connection removes the WAL sidecars it opened.
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+        apply_key(&conn, DatabaseKey::from_env()?.as_ref(), path)?;
+        let fetched: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM properties WHERE key = ?1)",
+            [REFERENCE_VERSION_KEY],
+            |row| row.get(0),
+        )?;
+        for table in INGESTED_TABLES {
+            let ingested: bool = conn.query_row(
+                &format!("SELECT EXISTS (SELECT 1 FROM {})", table),
+                [],
+                |row| row.get(0),
+            )?;
+            if ingested {
+                return Ok(false);
+            }
+        }
+        Ok(fetched)
+    };
+    inspect().unwrap_or(false)
+}
+
+/// A read pattern of reports and synthetic generation, and the covering index that serves it.
+struct QueryPattern {
+    name: &'static str,
+    used_by: &'static str,
+    /// Query timed before and after the index exists.
+    probe: &'static str,
+    index: &'static str,
+    definition: &'static str,
+}
+
+/// The patterns [`StatsStore::tune_indexes`] audits. The observation tables are `WITHOUT
+/// ROWID`, so their primary keys already cover lookups by rsid.
+const QUERY_PATTERNS: &[QueryPattern] = &[
+    QueryPattern {
+        name: "reference variants by locus",
+        used_by: "synthetic, simulate-cohort, bench, region queries",
+        probe: "SELECT rsid, chromosome, position, reference, alternates
+                FROM rsid_reference ORDER BY chromosome, position",
+        index: "idx_rsid_reference_locus",
+        definition: "CREATE INDEX IF NOT EXISTS idx_rsid_reference_locus
+                     ON rsid_reference(chromosome, position, reference, alternates)",
+    },
+    QueryPattern {
+        name: "allele totals across consent tags",
+        used_by: "synthetic --dp-epsilon, reference-load --from-observations",
+        probe: "SELECT rsid, allele, SUM(carriers), SUM(copies)
+                FROM allele_observations GROUP BY rsid, allele",
+        index: "idx_allele_observations_allele",
+        definition: "CREATE INDEX IF NOT EXISTS idx_allele_observations_allele
+                     ON allele_observations(rsid, allele, carriers, copies)",
+    },
+];
+
+/// Rows per chunk when streaming `rsid_reference`.
+pub const REFERENCE_CHUNK_ROWS: usize = 50_000;
+/// Default number of calls per batch of observations handed to the writer.
+pub const DEFAULT_BATCH_SIZE: usize = 10_000;
+/// Calls an adaptive [`CommitInterval`] starts at, and the bounds it stays within.
+pub const INITIAL_COMMIT_ROWS: usize = 100_000;
+pub const MIN_COMMIT_ROWS: usize = 10_000;
+pub const MAX_COMMIT_ROWS: usize = 2_000_000;
+/// Share of writing time an adaptive interval lets commits take: above it the interval
+/// doubles, below a quarter of it the interval halves.
+const COMMIT_TIME_SHARE: f64 = 0.05;
+/// Rows per multi-row upsert statement, keeping bound parameters well under SQLite's limit.
+pub(crate) const ROWS_PER_STATEMENT: usize = 500;
+/// Rows `ANALYZE` samples per index; enough for good plans without scanning large tables.
+const ANALYSIS_LIMIT: u32 = 1000;
+/// `PRAGMA auto_vacuum` value of a database created for incremental vacuuming.
+const AUTO_VACUUM_INCREMENTAL: i64 = 2;
+/// Attempts at taking or committing a write transaction before giving up, and the first wait
+/// between them (doubled after each).
+const WRITE_ATTEMPTS: u32 = 5;
+const WRITE_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Where SQLite keeps temporary tables and indices (`PRAGMA temp_store`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TempStore {
+    Default,
+    File,
+    Memory,
+}
+
+impl FromStr for TempStore {
+    type Err = BiosynthError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "default" => Ok(TempStore::Default),
+            "file" => Ok(TempStore::File),
+            "memory" => Ok(TempStore::Memory),
+            _ => Err(BiosynthError::InvalidArgument(format!(
+                "Unknown temp store {:?} (expected default, file or memory)",
+                value
+            ))),
+        }
+    }
+}
+
+/// What [`StatsStore::parse_observations`] does with rows repeating an rsid already seen in
+/// the same file, after [remapping](StatsStore::with_rsid_merges).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    /// Counts the first row for each rsid.
+    #[default]
+    First,
+    /// Counts the last row for each rsid; the file is read twice.
+    Last,
+    /// Counts no row for an rsid that repeats; the file is read twice.
+    Skip,
+    /// Fails the file at its first repeated rsid.
+    Error,
+}
+
+impl DuplicatePolicy {
+    /// Whether a row can only be kept once the rest of the file has been seen.
+    fn needs_lookahead(self) -> bool {
+        matches!(self, DuplicatePolicy::Last | DuplicatePolicy::Skip)
+    }
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = BiosynthError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "first" => Ok(DuplicatePolicy::First),
+            "last" => Ok(DuplicatePolicy::Last),
+            "skip" => Ok(DuplicatePolicy::Skip),
+            "error" => Ok(DuplicatePolicy::Error),
+            _ => Err(BiosynthError::InvalidArgument(format!(
+                "Unknown duplicate policy {:?} (expected first, last, skip or error)",
+                value
+            ))),
+        }
+    }
+}
+
+/// Decides, row by row, which rows of one file a [`DuplicatePolicy`] keeps.
+struct DuplicateFilter {
+    policy: DuplicatePolicy,
+    seen: HashSet<Rsid>,
+    /// Rows still to come for each rsid that repeats, for policies that need lookahead.
+    repeated: HashMap<Rsid, usize>,
+}
+
+impl DuplicateFilter {
+    fn keep(&mut self, rsid: &Rsid) -> Result<bool> {
+        if self.policy.needs_lookahead() {
+            return Ok(match self.repeated.get_mut(rsid) {
+                None => true,
+                Some(_) if self.policy == DuplicatePolicy::Skip => false,
+                Some(remaining) => {
+                    *remaining -= 1;
+                    *remaining == 0
+                }
+            });
+        }
+        if self.seen.insert(rsid.clone()) {
+            return Ok(true);
+        }
+        match self.policy {
+            DuplicatePolicy::Error => Err(BiosynthError::Parse(format!(
+                "rsid {} appears more than once",
+                rsid
+            ))),
+            _ => Ok(false),
+        }
+    }
+}
+
+/// What [`StatsStore::parse_observations`] counts for one file as its calls go by.
+struct FileTally {
+    duplicates: DuplicateFilter,
+    duplicate_rows: usize,
+    sex_chromosomes: SexChromosomeCalls,
+    fingerprint: BuildFingerprint,
+    strand_corrections: usize,
+    rsid_remaps: usize,
+    position_checks: usize,
+    position_mismatches: usize,
+}
+
+impl FileTally {
+    /// Adds the counts to `parsed`'s summary, leaving out the rows dropped as duplicates.
+    fn finish(self, parsed: &mut ParsedFile) {
+        let summary = &mut parsed.summary;
+        summary.strand_corrections = self.strand_corrections;
+        summary.rsid_remaps = self.rsid_remaps;
+        summary.position_checks = self.position_checks;
+        summary.position_mismatches = self.position_mismatches;
+        summary.variant_count -= self.duplicate_rows;
+        summary.duplicate_rows = self.duplicate_rows;
+        summary.sentinel_calls = self.fingerprint.calls();
+        summary.detected_build = self.fingerprint.build();
+        summary.mixed_build = self.fingerprint.is_mixed();
+        summary.sex_chromosomes = self.sex_chromosomes;
+    }
+}
+
+/// Performance pragmas for every connection a [`StatsStore`] opens; `None` keeps SQLite's
+/// default. See [`SqliteTuning::bulk_load`] for large ingest runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SqliteTuning {
+    /// Page cache size: pages if positive, KiB if negative.
+    pub cache_size: Option<i64>,
+    /// Bytes of the database file read through memory-mapped I/O.
+    pub mmap_size: Option<u64>,
+    pub temp_store: Option<TempStore>,
+    /// Page size in bytes, a power of two from 512 to 65536. Only applies when the database
+    /// file is created.
+    pub page_size: Option<u32>,
+    /// WAL pages written before SQLite checkpoints automatically; 0 disables it.
+    pub wal_autocheckpoint: Option<u32>,
+}
+
+impl SqliteTuning {
+    /// The bulk-load profile: a 256 MiB page cache, 1 GiB of memory-mapped reads, temporary
+    /// data in memory, 16 KiB pages for new databases, and a checkpoint every 10,000 WAL pages
+    /// instead of every 1,000. It trades memory for fewer small reads, writes and fsyncs.
+    pub fn bulk_load() -> Self {
+        Self {
+            cache_size: Some(-256 * 1024),
+            mmap_size: Some(1 << 30),
+            temp_store: Some(TempStore::Memory),
+            page_size: Some(16 * 1024),
+            wal_autocheckpoint: Some(10_000),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(page_size) = self.page_size {
+            if !(512..=65536).contains(&page_size) || !page_size.is_power_of_two() {
+                return Err(BiosynthError::InvalidArgument(format!(
+                    "Page size {} is not a power of two from 512 to 65536",
+                    page_size
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Consent metadata recorded with every file a [`StatsStore`] ingests.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// The consent under which the source files may be used, e.g. `research-broad`.
+    pub consent_tag: Option<String>,
+    /// Identifier of the dataset the files came from.
+    pub source_dataset: Option<String>,
+}
+
+/// Every file recorded in a database, loaded once so skip checks need no query per file.
+#[derive(Debug, Clone, Default)]
+pub struct RecordedFiles {
+    salt: Option<String>,
+    file_ids: HashSet<[u8; 32]>,
+}
+
+impl RecordedFiles {
+    /// Whether `path` was recorded when this snapshot was taken.
+    pub fn contains(&self, path: &Path) -> bool {
+        self.salt
+            .as_ref()
+            .is_some_and(|salt| self.file_ids.contains(&file_id_digest(salt, path)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.file_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.file_ids.is_empty()
+    }
+}
+
+/// How many calls a [`StatsWriter`] writes before committing. Commits only happen between
+/// files, so a file's rows always land together.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CommitInterval {
+    /// Start at [`INITIAL_COMMIT_ROWS`] and follow the observed commit (fsync) latency:
+    /// slow disks commit less often, fast ones more often to keep the write lock and WAL small.
+    #[default]
+    Adaptive,
+    /// Commit once at least this many calls are pending; `Fixed(1)` commits every file.
+    Fixed(usize),
+}
+
+impl CommitInterval {
+    fn initial_rows(self) -> usize {
+        match self {
+            CommitInterval::Adaptive => INITIAL_COMMIT_ROWS,
+            CommitInterval::Fixed(rows) => rows,
+        }
+    }
+
+    /// The interval to use after a commit that took `committing` following `writing`.
+    fn next_rows(self, rows: usize, writing: Duration, committing: Duration) -> usize {
+        if let CommitInterval::Fixed(rows) = self {
+            return rows;
+        }
+        let budget = writing.as_secs_f64() * COMMIT_TIME_SHARE;
+        let spent = committing.as_secs_f64();
+        if spent > budget {
+            (rows * 2).min(MAX_COMMIT_ROWS)
+        } else if spent * 4.0 < budget {
+            (rows / 2).max(MIN_COMMIT_ROWS)
+        } else {
+            rows
+        }
+    }
+}
+
+/// Handle to a genostats SQLite database; the schema is created on connect.
+///
+/// Ingested files are recorded under a salted SHA-256 of their path, never the path itself, so
+/// a shared database does not reveal directory layouts. The salt lives in the database, so this
+/// hides paths from casual inspection but not from someone testing guessed paths against it.
+///
+/// A database switched to aggregate-only mode ([`StatsStore::enable_aggregate_only`]) keeps no
+/// per-file rows at all, only running totals, and every summary read from it is suppressed at
+/// the recorded threshold. The mode is permanent. A run's observations are held in memory and
+/// only cells whose total reaches the threshold are written, so the file never holds a count
+/// below it.
+///
+/// Observations are kept per consent tag ([`StatsStore::with_provenance`]); a store restricted
+/// with [`StatsStore::with_consent_filter`] reads only observations from files with those tags.
+#[derive(Debug, Clone)]
+pub struct StatsStore {
+    sqlite_path: PathBuf,
+    file_map: Option<PathBuf>,
+    staging: Option<Arc<StagingArea>>,
+    key: Option<DatabaseKey>,
+    provenance: Provenance,
+    consent_filter: Option<Vec<String>>,
+    batch_size: usize,
+    commit_interval: CommitInterval,
+    tuning: SqliteTuning,
+    memory: Option<Arc<MemoryDatabase>>,
+    strand: Option<Arc<StrandReference>>,
+    merges: Option<Arc<RsidMerges>>,
+    positions: Option<Arc<PositionReference>>,
+    duplicates: DuplicatePolicy,
+    sentinels: Option<Arc<BuildSentinels>>,
+    parse: Arc<ParseOptions>,
+    #[cfg(feature = "columnar")]
+    columnar: bool,
+}
+
+/// The `memdb` database every connection of an in-memory [`StatsStore`] shares. SQLite frees
+/// it when its last connection closes, so the store holds one open for its whole lifetime.
+#[derive(Debug)]
+struct MemoryDatabase {
+    uri: String,
+    _anchor: Mutex<Connection>,
+}
+
+/// Distinguishes the in-memory databases of one process.
+static NEXT_MEMORY_DATABASE: AtomicUsize = AtomicUsize::new(0);
+/// Sealed references already checked by this process, as (path, digest), so reopening the same
+/// store does not hash the reference again.
+static VERIFIED_REFERENCES: Mutex<Vec<(PathBuf, String)>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Serialize)]
+pub struct SummaryReport {
+    pub files_processed: usize,
+    pub total_variants: u64,
+    /// `None` once [privatized](SummaryReport::privatize): one file can add any number of
+    /// skipped rows, so no noise scale bounds its contribution.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped_rows: Option<u64>,
+    pub unique_rsids: u64,
+    pub formats_seen: Vec<CategoryCount>,
+    pub builds_seen: Vec<CategoryCount>,
+    pub sqlite_path: PathBuf,
+    /// Privacy budget of the noise applied by [`SummaryReport::privatize`], if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub epsilon: Option<f64>,
+    /// Threshold applied by [`SummaryReport::suppress`], if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_count: Option<u64>,
+    /// Whether the database is in aggregate-only mode.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub aggregate_only: bool,
+    /// QC warning cells withheld for falling below `min_count`.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub suppressed_cells: usize,
+    /// `table.column` names withheld by [`SummaryReport::apply_policy`].
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub redacted: Vec<String>,
+    /// Consent tags file counts were restricted to, if any.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub consent_tags: Vec<String>,
+    /// Files flagged with each [QC warning](crate::qc::QcWarning).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub qc_warnings: Vec<CategoryCount>,
+}
+
+impl SummaryReport {
+    /// Replaces the counts of source files with a differentially private release spending
+    /// `noise`'s whole budget.
+    ///
+    /// One file changes `files_processed` and each QC warning cell by at most 1, so those
+    /// `1 + QcWarning::ALL.len()` counts split the budget evenly, every warning noised whether
+    /// or not any file was flagged. `skipped_rows` is withheld, as its sensitivity is unbounded.
+    /// `unique_rsids` and the format and build counts describe reference rows, not files, and
+    /// are released as they are.
+    pub fn privatize(&mut self, noise: &LaplaceNoise, rng: &mut dyn RngCore) {
+        self.epsilon = Some(noise.epsilon());
+        let noise = noise.split(1 + QcWarning::ALL.len());
+        self.files_processed = noise.noisy_count(self.files_processed as u64, rng) as usize;
+        self.skipped_rows = None;
+        let flagged = std::mem::take(&mut self.qc_warnings);
+        for warning in QcWarning::ALL {
+            let count = flagged
+                .iter()
+                .find(|entry| entry.value.as_deref() == Some(warning.name()))
+                .map_or(0, |entry| entry.count);
+            let count = noise.noisy_count(count, rng);
+            if count > 0 {
+                self.qc_warnings.push(CategoryCount {
+                    value: Some(warning.name().to_string()),
+                    count,
+                });
+            }
+        }
+        self.qc_warnings
+            .sort_by_key(|entry| std::cmp::Reverse(entry.count));
+    }
+
+    /// Drops QC warning cells flagged in fewer files than the threshold. Format and build cells
+    /// count reference rows rather than files, so no threshold on them protects anyone.
+    pub fn suppress(&mut self, suppression: &Suppression) {
+        let before = self.qc_warnings.len();
+        self.qc_warnings
+            .retain(|entry| suppression.allows(entry.count));
+        self.suppressed_cells += before - self.qc_warnings.len();
+        self.min_count = Some(suppression.min_count());
+    }
+
+    /// Enforces an export policy: refuses if a table the summary reads is not allowed, empties
+    /// `formats_seen` / `builds_seen` when `formats.name` / `formats.genome_build` are not, and
+    /// applies the policy's `min_count`. Call after [`privatize`](Self::privatize).
+    pub fn apply_policy(&mut self, policy: &ExportPolicy) -> Result<()> {
+        Self::check_policy(policy, self.epsilon)?;
+        self.redacted = policy.redactions(SUMMARY_RELEASE, SUMMARY_COLUMNS)?;
+        if self.redacted.iter().any(|column| column == "formats.name") {
+            self.formats_seen.clear();
+        }
+        if self
+            .redacted
+            .iter()
+            .any(|column| column == "formats.genome_build")
+        {
+            self.builds_seen.clear();
+        }
+        if let Some(min_count) = policy.min_count {
+            let min_count = min_count.max(self.min_count.unwrap_or(0));
+            self.suppress(&Suppression::new(min_count));
+        }
+        Ok(())
+    }
+
+    /// The refusals of [`apply_policy`](Self::apply_policy), for checking before any work is done.
+    pub fn check_policy(policy: &ExportPolicy, epsilon: Option<f64>) -> Result<()> {
+        policy.check_noise(SUMMARY_RELEASE, epsilon)?;
+        policy.redactions(SUMMARY_RELEASE, SUMMARY_COLUMNS)?;
+        Ok(())
+    }
+}
+
+const SUMMARY_RELEASE: &str = "genostats summary";
+const SUMMARY_COLUMNS: &[ReleasedColumn<'static>] = &[
+    ("files", None),
+    ("rsid_reference", None),
+    ("formats", Some("name")),
+    ("formats", Some("genome_build")),
+];
+
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+/// How one read pattern ran before and after [`StatsStore::tune_indexes`].
+#[derive(Debug, Serialize)]
+pub struct IndexTuning {
+    pub pattern: String,
+    pub used_by: String,
+    pub index: String,
+    /// Whether the index was already there before this run.
+    pub existed: bool,
+    /// Whether this run created it.
+    pub created: bool,
+    /// `EXPLAIN QUERY PLAN` details of the pattern's query.
+    pub plan_before: Vec<String>,
+    pub before_seconds: f64,
+    /// `None` on an audit-only run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan_after: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after_seconds: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryCount {
+    pub value: Option<String>,
+    pub count: u64,
+}
+
+impl StatsStore {
+    /// Opens (creating if needed) the database at `path`, unlocking it with the key from the
+    /// environment if one is set; see [`encryption`](crate::encryption).
+    pub fn connect(path: &Path) -> Result<Self> {
+        Self::connect_with_key(path, DatabaseKey::from_env()?)
+    }
+
+    /// [`connect`](Self::connect) with `tuning` applied to every connection, including the one
+    /// that creates the database.
+    pub fn connect_tuned(path: &Path, tuning: SqliteTuning) -> Result<Self> {
+        Self::open(path, DatabaseKey::from_env()?, tuning)
+    }
+
+    /// [`connect`](Self::connect) with an explicit key, ignoring the environment.
+    pub fn connect_with_key(path: &Path, key: Option<DatabaseKey>) -> Result<Self> {
+        Self::open(path, key, SqliteTuning::default())
+    }
+
+    /// Aggregates in a private in-memory copy of the database at `path` (empty if it does not
+    /// exist yet); nothing reaches `path` until [`persist`](Self::persist). Much faster for
+    /// one-shot batch jobs on machines with the RAM to hold the whole database. Encrypted
+    /// databases are not supported.
+    pub fn connect_in_memory(path: &Path, tuning: SqliteTuning) -> Result<Self> {
+        if DatabaseKey::from_env()?.is_some() {
+            return Err(BiosynthError::InvalidArgument(
+                "An in-memory database cannot be encrypted; unset the database key".into(),
+            ));
+        }
+        tuning.validate()?;
+        create_parent(path)?;
+        let uri = format!(
+            "file:/biosynth-{}-{}?vfs=memdb",
+            std::process::id(),
+            NEXT_MEMORY_DATABASE.fetch_add(1, Ordering::Relaxed)
+        );
+        let anchor = Connection::open(&uri).context("Open in-memory database")?;
+        if path.exists() {
+            // Vacuuming rather than copying pages leaves the WAL flag, which memdb cannot
+            // honour, out of the copy's header.
+            Connection::open(path)
+                .and_then(|source| source.execute("VACUUM INTO ?1", [&uri]))
+                .with_context(|| format!("Load {:?} into memory", path))?;
+        }
+        configure_connection(&anchor, &tuning)
+            .and_then(|()| init_schema(&anchor))
+            .map_err(|source| BiosynthError::Schema {
+                context: format!("Initialise in-memory copy of {:?}", path),
+                source,
+            })?;
+        verify_reference(&anchor, path)?;
+        Ok(Self {
+            memory: Some(Arc::new(MemoryDatabase {
+                uri,
+                _anchor: Mutex::new(anchor),
+            })),
+            ..Self::unopened(path, None, tuning)
+        })
+    }
+
+    fn open(path: &Path, key: Option<DatabaseKey>, tuning: SqliteTuning) -> Result<Self> {
+        tuning.validate()?;
+        create_parent(path)?;
+        let conn =
+            Connection::open(path).with_context(|| format!("Open database at {:?}", path))?;
+        apply_key(&conn, key.as_ref(), path)?;
+        configure_connection(&conn, &tuning)
+            .and_then(|()| init_schema(&conn))
+            .map_err(|source| BiosynthError::Schema {
+                context: format!("Initialise schema in {:?}", path),
+                source,
+            })?;
+        verify_reference(&conn, path)?;
+        Ok(Self::unopened(path, key, tuning))
+    }
+
+    fn unopened(path: &Path, key: Option<DatabaseKey>, tuning: SqliteTuning) -> Self {
+        Self {
+            sqlite_path: path.to_path_buf(),
+            file_map: None,
+            staging: None,
+            key,
+            provenance: Provenance::default(),
+            consent_filter: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            commit_interval: CommitInterval::default(),
+            tuning,
+            memory: None,
+            strand: None,
+            merges: None,
+            positions: None,
+            duplicates: DuplicatePolicy::default(),
+            sentinels: None,
+            parse: Arc::default(),
+            #[cfg(feature = "columnar")]
+            columnar: false,
+        }
+    }
+
+    /// Writes an [in-memory](Self::connect_in_memory) store to its path, atomically replacing
+    /// the database there. Does nothing for a store that writes to the file directly.
+    pub fn persist(&self) -> Result<()> {
+        if self.memory.is_none() {
+            return Ok(());
+        }
+        let parent = match self.sqlite_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let staged = tempfile::Builder::new()
+            .prefix(".biosynth-")
+            .suffix(".sqlite")
+            .tempfile_in(parent)
+            .with_context(|| format!("Create staging file in {:?}", parent))?;
+        if let Ok(existing) = fs::metadata(&self.sqlite_path) {
+            fs::set_permissions(staged.path(), existing.permissions())
+                .with_context(|| format!("Set permissions on {:?}", staged.path()))?;
+        }
+        // Copy page by page: `VACUUM INTO` would write through the memdb VFS and never reach disk.
+        self.open_connection()?
+            .backup(DatabaseName::Main, staged.path(), None::<fn(Progress)>)
+            .with_context(|| format!("Write in-memory database to {:?}", staged.path()))?;
+        staged
+            .persist(&self.sqlite_path)
+            .map_err(|error| error.error)
+            .with_context(|| format!("Replace {:?}", self.sqlite_path))?;
+        Ok(())
+    }
+
+    /// Records `provenance` with every file this store ingests.
+    pub fn with_provenance(mut self, provenance: Provenance) -> Result<Self> {
+        for value in [&provenance.consent_tag, &provenance.source_dataset]
+            .into_iter()
+            .flatten()
+        {
+            if value.trim().is_empty() || value.contains(':') {
+                return Err(BiosynthError::InvalidArgument(format!(
+                    "Consent tags and dataset IDs must be non-empty and contain no ':', got {:?}",
+                    value
+                )));
+            }
+        }
+        self.provenance = provenance;
+        Ok(self)
+    }
+
+    /// Restricts observation reads ([`observed_variants`](Self::observed_variants),
+    /// [`allele_copies`](Self::allele_copies), [`summary`](Self::summary) file counts) to files
+    /// ingested with one of `tags`. Untagged files never match.
+    pub fn with_consent_filter(mut self, tags: Vec<String>) -> Self {
+        self.consent_filter = Some(tags);
+        self
+    }
+
+    /// Hands observations from [`parse_observations`](Self::parse_observations) to the writer in
+    /// runs of this many calls; larger batches mean fewer, bigger statements. Each file is still
+    /// committed as one transaction.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Result<Self> {
+        if batch_size == 0 {
+            return Err(BiosynthError::InvalidArgument(
+                "Batch size must be at least 1".into(),
+            ));
+        }
+        self.batch_size = batch_size;
+        Ok(self)
+    }
+
+    /// Sets how often the [`writer`](Self::writer) commits; adaptive by default.
+    pub fn with_commit_interval(mut self, interval: CommitInterval) -> Result<Self> {
+        if interval == CommitInterval::Fixed(0) {
+            return Err(BiosynthError::InvalidArgument(
+                "Commit interval must be at least 1 row".into(),
+            ));
+        }
+        self.commit_interval = interval;
+        Ok(self)
+    }
+
+    /// Has the [`writer`](Self::writer) hold observations as Arrow batches and write them
+    /// aggregated, one upsert per rsid and allele, when it commits; see
+    /// [`columnar`](crate::columnar). Uses memory in proportion to the commit interval.
+    #[cfg(feature = "columnar")]
+    pub fn with_columnar_aggregation(mut self, enabled: bool) -> Self {
+        self.columnar = enabled;
+        self
+    }
+
+    /// The `?1` parameter of [`CONSENTED_OBSERVATIONS`].
+    fn consent_param(&self) -> Option<String> {
+        self.consent_filter
+            .as_ref()
+            .map(|tags| serde_json::Value::from(tags.clone()).to_string())
+    }
+
+    /// Appends `file_id<TAB>path` for every recorded file to `path`, a local mapping that should
+    /// stay with whoever ran the ingest.
+    pub fn with_file_map(mut self, path: PathBuf) -> Self {
+        self.file_map = Some(path);
+        self
+    }
+
+    /// Decompresses `.gz` inputs into `staging` rather than a default temp directory, e.g. a
+    /// [`StagingArea::secure`] one.
+    pub fn with_staging(mut self, staging: StagingArea) -> Self {
+        self.staging = Some(Arc::new(staging));
+        self
+    }
+
+    /// Complements calls that only match `reference` on the opposite strand before they are
+    /// counted; see [`strand`](crate::strand).
+    pub fn with_strand_correction(mut self, reference: StrandReference) -> Self {
+        self.strand = Some(Arc::new(reference));
+        self
+    }
+
+    /// Counts calls at retired rsids under the rsid `merges` says they now live under.
+    pub fn with_rsid_merges(mut self, merges: RsidMerges) -> Self {
+        self.merges = Some(Arc::new(merges));
+        self
+    }
+
+    /// Compares each call's chromosome and position with the SNPs in `reference`, counting
+    /// disagreements in [`ParseSummary::position_mismatches`].
+    pub fn with_position_check(mut self, reference: PositionReference) -> Self {
+        self.positions = Some(Arc::new(reference));
+        self
+    }
+
+    /// Fingerprints each file's genome build from where it places the rsids in `sentinels`; see
+    /// [`builds`](crate::builds).
+    pub fn with_build_sentinels(mut self, sentinels: BuildSentinels) -> Self {
+        self.sentinels = Some(Arc::new(sentinels));
+        self
+    }
+
+    /// Which row to count when a file repeats an rsid; [`DuplicatePolicy::First`] by default.
+    pub fn with_duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// How [`parse_observations`](Self::parse_observations) parses each file: genotype
+    /// validation, column overrides, extra header aliases, and threads per mapped file.
+    pub fn with_parse_options(mut self, options: ParseOptions) -> Self {
+        self.parse = Arc::new(options);
+        self
+    }
+
+    /// The pseudonymous identifier `path` is recorded under, or `None` before the first file has
+    /// been recorded (no salt exists yet).
+    pub fn file_id(&self, path: &Path) -> Result<Option<String>> {
+        let conn = self.open_connection()?;
+        Ok(file_id_salt(&conn, false)?.map(|salt| hash_file_id(&salt, path)))
+    }
+
+    /// Records a SHA-256 of the `rsid_reference` table that every later open checks, so a
+    /// truncated or altered reference is refused. Changes made through the store keep the
+    /// digest current. Returns the digest.
+    pub fn seal_reference(&self) -> Result<String> {
+        let conn = self.open_connection()?;
+        let digest = reference_digest(&conn)?;
+        store_reference_digest(&conn, &digest)?;
+        Ok(digest)
+    }
+
+    /// Records the release the reference was fetched at, so later runs can name it.
+    pub fn set_reference_version(&self, version: &str) -> Result<()> {
+        let conn = self.open_connection()?;
+        conn.execute(
+            "INSERT INTO properties (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![REFERENCE_VERSION_KEY, version],
+        )
+        .context("Store reference version")?;
+        Ok(())
+    }
+
+    /// The release recorded by [`set_reference_version`](Self::set_reference_version), if any.
+    pub fn reference_version(&self) -> Result<Option<String>> {
+        let conn = self.open_connection()?;
+        conn.query_row(
+            "SELECT value FROM properties WHERE key = ?1",
+            [REFERENCE_VERSION_KEY],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Read reference version")
+    }
+
+    /// Records the HTTP validators the reference was downloaded with, replacing any earlier
+    /// ones, so a later check can ask the server whether it changed.
+    pub fn set_reference_validators(&self, validators: &RemoteValidators) -> Result<()> {
+        let mut conn = self.open_connection()?;
+        let tx = conn.transaction()?;
+        let compressed = validators.compressed.then(|| "1".to_string());
+        let pairs = [
+            (REFERENCE_ETAG_KEY, &validators.etag),
+            (REFERENCE_LAST_MODIFIED_KEY, &validators.last_modified),
+            (REFERENCE_COMPRESSED_KEY, &compressed),
+        ];
+        for (key, value) in pairs {
+            match value {
+                Some(value) => tx.execute(
+                    "INSERT INTO properties (key, value) VALUES (?1, ?2)
+                     ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                    params![key, value],
+                ),
+                None => tx.execute("DELETE FROM properties WHERE key = ?1", [key]),
+            }
+            .context("Store reference validators")?;
+        }
+        tx.commit().context("Store reference validators")?;
+        Ok(())
+    }
+
+    /// The validators recorded by [`set_reference_validators`](Self::set_reference_validators).
+    pub fn reference_validators(&self) -> Result<RemoteValidators> {
+        let conn = self.open_connection()?;
+        let read = |key: &str| -> Result<Option<String>> {
+            conn.query_row(
+                "SELECT value FROM properties WHERE key = ?1",
+                [key],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Read reference validators")
+        };
+        Ok(RemoteValidators {
+            etag: read(REFERENCE_ETAG_KEY)?,
+            last_modified: read(REFERENCE_LAST_MODIFIED_KEY)?,
+            compressed: read(REFERENCE_COMPRESSED_KEY)?.is_some(),
+        })
+    }
+
+    /// Switches the database to aggregate-only mode with threshold `suppression`. Re-enabling
+    /// can raise the threshold but never lower it. Fails if per-file rows were already written.
+    ///
+    /// From then on a [`StatsWriter`] holds each run's observation and QC warning counts in
+    /// memory and, at [`finish`](StatsWriter::finish), writes only those whose total with the
+    /// stored count reaches the threshold. Cells still below it are discarded, not carried over
+    /// to later runs, so ingest a cohort in as few runs as possible.
+    pub fn enable_aggregate_only(&self, suppression: &Suppression) -> Result<()> {
+        let conn = self.open_connection()?;
+        let recorded: i64 = conn.query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0))?;
+        if recorded > 0 {
+            return Err(BiosynthError::InvalidArgument(format!(
+                "{:?} already holds {} per-file records; aggregate-only mode needs a fresh database",
+                self.sqlite_path, recorded
+            )));
+        }
+        conn.execute(
+            "INSERT INTO properties (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET
+                value = MAX(CAST(value AS INTEGER), CAST(excluded.value AS INTEGER))",
+            params![AGGREGATE_ONLY_KEY, suppression.min_count().to_string()],
+        )
+        .context("Enable aggregate-only mode")?;
+        Ok(())
+    }
+
+    /// The enforced threshold if the database is in aggregate-only mode.
+    pub fn aggregate_only(&self) -> Result<Option<Suppression>> {
+        let conn = self.open_connection()?;
+        aggregate_only(&conn)
+    }
+
+    pub fn open_connection(&self) -> Result<Connection> {
+        let conn = match &self.memory {
+            Some(memory) => Connection::open(&memory.uri),
+            None => Connection::open(&self.sqlite_path),
+        }
+        .with_context(|| format!("Open database at {:?}", self.sqlite_path))?;
+        apply_key(&conn, self.key.as_ref(), &self.sqlite_path)?;
+        configure_connection(&conn, &self.tuning).map_err(|source| BiosynthError::Schema {
+            context: format!("Configure database at {:?}", self.sqlite_path),
+            source,
+        })?;
+        Ok(conn)
+    }
+
+    pub fn has_file(&self, path: &Path) -> Result<bool> {
+        if self.aggregate_only()?.is_some() {
+            return Err(BiosynthError::InvalidArgument(
+                "An aggregate-only database does not record which files were ingested".to_string(),
+            ));
+        }
+        let Some(file_id) = self.file_id(path)? else {
+            return Ok(false);
+        };
+        let conn = self.open_connection()?;
+        let found = conn
+            .query_row("SELECT 1 FROM files WHERE file_id = ?1", [file_id], |_| {
+                Ok(())
+            })
+            .optional()
+            .context("Look up recorded file")?;
+        Ok(found.is_some())
+    }
+
+    /// Loads the IDs of every recorded file, for checking many paths at once; see [`has_file`].
+    ///
+    /// [`has_file`]: StatsStore::has_file
+    pub fn recorded_files(&self) -> Result<RecordedFiles> {
+        if self.aggregate_only()?.is_some() {
+            return Err(BiosynthError::InvalidArgument(
+                "An aggregate-only database does not record which files were ingested".to_string(),
+            ));
+        }
+        let conn = self.open_connection()?;
+        let Some(salt) = file_id_salt(&conn, false)? else {
+            return Ok(RecordedFiles::default());
+        };
+        let mut stmt = conn
+            .prepare("SELECT file_id FROM files")
+            .context("Prepare recorded files query")?;
+        let mut rows = stmt.query([]).context("Query recorded files")?;
+        let mut file_ids = HashSet::new();
+        while let Some(row) = rows.next().context("Read recorded file")? {
+            let file_id: String = row.get(0).context("Read recorded file")?;
+            if let Some(digest) = parse_digest(&file_id) {
+                file_ids.insert(digest);
+            }
+        }
+        Ok(RecordedFiles {
+            salt: Some(salt),
+            file_ids,
+        })
+    }
+
+    /// Counts one call towards the per-rsid and per-allele observation tables. Calls without a
+    /// numeric rsid or with no called alleles are ignored.
+    pub fn record_variant_in_tx(
+        tx: &Transaction<'_>,
+        variant: &VariantRecord,
+        _metadata: &FileMetadata,
+        consent_tag: &str,
+    ) -> Result<()> {
+        if aggregate_only(tx)?.is_some() {
+            return Err(BiosynthError::InvalidArgument(
+                "An aggregate-only database only takes observations through a StatsWriter"
+                    .to_string(),
+            ));
+        }
+        let mut observations = Observations::default();
+        observations.push(variant);
+        observations.write(tx, consent_tag)
+    }
+
+    /// Parsing half of [`ingest_file`](StatsBackend::ingest_file): parses `path` (decompressing
+    /// `.gz` inputs into the staging area, and [archive entries](crate::archive) and
+    /// [standard input](crate::buffers::STDIN_PATH) into memory;
+    /// [PLINK](crate::plink), [Final Report](crate::final_report), and [VCF](crate::vcf)
+    /// samples are read from their fileset, report, or VCF)
+    /// and hands its observations to `on_batch` in runs of
+    /// the [batch size](Self::with_batch_size), without touching the database. Calls are
+    /// remapped and strand-corrected first if [`with_rsid_merges`](Self::with_rsid_merges) and
+    /// [`with_strand_correction`](Self::with_strand_correction) are set, and their positions
+    /// checked if [`with_position_check`](Self::with_position_check) is, and the file's build
+    /// fingerprinted if [`with_build_sentinels`](Self::with_build_sentinels) is. Rows repeating
+    /// an rsid are handled by the [duplicate policy](Self::with_duplicate_policy).
+    pub fn parse_observations<F>(&self, path: &Path, on_batch: F) -> Result<ParsedFile>
+    where
+        F: FnMut(Observations) -> Result<()>,
+    {
+        with_source(
+            path,
+            self.staging.as_deref(),
+            self.parse.threads,
+            |source| self.parse_source(source, on_batch),
+        )
+    }
+
+    /// Like [`parse_observations`](Self::parse_observations), for `paths` parsed as one unit.
+    /// The sample paths of a [Final Report](crate::final_report) or multi-sample
+    /// [VCF](crate::vcf) are read in one pass over the file (a VCF is checked along the way),
+    /// and their batches pool the samples' calls; other paths are parsed in turn. A failure
+    /// fails every path.
+    pub fn parse_sample_observations<F>(
+        &self,
+        paths: &[PathBuf],
+        mut on_batch: F,
+    ) -> Result<ParsedSamples>
+    where
+        F: FnMut(Observations) -> Result<()>,
+    {
+        let samples: Option<Vec<(SampleFile<'_>, usize)>> =
+            paths.iter().map(|path| SampleFile::of(path)).collect();
+        let file = samples.as_deref().and_then(|samples| {
+            let (file, _) = samples.first()?;
+            samples
+                .iter()
+                .all(|(other, _)| other == file)
+                .then_some(*file)
+        });
+        let (Some(file), Some(samples)) = (file, samples) else {
+            let files = paths
+                .iter()
+                .map(|path| self.parse_observations(path, &mut on_batch))
+                .collect::<Result<_>>()?;
+            return Ok(ParsedSamples {
+                files,
+                vcf_validation: None,
+            });
+        };
+
+        let samples: Vec<usize> = samples.into_iter().map(|(_, sample)| sample).collect();
+        let repeated = if self.duplicates.needs_lookahead() {
+            self.repeated_sample_rsids(file, &samples)?
+        } else {
+            vec![HashMap::new(); samples.len()]
+        };
+        let mut tallies: Vec<FileTally> = repeated
+            .into_iter()
+            .map(|repeated| self.tally(repeated))
+            .collect();
+        let mut batch = Observations::default();
+        let (mut files, vcf_validation) =
+            file.process(&samples, &self.parse, |sample, variant, _| {
+                self.observe(&mut tallies[sample], variant, &mut batch)?;
+                if batch.len() >= self.batch_size {
+                    on_batch(std::mem::take(&mut batch))?;
+                }
+                Ok(())
+            })?;
+        if !batch.is_empty() {
+            on_batch(batch)?;
+        }
+        for (tally, parsed) in tallies.into_iter().zip(&mut files) {
+            tally.finish(parsed);
+        }
+        Ok(ParsedSamples {
+            files,
+            vcf_validation,
+        })
+    }
+
+    fn parse_source<F>(&self, source: ParseSource<'_>, mut on_batch: F) -> Result<ParsedFile>
+    where
+        F: FnMut(Observations) -> Result<()>,
+    {
+        let repeated = if self.duplicates.needs_lookahead() {
+            self.repeated_rsids(source)?
+        } else {
+            HashMap::new()
+        };
+        let mut tally = self.tally(repeated);
+        let mut batch = Observations::default();
+        let mut parsed = source.process(&self.parse, |variant, _| {
+            self.observe(&mut tally, variant, &mut batch)?;
+            if batch.len() >= self.batch_size {
+                on_batch(std::mem::take(&mut batch))?;
+            }
+            Ok(())
+        })?;
+        if !batch.is_empty() {
+            on_batch(batch)?;
+        }
+        tally.finish(&mut parsed);
+        Ok(parsed)
+    }
+
+    /// A fresh tally for one file, whose rsids repeating `repeated` times are known ahead if
+    /// the duplicate policy needs them.
+    fn tally(&self, repeated: HashMap<Rsid, usize>) -> FileTally {
+        FileTally {
+            duplicates: DuplicateFilter {
+                policy: self.duplicates,
+                seen: HashSet::new(),
+                repeated,
+            },
+            duplicate_rows: 0,
+            sex_chromosomes: SexChromosomeCalls::default(),
+            fingerprint: BuildFingerprint::default(),
+            strand_corrections: 0,
+            rsid_remaps: 0,
+            position_checks: 0,
+            position_mismatches: 0,
+        }
+    }
+
+    /// Remaps, checks, and strand-corrects `variant`, counting what was done in `tally`, and
+    /// adds it to `batch` unless the duplicate policy drops it.
+    fn observe(
+        &self,
+        tally: &mut FileTally,
+        variant: &VariantRecord,
+        batch: &mut Observations,
+    ) -> Result<()> {
+        let mut fixed = None;
+        if let Some(rsid) = self.remapped_rsid(&variant.rsid) {
+            tally.rsid_remaps += 1;
+            fixed = Some(VariantRecord {
+                rsid,
+                ..variant.clone()
+            });
+        }
+        let record = fixed.as_ref().unwrap_or(variant);
+        if !tally.duplicates.keep(&record.rsid)? {
+            tally.duplicate_rows += 1;
+            return Ok(());
+        }
+        tally
+            .sex_chromosomes
+            .observe(&record.chromosome, &record.genotype);
+        if let Some(build) = self.sentinels.as_deref().and_then(|sentinels| {
+            sentinels.build_of(&record.rsid, &record.chromosome, record.position)
+        }) {
+            tally.fingerprint.observe(build);
+        }
+        if let Some(agrees) = self.positions.as_deref().and_then(|positions| {
+            positions.agrees(&record.rsid, &record.chromosome, record.position)
+        }) {
+            tally.position_checks += 1;
+            if !agrees {
+                tally.position_mismatches += 1;
+            }
+        }
+        if let Some(genotype) = self
+            .strand
+            .as_deref()
+            .and_then(|strand| strand.corrected_genotype(&record.rsid, &record.genotype))
+        {
+            tally.strand_corrections += 1;
+            fixed = Some(VariantRecord {
+                genotype,
+                ..record.clone()
+            });
+        }
+        batch.push(fixed.as_ref().unwrap_or(variant));
+        Ok(())
+    }
+
+    /// `rsid` under its current ID, if the loaded [merges](Self::with_rsid_merges) retired it.
+    fn remapped_rsid(&self, rsid: &Rsid) -> Option<Rsid> {
+        self.merges.as_deref().and_then(|merges| merges.remap(rsid))
+    }
+
+    /// How many rows of `source` name each rsid that appears more than once, after remapping.
+    fn repeated_rsids(&self, source: ParseSource<'_>) -> Result<HashMap<Rsid, usize>> {
+        let mut rows: HashMap<Rsid, usize> = HashMap::new();
+        source.process(&self.parse, |variant, _| {
+            self.count_rsid(&mut rows, variant);
+            Ok(())
+        })?;
+        rows.retain(|_, rows| *rows > 1);
+        Ok(rows)
+    }
+
+    /// [`repeated_rsids`](Self::repeated_rsids) for each of `file`'s `samples`, read in one
+    /// pass.
+    fn repeated_sample_rsids(
+        &self,
+        file: SampleFile<'_>,
+        samples: &[usize],
+    ) -> Result<Vec<HashMap<Rsid, usize>>> {
+        let mut rows: Vec<HashMap<Rsid, usize>> = vec![HashMap::new(); samples.len()];
+        file.process(samples, &self.parse, |sample, variant, _| {
+            self.count_rsid(&mut rows[sample], variant);
+            Ok(())
+        })?;
+        for rows in &mut rows {
+            rows.retain(|_, rows| *rows > 1);
+        }
+        Ok(rows)
+    }
+
+    fn count_rsid(&self, rows: &mut HashMap<Rsid, usize>, variant: &VariantRecord) {
+        let rsid = self
+            .remapped_rsid(&variant.rsid)
+            .unwrap_or_else(|| variant.rsid.clone());
+        *rows.entry(rsid).or_default() += 1;
+    }
+
+    /// Opens the connection all of a run's parsed files should be written through. Call
+    /// [`StatsWriter::finish`] to commit the last files and see any error doing so.
+    pub fn writer(&self) -> Result<StatsWriter<'_>> {
+        let conn = self.open_connection()?;
+        let threshold = aggregate_only(&conn)?;
+        if threshold.is_some() {
+            conn.execute_batch(PENDING_SCHEMA)
+                .context("Create in-memory aggregate-only tables")?;
+        }
+        Ok(StatsWriter {
+            store: self,
+            conn,
+            threshold,
+            interval: self.commit_interval,
+            commit_rows: self.commit_interval.initial_rows(),
+            pending: None,
+            #[cfg(feature = "columnar")]
+            columnar: self.columnar.then(ObservationBatches::default),
+        })
+    }
+
+    /// Observed rsids and alleles, leaving out any seen in fewer than `min_individuals` files
+    /// (or the aggregate-only threshold, if higher): rare observed variants identify people.
+    pub fn observed_variants(&self, min_individuals: u64) -> Result<Vec<ObservedVariant>> {
+        let conn = self.open_connection()?;
+        let min_individuals = self.effective_min_individuals(&conn, min_individuals)?;
+        let mut stmt = conn.prepare(&format!(
+            "{}
+             SELECT o.rsid, o.chromosome, o.position, o.files, a.allele, a.carriers,
+                    CAST(a.copies AS REAL) / SUM(a.copies) OVER (PARTITION BY a.rsid)
+             FROM o JOIN a ON a.rsid = o.rsid
+             WHERE o.files >= ?2
+             ORDER BY o.rsid, a.copies DESC, a.allele",
+            CONSENTED_OBSERVATIONS
+        ))?;
+        let mut rows = stmt.query(params![self.consent_param(), min_individuals as i64])?;
+        let mut variants: Vec<ObservedVariant> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let rsid: i64 = row.get(0)?;
+            if variants.last().is_none_or(|last| last.rsid != rsid) {
+                variants.push(ObservedVariant {
+                    rsid,
+                    chromosome: row.get(1)?,
+                    position: row.get(2)?,
+                    files: row.get::<_, i64>(3)? as u64,
+                    alleles: Vec::new(),
+                });
+            }
+            let carriers = row.get::<_, i64>(5)? as u64;
+            if carriers >= min_individuals {
+                let variant = variants.last_mut().expect("pushed above");
+                variant.alleles.push(ObservedAllele {
+                    allele: row.get(4)?,
+                    carriers,
+                    frequency: row.get(6)?,
+                });
+            }
+        }
+        variants.retain(|variant| !variant.alleles.is_empty());
+        Ok(variants)
+    }
+
+    /// Refreshes the query planner's statistics after a bulk load: `ANALYZE` over a sample of
+    /// each index, then `PRAGMA optimize`. With `vacuum`, free pages go back to the filesystem,
+    /// incrementally if the database uses `auto_vacuum = INCREMENTAL` and otherwise through a
+    /// full `VACUUM`, which rewrites the whole file.
+    pub fn optimize(&self, vacuum: bool) -> Result<()> {
+        let conn = self.open_connection()?;
+        conn.execute_batch(&format!(
+            "PRAGMA analysis_limit = {}; ANALYZE; PRAGMA optimize;",
+            ANALYSIS_LIMIT
+        ))
+        .context("Analyze database")?;
+        if !vacuum {
+            return Ok(());
+        }
+        let auto_vacuum: i64 = conn
+            .pragma_query_value(None, "auto_vacuum", |row| row.get(0))
+            .context("Read auto_vacuum mode")?;
+        if auto_vacuum == AUTO_VACUUM_INCREMENTAL {
+            // Each step frees one page.
+            let mut stmt = conn.prepare("PRAGMA incremental_vacuum")?;
+            let mut rows = stmt.query([])?;
+            while rows.next().context("Vacuum database")?.is_some() {}
+        } else {
+            conn.execute_batch("VACUUM").context("Vacuum database")?;
+        }
+        Ok(())
+    }
+
+    /// Audits the queries reports and synthetic generation run, timing each and reading its
+    /// plan. With `create`, adds the covering index a pattern lacks and times it again.
+    pub fn tune_indexes(&self, create: bool) -> Result<Vec<IndexTuning>> {
+        let conn = self.open_connection()?;
+        QUERY_PATTERNS
+            .iter()
+            .map(|pattern| {
+                let existed = conn
+                    .query_row(
+                        "SELECT 1 FROM sqlite_master WHERE type = 'index' AND name = ?1",
+                        [pattern.index],
+                        |_| Ok(()),
+                    )
+                    .optional()
+                    .with_context(|| format!("Look up index {}", pattern.index))?
+                    .is_some();
+                let plan_before = query_plan(&conn, pattern.probe)?;
+                let before_seconds = time_query(&conn, pattern.probe)?;
+                let created = create && !existed;
+                if created {
+                    conn.execute_batch(pattern.definition)
+                        .with_context(|| format!("Create index {}", pattern.index))?;
+                }
+                let (plan_after, after_seconds) = if create {
+                    (
+                        Some(query_plan(&conn, pattern.probe)?),
+                        Some(time_query(&conn, pattern.probe)?),
+                    )
+                } else {
+                    (None, None)
+                };
+                Ok(IndexTuning {
+                    pattern: pattern.name.to_string(),
+                    used_by: pattern.used_by.to_string(),
+                    index: pattern.index.to_string(),
+                    existed,
+                    created,
+                    plan_before,
+                    before_seconds,
+                    plan_after,
+                    after_seconds,
+                })
+            })
+            .collect()
+    }
+
+    /// Adds `rsid_reference` rows for observed rsids the reference lacks, excluding variants
+    /// seen in fewer than `min_individuals` files. The most common observed allele stands in
+    /// for the reference allele, since genotype calls do not say which allele is reference.
+    pub fn derive_references(&self, min_individuals: u64) -> Result<DerivedReferences> {
+        let mut conn = self.open_connection()?;
+        let min_individuals = self.effective_min_individuals(&conn, min_individuals)?;
+        let consent = self.consent_param();
+        let observed: i64 = conn.query_row(
+            &format!("{} SELECT COUNT(*) FROM o", CONSENTED_OBSERVATIONS),
+            params![consent],
+            |row| row.get(0),
+        )?;
+        let excluded_alleles: i64 = conn.query_row(
+            &format!(
+                "{} SELECT COUNT(*) FROM a JOIN o ON o.rsid = a.rsid
+                 WHERE o.files >= ?2 AND a.carriers < ?2",
+                CONSENTED_OBSERVATIONS
+            ),
+            params![consent, min_individuals as i64],
+            |row| row.get(0),
+        )?;
+        let variants = self.observed_variants(min_individuals)?;
+
+        let mut outcome = DerivedReferences {
+            excluded_rsids: observed as usize - variants.len(),
+            excluded_alleles: excluded_alleles as usize,
+            min_individuals,
+            ..DerivedReferences::default()
+        };
+        let tx = conn.transaction()?;
+        for variant in &variants {
+            let (reference, alternates) = variant.alleles.split_first().expect("non-empty");
+            let alternates = alternates
+                .iter()
+                .map(|allele| allele.allele.as_str())
+                .collect::<Vec<_>>()
+                .join(",");
+            let inserted = tx
+                .prepare_cached(
+                    "INSERT OR IGNORE INTO rsid_reference
+                        (rsid, chromosome, position, reference, alternates)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                )?
+                .execute(params![
+                    variant.rsid,
+                    variant.chromosome,
+                    variant.position,
+                    reference.allele,
+                    alternates,
+                ])?;
+            if inserted > 0 {
+                outcome.derived += 1;
+            } else {
+                outcome.existing += 1;
+            }
+        }
+        reseal_reference(&tx)?;
+        tx.commit()?;
+        Ok(outcome)
+    }
+
+    /// Raw allele copy counts per observed rsid. These are exact and unsuppressed; release them
+    /// only through a mechanism such as [`DpAccounting`](crate::privacy::DpAccounting).
+    pub fn allele_copies(&self) -> Result<HashMap<i64, HashMap<String, u64>>> {
+        let conn = self.open_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "{} SELECT rsid, allele, copies FROM a",
+            CONSENTED_OBSERVATIONS
+        ))?;
+        let mut rows = stmt.query(params![self.consent_param()])?;
+        let mut copies: HashMap<i64, HashMap<String, u64>> = HashMap::new();
+        while let Some(row) = rows.next()? {
+            copies
+                .entry(row.get(0)?)
+                .or_default()
+                .insert(row.get(1)?, row.get::<_, i64>(2)? as u64);
+        }
+        Ok(copies)
+    }
+
+    /// Source files carrying each allele per observed rsid. These are exact and unsuppressed,
+    /// like [`allele_copies`](Self::allele_copies).
+    pub fn allele_carriers(&self) -> Result<HashMap<i64, HashMap<String, u64>>> {
+        let conn = self.open_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "{} SELECT rsid, allele, carriers FROM a",
+            CONSENTED_OBSERVATIONS
+        ))?;
+        let mut rows = stmt.query(params![self.consent_param()])?;
+        let mut carriers: HashMap<i64, HashMap<String, u64>> = HashMap::new();
+        while let Some(row) = rows.next()? {
+            carriers
+                .entry(row.get(0)?)
+                .or_default()
+                .insert(row.get(1)?, row.get::<_, i64>(2)? as u64);
+        }
+        Ok(carriers)
+    }
+
+    /// Source files observing each rsid. These are exact and unsuppressed, like
+    /// [`allele_copies`](Self::allele_copies).
+    pub fn rsid_file_counts(&self) -> Result<HashMap<i64, u64>> {
+        let conn = self.open_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "{} SELECT rsid, files FROM o",
+            CONSENTED_OBSERVATIONS
+        ))?;
+        let mut rows = stmt.query(params![self.consent_param()])?;
+        let mut files = HashMap::new();
+        while let Some(row) = rows.next()? {
+            files.insert(row.get(0)?, row.get::<_, i64>(1)? as u64);
+        }
+        Ok(files)
+    }
+
+    fn effective_min_individuals(&self, conn: &Connection, min_individuals: u64) -> Result<u64> {
+        let enforced = aggregate_only(conn)?.map_or(0, |suppression| suppression.min_count());
+        Ok(min_individuals.max(enforced).max(1))
+    }
+
+    pub fn record_file(
+        &self,
+        conn: &Connection,
+        metadata: &FileMetadata,
+        summary: &ParseSummary,
+        duration: Duration,
+        path: &Path,
+    ) -> Result<()> {
+        let format_id = record_vendor_format(conn, metadata)?;
+        if aggregate_only(conn)?.is_some() {
+            if self.file_map.is_some() {
+                return Err(BiosynthError::InvalidArgument(
+                    "A file map cannot be written for an aggregate-only database".to_string(),
+                ));
+            }
+            // Held with the run's observations until the writer finishes.
+            let tag = self.provenance.consent_tag.as_deref();
+            add_to_pending_counter(conn, &tagged_key(FILES_INGESTED_KEY, tag), 1)?;
+            add_to_pending_counter(
+                conn,
+                &tagged_key(SKIPPED_ROWS_KEY, tag),
+                summary.skipped_rows as i64,
+            )?;
+            for warning in summary.sex_chromosomes.warnings() {
+                add_to_pending_counter(conn, &tagged_key(&qc_warning_key(warning), tag), 1)?;
+            }
+            return Ok(());
+        }
+
+        let salt = file_id_salt(conn, true)?.expect("salt is created on demand");
+        let file_id = hash_file_id(&salt, path);
+        let ingested_at = unix_now();
+        conn.execute(
+            "INSERT OR REPLACE INTO files
+                (file_id, variant_count, skipped_rows, duration_ms, ingested_at, consent_tag,
+                 source_dataset)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                file_id,
+                summary.variant_count as i64,
+                summary.skipped_rows as i64,
+                duration.as_millis() as i64,
+                ingested_at,
+                self.provenance.consent_tag,
+                self.provenance.source_dataset,
+            ],
+        )
+        .context("Record ingested file")?;
+        conn.execute(
+            "DELETE FROM file_qc_warnings WHERE file_id = ?1",
+            [&file_id],
+        )
+        .context("Clear file QC warnings")?;
+        for warning in summary.sex_chromosomes.warnings() {
+            conn.execute(
+                "INSERT INTO file_qc_warnings (file_id, warning) VALUES (?1, ?2)",
+                params![file_id, warning.name()],
+            )
+            .context("Record file QC warning")?;
+        }
+        match format_id {
+            Some(format_id) => conn.execute(
+                "INSERT OR REPLACE INTO file_formats (file_id, format_id, vendor_version)
+                 VALUES (?1, ?2, ?3)",
+                params![file_id, format_id, metadata.vendor_version],
+            ),
+            None => conn.execute("DELETE FROM file_formats WHERE file_id = ?1", [&file_id]),
+        }
+        .context("Record file format")?;
+
+        if let Some(map_path) = &self.file_map {
+            let mut map = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(map_path)
+                .with_context(|| format!("Open file map {:?}", map_path))?;
+            map.write_all(format!("{}\t{}\n", file_id, path.display()).as_bytes())
+                .with_context(|| format!("Write file map {:?}", map_path))?;
+        }
+        Ok(())
+    }
+
+    /// Inserts or replaces one reference row, [normalized](ReferenceVariant::normalized).
+    pub fn upsert_reference_in_tx(
+        tx: &Transaction<'_>,
+        reference: &ReferenceVariant,
+    ) -> Result<()> {
+        let reference = reference.normalized();
+        tx.execute(
+            "INSERT INTO rsid_reference (rsid, chromosome, position, reference, alternates)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(rsid) DO UPDATE SET
+                chromosome=excluded.chromosome,
+                position=excluded.position,
+                reference=excluded.reference,
+                alternates=excluded.alternates",
+            params![
+                reference.rsid,
+                reference.chromosome,
+                reference.position,
+                reference.reference,
+                reference.alternates,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn summary(&self) -> Result<SummaryReport> {
+        let conn = self.open_connection()?;
+        let unique_rsids: i64 = conn
+            .query_row("SELECT COUNT(*) FROM rsid_reference", [], |row| row.get(0))
+            .unwrap_or(0);
+
+        let formats_seen = self.collect_category_counts(
+            &conn,
+            "SELECT f.name, COUNT(rr.rsid)
+             FROM formats f
+             LEFT JOIN rsid_reference rr ON rr.format_id = f.id
+             GROUP BY f.id
+             ORDER BY COUNT(rr.rsid) DESC",
+        )?;
+        let builds_seen = self.collect_category_counts(
+            &conn,
+            "SELECT genome_build, COUNT(*) FROM formats GROUP BY genome_build ORDER BY COUNT(*) DESC",
+        )?;
+        let total_variants = formats_seen.iter().map(|entry| entry.count).sum();
+        let enforced = aggregate_only(&conn)?;
+        let (files_processed, skipped_rows): (i64, i64) = match (&enforced, &self.consent_filter) {
+            (Some(_), None) => (
+                read_integer_property(&conn, FILES_INGESTED_KEY)?.unwrap_or(0),
+                read_integer_property(&conn, SKIPPED_ROWS_KEY)?.unwrap_or(0),
+            ),
+            (Some(_), Some(tags)) => {
+                let (mut files, mut skipped) = (0, 0);
+                for tag in tags {
+                    let tag = Some(tag.as_str());
+                    files += read_integer_property(&conn, &tagged_key(FILES_INGESTED_KEY, tag))?
+                        .unwrap_or(0);
+                    skipped += read_integer_property(&conn, &tagged_key(SKIPPED_ROWS_KEY, tag))?
+                        .unwrap_or(0);
+                }
+                (files, skipped)
+            }
+            (None, _) => conn.query_row(
+                "SELECT COUNT(*), COALESCE(SUM(skipped_rows), 0) FROM files
+                 WHERE ?1 IS NULL OR consent_tag IN (SELECT value FROM json_each(?1))",
+                params![self.consent_param()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?,
+        };
+
+        let qc_warnings = match (&enforced, &self.consent_filter) {
+            (Some(_), filter) => {
+                let tags: Vec<Option<&str>> = match filter {
+                    Some(tags) => tags.iter().map(|tag| Some(tag.as_str())).collect(),
+                    None => vec![None],
+                };
+                let mut counts = Vec::new();
+                for warning in QcWarning::ALL {
+                    let mut count = 0;
+                    for tag in &tags {
+                        count += read_integer_property(
+                            &conn,
+                            &tagged_key(&qc_warning_key(warning), *tag),
+                        )?
+                        .unwrap_or(0);
+                    }
+                    if count > 0 {
+                        counts.push(CategoryCount {
+                            value: Some(warning.name().to_string()),
+                            count: count as u64,
+                        });
+                    }
+                }
+                counts
+            }
+            (None, _) => {
+                let mut stmt = conn.prepare(
+                    "SELECT w.warning, COUNT(*) FROM file_qc_warnings w
+                     JOIN files f ON f.file_id = w.file_id
+                     WHERE ?1 IS NULL OR f.consent_tag IN (SELECT value FROM json_each(?1))
+                     GROUP BY w.warning
+                     ORDER BY COUNT(*) DESC",
+                )?;
+                let counts = stmt
+                    .query_map(params![self.consent_param()], |row| {
+                        Ok(CategoryCount {
+                            value: row.get(0)?,
+                            count: row.get::<_, i64>(1)? as u64,
+                        })
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                counts
+            }
+        };
+
+        let mut report = SummaryReport {
+            files_processed: files_processed as usize,
+            total_variants,
+            skipped_rows: Some(skipped_rows as u64),
+            unique_rsids: unique_rsids as u64,
+            formats_seen,
+            builds_seen,
+            sqlite_path: self.sqlite_path.clone(),
+            epsilon: None,
+            min_count: None,
+            aggregate_only: enforced.is_some(),
+            suppressed_cells: 0,
+            redacted: Vec::new(),
+            consent_tags: self.consent_filter.clone().unwrap_or_default(),
+            qc_warnings,
+        };
+        if let Some(suppression) = &enforced {
+            report.suppress(suppression);
+        }
+        Ok(report)
+    }
+
+    pub fn all_references(&self, limit: Option<usize>) -> Result<Vec<ReferenceVariant>> {
+        let mut references = Vec::new();
+        self.for_each_reference_chunk(limit, REFERENCE_CHUNK_ROWS, |chunk| {
+            references.extend(chunk);
+            Ok(())
+        })?;
+        Ok(references)
+    }
+
+    /// Streams [`all_references`](Self::all_references) through one cursor, `chunk_size` rows
+    /// at a time, so the table never has to fit in memory. Returns how many rows were streamed.
+    pub fn for_each_reference_chunk<F>(
+        &self,
+        limit: Option<usize>,
+        chunk_size: usize,
+        mut on_chunk: F,
+    ) -> Result<usize>
+    where
+        F: FnMut(Vec<ReferenceVariant>) -> Result<()>,
+    {
+        let chunk_size = chunk_size.max(1);
+        let conn = self.open_connection()?;
+        let mut base_query = String::from(
+            "SELECT rsid, chromosome, position, reference, alternates
+             FROM rsid_reference
+             ORDER BY chromosome, position",
+        );
+        let mut stmt = if limit.is_some() {
+            base_query.push_str(" LIMIT ?1");
+            conn.prepare(&base_query)?
+        } else {
+            conn.prepare(&base_query)?
+        };
+        let mut rows = if let Some(limit) = limit {
+            stmt.query([limit as i64])?
+        } else {
+            stmt.query([])?
+        };
+        let mut chunk = Vec::new();
+        let mut streamed = 0;
+        while let Some(row) = rows.next()? {
+            chunk.push(ReferenceVariant {
+                rsid: row.get(0)?,
+                chromosome: row.get(1)?,
+                position: row.get(2)?,
+                reference: row.get(3)?,
+                alternates: row.get(4)?,
+            });
+            if chunk.len() == chunk_size {
+                streamed += chunk.len();
+                on_chunk(std::mem::take(&mut chunk))?;
+            }
+        }
+        if !chunk.is_empty() {
+            streamed += chunk.len();
+            on_chunk(chunk)?;
+        }
+        Ok(streamed)
+    }
+
+    /// Replaces the `rsid_merges` table's rows for the retired rsids in `merges`, returning
+    /// how many were written.
+    pub fn store_rsid_merges(&self, merges: &RsidMerges) -> Result<usize> {
+        let mut conn = self.open_connection()?;
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx
+                .prepare("INSERT OR REPLACE INTO rsid_merges (retired, current) VALUES (?1, ?2)")?;
+            for (retired, current) in merges.iter() {
+                stmt.execute(params![retired, current])?;
+            }
+        }
+        tx.commit()?;
+        Ok(merges.len())
+    }
+
+    /// The `rsid_merges` table, for [`with_rsid_merges`](Self::with_rsid_merges).
+    pub fn rsid_merges(&self) -> Result<RsidMerges> {
+        let conn = self.open_connection()?;
+        let mut stmt = conn.prepare("SELECT retired, current FROM rsid_merges")?;
+        let mut rows = stmt.query([])?;
+        let mut merges = RsidMerges::default();
+        while let Some(row) = rows.next()? {
+            merges.insert(row.get(0)?, row.get(1)?);
+        }
+        Ok(merges)
+    }
+
+    /// Replaces the `build_sentinels` table's rows for the rsids and builds in `sentinels`,
+    /// returning how many were written.
+    pub fn store_build_sentinels(&self, sentinels: &BuildSentinels) -> Result<usize> {
+        let mut conn = self.open_connection()?;
+        let tx = conn.transaction()?;
+        let mut stored = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO build_sentinels (rsid, genome_build, chromosome, position)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (rsid, sentinel) in sentinels.iter() {
+                stmt.execute(params![
+                    rsid,
+                    sentinel.build.to_string(),
+                    sentinel.chromosome,
+                    sentinel.position
+                ])?;
+                stored += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(stored)
+    }
+
+    /// The `build_sentinels` table, for [`with_build_sentinels`](Self::with_build_sentinels).
+    pub fn build_sentinels(&self) -> Result<BuildSentinels> {
+        let conn = self.open_connection()?;
+        let mut stmt =
+            conn.prepare("SELECT rsid, genome_build, chromosome, position FROM build_sentinels")?;
+        let mut rows = stmt.query([])?;
+        let mut sentinels = BuildSentinels::default();
+        while let Some(row) = rows.next()? {
+            let build: String = row.get(1)?;
+            sentinels.insert(
+                row.get(0)?,
+                Sentinel {
+                    build: build.parse()?,
+                    chromosome: row.get(2)?,
+                    position: row.get(3)?,
+                },
+            );
+        }
+        Ok(sentinels)
+    }
+
+    /// The SNP alleles of the `rsid_reference` table, for
+    /// [`with_strand_correction`](Self::with_strand_correction).
+    pub fn strand_reference(&self) -> Result<StrandReference> {
+        let mut reference = StrandReference::default();
+        self.for_each_reference_chunk(None, REFERENCE_CHUNK_ROWS, |chunk| {
+            reference.extend(&chunk);
+            Ok(())
+        })?;
+        Ok(reference)
+    }
+
+    /// The SNP coordinates of the `rsid_reference` table, for
+    /// [`with_position_check`](Self::with_position_check).
+    pub fn position_reference(&self) -> Result<PositionReference> {
+        let mut reference = PositionReference::default();
+        self.for_each_reference_chunk(None, REFERENCE_CHUNK_ROWS, |chunk| {
+            reference.extend(&chunk);
+            Ok(())
+        })?;
+        Ok(reference)
+    }
+
+    /// Row count of every table the audit log tracks.
+    pub fn table_counts(&self) -> Result<BTreeMap<String, i64>> {
+        let conn = self.open_connection()?;
+        AUDITED_TABLES
+            .iter()
+            .map(|table| {
+                let count: i64 = conn
+                    .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                        row.get(0)
+                    })
+                    .with_context(|| format!("Count rows in {}", table))?;
+                Ok((table.to_string(), count))
+            })
+            .collect()
+    }
+
+    /// Appends `event` to the audit log, returning its id.
+    pub fn append_audit(&self, event: &AuditEvent) -> Result<i64> {
+        let conn = self.open_connection()?;
+        conn.execute(
+            "INSERT INTO audit_log
+                (timestamp, user, command, args, access, succeeded, row_deltas)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                unix_now(),
+                event.user,
+                event.command,
+                serde_json::to_string(&event.args).context("Encode audit args")?,
+                event.access.as_str(),
+                event.succeeded,
+                serde_json::to_string(&event.row_deltas).context("Encode audit row deltas")?,
+            ],
+        )
+        .context("Append audit log entry")?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Audit entries, newest first.
+    pub fn audit_log(&self, limit: Option<usize>) -> Result<Vec<AuditEntry>> {
+        let conn = self.open_connection()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, timestamp, user, command, args, access, succeeded, row_deltas
+             FROM audit_log ORDER BY id DESC LIMIT ?1",
+        )?;
+        let limit = limit.map_or(-1, |limit| limit as i64);
+        let rows = stmt.query_map([limit], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, bool>(6)?,
+                row.get::<_, String>(7)?,
+            ))
+        })?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (id, timestamp, user, command, args, access, succeeded, row_deltas) =
+                row.context("Read audit log")?;
+            entries.push(AuditEntry {
+                id,
+                timestamp,
+                event: AuditEvent {
+                    user,
+                    command,
+                    args: serde_json::from_str(&args).context("Decode audit args")?,
+                    access: AuditAccess::from_column(&access),
+                    succeeded,
+                    row_deltas: serde_json::from_str(&row_deltas)
+                        .context("Decode audit row deltas")?,
+                },
+            });
+        }
+        Ok(entries)
+    }
+
+    fn collect_category_counts(
+        &self,
+        conn: &Connection,
+        query: &str,
+    ) -> Result<Vec<CategoryCount>> {
+        let mut stmt = conn.prepare(query)?;
+        let mut rows = stmt.query([])?;
+        let mut results = Vec::new();
+        while let Some(row) = rows.next()? {
+            let value: Option<String> = row.get(0)?;
+            let count: i64 = row.get(1)?;
+            results.push(CategoryCount {
+                value,
+                count: count as u64,
+            });
+        }
+        Ok(results)
+    }
+}
+
+impl StatsBackend for StatsStore {
+    fn has_file(&self, path: &Path) -> Result<bool> {
+        StatsStore::has_file(self, path)
+    }
+
+    fn ingest_file(&self, path: &Path) -> Result<ParsedFile> {
+        let started = Instant::now();
+        let mut writer = self.writer()?;
+        let parsed =
+            writer.write_file(path, started, |sink| self.parse_observations(path, sink))?;
+        writer.finish()?;
+        Ok(parsed)
+    }
+
+    fn upsert_references(&self, references: &[ReferenceVariant]) -> Result<usize> {
+        let mut conn = self.open_connection()?;
+        let tx = conn.transaction()?;
+        for reference in references {
+            StatsStore::upsert_reference_in_tx(&tx, reference)?;
+        }
+        reseal_reference(&tx)?;
+        tx.commit()?;
+        Ok(references.len())
+    }
+
+    fn summary(&self) -> Result<SummaryReport> {
+        StatsStore::summary(self)
+    }
+
+    fn optimize(&self, vacuum: bool) -> Result<()> {
+        StatsStore::optimize(self, vacuum)
+    }
+
+    fn all_references(&self, limit: Option<usize>) -> Result<Vec<ReferenceVariant>> {
+        StatsStore::all_references(self, limit)
+    }
+
+    fn for_each_reference_chunk(
+        &self,
+        limit: Option<usize>,
+        chunk_size: usize,
+        on_chunk: &mut dyn FnMut(Vec<ReferenceVariant>) -> Result<()>,
+    ) -> Result<usize> {
+        StatsStore::for_each_reference_chunk(self, limit, chunk_size, on_chunk)
+    }
+}
+
+/// Observation rows for a run of calls, written as multi-row upserts by [`StatsWriter`].
+///
+/// Rows are not merged in memory: a file that lists an rsid twice counts twice, exactly as with
+/// one statement per call.
+#[derive(Debug, Default)]
+pub struct Observations {
+    calls: usize,
+    /// `(rsid, chromosome, position)` per call.
+    pub(crate) rsids: Vec<(i64, Arc<str>, i64)>,
+    /// `(rsid, allele, copies)` per distinct allele of each call.
+    pub(crate) alleles: Vec<(i64, String, i64)>,
+}
+
+impl Observations {
+    /// Adds one call. Calls without a numeric rsid or with no called alleles are ignored.
+    pub fn push(&mut self, variant: &VariantRecord) {
+        let Some(rsid) = variant.rsid.number() else {
+            return;
+        };
+        let mut alleles: Vec<char> = variant
+            .genotype
+            .chars()
+            .filter(char::is_ascii_alphabetic)
+            .map(|allele| allele.to_ascii_uppercase())
+            .collect();
+        if alleles.is_empty() {
+            return;
+        }
+        alleles.sort_unstable();
+
+        self.rsids
+            .push((rsid, Arc::clone(&variant.chromosome), variant.position));
+        for run in alleles.chunk_by(|a, b| a == b) {
+            self.alleles
+                .push((rsid, run[0].to_string(), run.len() as i64));
+        }
+        self.calls += 1;
+    }
+
+    /// Calls held.
+    pub fn len(&self) -> usize {
+        self.calls
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls == 0
+    }
+
+    /// Upserts every row under `consent_tag`.
+    fn write(&self, conn: &Connection, consent_tag: &str) -> Result<()> {
+        for chunk in self.rsids.chunks(ROWS_PER_STATEMENT) {
+            let values = value_rows(chunk.len(), 3, |base| {
+                format!("(?{}, ?1, ?{}, ?{}, 1)", base, base + 1, base + 2)
+            });
+            let mut params: Vec<&dyn ToSql> = vec![&consent_tag];
+            for (rsid, chromosome, position) in chunk {
+                params.extend([rsid as &dyn ToSql, chromosome, position]);
+            }
+            conn.prepare_cached(&format!(
+                "INSERT INTO rsid_observations (rsid, consent_tag, chromosome, position, files)
+                 VALUES {}
+                 ON CONFLICT(rsid, consent_tag) DO UPDATE SET files = files + 1",
+                values
+            ))?
+            .execute(params.as_slice())?;
+        }
+        for chunk in self.alleles.chunks(ROWS_PER_STATEMENT) {
+            let values = value_rows(chunk.len(), 3, |base| {
+                format!("(?{}, ?1, ?{}, 1, ?{})", base, base + 1, base + 2)
+            });
+            let mut params: Vec<&dyn ToSql> = vec![&consent_tag];
+            for (rsid, allele, copies) in chunk {
+                params.extend([rsid as &dyn ToSql, allele, copies]);
+            }
+            conn.prepare_cached(&format!(
+                "INSERT INTO allele_observations (rsid, consent_tag, allele, carriers, copies)
+                 VALUES {}
+                 ON CONFLICT(rsid, consent_tag, allele) DO UPDATE SET
+                    carriers = carriers + 1,
+                    copies = copies + excluded.copies",
+                values
+            ))?
+            .execute(params.as_slice())?;
+        }
+        Ok(())
+    }
+}
+
+/// A single connection that writes parsed files. Parsing on many threads and writing through
+/// one writer avoids the lock contention of a connection per worker.
+///
+/// Files share a transaction until the [`CommitInterval`] is reached; each file is written
+/// under its own savepoint, so a failed file leaves the others in place. Dropping the writer
+/// commits what is pending, ignoring errors.
+pub struct StatsWriter<'a> {
+    store: &'a StatsStore,
+    conn: Connection,
+    /// The aggregate-only threshold, if the run is held in memory until [`finish`].
+    ///
+    /// [`finish`]: StatsWriter::finish
+    threshold: Option<Suppression>,
+    interval: CommitInterval,
+    commit_rows: usize,
+    pending: Option<PendingCommit>,
+    /// Observations of the files pending commit, in columnar mode.
+    #[cfg(feature = "columnar")]
+    columnar: Option<ObservationBatches>,
+}
+
+/// The open transaction of a [`StatsWriter`].
+struct PendingCommit {
+    began: Instant,
+    rows: usize,
+    files: usize,
+}
+
+impl StatsWriter<'_> {
+    /// Writes the observations `produce` hands to its sink, then records `path`: nothing from
+    /// the file is kept if `produce` or any write fails. `started` is when work on the file
+    /// began, for its recorded duration.
+    pub fn write_file<F>(&mut self, path: &Path, started: Instant, produce: F) -> Result<ParsedFile>
+    where
+        F: FnOnce(&mut dyn FnMut(Observations) -> Result<()>) -> Result<ParsedFile>,
+    {
+        let parsed = self.write_samples(&[path.to_path_buf()], started, |sink| {
+            Ok(ParsedSamples {
+                files: vec![produce(sink)?],
+                vcf_validation: None,
+            })
+        })?;
+        Ok(parsed
+            .files
+            .into_iter()
+            .next()
+            .expect("one file was written"))
+    }
+
+    /// Like [`write_file`](Self::write_file), for `paths` parsed as one unit, as
+    /// [`StatsStore::parse_sample_observations`] does: each path is recorded with its
+    /// [`ParsedFile`] and an even share of the unit's duration, and a failure keeps nothing
+    /// from any of them.
+    pub fn write_samples<F>(
+        &mut self,
+        paths: &[PathBuf],
+        started: Instant,
+        produce: F,
+    ) -> Result<ParsedSamples>
+    where
+        F: FnOnce(&mut dyn FnMut(Observations) -> Result<()>) -> Result<ParsedSamples>,
+    {
+        self.begin()?;
+        let conn = &self.conn;
+        conn.execute_batch("SAVEPOINT ingest_file")
+            .context("Start file savepoint")?;
+        let consent_tag = self
+            .store
+            .provenance
+            .consent_tag
+            .as_deref()
+            .unwrap_or_default();
+        let mut rows = 0;
+        #[cfg(feature = "columnar")]
+        let (columnar, mut batches) = (self.columnar.is_some(), ObservationBatches::default());
+        let result = produce(&mut |observations| {
+            rows += observations.len();
+            #[cfg(feature = "columnar")]
+            if columnar {
+                return batches.push(&observations);
+            }
+            observations.write(conn, consent_tag)
+        })
+        .and_then(|parsed| {
+            if parsed.files.len() != paths.len() {
+                return Err(BiosynthError::InvalidArgument(format!(
+                    "Parsed {} files for {} paths",
+                    parsed.files.len(),
+                    paths.len()
+                )));
+            }
+            let duration = started.elapsed() / paths.len().max(1) as u32;
+            for (path, file) in paths.iter().zip(&parsed.files) {
+                self.store
+                    .record_file(conn, &file.metadata, &file.summary, duration, path)?;
+            }
+            conn.execute_batch("RELEASE ingest_file")
+                .context("Release file savepoint")?;
+            Ok(parsed)
+        });
+
+        let parsed = match result {
+            Ok(parsed) => parsed,
+            Err(err) if conn.is_autocommit() => {
+                // SQLite rolled back the whole transaction, earlier files included.
+                let lost = self.pending.take().map_or(0, |pending| pending.files);
+                #[cfg(feature = "columnar")]
+                if let Some(held) = &mut self.columnar {
+                    held.clear();
+                }
+                return Err(match err {
+                    BiosynthError::Database { context, source } if lost > 0 => {
+                        BiosynthError::Database {
+                            context: format!(
+                                "{} (rolling back the {} files written since the last commit)",
+                                context, lost
+                            ),
+                            source,
+                        }
+                    }
+                    err => err,
+                });
+            }
+            Err(err) => {
+                conn.execute_batch("ROLLBACK TO ingest_file; RELEASE ingest_file")
+                    .context("Roll back failed ingest")?;
+                return Err(err);
+            }
+        };
+        #[cfg(feature = "columnar")]
+        if let Some(held) = &mut self.columnar {
+            held.append(batches);
+        }
+        let pending = self.pending.as_mut().expect("transaction is open");
+        pending.rows += rows;
+        pending.files += paths.len();
+        if pending.rows >= self.commit_rows {
+            self.commit()?;
+        }
+        Ok(parsed)
+    }
+
+    /// Calls the writer currently commits after.
+    pub fn commit_rows(&self) -> usize {
+        self.commit_rows
+    }
+
+    /// Commits the files still pending. On an aggregate-only database, this is when the run's
+    /// counts that reach the threshold are written; dropping the writer instead discards them.
+    pub fn finish(mut self) -> Result<()> {
+        if let Some(threshold) = self.threshold {
+            self.begin()?;
+            let merged = self
+                .write_held()
+                .and_then(|()| merge_pending(&self.conn, threshold.min_count()));
+            if let Err(err) = merged {
+                let _ = self.conn.execute_batch("ROLLBACK");
+                self.pending = None;
+                return Err(err);
+            }
+        }
+        self.commit()
+    }
+
+    fn begin(&mut self) -> Result<()> {
+        if self.pending.is_none() {
+            execute_with_retry(&self.conn, "BEGIN IMMEDIATE")?;
+            self.pending = Some(PendingCommit {
+                began: Instant::now(),
+                rows: 0,
+                files: 0,
+            });
+        }
+        Ok(())
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        let Some(pending) = self.pending.take() else {
+            return Ok(());
+        };
+        let written = self.write_held();
+        let writing = pending.began.elapsed();
+        let committing = Instant::now();
+        let result = written.and_then(|()| execute_with_retry(&self.conn, "COMMIT"));
+        if result.is_err() && !self.conn.is_autocommit() {
+            self.conn
+                .execute_batch("ROLLBACK")
+                .context("Roll back failed commit")?;
+        }
+        result?;
+        self.commit_rows = self
+            .interval
+            .next_rows(self.commit_rows, writing, committing.elapsed());
+        Ok(())
+    }
+
+    /// Writes the observations held in columnar mode, aggregated; a no-op otherwise.
+    fn write_held(&mut self) -> Result<()> {
+        #[cfg(feature = "columnar")]
+        if let Some(held) = self.columnar.as_mut().filter(|held| !held.is_empty()) {
+            let consent_tag = self
+                .store
+                .provenance
+                .consent_tag
+                .as_deref()
+                .unwrap_or_default();
+            let result = held
+                .aggregate()
+                .and_then(|aggregated| aggregated.write(&self.conn, consent_tag));
+            held.clear();
+            return result;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for StatsWriter<'_> {
+    fn drop(&mut self) {
+        let _ = self.commit();
+    }
+}
+
+/// Runs `BEGIN IMMEDIATE` or `COMMIT`, retrying with backoff if another process still holds
+/// the write lock once the busy timeout has run out. A `COMMIT` that fails as busy leaves the
+/// transaction open, so it can be retried.
+fn execute_with_retry(conn: &Connection, sql: &str) -> Result<()> {
+    let mut delay = WRITE_RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        match conn.execute_batch(sql) {
+            Err(rusqlite::Error::SqliteFailure(err, _))
+                if err.code == ErrorCode::DatabaseBusy && attempt < WRITE_ATTEMPTS =>
+            {
+                std::thread::sleep(delay);
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result.with_context(|| format!("Run {}", sql)),
+        }
+    }
+}
+
+/// `rows` comma-separated `VALUES` tuples of `width` parameters each, after the shared `?1`.
+pub(crate) fn value_rows(rows: usize, width: usize, row: impl Fn(usize) -> String) -> String {
+    (0..rows)
+        .map(|idx| row(2 + idx * width))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn init_schema(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS formats (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            genome_build TEXT
+        );
+        CREATE TABLE IF NOT EXISTS rsid_reference (
+            rsid INTEGER PRIMARY KEY,
+            format_id INTEGER NOT NULL DEFAULT 1,
+            chromosome TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            reference TEXT NOT NULL,
+            alternates TEXT NOT NULL,
+            FOREIGN KEY(format_id) REFERENCES formats(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_rsid_reference_format ON rsid_reference(format_id);
+        CREATE TABLE IF NOT EXISTS properties (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS files (
+            file_id TEXT PRIMARY KEY,
+            variant_count INTEGER NOT NULL,
+            skipped_rows INTEGER NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            ingested_at INTEGER NOT NULL,
+            consent_tag TEXT,
+            source_dataset TEXT
+        );
+        CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            timestamp INTEGER NOT NULL,
+            user TEXT,
+            command TEXT NOT NULL,
+            args TEXT NOT NULL,
+            access TEXT NOT NULL,
+            succeeded INTEGER NOT NULL,
+            row_deltas TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS rsid_observations (
+            rsid INTEGER NOT NULL,
+            consent_tag TEXT NOT NULL DEFAULT '',
+            chromosome TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            files INTEGER NOT NULL,
+            PRIMARY KEY (rsid, consent_tag)
+        ) WITHOUT ROWID;
+        CREATE TABLE IF NOT EXISTS allele_observations (
+            rsid INTEGER NOT NULL,
+            consent_tag TEXT NOT NULL DEFAULT '',
+            allele TEXT NOT NULL,
+            carriers INTEGER NOT NULL,
+            copies INTEGER NOT NULL,
+            PRIMARY KEY (rsid, consent_tag, allele)
+        ) WITHOUT ROWID;
+        CREATE TABLE IF NOT EXISTS rsid_merges (
+            retired INTEGER PRIMARY KEY,
+            current INTEGER NOT NULL
+        ) WITHOUT ROWID;
+        CREATE TABLE IF NOT EXISTS file_qc_warnings (
+            file_id TEXT NOT NULL,
+            warning TEXT NOT NULL,
+            PRIMARY KEY (file_id, warning)
+        ) WITHOUT ROWID;
+        CREATE TABLE IF NOT EXISTS file_formats (
+            file_id TEXT PRIMARY KEY,
+            format_id INTEGER NOT NULL,
+            vendor_version TEXT,
+            FOREIGN KEY(format_id) REFERENCES formats(id) ON DELETE CASCADE
+        ) WITHOUT ROWID;
+        CREATE TABLE IF NOT EXISTS build_sentinels (
+            rsid INTEGER NOT NULL,
+            genome_build TEXT NOT NULL,
+            chromosome INTEGER NOT NULL,
+            position INTEGER NOT NULL,
+            PRIMARY KEY (rsid, genome_build)
+        ) WITHOUT ROWID;
+        CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+        BEGIN
+            SELECT RAISE(ABORT, 'audit_log is append-only');
+        END;
+        CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+        BEGIN
+            SELECT RAISE(ABORT, 'audit_log is append-only');
+        END;
+        "#,
+    )?;
+    seed_formats(conn)?;
+    Ok(())
+}
+
+/// The `formats` row of the file's [vendor](crate::vendors), added on first sight; a build
+/// named in the header replaces the one recorded. `None` when no vendor was recognized.
+fn record_vendor_format(conn: &Connection, metadata: &FileMetadata) -> Result<Option<i64>> {
+    let Some(vendor) = metadata.vendor else {
+        return Ok(None);
+    };
+    conn.execute(
+        "INSERT INTO formats (name, genome_build) VALUES (?1, ?2)
+         ON CONFLICT(name) DO UPDATE SET
+            genome_build = COALESCE(excluded.genome_build, formats.genome_build)",
+        params![
+            vendor.name(),
+            metadata.declared_build().map(|build| build.to_string())
+        ],
+    )
+    .context("Record vendor format")?;
+    let format_id = conn
+        .query_row(
+            "SELECT id FROM formats WHERE name = ?1",
+            [vendor.name()],
+            |row| row.get(0),
+        )
+        .context("Look up vendor format")?;
+    Ok(Some(format_id))
+}
+
+fn seed_formats(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO formats (id, name, genome_build) VALUES (?1, ?2, ?3)",
+        params![1_i64, "dynamic_dna", "GRCh38"],
+    )?;
+    Ok(())
+}
+
+/// The `EXPLAIN QUERY PLAN` detail lines of `sql`.
+fn query_plan(conn: &Connection, sql: &str) -> Result<Vec<String>> {
+    let mut stmt = conn
+        .prepare(&format!("EXPLAIN QUERY PLAN {}", sql))
+        .context("Prepare query plan")?;
+    let details = stmt
+        .query_map([], |row| row.get::<_, String>(3))
+        .context("Explain query")?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .context("Read query plan")?;
+    Ok(details)
+}
+
+/// Seconds taken to step through every row of `sql`.
+fn time_query(conn: &Connection, sql: &str) -> Result<f64> {
+    let started = Instant::now();
+    let mut stmt = conn.prepare(sql).context("Prepare timed query")?;
+    let mut rows = stmt.query([]).context("Run timed query")?;
+    while rows.next().context("Read timed query")?.is_some() {}
+    Ok(started.elapsed().as_secs_f64())
+}
+
+fn create_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.exists() {
+            fs::create_dir_all(parent).with_context(|| format!("Create {:?}", parent))?;
+        }
+    }
+    Ok(())
+}
+
+fn configure_connection(conn: &Connection, tuning: &SqliteTuning) -> rusqlite::Result<()> {
+    // The page size is fixed once the file is written, which switching to WAL does.
+    if let Some(page_size) = tuning.page_size {
+        conn.pragma_update(None, "page_size", page_size)?;
+    }
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    // Other processes may be writing to the same database; wait for their locks.
+    conn.busy_timeout(Duration::from_secs(30))?;
+    if let Some(cache_size) = tuning.cache_size {
+        conn.pragma_update(None, "cache_size", cache_size)?;
+    }
+    if let Some(mmap_size) = tuning.mmap_size {
+        conn.pragma_update(None, "mmap_size", mmap_size as i64)?;
+    }
+    if let Some(temp_store) = tuning.temp_store {
+        let mode = match temp_store {
+            TempStore::Default => "DEFAULT",
+            TempStore::File => "FILE",
+            TempStore::Memory => "MEMORY",
+        };
+        conn.pragma_update(None, "temp_store", mode)?;
+    }
+    if let Some(pages) = tuning.wal_autocheckpoint {
+        conn.pragma_update(None, "wal_autocheckpoint", pages)?;
+    }
+    Ok(())
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+fn aggregate_only(conn: &Connection) -> Result<Option<Suppression>> {
+    let min_count = read_integer_property(conn, AGGREGATE_ONLY_KEY)?;
+    Ok(min_count.map(|count| Suppression::new(count.max(0) as u64)))
+}
+
+/// Adds to a counter held until an aggregate-only run finishes; see [`PENDING_SCHEMA`].
+fn add_to_pending_counter(conn: &Connection, key: &str, amount: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO temp.pending_counters (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = value + excluded.value",
+        params![key, amount],
+    )
+    .with_context(|| format!("Update {} counter", key))?;
+    Ok(())
+}
+
+/// Moves an aggregate-only run from the in-memory [`PENDING_SCHEMA`] tables into the database:
+/// observation and QC warning cells whose total with the stored count reaches `min_count`, and
+/// the file and skipped-row totals. Everything else is dropped with the connection.
+fn merge_pending(conn: &Connection, min_count: u64) -> Result<()> {
+    let min_count = min_count as i64;
+    conn.execute(
+        "INSERT INTO main.rsid_observations (rsid, consent_tag, chromosome, position, files)
+         SELECT p.rsid, p.consent_tag, p.chromosome, p.position, p.files
+         FROM temp.rsid_observations p
+         LEFT JOIN main.rsid_observations m
+             ON m.rsid = p.rsid AND m.consent_tag = p.consent_tag
+         WHERE p.files + COALESCE(m.files, 0) >= ?1
+         ON CONFLICT(rsid, consent_tag) DO UPDATE SET files = files + excluded.files",
+        [min_count],
+    )
+    .context("Write aggregate-only rsid observations")?;
+    conn.execute(
+        "INSERT INTO main.allele_observations (rsid, consent_tag, allele, carriers, copies)
+         SELECT p.rsid, p.consent_tag, p.allele, p.carriers, p.copies
+         FROM temp.allele_observations p
+         LEFT JOIN main.allele_observations m
+             ON m.rsid = p.rsid AND m.consent_tag = p.consent_tag AND m.allele = p.allele
+         WHERE p.carriers + COALESCE(m.carriers, 0) >= ?1
+         ON CONFLICT(rsid, consent_tag, allele) DO UPDATE SET
+            carriers = carriers + excluded.carriers,
+            copies = copies + excluded.copies",
+        [min_count],
+    )
+    .context("Write aggregate-only allele observations")?;
+    conn.execute(
+        "INSERT INTO main.properties (key, value)
+         SELECT p.key, p.value
+         FROM temp.pending_counters p
+         LEFT JOIN main.properties m ON m.key = p.key
+         WHERE substr(p.key, 1, length(?2)) != ?2
+            OR p.value + COALESCE(CAST(m.value AS INTEGER), 0) >= ?1
+         ON CONFLICT(key) DO UPDATE SET value = CAST(value AS INTEGER) + excluded.value",
+        params![min_count, QC_WARNING_KEY_PREFIX],
+    )
+    .context("Write aggregate-only counters")?;
+    conn.execute_batch(
+        "DELETE FROM temp.rsid_observations;
+         DELETE FROM temp.allele_observations;
+         DELETE FROM temp.pending_counters;",
+    )
+    .context("Clear aggregate-only run")?;
+    Ok(())
+}
+
+/// The aggregate-only counter of files flagged with `warning`.
+fn qc_warning_key(warning: QcWarning) -> String {
+    format!("{}{}", QC_WARNING_KEY_PREFIX, warning.name())
+}
+
+/// `key`, suffixed with the consent tag if there is one.
+fn tagged_key(key: &str, consent_tag: Option<&str>) -> String {
+    match consent_tag {
+        Some(tag) => format!("{}:{}", key, tag),
+        None => key.to_string(),
+    }
+}
+
+/// SHA-256 over every `rsid_reference` row in rsid order, one tab-separated line per row.
+fn reference_digest(conn: &Connection) -> Result<String> {
+    let mut stmt = conn.prepare(
+        "SELECT rsid, format_id, chromosome, position, reference, alternates
+         FROM rsid_reference ORDER BY rsid",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut hasher = Sha256::new();
+    let mut line = Vec::new();
+    while let Some(row) = rows.next()? {
+        line.clear();
+        for column in 0..6 {
+            if column > 0 {
+                line.push(b'\t');
+            }
+            match row.get_ref(column)? {
+                ValueRef::Integer(value) => write!(line, "{}", value)?,
+                ValueRef::Real(value) => write!(line, "{}", value)?,
+                ValueRef::Text(bytes) | ValueRef::Blob(bytes) => line.extend_from_slice(bytes),
+                ValueRef::Null => {}
+            }
+        }
+        line.push(b'\n');
+        hasher.update(&line);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+fn stored_reference_digest(conn: &Connection) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT value FROM properties WHERE key = ?1",
+        [REFERENCE_SHA256_KEY],
+        |row| row.get(0),
+    )
+    .optional()
+    .context("Read reference digest")
+}
+
+fn store_reference_digest(conn: &Connection, digest: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO properties (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![REFERENCE_SHA256_KEY, digest],
+    )
+    .context("Store reference digest")?;
+    Ok(())
+}
+
+/// Refuses a sealed reference whose rows no longer match the recorded digest.
+fn verify_reference(conn: &Connection, path: &Path) -> Result<()> {
+    let Some(expected) = stored_reference_digest(conn)? else {
+        return Ok(());
+    };
+    let checked = (path.to_path_buf(), expected);
+    let mut verified = VERIFIED_REFERENCES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if verified.contains(&checked) {
+        return Ok(());
+    }
+    let actual = reference_digest(conn)?;
+    if actual != checked.1 {
+        return Err(BiosynthError::ChecksumMismatch {
+            subject: format!("the reference table in {:?}", path),
+            expected: checked.1,
+            actual,
+        });
+    }
+    verified.push(checked);
+    Ok(())
+}
+
+/// Brings a sealed reference's digest up to date after the store changed it.
+fn reseal_reference(conn: &Connection) -> Result<()> {
+    if stored_reference_digest(conn)?.is_some() {
+        store_reference_digest(conn, &reference_digest(conn)?)?;
+    }
+    Ok(())
+}
+
+fn read_integer_property(conn: &Connection, key: &str) -> Result<Option<i64>> {
+    conn.query_row(
+        "SELECT CAST(value AS INTEGER) FROM properties WHERE key = ?1",
+        [key],
+        |row| row.get(0),
+    )
+    .optional()
+    .with_context(|| format!("Read {} property", key))
+}
+
+/// Reads the file ID salt, generating and storing one first when `create` is set.
+fn file_id_salt(conn: &Connection, create: bool) -> Result<Option<String>> {
+    if create {
+        let salt: [u8; 32] = rand::thread_rng().gen();
+        // A concurrent ingest may win the race; whichever salt landed first is kept.
+        conn.execute(
+            "INSERT OR IGNORE INTO properties (key, value) VALUES (?1, ?2)",
+            params![FILE_ID_SALT_KEY, hex(&salt)],
+        )
+        .context("Store file ID salt")?;
+    }
+    conn.query_row(
+        "SELECT value FROM properties WHERE key = ?1",
+        [FILE_ID_SALT_KEY],
+        |row| row.get(0),
+    )
+    .optional()
+    .context("Read file ID salt")
+}
+
+fn hash_file_id(salt: &str, path: &Path) -> String {
+    hex(&file_id_digest(salt, path))
+}
+
+fn file_id_digest(salt: &str, path: &Path) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(path.to_string_lossy().as_bytes());
+    hasher.finalize().into()
+}
+
+/// The inverse of [`hex`] for a SHA-256 digest.
+fn parse_digest(raw: &str) -> Option<[u8; 32]> {
+    if raw.len() != 64 || !raw.is_ascii() {
+        return None;
+    }
+    let mut digest = [0u8; 32];
+    for (byte, pair) in digest.iter_mut().zip(raw.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(digest)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,75 @@
+//! Correcting calls reported on the opposite strand to the reference.
+//!
+//! Some arrays report a SNP's alleles on the reverse strand, so an `A/G` site arrives as `TC`.
+//! A call is flipped back only when its alleles are not all reference alleles but their
+//! complements are. Palindromic sites (`A/T`, `C/G`) read the same on both strands and are
+//! never flipped.
+
+use std::collections::HashMap;
+
+use crate::genotype::Rsid;
+use crate::liftover::complement_genotype;
+use crate::stats::ReferenceVariant;
+
+/// The single-base reference and alternate alleles of each SNP in a reference, as a bit per
+/// base, for checking calls against.
+#[derive(Debug, Clone, Default)]
+pub struct StrandReference {
+    alleles: HashMap<i64, u8>,
+}
+
+impl StrandReference {
+    /// Adds the SNPs among `references`; indels and multi-base alleles are left out.
+    pub fn extend<'a>(&mut self, references: impl IntoIterator<Item = &'a ReferenceVariant>) {
+        for reference in references {
+            let Some(alleles) = reference.snp_alleles() else {
+                continue;
+            };
+            let mask = alleles
+                .into_iter()
+                .try_fold(0, |mask, base| base_bit(base).map(|bit| mask | bit));
+            if let Some(mask) = mask.filter(|mask| *mask != 0) {
+                self.alleles.insert(reference.rsid, mask);
+            }
+        }
+    }
+
+    /// SNPs held.
+    pub fn len(&self) -> usize {
+        self.alleles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.alleles.is_empty()
+    }
+
+    /// `genotype` complemented, if only its complement matches the reference alleles of
+    /// `rsid`. Calls at unknown rsids, no-calls, and indel calls are left alone.
+    pub fn corrected_genotype(&self, rsid: &Rsid, genotype: &str) -> Option<String> {
+        let reference = *self.alleles.get(&rsid.number()?)?;
+        let mut observed = 0;
+        for base in genotype.chars().filter(char::is_ascii_alphabetic) {
+            observed |= base_bit(base.to_ascii_uppercase())?;
+        }
+        let complemented = complement_mask(observed);
+        (observed & !reference != 0 && complemented & !reference == 0)
+            .then(|| complement_genotype(genotype))
+    }
+}
+
+fn base_bit(base: char) -> Option<u8> {
+    match base {
+        'A' => Some(0b0001),
+        'C' => Some(0b0010),
+        'G' => Some(0b0100),
+        'T' => Some(0b1000),
+        _ => None,
+    }
+}
+
+/// Swaps A with T and C with G.
+fn complement_mask(mask: u8) -> u8 {
+    let a_t = ((mask & 0b0001) << 3) | ((mask & 0b1000) >> 3);
+    let c_g = ((mask & 0b0010) << 1) | ((mask & 0b0100) >> 1);
+    a_t | c_g
+}
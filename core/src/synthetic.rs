@@ -0,0 +1,1104 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::alleles::{normalize_allele, normalize_genotype};
+use crate::buffers;
+use crate::error::{BiosynthError, Context, Result};
+use crate::formats::{DynamicDnaWriter, FormatWriter};
+use crate::overlay::{OverlayDocument, OverlayVariant};
+use crate::privacy::DpAccounting;
+use crate::progress::{emit, ProgressEvent};
+use crate::stats::{ReferenceVariant, StatsBackend, REFERENCE_CHUNK_ROWS};
+
+/// Participant sex, used to decide whether Y-chromosome rows carry calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sex {
+    Male,
+    Female,
+}
+
+const NO_CALL: &str = "--";
+/// Most rows [`SyntheticGeneratorBuilder::chunk_threads`] puts in one chunk; longer
+/// chromosomes are split.
+const PARALLEL_CHUNK_ROWS: usize = 100_000;
+
+/// Per-rsid SNP allele distributions that replace the flat `alt_frequency` draw. Both alleles
+/// of a call are drawn independently (Hardy-Weinberg); rsids without a distribution fall back
+/// to `alt_frequency`.
+#[derive(Debug, Clone, Default)]
+pub struct AlleleFrequencies {
+    by_rsid: HashMap<i64, SamplingTable<char>>,
+}
+
+impl AlleleFrequencies {
+    /// Sets the distribution for `rsid`; weights need not sum to one.
+    pub fn insert(&mut self, rsid: i64, weights: Vec<(char, f64)>) {
+        self.by_rsid.insert(rsid, SamplingTable::new(weights));
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_rsid.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_rsid.is_empty()
+    }
+
+    /// Frequencies from observed allele `copies`, noised for an `epsilon`-DP release over the
+    /// SNPs in `references` (see [`DpAccounting`]). Candidate alleles come from the public
+    /// reference, never from the observations, so which alleles were seen is not leaked.
+    pub fn differentially_private(
+        references: &[ReferenceVariant],
+        copies: &HashMap<i64, HashMap<String, u64>>,
+        epsilon: f64,
+        rng: &mut dyn RngCore,
+    ) -> Result<(Self, DpAccounting)> {
+        let snps: Vec<(i64, Vec<char>)> = references
+            .iter()
+            .filter_map(|reference| Some((reference.rsid, reference.snp_alleles()?)))
+            .collect();
+        let (accounting, noise) = DpAccounting::allele_counts(epsilon, snps.len())?;
+
+        let mut frequencies = Self::default();
+        for (rsid, alleles) in snps {
+            let observed = copies.get(&rsid);
+            let mut weights: Vec<(char, f64)> = alleles
+                .iter()
+                .map(|allele| {
+                    let count = observed
+                        .and_then(|counts| counts.get(allele.to_string().as_str()))
+                        .copied()
+                        .unwrap_or(0);
+                    (*allele, (count as f64 + noise.sample(rng)).max(0.0))
+                })
+                .collect();
+            if weights.iter().all(|(_, weight)| *weight == 0.0) {
+                // Nothing survived the noise; fall back to the reference allele alone.
+                weights = vec![(alleles[0], 1.0)];
+            }
+            frequencies.insert(rsid, weights);
+        }
+        Ok((frequencies, accounting))
+    }
+
+    fn sample_genotype(&self, rsid: i64, rng: &mut dyn RngCore) -> Option<String> {
+        let table = self.by_rsid.get(&rsid)?;
+        if table.total() <= 0.0 {
+            return None;
+        }
+        let mut pair = [*table.sample(rng), *table.sample(rng)];
+        pair.sort_unstable();
+        Some(pair.iter().collect())
+    }
+}
+
+/// Cumulative weights over the values a draw can take, so sampling is one random number and a
+/// binary search.
+#[derive(Debug, Clone)]
+struct SamplingTable<T> {
+    values: Vec<T>,
+    cumulative: Vec<f64>,
+}
+
+/// The homozygous genotypes a reference's row can take under the flat `alt_frequency` draw.
+type GenotypeTable = SamplingTable<Box<str>>;
+
+impl<T> SamplingTable<T> {
+    fn new(weights: impl IntoIterator<Item = (T, f64)>) -> Self {
+        let mut total = 0.0;
+        let (values, cumulative) = weights
+            .into_iter()
+            .map(|(value, weight)| {
+                total += weight;
+                (value, total)
+            })
+            .unzip();
+        Self { values, cumulative }
+    }
+
+    fn total(&self) -> f64 {
+        self.cumulative.last().copied().unwrap_or(0.0)
+    }
+
+    /// Draws a value in proportion to its weight. A table with one value draws nothing.
+    fn sample(&self, rng: &mut dyn RngCore) -> &T {
+        let last = self.values.len() - 1;
+        if last == 0 {
+            return &self.values[0];
+        }
+        let target = rng.gen::<f64>() * self.total();
+        &self.values[self
+            .cumulative
+            .partition_point(|bound| *bound <= target)
+            .min(last)]
+    }
+}
+
+enum VariantKind {
+    Snp,
+    Mnv,
+    Insertion,
+    Deletion,
+}
+
+/// Writes one synthetic Dynamic DNA file to `path`, returning the number of rows written.
+/// A `seed` makes the output reproducible.
+pub fn write_single_file(
+    path: &Path,
+    references: &[ReferenceVariant],
+    overlays: &[OverlaySpec],
+    alt_frequency: f64,
+    seed: Option<u64>,
+    sex: Option<Sex>,
+) -> Result<usize> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Create directory {:?}", parent))?;
+        }
+    }
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let file = File::create(path).with_context(|| format!("Create {:?}", path))?;
+    let mut writer = buffers::writer(file);
+    let written = write_rows(
+        &mut writer,
+        references,
+        overlays,
+        alt_frequency,
+        sex,
+        &mut rng,
+    )?;
+    writer.flush()?;
+    Ok(written)
+}
+
+/// Configured synthetic generator; cheap to clone and safe to share across threads.
+///
+/// ```no_run
+/// use biosynth_core::stats::StatsStore;
+/// use biosynth_core::synthetic::{Sex, SyntheticGenerator};
+///
+/// let references = StatsStore::connect("data/genostats.sqlite".as_ref())?.all_references(None)?;
+/// let generator = SyntheticGenerator::builder()
+///     .references(references)
+///     .alt_frequency(0.02)
+///     .sex(Sex::Female)
+///     .seed(42)
+///     .build()?;
+/// let rows = generator.write_to(std::io::stdout().lock())?;
+/// # Ok::<(), biosynth_core::BiosynthError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct SyntheticGenerator {
+    references: ReferenceSource,
+    overlays: Arc<[OverlaySpec]>,
+    format: Arc<dyn FormatWriter>,
+    alt_frequency: f64,
+    frequencies: Option<Arc<AlleleFrequencies>>,
+    seed: Option<u64>,
+    sex: Option<Sex>,
+    rng_source: Option<RngSource>,
+    hooks: RowHooks,
+    progress: Option<Sender<ProgressEvent>>,
+    chunk_threads: Option<usize>,
+    /// One table per loaded reference, built once and shared by every file.
+    genotypes: Arc<[GenotypeTable]>,
+}
+
+/// Where a generator's reference variants come from.
+#[derive(Clone)]
+enum ReferenceSource {
+    Loaded(Arc<[ReferenceVariant]>),
+    /// Read from the backend again for every file, one chunk at a time.
+    Streamed {
+        backend: Arc<dyn StatsBackend>,
+        limit: Option<usize>,
+    },
+}
+
+impl std::fmt::Debug for ReferenceSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Loaded(references) => write!(f, "Loaded({})", references.len()),
+            Self::Streamed { limit, .. } => write!(f, "Streamed {{ limit: {:?} }}", limit),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct RowHooks(Vec<RowHook>);
+
+impl std::fmt::Debug for RowHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RowHooks({})", self.0.len())
+    }
+}
+
+/// Produces a fresh random source for each generated file.
+#[derive(Clone)]
+struct RngSource(Arc<dyn Fn() -> Box<dyn RngCore + Send> + Send + Sync>);
+
+impl std::fmt::Debug for RngSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RngSource(..)")
+    }
+}
+
+impl SyntheticGenerator {
+    pub fn builder() -> SyntheticGeneratorBuilder {
+        SyntheticGeneratorBuilder::default()
+    }
+
+    /// Writes one file's worth of rows, returning the number written. With a seed, every call
+    /// produces identical output.
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<usize> {
+        let mut rng: Box<dyn RngCore + Send> = match (&self.rng_source, self.seed) {
+            (Some(source), _) => (source.0)(),
+            (None, Some(seed)) => Box::new(StdRng::seed_from_u64(seed)),
+            (None, None) => Box::new(StdRng::from_entropy()),
+        };
+        self.write_with_rng(writer, &mut *rng)
+    }
+
+    /// Like [`write_to`](Self::write_to), but draws every random choice from `rng`.
+    pub fn write_with_rng<W: Write>(&self, mut writer: W, rng: &mut dyn RngCore) -> Result<usize> {
+        if let (ReferenceSource::Loaded(references), Some(threads)) =
+            (&self.references, self.chunk_threads)
+        {
+            let written =
+                self.write_chunked(&mut writer, references, &self.genotypes, threads, rng)?;
+            writer.flush()?;
+            return Ok(written);
+        }
+        let mut rows = RowEmitter::start(
+            &mut writer,
+            self.format.as_ref(),
+            &self.overlays,
+            self.frequencies.as_deref(),
+            self.sex,
+            rng,
+            &self.hooks.0,
+        )?;
+        match &self.references {
+            ReferenceSource::Loaded(references) => rows.references(references, &self.genotypes)?,
+            ReferenceSource::Streamed { backend, limit } => {
+                let streamed = backend.for_each_reference_chunk(
+                    *limit,
+                    REFERENCE_CHUNK_ROWS,
+                    &mut |chunk| {
+                        rows.references(&chunk, &genotype_tables(&chunk, self.alt_frequency))
+                    },
+                )?;
+                if streamed == 0 {
+                    return Err(BiosynthError::InvalidArgument(
+                        "SyntheticGenerator requires at least one reference variant".into(),
+                    ));
+                }
+            }
+        }
+        let written = rows.finish()?;
+        writer.flush()?;
+        Ok(written)
+    }
+
+    /// Renders up to `threads` chunks at a time into buffers and writes them in order.
+    fn write_chunked(
+        &self,
+        writer: &mut dyn Write,
+        references: &[ReferenceVariant],
+        genotypes: &[GenotypeTable],
+        threads: usize,
+        rng: &mut dyn RngCore,
+    ) -> Result<usize> {
+        let mut overlay_assignments = prepare_overlay_assignments(&self.overlays, rng)?;
+        self.format.write_header(writer)?;
+
+        // Each chunk takes its genotype tables, the overlays on its rsids and a seed for its own
+        // random stream.
+        let mut offset = 0;
+        let mut chunks = references
+            .chunk_by(|a, b| a.chromosome == b.chromosome)
+            .flat_map(|contig| contig.chunks(PARALLEL_CHUNK_ROWS))
+            .map(|chunk| {
+                let tables = &genotypes[offset..offset + chunk.len()];
+                offset += chunk.len();
+                let overlays: HashMap<i64, OverlayAssignment> = chunk
+                    .iter()
+                    .filter_map(|reference| overlay_assignments.remove_entry(&reference.rsid))
+                    .collect();
+                (chunk, tables, overlays, rng.next_u64())
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .peekable();
+
+        let mut written = 0;
+        while chunks.peek().is_some() {
+            let wave: Vec<_> = chunks.by_ref().take(threads).collect();
+            let rendered: Vec<Result<(Vec<u8>, usize)>> = std::thread::scope(|scope| {
+                let workers: Vec<_> = wave
+                    .into_iter()
+                    .map(|(chunk, tables, overlays, seed)| {
+                        scope.spawn(move || self.render_chunk(chunk, tables, overlays, seed))
+                    })
+                    .collect();
+                workers
+                    .into_iter()
+                    .map(|worker| {
+                        worker
+                            .join()
+                            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                    })
+                    .collect()
+            });
+            for result in rendered {
+                let (buffer, rows) = result?;
+                writer.write_all(&buffer)?;
+                written += rows;
+            }
+        }
+
+        let mut rows = RowEmitter::new(
+            writer,
+            self.format.as_ref(),
+            overlay_assignments,
+            self.frequencies.as_deref(),
+            self.sex,
+            rng,
+            &self.hooks.0,
+        );
+        rows.written = written;
+        rows.finish()
+    }
+
+    fn render_chunk(
+        &self,
+        references: &[ReferenceVariant],
+        genotypes: &[GenotypeTable],
+        overlays: HashMap<i64, OverlayAssignment>,
+        seed: u64,
+    ) -> Result<(Vec<u8>, usize)> {
+        let mut buffer = Vec::new();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut rows = RowEmitter::new(
+            &mut buffer,
+            self.format.as_ref(),
+            overlays,
+            self.frequencies.as_deref(),
+            self.sex,
+            &mut rng,
+            &self.hooks.0,
+        );
+        rows.references(references, genotypes)?;
+        let written = rows.written;
+        Ok((buffer, written))
+    }
+
+    /// Writes to `path`, creating parent directories as needed. Reports to the
+    /// [`progress`](SyntheticGeneratorBuilder::progress) channel, if one is set.
+    pub fn write_file(&self, path: &Path) -> Result<usize> {
+        let progress = self.progress.as_ref();
+        emit(
+            progress,
+            ProgressEvent::Started {
+                path: path.to_path_buf(),
+            },
+        );
+        let result = self.create_file(path);
+        emit(
+            progress,
+            match &result {
+                Ok(rows) => ProgressEvent::Finished {
+                    path: path.to_path_buf(),
+                    rows: *rows,
+                },
+                Err(err) => ProgressEvent::Failed {
+                    path: path.to_path_buf(),
+                    error: err.to_string(),
+                },
+            },
+        );
+        result
+    }
+
+    fn create_file(&self, path: &Path) -> Result<usize> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("Create directory {:?}", parent))?;
+            }
+        }
+        let file = File::create(path).with_context(|| format!("Create {:?}", path))?;
+        self.write_to(buffers::writer(file))
+    }
+}
+
+/// Builder for [`SyntheticGenerator`]. Only `references` is required.
+#[derive(Debug, Clone)]
+pub struct SyntheticGeneratorBuilder {
+    references: Option<ReferenceSource>,
+    overlays: Arc<[OverlaySpec]>,
+    format: Arc<dyn FormatWriter>,
+    alt_frequency: f64,
+    frequencies: Option<Arc<AlleleFrequencies>>,
+    seed: Option<u64>,
+    sex: Option<Sex>,
+    rng_source: Option<RngSource>,
+    hooks: RowHooks,
+    progress: Option<Sender<ProgressEvent>>,
+    chunk_threads: Option<usize>,
+}
+
+impl Default for SyntheticGeneratorBuilder {
+    fn default() -> Self {
+        Self {
+            references: None,
+            overlays: Arc::from(Vec::new()),
+            format: Arc::new(DynamicDnaWriter),
+            alt_frequency: 0.01,
+            frequencies: None,
+            seed: None,
+            sex: None,
+            rng_source: None,
+            hooks: RowHooks::default(),
+            progress: None,
+            chunk_threads: None,
+        }
+    }
+}
+
+impl SyntheticGeneratorBuilder {
+    /// Reference variants to emit, typically from [`StatsStore::all_references`](crate::stats::StatsStore::all_references).
+    pub fn references(mut self, references: impl Into<Arc<[ReferenceVariant]>>) -> Self {
+        self.references = Some(ReferenceSource::Loaded(references.into()));
+        self
+    }
+
+    /// Streams references from `backend` for every file instead of holding them in memory, in
+    /// place of [`references`](Self::references). Memory stays bounded by
+    /// [`REFERENCE_CHUNK_ROWS`] at the cost of reading the table once per file.
+    pub fn stream_references(
+        mut self,
+        backend: Arc<dyn StatsBackend>,
+        limit: Option<usize>,
+    ) -> Self {
+        self.references = Some(ReferenceSource::Streamed { backend, limit });
+        self
+    }
+
+    /// Variants forced into every file; see [`parse_overlay_specs`].
+    pub fn overlays(mut self, overlays: impl Into<Arc<[OverlaySpec]>>) -> Self {
+        self.overlays = overlays.into();
+        self
+    }
+
+    /// Output format (default [`DynamicDnaWriter`]); see [`FormatRegistry`](crate::formats::FormatRegistry)
+    /// to look one up by name.
+    pub fn format(mut self, format: Arc<dyn FormatWriter>) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Probability of substituting an ALT allele for the reference (default 0.01).
+    pub fn alt_frequency(mut self, alt_frequency: f64) -> Self {
+        self.alt_frequency = alt_frequency;
+        self
+    }
+
+    /// Per-rsid allele distributions, e.g. [`AlleleFrequencies::differentially_private`].
+    pub fn allele_frequencies(mut self, frequencies: Arc<AlleleFrequencies>) -> Self {
+        self.frequencies = Some(frequencies);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn sex(mut self, sex: Sex) -> Self {
+        self.sex = Some(sex);
+        self
+    }
+
+    /// Replaces the built-in `StdRng` with a custom random source, called once per file.
+    /// Use this to inject deterministic sequences or a noise mechanism of your own.
+    pub fn rng_factory<F, R>(mut self, factory: F) -> Self
+    where
+        F: Fn() -> R + Send + Sync + 'static,
+        R: RngCore + Send + 'static,
+    {
+        self.rng_source = Some(RngSource(Arc::new(move || Box::new(factory()))));
+        self
+    }
+
+    /// Adds a hook run on every row before it is written; hooks run in the order added and
+    /// can edit the row, drop it, or annotate it.
+    ///
+    /// ```no_run
+    /// # use biosynth_core::synthetic::{RowAction, SyntheticGenerator};
+    /// # let references = Vec::new();
+    /// let generator = SyntheticGenerator::builder()
+    ///     .references(references)
+    ///     .hook(|row| {
+    ///         if row.gs < 0.3 {
+    ///             row.genotype = "--".into();
+    ///         }
+    ///         RowAction::Keep
+    ///     })
+    ///     .build()?;
+    /// # Ok::<(), biosynth_core::BiosynthError>(())
+    /// ```
+    pub fn hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&mut SyntheticRow) -> RowAction + Send + Sync + 'static,
+    {
+        self.hooks.0.push(Arc::new(hook));
+        self
+    }
+
+    /// Sends a [`ProgressEvent`] when each [`write_file`](SyntheticGenerator::write_file) call
+    /// starts and ends.
+    pub fn progress(mut self, sender: Sender<ProgressEvent>) -> Self {
+        self.progress = Some(sender);
+        self
+    }
+
+    /// Generates each file in chromosome chunks on `threads` threads, stitched back together in
+    /// order. Every chunk draws from its own random stream, so seeded output is reproducible
+    /// (for any thread count) but differs from sequential generation.
+    pub fn chunk_threads(mut self, threads: usize) -> Self {
+        self.chunk_threads = Some(threads);
+        self
+    }
+
+    pub fn build(self) -> Result<SyntheticGenerator> {
+        let Some(references) = self.references else {
+            return Err(BiosynthError::InvalidArgument(
+                "SyntheticGenerator requires references".into(),
+            ));
+        };
+        if matches!(&references, ReferenceSource::Loaded(references) if references.is_empty()) {
+            return Err(BiosynthError::InvalidArgument(
+                "SyntheticGenerator requires at least one reference variant".into(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.alt_frequency) {
+            return Err(BiosynthError::InvalidArgument(
+                "alt_frequency must be between 0 and 1".into(),
+            ));
+        }
+        match (self.chunk_threads, &references) {
+            (Some(0), _) => {
+                return Err(BiosynthError::InvalidArgument(
+                    "chunk_threads must be at least 1".into(),
+                ))
+            }
+            (Some(_), ReferenceSource::Streamed { .. }) => {
+                return Err(BiosynthError::InvalidArgument(
+                    "chunk_threads needs in-memory references, not stream_references".into(),
+                ))
+            }
+            _ => {}
+        }
+        if self.seed.is_some() && self.rng_source.is_some() {
+            return Err(BiosynthError::InvalidArgument(
+                "seed has no effect with a custom rng_factory; seed the factory instead".into(),
+            ));
+        }
+        let genotypes = match &references {
+            ReferenceSource::Loaded(references) => {
+                genotype_tables(references, self.alt_frequency).into()
+            }
+            ReferenceSource::Streamed { .. } => Arc::from([]),
+        };
+        Ok(SyntheticGenerator {
+            references,
+            overlays: self.overlays,
+            format: self.format,
+            alt_frequency: self.alt_frequency,
+            frequencies: self.frequencies,
+            seed: self.seed,
+            sex: self.sex,
+            rng_source: self.rng_source,
+            hooks: self.hooks,
+            progress: self.progress,
+            chunk_threads: self.chunk_threads,
+            genotypes,
+        })
+    }
+}
+
+/// Writes the header and one row per reference. When `sex` is female, Y-chromosome
+/// rows are emitted as no-calls (`--`), matching what vendor exports contain. Every random
+/// choice is drawn from `rng`, so any [`RngCore`] can drive generation.
+pub fn write_rows<W: Write>(
+    writer: &mut W,
+    references: &[ReferenceVariant],
+    overlays: &[OverlaySpec],
+    alt_frequency: f64,
+    sex: Option<Sex>,
+    rng: &mut dyn RngCore,
+) -> Result<usize> {
+    write_rows_with_hooks(writer, references, overlays, alt_frequency, sex, rng, &[])
+}
+
+/// [`write_rows`], passing every row through `hooks` in order before it is written.
+/// Returns the number of rows actually written.
+pub fn write_rows_with_hooks<W: Write>(
+    writer: &mut W,
+    references: &[ReferenceVariant],
+    overlays: &[OverlaySpec],
+    alt_frequency: f64,
+    sex: Option<Sex>,
+    rng: &mut dyn RngCore,
+    hooks: &[RowHook],
+) -> Result<usize> {
+    write_formatted(
+        writer,
+        &DynamicDnaWriter,
+        references,
+        overlays,
+        alt_frequency,
+        None,
+        sex,
+        rng,
+        hooks,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn write_formatted(
+    writer: &mut dyn Write,
+    format: &dyn FormatWriter,
+    references: &[ReferenceVariant],
+    overlays: &[OverlaySpec],
+    alt_frequency: f64,
+    frequencies: Option<&AlleleFrequencies>,
+    sex: Option<Sex>,
+    rng: &mut dyn RngCore,
+    hooks: &[RowHook],
+) -> Result<usize> {
+    let mut rows = RowEmitter::start(writer, format, overlays, frequencies, sex, rng, hooks)?;
+    rows.references(references, &genotype_tables(references, alt_frequency))?;
+    rows.finish()
+}
+
+/// Writes one file's rows as references arrive, so they can come in chunks.
+struct RowEmitter<'a> {
+    writer: &'a mut dyn Write,
+    format: &'a dyn FormatWriter,
+    frequencies: Option<&'a AlleleFrequencies>,
+    sex: Option<Sex>,
+    rng: &'a mut dyn RngCore,
+    hooks: &'a [RowHook],
+    overlay_assignments: HashMap<i64, OverlayAssignment>,
+    written: usize,
+}
+
+impl<'a> RowEmitter<'a> {
+    /// Assigns overlays and writes the header.
+    #[allow(clippy::too_many_arguments)]
+    fn start(
+        writer: &'a mut dyn Write,
+        format: &'a dyn FormatWriter,
+        overlays: &[OverlaySpec],
+        frequencies: Option<&'a AlleleFrequencies>,
+        sex: Option<Sex>,
+        rng: &'a mut dyn RngCore,
+        hooks: &'a [RowHook],
+    ) -> Result<Self> {
+        let overlay_assignments = prepare_overlay_assignments(overlays, rng)?;
+        format.write_header(writer)?;
+        Ok(Self::new(
+            writer,
+            format,
+            overlay_assignments,
+            frequencies,
+            sex,
+            rng,
+            hooks,
+        ))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        writer: &'a mut dyn Write,
+        format: &'a dyn FormatWriter,
+        overlay_assignments: HashMap<i64, OverlayAssignment>,
+        frequencies: Option<&'a AlleleFrequencies>,
+        sex: Option<Sex>,
+        rng: &'a mut dyn RngCore,
+        hooks: &'a [RowHook],
+    ) -> Self {
+        Self {
+            writer,
+            format,
+            frequencies,
+            sex,
+            rng,
+            hooks,
+            overlay_assignments,
+            written: 0,
+        }
+    }
+
+    /// Writes one row per reference, in order, drawing from the matching entry of `genotypes`
+    /// unless the rsid has allele frequencies.
+    fn references(
+        &mut self,
+        references: &[ReferenceVariant],
+        genotypes: &[GenotypeTable],
+    ) -> Result<()> {
+        for (reference, table) in references.iter().zip(genotypes) {
+            let row = if let Some(assignment) = self.overlay_assignments.remove(&reference.rsid) {
+                SyntheticRow::overlay(assignment, self.rng)
+            } else {
+                let genotype =
+                    if self.sex == Some(Sex::Female) && is_y_chromosome(&reference.chromosome) {
+                        NO_CALL.to_string()
+                    } else {
+                        self.frequencies
+                            .and_then(|frequencies| {
+                                frequencies.sample_genotype(reference.rsid, self.rng)
+                            })
+                            .unwrap_or_else(|| table.sample(self.rng).to_string())
+                    };
+                SyntheticRow::new(
+                    reference.rsid,
+                    reference.chromosome.clone(),
+                    reference.position,
+                    genotype,
+                    false,
+                    self.rng,
+                )
+            };
+            if emit_row(self.writer, self.format, row, self.hooks)? {
+                self.written += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes overlay variants absent from the references, then the footer.
+    fn finish(mut self) -> Result<usize> {
+        for assignment in std::mem::take(&mut self.overlay_assignments).into_values() {
+            if emit_row(
+                self.writer,
+                self.format,
+                SyntheticRow::overlay(assignment, self.rng),
+                self.hooks,
+            )? {
+                self.written += 1;
+            }
+        }
+        self.format.write_footer(self.writer, self.written)?;
+        Ok(self.written)
+    }
+}
+
+/// One generated row, as seen by a [`RowHook`] before it is written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntheticRow {
+    /// Numeric rsid; written as `rs<rsid>`.
+    pub rsid: i64,
+    pub chromosome: String,
+    pub position: i64,
+    pub genotype: String,
+    /// GenCall score.
+    pub gs: f64,
+    /// B allele frequency.
+    pub baf: f64,
+    /// Log R ratio.
+    pub lrr: f64,
+    /// True when the row came from an overlay rather than the reference panel.
+    pub overlay: bool,
+}
+
+impl SyntheticRow {
+    fn new(
+        rsid: i64,
+        chromosome: String,
+        position: i64,
+        genotype: String,
+        overlay: bool,
+        rng: &mut dyn RngCore,
+    ) -> Self {
+        Self {
+            rsid,
+            chromosome,
+            position,
+            genotype,
+            gs: rng.gen_range(0.2..=1.0),
+            baf: rng.gen_range(0.0..=1.0),
+            lrr: rng.gen_range(-0.5..=0.5),
+            overlay,
+        }
+    }
+
+    fn overlay(assignment: OverlayAssignment, rng: &mut dyn RngCore) -> Self {
+        let OverlayAssignment { spec, genotype } = assignment;
+        Self::new(
+            spec.rsid,
+            spec.chromosome,
+            spec.position,
+            genotype,
+            true,
+            rng,
+        )
+    }
+}
+
+/// What to do with a row after a [`RowHook`] has seen it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RowAction {
+    /// Write the (possibly modified) row.
+    Keep,
+    /// Omit the row from the output.
+    Drop,
+    /// Write the row preceded by a comment line carrying the note.
+    Annotate(String),
+}
+
+/// Callback invoked for every generated row; may mutate it in place.
+pub type RowHook = Arc<dyn Fn(&mut SyntheticRow) -> RowAction + Send + Sync>;
+
+fn emit_row(
+    writer: &mut dyn Write,
+    format: &dyn FormatWriter,
+    mut row: SyntheticRow,
+    hooks: &[RowHook],
+) -> Result<bool> {
+    let mut notes = Vec::new();
+    for hook in hooks {
+        match hook(&mut row) {
+            RowAction::Keep => {}
+            RowAction::Drop => return Ok(false),
+            RowAction::Annotate(note) => notes.push(note),
+        }
+    }
+    for note in notes {
+        format.write_annotation(writer, &row, &note)?;
+    }
+    format.write_row(writer, &row)?;
+    Ok(true)
+}
+
+/// Parses an overlay JSON document (groups of variants to force into generated files); see
+/// [`crate::overlay`] for the format.
+pub fn parse_overlay_specs(raw_json: &str) -> Result<Vec<OverlaySpec>> {
+    overlay_specs(&OverlayDocument::parse(raw_json)?)
+}
+
+/// The variants of an already parsed overlay document.
+pub fn overlay_specs(document: &OverlayDocument) -> Result<Vec<OverlaySpec>> {
+    document.variants().map(OverlaySpec::from_variant).collect()
+}
+
+fn prepare_overlay_assignments(
+    overlays: &[OverlaySpec],
+    rng: &mut dyn RngCore,
+) -> Result<HashMap<i64, OverlayAssignment>> {
+    let mut assignments: HashMap<i64, OverlayAssignment> = HashMap::new();
+    for spec in overlays {
+        let genotype = spec.random_genotype(rng)?;
+        if assignments
+            .insert(
+                spec.rsid,
+                OverlayAssignment {
+                    spec: spec.clone(),
+                    genotype,
+                },
+            )
+            .is_some()
+        {
+            return Err(BiosynthError::InvalidArgument(format!(
+                "Duplicate overlay rsid detected: {}",
+                spec.rsid
+            )));
+        }
+    }
+    Ok(assignments)
+}
+
+/// A variant forced into generated output with one of a fixed set of genotypes.
+#[derive(Debug, Clone)]
+pub struct OverlaySpec {
+    rsid: i64,
+    chromosome: String,
+    position: i64,
+    genotype_options: Vec<String>,
+}
+
+impl OverlaySpec {
+    pub fn from_variant(raw: &OverlayVariant) -> Result<Self> {
+        let rsid = raw
+            .rsid
+            .trim()
+            .trim_start_matches("rs")
+            .parse::<i64>()
+            .map_err(|_| BiosynthError::Parse(format!("parse overlay rsid {}", raw.rsid)))?;
+        let genotype_options = if let Some(options) = &raw.genotypes {
+            if options.is_empty() {
+                return Err(BiosynthError::Parse(format!(
+                    "Variant {} has empty genotype list",
+                    raw.rsid
+                )));
+            }
+            options
+                .iter()
+                .map(|option| normalize_genotype(option))
+                .collect()
+        } else if let Some(reference) = &raw.reference {
+            let reference = normalize_allele(reference);
+            let alternates = match &raw.alternates {
+                Some(alternates) => alternates
+                    .iter()
+                    .map(|alternate| normalize_allele(alternate))
+                    .collect(),
+                None => vec![reference.clone()],
+            };
+            generate_genotype_combinations(&reference, &alternates)
+        } else {
+            return Err(BiosynthError::Parse(format!(
+                "Variant {} must specify either genotypes or reference/alternates",
+                raw.rsid
+            )));
+        };
+
+        Ok(Self {
+            rsid,
+            chromosome: raw.chromosome.clone(),
+            position: raw.position,
+            genotype_options,
+        })
+    }
+
+    fn random_genotype(&self, rng: &mut dyn RngCore) -> Result<String> {
+        if self.genotype_options.is_empty() {
+            return Err(BiosynthError::InvalidArgument(format!(
+                "Variant {} has no genotype options",
+                self.rsid
+            )));
+        }
+        let idx = rng.gen_range(0..self.genotype_options.len());
+        Ok(self.genotype_options[idx].clone())
+    }
+}
+
+struct OverlayAssignment {
+    spec: OverlaySpec,
+    genotype: String,
+}
+
+fn generate_genotype_combinations(reference: &str, alternates: &[String]) -> Vec<String> {
+    let mut alleles = Vec::new();
+    alleles.push(reference.to_string());
+    for alt in alternates {
+        if !alt.is_empty() {
+            alleles.push(alt.clone());
+        }
+    }
+    alleles.sort();
+    alleles.dedup();
+
+    let mut combos = Vec::new();
+    for i in 0..alleles.len() {
+        for j in i..alleles.len() {
+            let combo = format!("{}{}", alleles[i], alleles[j]);
+            combos.push(combo);
+        }
+    }
+    combos
+}
+
+fn is_y_chromosome(chromosome: &str) -> bool {
+    let trimmed = chromosome.trim_start_matches("chr");
+    trimmed.eq_ignore_ascii_case("y") || trimmed == "24"
+}
+
+/// Parses each reference's alternates once into its [`GenotypeTable`].
+fn genotype_tables(references: &[ReferenceVariant], alt_frequency: f64) -> Vec<GenotypeTable> {
+    references
+        .iter()
+        .map(|reference| genotype_table(reference, alt_frequency))
+        .collect()
+}
+
+/// The reference genotype weighs `1 - alt_frequency`; the alternates share the rest evenly.
+/// Alleles are [normalized](normalize_allele) first, whatever the stored row holds.
+fn genotype_table(reference: &ReferenceVariant, alt_frequency: f64) -> GenotypeTable {
+    let reference_allele = normalize_allele(&reference.reference);
+    let alt_list = reference
+        .alternates
+        .split(',')
+        .map(normalize_allele)
+        .filter(|alt| !alt.is_empty())
+        .collect::<Vec<_>>();
+    let homozygous = |symbol: &str| -> Box<str> { format!("{symbol}{symbol}").into() };
+    if alt_list.is_empty() {
+        return SamplingTable::new([(homozygous(&reference_allele), 1.0)]);
+    }
+
+    match determine_variant_kind(&reference_allele, &alt_list) {
+        kind @ (VariantKind::Snp | VariantKind::Mnv) => {
+            let symbol = |allele: &str| match kind {
+                VariantKind::Mnv => homozygous(
+                    &allele
+                        .chars()
+                        .next()
+                        .map(|c| c.to_string())
+                        .unwrap_or_else(|| "?".into()),
+                ),
+                _ => homozygous(allele),
+            };
+            let alt_weight = alt_frequency / alt_list.len() as f64;
+            SamplingTable::new(
+                std::iter::once((symbol(&reference_allele), 1.0 - alt_frequency))
+                    .chain(alt_list.iter().map(|allele| (symbol(allele), alt_weight))),
+            )
+        }
+        VariantKind::Insertion => SamplingTable::new([
+            (homozygous("D"), 1.0 - alt_frequency),
+            (homozygous("I"), alt_frequency),
+        ]),
+        VariantKind::Deletion => SamplingTable::new([
+            (homozygous("I"), 1.0 - alt_frequency),
+            (homozygous("D"), alt_frequency),
+        ]),
+    }
+}
+
+fn determine_variant_kind(reference: &str, alts: &[String]) -> VariantKind {
+    let ref_len = reference.len();
+    if alts.is_empty() {
+        return VariantKind::Snp;
+    }
+    if ref_len == 1 && alts.iter().all(|alt| alt.len() == 1) {
+        return VariantKind::Snp;
+    }
+    let first_alt_len = alts[0].len();
+    let all_alt_same_len = alts.iter().all(|alt| alt.len() == ref_len);
+    if all_alt_same_len && ref_len > 1 {
+        VariantKind::Mnv
+    } else if first_alt_len > ref_len {
+        VariantKind::Insertion
+    } else if first_alt_len < ref_len {
+        VariantKind::Deletion
+    } else {
+        VariantKind::Mnv
+    }
+}
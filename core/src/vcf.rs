@@ -0,0 +1,451 @@
+//! VCF conformance checks: the header problems, undeclared contigs, unsorted records, and
+//! malformed `GT` fields that make tools like bcftools reject a file. The genotype parser reads
+//! VCFs leniently, so `bvs genostats` runs these checks on its VCF inputs to flag the ones
+//! other tools would refuse.
+//!
+//! The parser reads a VCF as one sample's calls. A multi-sample VCF is split by addressing
+//! each sample as if the file were a directory: `cohort.vcf.gz/sample-2` is its second sample
+//! column. [`sample_paths`] lists those paths, [`process_sample`] parses one, and
+//! [`process_samples`] parses several in one pass over the file, checking it on the way.
+//!
+//! ```no_run
+//! use biosynth_core::vcf::validate_vcf_file;
+//!
+//! let validation = validate_vcf_file("sample.vcf.gz".as_ref())?;
+//! for problem in &validation.problems {
+//!     eprintln!("line {}: {}", problem.line, problem.message);
+//! }
+//! # Ok::<(), biosynth_core::BiosynthError>(())
+//! ```
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, Read};
+use std::path::{Path, PathBuf};
+
+use flate2::read::MultiGzDecoder;
+use serde::Serialize;
+
+use crate::buffers;
+use crate::error::{BiosynthError, Context, Result};
+use crate::genotype::{FileMetadata, GenotypeReader, ParseOptions, ParsedFile, VariantRecord};
+
+/// Problems a [`VcfValidation`] keeps; later ones are only counted.
+pub const MAX_RECORDED_PROBLEMS: usize = 100;
+const SAMPLE_PREFIX: &str = "sample-";
+/// The columns every `#CHROM` line starts with, in order.
+const MANDATORY_COLUMNS: [&str; 8] = [
+    "#CHROM", "POS", "ID", "REF", "ALT", "QUAL", "FILTER", "INFO",
+];
+
+/// One way a VCF breaks the specification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VcfProblem {
+    /// 1-based line number.
+    pub line: usize,
+    pub message: String,
+}
+
+/// What [`validate_vcf`] found in one VCF.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VcfValidation {
+    pub records: usize,
+    pub samples: usize,
+    /// The first [`MAX_RECORDED_PROBLEMS`] problems, in file order.
+    pub problems: Vec<VcfProblem>,
+    pub total_problems: usize,
+}
+
+impl VcfValidation {
+    pub fn is_valid(&self) -> bool {
+        self.total_problems == 0
+    }
+
+    fn report(&mut self, line: usize, message: String) {
+        self.total_problems += 1;
+        if self.problems.len() < MAX_RECORDED_PROBLEMS {
+            self.problems.push(VcfProblem { line, message });
+        }
+    }
+}
+
+/// Whether `path` is named like a VCF: `.vcf`, or `.vcf.gz` for a compressed one.
+pub fn is_vcf(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    name.ends_with(".vcf") || name.ends_with(".vcf.gz")
+}
+
+/// Validates a `.vcf` or bgzipped `.vcf.gz` file.
+pub fn validate_vcf_file(path: &Path) -> Result<VcfValidation> {
+    validate_vcf(open(path)?)
+}
+
+/// The number of sample columns on `path`'s `#CHROM` line; 0 if it has none.
+pub fn sample_count(path: &Path) -> Result<usize> {
+    for line in open(path)?.lines() {
+        let line = line?;
+        if line.starts_with("#CHROM") {
+            let columns = line.trim_end_matches('\r').split('\t').count();
+            return Ok(columns.saturating_sub(MANDATORY_COLUMNS.len() + 1));
+        }
+        if !line.starts_with("##") {
+            break;
+        }
+    }
+    Ok(0)
+}
+
+/// One path per sample column of the VCF `path`, if it has more than one; empty otherwise.
+pub fn sample_paths(path: &Path) -> Result<Vec<PathBuf>> {
+    let samples = sample_count(path)?;
+    if samples < 2 {
+        return Ok(Vec::new());
+    }
+    Ok((1..=samples)
+        .map(|sample| path.join(format!("{}{}", SAMPLE_PREFIX, sample)))
+        .collect())
+}
+
+/// The VCF and 1-based sample number a path from [`sample_paths`] refers to; `None` for other
+/// paths.
+pub fn split_sample(path: &Path) -> Option<(&Path, usize)> {
+    let vcf = path.parent().filter(|vcf| is_vcf(vcf) && vcf.is_file())?;
+    let sample = path
+        .file_name()?
+        .to_str()?
+        .strip_prefix(SAMPLE_PREFIX)?
+        .parse()
+        .ok()
+        .filter(|sample| *sample > 0)?;
+    Some((vcf, sample))
+}
+
+/// Parses the calls of sample column `sample` (1-based) of the VCF `path`, calling
+/// `on_variant` for every usable record.
+pub fn process_sample<F>(
+    path: &Path,
+    sample: usize,
+    options: &ParseOptions,
+    mut on_variant: F,
+) -> Result<ParsedFile>
+where
+    F: FnMut(&VariantRecord, &FileMetadata) -> Result<()>,
+{
+    let parsed = read_samples(
+        path,
+        &[sample],
+        options,
+        |_| {},
+        |_, record, metadata| on_variant(record, metadata),
+    )?;
+    Ok(parsed.into_iter().next().expect("one sample was read"))
+}
+
+/// Parses the calls of sample columns `samples` (1-based) of the VCF `path` in one pass,
+/// calling `on_variant` with each call's index in `samples`, and checks the file as
+/// [`validate_vcf`] does along the way. Returns a [`ParsedFile`] per sample, in the order of
+/// `samples`, and what the check found.
+pub fn process_samples<F>(
+    path: &Path,
+    samples: &[usize],
+    options: &ParseOptions,
+    on_variant: F,
+) -> Result<(Vec<ParsedFile>, VcfValidation)>
+where
+    F: FnMut(usize, &VariantRecord, &FileMetadata) -> Result<()>,
+{
+    let mut validator = Validator::default();
+    let mut lines = 0;
+    let on_line = |line: &str| {
+        lines += 1;
+        validator.line(lines, line.trim_end_matches(['\n', '\r']));
+    };
+    let parsed = read_samples(path, samples, options, on_line, on_variant)?;
+    Ok((parsed, validator.finish(lines)))
+}
+
+fn read_samples<L, F>(
+    path: &Path,
+    samples: &[usize],
+    options: &ParseOptions,
+    on_line: L,
+    on_variant: F,
+) -> Result<Vec<ParsedFile>>
+where
+    L: FnMut(&str),
+    F: FnMut(usize, &VariantRecord, &FileMetadata) -> Result<()>,
+{
+    let count = sample_count(path)?;
+    if let Some(sample) = samples
+        .iter()
+        .find(|sample| **sample == 0 || **sample > count)
+    {
+        return Err(BiosynthError::InvalidArgument(format!(
+            "{:?} has {} samples, not a sample {}",
+            path, count, sample
+        )));
+    }
+    let columns: Vec<usize> = samples.iter().map(|sample| sample - 1).collect();
+    GenotypeReader::from_reader_with_options(open(path)?, options)?
+        .drain_samples(&columns, on_line, on_variant)
+}
+
+/// A buffered reader over a `.vcf`, or a `.vcf.gz` decompressed on the fly.
+fn open(path: &Path) -> Result<Box<dyn BufRead>> {
+    let file = File::open(path).with_context(|| format!("Open VCF {:?}", path))?;
+    let reader: Box<dyn Read> = if path.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(MultiGzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    Ok(Box::new(buffers::reader(reader)))
+}
+
+/// Checks a whole VCF: a leading `##fileformat=VCFv4.x` line, a `#CHROM` line with the
+/// mandatory columns (and `FORMAT` before any sample), a `##contig` line for every contig
+/// records use, records grouped by contig in position order, and every sample's `GT` against
+/// the record's alleles. Only read errors fail; everything else is a [`VcfProblem`].
+pub fn validate_vcf<R: BufRead>(reader: R) -> Result<VcfValidation> {
+    let mut validator = Validator::default();
+    let mut last_line = 0;
+    for (idx, line) in reader.lines().enumerate() {
+        last_line = idx + 1;
+        validator.line(last_line, line?.trim_end_matches('\r'));
+    }
+    Ok(validator.finish(last_line))
+}
+
+#[derive(Default)]
+struct Validator {
+    validation: VcfValidation,
+    /// The `#CHROM` line's column count, once seen.
+    columns: Option<usize>,
+    contigs: HashSet<String>,
+    /// Undeclared contigs already reported.
+    undeclared: HashSet<String>,
+    gt_declared: bool,
+    gt_undeclared_reported: bool,
+    /// Contigs whose records are finished, and the current contig with its last position.
+    finished: HashSet<String>,
+    current: Option<(String, i64)>,
+}
+
+impl Validator {
+    /// What was found, once line `last_line` was the last.
+    fn finish(mut self, last_line: usize) -> VcfValidation {
+        if self.columns.is_none() {
+            self.validation
+                .report(last_line.max(1), "no #CHROM header line".to_string());
+        }
+        self.validation
+    }
+
+    fn line(&mut self, number: usize, line: &str) {
+        if number == 1 && !line.starts_with("##fileformat=VCFv4.") {
+            self.report(number, "the first line must be ##fileformat=VCFv4.x");
+        }
+        if let Some(meta) = line.strip_prefix("##") {
+            if self.columns.is_some() {
+                self.report(number, "meta-information line after the #CHROM line");
+            }
+            self.meta(number, meta);
+        } else if line.starts_with('#') {
+            self.header(number, line);
+        } else if !line.is_empty() {
+            self.record(number, line);
+        }
+    }
+
+    fn meta(&mut self, number: usize, meta: &str) {
+        if let Some(fields) = meta.strip_prefix("contig=<") {
+            match structured_id(fields) {
+                Some(id) if !self.contigs.insert(id.to_string()) => {
+                    self.report(number, &format!("contig {} is declared twice", id))
+                }
+                Some(_) => {}
+                None => self.report(number, "##contig line without an ID"),
+            }
+        } else if let Some(fields) = meta.strip_prefix("FORMAT=<") {
+            self.gt_declared |= structured_id(fields) == Some("GT");
+        }
+    }
+
+    fn header(&mut self, number: usize, line: &str) {
+        if self.columns.is_some() {
+            self.report(number, "second #CHROM line");
+            return;
+        }
+        let columns: Vec<&str> = line.split('\t').collect();
+        self.columns = Some(columns.len());
+        if columns.len() < MANDATORY_COLUMNS.len()
+            || columns[..MANDATORY_COLUMNS.len()] != MANDATORY_COLUMNS
+        {
+            self.report(
+                number,
+                &format!(
+                    "the #CHROM line must start with the tab-separated columns {}",
+                    MANDATORY_COLUMNS.join(" ")
+                ),
+            );
+            return;
+        }
+        let Some((format, samples)) = columns[MANDATORY_COLUMNS.len()..].split_first() else {
+            return;
+        };
+        if *format != "FORMAT" {
+            self.report(number, "the column after INFO must be FORMAT");
+        }
+        if samples.is_empty() {
+            self.report(number, "FORMAT column without any sample column");
+        }
+        let mut names = HashSet::new();
+        for sample in samples {
+            if !names.insert(*sample) {
+                self.report(number, &format!("sample {} appears twice", sample));
+            }
+        }
+        self.validation.samples = samples.len();
+    }
+
+    fn record(&mut self, number: usize, line: &str) {
+        self.validation.records += 1;
+        let Some(columns) = self.columns else {
+            self.report(number, "record before the #CHROM line");
+            return;
+        };
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != columns {
+            self.report(
+                number,
+                &format!(
+                    "{} columns where the #CHROM line has {}",
+                    fields.len(),
+                    columns
+                ),
+            );
+            return;
+        }
+        let (chromosome, position, reference, alternates) =
+            (fields[0], fields[1], fields[3], fields[4]);
+        if !self.contigs.contains(chromosome) && self.undeclared.insert(chromosome.to_string()) {
+            self.report(
+                number,
+                &format!("contig {} is not declared in a ##contig line", chromosome),
+            );
+        }
+        match position.parse::<i64>() {
+            Ok(position) if position >= 0 => self.check_order(number, chromosome, position),
+            _ => self.report(number, &format!("POS {:?} is not a position", position)),
+        }
+        if reference.is_empty()
+            || !reference
+                .bytes()
+                .all(|base| matches!(base.to_ascii_uppercase(), b'A' | b'C' | b'G' | b'T' | b'N'))
+        {
+            self.report(
+                number,
+                &format!("REF {:?} is not a run of bases", reference),
+            );
+        }
+        let alleles = if alternates == "." {
+            0
+        } else {
+            let alleles: Vec<&str> = alternates.split(',').collect();
+            if alleles.iter().any(|allele| allele.is_empty()) {
+                self.report(number, &format!("ALT {:?} has an empty allele", alternates));
+            }
+            alleles.len()
+        };
+        if fields.len() > MANDATORY_COLUMNS.len() + 1 {
+            self.check_genotypes(number, &fields[MANDATORY_COLUMNS.len()..], alleles);
+        }
+    }
+
+    /// Records must come grouped by contig, in non-decreasing position order within one.
+    fn check_order(&mut self, number: usize, chromosome: &str, position: i64) {
+        match &mut self.current {
+            Some((current, last)) if current == chromosome => {
+                if position < *last {
+                    let message = format!(
+                        "position {} comes after position {} on contig {}",
+                        position, last, chromosome
+                    );
+                    self.report(number, &message);
+                } else {
+                    *last = position;
+                }
+                return;
+            }
+            Some((current, _)) => {
+                self.finished.insert(std::mem::take(current));
+            }
+            None => {}
+        }
+        if self.finished.contains(chromosome) {
+            self.report(
+                number,
+                &format!("records for contig {} are not contiguous", chromosome),
+            );
+        }
+        self.current = Some((chromosome.to_string(), position));
+    }
+
+    /// `columns` is `FORMAT` followed by the samples; `alternates` is the number of ALT
+    /// alleles, which bounds the allele indices a `GT` may use.
+    fn check_genotypes(&mut self, number: usize, columns: &[&str], alternates: usize) {
+        let keys: Vec<&str> = columns[0].split(':').collect();
+        let Some(gt_index) = keys.iter().position(|key| *key == "GT") else {
+            return;
+        };
+        if gt_index != 0 {
+            self.report(number, "GT must be the first FORMAT key");
+            return;
+        }
+        if !self.gt_declared && !self.gt_undeclared_reported {
+            self.gt_undeclared_reported = true;
+            self.report(number, "GT is used but not declared in a ##FORMAT line");
+        }
+        for (sample, value) in columns[1..].iter().enumerate() {
+            if value.split(':').count() > keys.len() {
+                self.report(
+                    number,
+                    &format!("sample {} has more values than FORMAT keys", sample + 1),
+                );
+            }
+            let gt = value.split(':').next().unwrap_or_default();
+            if !valid_gt(gt, alternates) {
+                self.report(
+                    number,
+                    &format!("sample {} has an invalid GT {:?}", sample + 1, gt),
+                );
+            }
+        }
+    }
+
+    fn report(&mut self, number: usize, message: &str) {
+        self.validation.report(number, message.to_string());
+    }
+}
+
+/// The `ID` of a structured meta line's `<...>` fields.
+fn structured_id(fields: &str) -> Option<&str> {
+    fields
+        .trim_end_matches('>')
+        .split(',')
+        .find_map(|field| field.strip_prefix("ID="))
+        .filter(|id| !id.is_empty())
+}
+
+/// Whether `gt` is `.` or allele indices up to `alternates`, joined by `/` or `|`.
+fn valid_gt(gt: &str, alternates: usize) -> bool {
+    !gt.is_empty()
+        && gt.split(['/', '|']).all(|allele| {
+            allele == "."
+                || allele.parse::<usize>().is_ok_and(|index| {
+                    index <= alternates && allele.bytes().all(|b| b.is_ascii_digit())
+                })
+        })
+}
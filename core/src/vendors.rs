@@ -0,0 +1,169 @@
+//! Recognizing which vendor exported a genotype file.
+//!
+//! Most vendors announce themselves in their header comments (`# This data file generated by
+//! 23andMe at: ...`); the rest are told apart by the shape of their column header, such as
+//! FTDNA's quoted `RSID,CHROMOSOME,POSITION,RESULT` or AncestryDNA's split `allele1`/`allele2`.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::liftover::GenomeBuild;
+
+/// Characters of a version string kept.
+const MAX_VERSION_LEN: usize = 32;
+
+/// A consumer genotyping company whose export format biosynth recognizes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Vendor {
+    #[serde(rename = "23andme")]
+    TwentyThreeAndMe,
+    #[serde(rename = "ancestrydna")]
+    AncestryDna,
+    #[serde(rename = "myheritage")]
+    MyHeritage,
+    #[serde(rename = "ftdna")]
+    FamilyTreeDna,
+    #[serde(rename = "livingdna")]
+    LivingDna,
+    #[serde(rename = "dynamic_dna")]
+    DynamicDna,
+}
+
+impl Vendor {
+    pub const ALL: [Vendor; 6] = [
+        Vendor::TwentyThreeAndMe,
+        Vendor::AncestryDna,
+        Vendor::MyHeritage,
+        Vendor::FamilyTreeDna,
+        Vendor::LivingDna,
+        Vendor::DynamicDna,
+    ];
+
+    /// The name reports and the `formats` table use; `dynamic_dna` matches the output format
+    /// of the same name.
+    pub fn name(self) -> &'static str {
+        match self {
+            Vendor::TwentyThreeAndMe => "23andme",
+            Vendor::AncestryDna => "ancestrydna",
+            Vendor::MyHeritage => "myheritage",
+            Vendor::FamilyTreeDna => "ftdna",
+            Vendor::LivingDna => "livingdna",
+            Vendor::DynamicDna => "dynamic_dna",
+        }
+    }
+
+    /// Lowercase phrases in a header comment that name this vendor.
+    fn signatures(self) -> &'static [&'static str] {
+        match self {
+            Vendor::TwentyThreeAndMe => &["23andme"],
+            Vendor::AncestryDna => &["ancestrydna"],
+            Vendor::MyHeritage => &["myheritage"],
+            Vendor::FamilyTreeDna => &["family tree dna", "familytreedna", "ftdna"],
+            Vendor::LivingDna => &["living dna", "livingdna"],
+            Vendor::DynamicDna => &["dynamic dna"],
+        }
+    }
+
+    /// Lowercase header keys (or their last words) whose value is the export's format or chip
+    /// version.
+    fn version_keys(self) -> &'static [&'static str] {
+        match self {
+            Vendor::AncestryDna => &["array version"],
+            Vendor::MyHeritage => &["format"],
+            Vendor::LivingDna => &["file version"],
+            _ => &[],
+        }
+    }
+}
+
+impl fmt::Display for Vendor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Vendor::TwentyThreeAndMe => "23andMe",
+            Vendor::AncestryDna => "AncestryDNA",
+            Vendor::MyHeritage => "MyHeritage",
+            Vendor::FamilyTreeDna => "FamilyTreeDNA",
+            Vendor::LivingDna => "Living DNA",
+            Vendor::DynamicDna => "Dynamic DNA",
+        })
+    }
+}
+
+/// What a file's header says about where it came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Fingerprint {
+    pub(crate) vendor: Option<Vendor>,
+    pub(crate) version: Option<String>,
+    pub(crate) genome_build: Option<GenomeBuild>,
+}
+
+/// Fingerprints a file from its leading comment lines (comment markers stripped) and its first
+/// other line, the column header or first row, as normalized column names.
+pub(crate) fn fingerprint(comments: &[&str], columns: &[String]) -> Fingerprint {
+    let lowered: Vec<String> = comments
+        .iter()
+        .map(|line| line.to_ascii_lowercase())
+        .collect();
+    let vendor = Vendor::ALL
+        .into_iter()
+        .find(|vendor| {
+            lowered.iter().any(|line| {
+                vendor
+                    .signatures()
+                    .iter()
+                    .any(|signature| line.contains(signature))
+            })
+        })
+        .or_else(|| vendor_by_columns(columns));
+    let version = vendor.and_then(|vendor| {
+        comments.iter().find_map(|line| {
+            let (key, value) = line.split_once([':', '='])?;
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+            let named = vendor.version_keys().iter().any(|known| {
+                key == *known
+                    || key
+                        .strip_suffix(known)
+                        .is_some_and(|rest| rest.ends_with(' '))
+            });
+            (named && !value.is_empty()).then(|| value.chars().take(MAX_VERSION_LEN).collect())
+        })
+    });
+    let mut builds = lowered.iter().filter_map(|line| named_build(line));
+    let genome_build = builds
+        .next()
+        .filter(|build| builds.all(|other| other == *build));
+    Fingerprint {
+        vendor,
+        version,
+        genome_build,
+    }
+}
+
+/// The build `text` (lowercase) names, such as `build 37` or `grch38`; `None` if it names none
+/// or both.
+pub(crate) fn named_build(text: &str) -> Option<GenomeBuild> {
+    let names = |aliases: &[&str]| aliases.iter().any(|alias| text.contains(alias));
+    match (
+        names(&["grch37", "hg19", "build 37", "build37"]),
+        names(&["grch38", "hg38", "build 38", "build38"]),
+    ) {
+        (true, false) => Some(GenomeBuild::Grch37),
+        (false, true) => Some(GenomeBuild::Grch38),
+        _ => None,
+    }
+}
+
+/// Vendors whose exports carry no identifying comments, told apart by their columns.
+fn vendor_by_columns(columns: &[String]) -> Option<Vendor> {
+    let names: Vec<&str> = columns.iter().map(String::as_str).collect();
+    match names[..] {
+        ["rsid", "chromosome", "position", "allele1", "allele2"] => Some(Vendor::AncestryDna),
+        ["rsid", "chromosome", "position", "result"] => Some(Vendor::FamilyTreeDna),
+        ["rsid", "chromosome", "position", "genotype", "gs", "baf", "lrr"] => {
+            Some(Vendor::DynamicDna)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,210 @@
+//! Excel workbooks as genotype input, as clinicians send extracts.
+//!
+//! An `.xlsx` file is a zip archive of XML parts. [`first_sheet_text`] reads the workbook's
+//! first worksheet and writes its cells out as tab-separated lines, which the genotype parser
+//! then reads like any other export: a header row naming the columns, then one row per call.
+
+use std::path::Path;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::archive::read_entry;
+use crate::error::{BiosynthError, Result};
+
+const WORKBOOK: &str = "xl/workbook.xml";
+const WORKBOOK_RELATIONSHIPS: &str = "xl/_rels/workbook.xml.rels";
+const SHARED_STRINGS: &str = "xl/sharedStrings.xml";
+/// Where spreadsheet tools put the first worksheet, for workbooks whose parts do not say.
+const DEFAULT_SHEET: &str = "xl/worksheets/sheet1.xml";
+
+/// Whether `path` is named like an Excel workbook.
+pub fn is_xlsx(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("xlsx"))
+}
+
+/// The first worksheet of `workbook` as text: one tab-separated line per row, with cells placed
+/// by their column so blank cells stay blank fields. Shared strings are resolved and booleans
+/// written as `TRUE`/`FALSE`; error cells are left blank.
+pub fn first_sheet_text(workbook: &Path) -> Result<Vec<u8>> {
+    let xml_error = |part: &str, err: quick_xml::Error| {
+        BiosynthError::Parse(format!("Read {} of {:?}: {}", part, workbook, err))
+    };
+    let sheet = first_sheet_part(workbook).map_err(|err| xml_error(WORKBOOK, err))?;
+    // Workbooks holding only numbers have no shared strings part.
+    let shared = match read_entry(workbook, SHARED_STRINGS) {
+        Ok(bytes) => shared_strings(&bytes).map_err(|err| xml_error(SHARED_STRINGS, err))?,
+        Err(_) => Vec::new(),
+    };
+    sheet_text(&read_entry(workbook, &sheet)?, &shared).map_err(|err| xml_error(&sheet, err))
+}
+
+/// The archive path of the first `<sheet>` the workbook lists, through its relationship ID.
+fn first_sheet_part(workbook: &Path) -> quick_xml::Result<String> {
+    let (Ok(listing), Ok(relationships)) = (
+        read_entry(workbook, WORKBOOK),
+        read_entry(workbook, WORKBOOK_RELATIONSHIPS),
+    ) else {
+        return Ok(DEFAULT_SHEET.to_string());
+    };
+    let Some(id) = first_attribute(&listing, b"sheet", b"id", |_| true)? else {
+        return Ok(DEFAULT_SHEET.to_string());
+    };
+    let target = first_attribute(&relationships, b"Relationship", b"Target", |element| {
+        attribute(element, b"Id").is_ok_and(|found| found.as_deref() == Some(id.as_str()))
+    })?;
+    Ok(match target {
+        Some(target) => match target.strip_prefix('/') {
+            Some(absolute) => absolute.to_string(),
+            None => format!("xl/{}", target),
+        },
+        None => DEFAULT_SHEET.to_string(),
+    })
+}
+
+/// Attribute `name` of the first `element` that `matches`.
+fn first_attribute<F>(
+    xml: &[u8],
+    element: &[u8],
+    name: &[u8],
+    matches: F,
+) -> quick_xml::Result<Option<String>>
+where
+    F: Fn(&BytesStart) -> bool,
+{
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(start) | Event::Empty(start)
+                if start.local_name().as_ref() == element && matches(&start) =>
+            {
+                return attribute(&start, name);
+            }
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// The value of attribute `name`, matched without its namespace prefix.
+fn attribute(element: &BytesStart, name: &[u8]) -> quick_xml::Result<Option<String>> {
+    for attribute in element.attributes() {
+        let attribute = attribute?;
+        if attribute.key.local_name().as_ref() == name {
+            return Ok(Some(attribute.unescape_value()?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+/// The shared string table: each `<si>`'s text runs joined, phonetic guides left out.
+fn shared_strings(xml: &[u8]) -> quick_xml::Result<Vec<String>> {
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = Vec::new();
+    let mut strings = Vec::new();
+    let (mut in_text, mut in_phonetic) = (false, false);
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(start) => match start.local_name().as_ref() {
+                b"si" => strings.push(String::new()),
+                b"t" => in_text = true,
+                b"rPh" => in_phonetic = true,
+                _ => {}
+            },
+            Event::Empty(empty) if empty.local_name().as_ref() == b"si" => {
+                strings.push(String::new())
+            }
+            Event::End(end) => match end.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"rPh" => in_phonetic = false,
+                _ => {}
+            },
+            Event::Text(text) if in_text && !in_phonetic => {
+                if let Some(string) = strings.last_mut() {
+                    string.push_str(&text.unescape()?);
+                }
+            }
+            Event::Eof => return Ok(strings),
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// A worksheet's rows as tab-separated lines.
+fn sheet_text(xml: &[u8], shared: &[String]) -> quick_xml::Result<Vec<u8>> {
+    let mut reader = Reader::from_reader(xml);
+    let mut buf = Vec::new();
+    let mut text = Vec::new();
+    let mut row: Vec<String> = Vec::new();
+    let mut column = 0;
+    let mut kind: Option<String> = None;
+    let mut value = String::new();
+    let mut in_value = false;
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(start) => match start.local_name().as_ref() {
+                b"row" => row.clear(),
+                b"c" => {
+                    column = match attribute(&start, b"r")? {
+                        Some(reference) => column_index(&reference).unwrap_or(row.len()),
+                        None => row.len(),
+                    };
+                    kind = attribute(&start, b"t")?;
+                    value.clear();
+                }
+                b"v" | b"t" => in_value = true,
+                _ => {}
+            },
+            Event::Text(content) if in_value => value.push_str(&content.unescape()?),
+            Event::End(end) => match end.local_name().as_ref() {
+                b"v" | b"t" => in_value = false,
+                b"c" => {
+                    let cell = match kind.as_deref() {
+                        Some("s") => value
+                            .trim()
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(|idx| shared.get(idx))
+                            .map_or("", String::as_str),
+                        Some("b") if value.trim() == "1" => "TRUE",
+                        Some("b") => "FALSE",
+                        Some("e") => "",
+                        _ => value.as_str(),
+                    };
+                    if row.len() <= column {
+                        row.resize(column + 1, String::new());
+                    }
+                    row[column] = cell.replace(['\t', '\r', '\n'], " ");
+                }
+                b"row" => {
+                    text.extend_from_slice(row.join("\t").as_bytes());
+                    text.push(b'\n');
+                }
+                _ => {}
+            },
+            Event::Eof => return Ok(text),
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+/// The 0-based column of a cell reference such as `B7` or `AA12`.
+fn column_index(reference: &str) -> Option<usize> {
+    let letters = reference
+        .bytes()
+        .take_while(|byte| byte.is_ascii_alphabetic());
+    let mut index = 0usize;
+    let mut any = false;
+    for letter in letters {
+        any = true;
+        index = index
+            .checked_mul(26)?
+            .checked_add(usize::from(letter.to_ascii_uppercase() - b'A') + 1)?;
+    }
+    any.then(|| index - 1)
+}
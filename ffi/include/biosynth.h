@@ -0,0 +1,80 @@
+#ifndef BIOSYNTH_H
+#define BIOSYNTH_H
+
+/* Generated by cbindgen from ffi/src/lib.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum BiosynthStatus {
+  BIOSYNTH_STATUS_OK = 0,
+  BIOSYNTH_STATUS_ERROR = 1,
+  BIOSYNTH_STATUS_INVALID_ARGUMENT = 2,
+  // The record callback asked to stop early.
+  BIOSYNTH_STATUS_STOPPED = 3,
+} BiosynthStatus;
+
+// One parsed row. The strings are only valid for the duration of the callback.
+typedef struct BiosynthRecord {
+  const char *rsid;
+  const char *chromosome;
+  int64_t position;
+  const char *genotype;
+} BiosynthRecord;
+
+// Called once per parsed row; return non-zero to stop parsing.
+typedef int32_t (*BiosynthRecordCallback)(const struct BiosynthRecord *record, void *user_data);
+
+typedef struct BiosynthParseSummary {
+  size_t variant_count;
+  size_t skipped_rows;
+} BiosynthParseSummary;
+
+// Options for [`biosynth_generate_file`]. Zero-initialise and set what you need.
+typedef struct BiosynthGenerateOptions {
+  // Probability of emitting an ALT allele (0 to 1).
+  double alt_frequency;
+  // Seed for reproducible output; only used when `use_seed` is true.
+  uint64_t seed;
+  bool use_seed;
+  // Maximum reference rows to emit; 0 means all.
+  size_t limit;
+} BiosynthGenerateOptions;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Message for the last failed call on this thread, or NULL. Valid until the next call.
+const char *biosynth_last_error(void);
+
+// Parses `len` bytes of genotype file contents, invoking `callback` for every row.
+// `summary_out` may be NULL.
+//
+// # Safety
+// `data` must point to `len` readable bytes and `summary_out` must be NULL or writable.
+enum BiosynthStatus biosynth_parse_buffer(const uint8_t *data,
+                                          size_t len,
+                                          BiosynthRecordCallback callback,
+                                          void *user_data,
+                                          struct BiosynthParseSummary *summary_out);
+
+// Generates one synthetic genotype file from the references in a genostats database.
+// `rows_out` may be NULL.
+//
+// # Safety
+// `sqlite_path` and `output_path` must be NUL-terminated UTF-8 strings, `options` must be
+// NULL or readable, and `rows_out` must be NULL or writable.
+enum BiosynthStatus biosynth_generate_file(const char *sqlite_path,
+                                           const char *output_path,
+                                           const struct BiosynthGenerateOptions *options,
+                                           size_t *rows_out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* BIOSYNTH_H */
@@ -0,0 +1,207 @@
+//! C ABI over biosynth-core. The header is generated into `include/biosynth.h` on build.
+//!
+//! Every function returns a [`BiosynthStatus`]; on failure, [`biosynth_last_error`] describes
+//! what went wrong on the calling thread.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::ptr;
+
+use anyhow::{anyhow, bail, Result};
+use biosynth_core::stats::StatsStore;
+use biosynth_core::synthetic::write_single_file;
+use biosynth_core::GenotypeReader;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BiosynthStatus {
+    Ok = 0,
+    Error = 1,
+    InvalidArgument = 2,
+    /// The record callback asked to stop early.
+    Stopped = 3,
+}
+
+/// One parsed row. The strings are only valid for the duration of the callback.
+#[repr(C)]
+pub struct BiosynthRecord {
+    pub rsid: *const c_char,
+    pub chromosome: *const c_char,
+    pub position: i64,
+    pub genotype: *const c_char,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BiosynthParseSummary {
+    pub variant_count: usize,
+    pub skipped_rows: usize,
+}
+
+/// Called once per parsed row; return non-zero to stop parsing.
+pub type BiosynthRecordCallback =
+    Option<extern "C" fn(record: *const BiosynthRecord, user_data: *mut c_void) -> i32>;
+
+/// Options for [`biosynth_generate_file`]. Zero-initialise and set what you need.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BiosynthGenerateOptions {
+    /// Probability of emitting an ALT allele (0 to 1).
+    pub alt_frequency: f64,
+    /// Seed for reproducible output; only used when `use_seed` is true.
+    pub seed: u64,
+    pub use_seed: bool,
+    /// Maximum reference rows to emit; 0 means all.
+    pub limit: usize,
+}
+
+/// Message for the last failed call on this thread, or NULL. Valid until the next call.
+#[no_mangle]
+pub extern "C" fn biosynth_last_error() -> *const c_char {
+    LAST_ERROR.with(|slot| {
+        slot.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Parses `len` bytes of genotype file contents, invoking `callback` for every row.
+/// `summary_out` may be NULL.
+///
+/// # Safety
+/// `data` must point to `len` readable bytes and `summary_out` must be NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn biosynth_parse_buffer(
+    data: *const u8,
+    len: usize,
+    callback: BiosynthRecordCallback,
+    user_data: *mut c_void,
+    summary_out: *mut BiosynthParseSummary,
+) -> BiosynthStatus {
+    if data.is_null() && len > 0 {
+        return fail(BiosynthStatus::InvalidArgument, anyhow!("data is NULL"));
+    }
+    let bytes = if len == 0 {
+        &[][..]
+    } else {
+        std::slice::from_raw_parts(data, len)
+    };
+
+    guard(|| {
+        let mut reader = GenotypeReader::from_reader(bytes)?;
+        let mut status = BiosynthStatus::Ok;
+        for record in reader.by_ref() {
+            let record = record?;
+            let Some(callback) = callback else {
+                continue;
+            };
+            let rsid = CString::new(record.rsid.to_string())?;
+            let chromosome = CString::new(&*record.chromosome)?;
+            let genotype = CString::new(record.genotype)?;
+            let row = BiosynthRecord {
+                rsid: rsid.as_ptr(),
+                chromosome: chromosome.as_ptr(),
+                position: record.position,
+                genotype: genotype.as_ptr(),
+            };
+            if callback(&row, user_data) != 0 {
+                status = BiosynthStatus::Stopped;
+                break;
+            }
+        }
+        if !summary_out.is_null() {
+            let summary = reader.summary();
+            *summary_out = BiosynthParseSummary {
+                variant_count: summary.variant_count,
+                skipped_rows: summary.skipped_rows,
+            };
+        }
+        Ok(status)
+    })
+}
+
+/// Generates one synthetic genotype file from the references in a genostats database.
+/// `rows_out` may be NULL.
+///
+/// # Safety
+/// `sqlite_path` and `output_path` must be NUL-terminated UTF-8 strings, `options` must be
+/// NULL or readable, and `rows_out` must be NULL or writable.
+#[no_mangle]
+pub unsafe extern "C" fn biosynth_generate_file(
+    sqlite_path: *const c_char,
+    output_path: *const c_char,
+    options: *const BiosynthGenerateOptions,
+    rows_out: *mut usize,
+) -> BiosynthStatus {
+    let (sqlite_path, output_path) = match (path_arg(sqlite_path), path_arg(output_path)) {
+        (Ok(sqlite), Ok(output)) => (sqlite, output),
+        (Err(err), _) | (_, Err(err)) => return fail(BiosynthStatus::InvalidArgument, err),
+    };
+    let options = if options.is_null() {
+        BiosynthGenerateOptions {
+            alt_frequency: 0.01,
+            ..Default::default()
+        }
+    } else {
+        *options
+    };
+
+    guard(|| {
+        if !(0.0..=1.0).contains(&options.alt_frequency) {
+            bail!("alt_frequency must be between 0 and 1");
+        }
+        let limit = (options.limit > 0).then_some(options.limit);
+        let references = StatsStore::connect(&sqlite_path)?.all_references(limit)?;
+        if references.is_empty() {
+            bail!("No reference rows found in {}", sqlite_path.display());
+        }
+        let seed = options.use_seed.then_some(options.seed);
+        let rows = write_single_file(
+            &output_path,
+            &references,
+            &[],
+            options.alt_frequency,
+            seed,
+            None,
+        )?;
+        if !rows_out.is_null() {
+            *rows_out = rows;
+        }
+        Ok(BiosynthStatus::Ok)
+    })
+}
+
+unsafe fn path_arg(value: *const c_char) -> Result<PathBuf> {
+    if value.is_null() {
+        bail!("path is NULL");
+    }
+    Ok(PathBuf::from(CStr::from_ptr(value).to_str()?))
+}
+
+fn guard(body: impl FnOnce() -> Result<BiosynthStatus>) -> BiosynthStatus {
+    match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(status)) => {
+            set_last_error(None);
+            status
+        }
+        Ok(Err(err)) => fail(BiosynthStatus::Error, err),
+        Err(_) => fail(BiosynthStatus::Error, anyhow!("panic inside biosynth")),
+    }
+}
+
+fn fail(status: BiosynthStatus, err: anyhow::Error) -> BiosynthStatus {
+    set_last_error(Some(format!("{:#}", err)));
+    status
+}
+
+fn set_last_error(message: Option<String>) {
+    let message =
+        message.map(|text| CString::new(text.replace('\0', " ")).expect("NUL bytes were replaced"));
+    LAST_ERROR.with(|slot| *slot.borrow_mut() = message);
+}
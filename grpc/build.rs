@@ -0,0 +1,9 @@
+fn main() {
+    // protox compiles the schema in pure Rust, so building does not need protoc installed.
+    let descriptors =
+        protox::compile(["biosynth.proto"], ["proto"]).expect("compile proto/biosynth.proto");
+    tonic_build::configure()
+        .compile_fds(descriptors)
+        .expect("generate gRPC bindings");
+    println!("cargo:rerun-if-changed=proto/biosynth.proto");
+}
@@ -0,0 +1,230 @@
+//! gRPC service over biosynth-core: synthetic generation and reference stats, so the generator
+//! can run as a sidecar next to the services under test. The schema is in `proto/biosynth.proto`.
+
+// `tonic::Status` is large, and every handler returns it by value.
+#![allow(clippy::result_large_err)]
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use biosynth_core::formats::FormatRegistry;
+use biosynth_core::stats::{CategoryCount, StatsStore};
+use biosynth_core::synthetic::{parse_overlay_specs, Sex, SyntheticGenerator};
+use biosynth_core::BiosynthError;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+pub mod proto {
+    tonic::include_proto!("biosynth.v1");
+}
+
+use proto::biosynth_server::Biosynth;
+pub use proto::biosynth_server::BiosynthServer;
+use proto::generate_synthetic_response::Payload;
+
+/// Bytes buffered before a data chunk is sent to the client.
+const CHUNK_SIZE: usize = 64 * 1024;
+/// Chunks queued per stream before generation waits on the client.
+const STREAM_DEPTH: usize = 16;
+
+/// Serves every RPC from the genostats database at `sqlite_path`.
+#[derive(Debug, Clone)]
+pub struct BiosynthService {
+    store: Arc<StatsStore>,
+    formats: Arc<FormatRegistry>,
+}
+
+impl BiosynthService {
+    pub fn new(sqlite_path: PathBuf) -> anyhow::Result<Self> {
+        Ok(Self {
+            store: Arc::new(StatsStore::connect(&sqlite_path)?),
+            formats: Arc::new(FormatRegistry::new()),
+        })
+    }
+
+    /// Replaces the formats clients may request by name.
+    pub fn with_formats(mut self, formats: FormatRegistry) -> Self {
+        self.formats = Arc::new(formats);
+        self
+    }
+
+    fn generator(
+        &self,
+        request: &proto::GenerateSyntheticRequest,
+    ) -> Result<SyntheticGenerator, Status> {
+        let format_name = if request.format.is_empty() {
+            "dynamic_dna"
+        } else {
+            &request.format
+        };
+        let format = self
+            .formats
+            .get(format_name)
+            .ok_or_else(|| Status::invalid_argument(format!("Unknown format {:?}", format_name)))?;
+        let limit = (request.limit > 0).then_some(request.limit as usize);
+        let references = self.store.all_references(limit).map_err(to_status)?;
+        let overlays = if request.overlays_json.is_empty() {
+            Vec::new()
+        } else {
+            parse_overlay_specs(&request.overlays_json).map_err(to_status)?
+        };
+        let alt_frequency = if request.alt_frequency == 0.0 {
+            0.01
+        } else {
+            request.alt_frequency
+        };
+
+        let mut builder = SyntheticGenerator::builder()
+            .references(references)
+            .overlays(overlays)
+            .format(format)
+            .alt_frequency(alt_frequency);
+        if let Some(seed) = request.seed {
+            builder = builder.seed(seed);
+        }
+        match request.sex() {
+            proto::Sex::Unspecified => {}
+            proto::Sex::Male => builder = builder.sex(Sex::Male),
+            proto::Sex::Female => builder = builder.sex(Sex::Female),
+        }
+        builder.build().map_err(to_status)
+    }
+}
+
+type ResponseStream<T> = ReceiverStream<Result<T, Status>>;
+
+#[tonic::async_trait]
+impl Biosynth for BiosynthService {
+    type GenerateSyntheticStream = ResponseStream<proto::GenerateSyntheticResponse>;
+    type ListReferencesStream = ResponseStream<proto::ReferenceVariant>;
+
+    async fn generate_synthetic(
+        &self,
+        request: Request<proto::GenerateSyntheticRequest>,
+    ) -> Result<Response<Self::GenerateSyntheticStream>, Status> {
+        let service = self.clone();
+        let request = request.into_inner();
+        let generator = tokio::task::spawn_blocking(move || service.generator(&request))
+            .await
+            .map_err(|err| Status::internal(err.to_string()))??;
+
+        let (tx, rx) = mpsc::channel(STREAM_DEPTH);
+        tokio::task::spawn_blocking(move || {
+            let mut sink = ChunkSender {
+                tx: tx.clone(),
+                buffer: Vec::with_capacity(CHUNK_SIZE),
+            };
+            let message = match generator.write_to(&mut sink) {
+                Ok(rows) => Ok(proto::GenerateSyntheticResponse {
+                    payload: Some(Payload::Summary(proto::GenerateSyntheticSummary {
+                        rows: rows as u64,
+                    })),
+                }),
+                Err(err) => Err(to_status(err)),
+            };
+            // A send error means the client hung up; there is nobody left to tell.
+            let _ = tx.blocking_send(message);
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn get_summary(
+        &self,
+        _request: Request<proto::GetSummaryRequest>,
+    ) -> Result<Response<proto::GetSummaryResponse>, Status> {
+        let store = self.store.clone();
+        let report = tokio::task::spawn_blocking(move || store.summary())
+            .await
+            .map_err(|err| Status::internal(err.to_string()))?
+            .map_err(to_status)?;
+        Ok(Response::new(proto::GetSummaryResponse {
+            total_variants: report.total_variants,
+            unique_rsids: report.unique_rsids,
+            formats_seen: category_counts(report.formats_seen),
+            builds_seen: category_counts(report.builds_seen),
+        }))
+    }
+
+    async fn list_references(
+        &self,
+        request: Request<proto::ListReferencesRequest>,
+    ) -> Result<Response<Self::ListReferencesStream>, Status> {
+        let limit = request.into_inner().limit;
+        let limit = (limit > 0).then_some(limit as usize);
+        let store = self.store.clone();
+        let references = tokio::task::spawn_blocking(move || store.all_references(limit))
+            .await
+            .map_err(|err| Status::internal(err.to_string()))?
+            .map_err(to_status)?;
+
+        let (tx, rx) = mpsc::channel(STREAM_DEPTH);
+        tokio::spawn(async move {
+            for reference in references {
+                let message = proto::ReferenceVariant {
+                    rsid: reference.rsid,
+                    chromosome: reference.chromosome,
+                    position: reference.position,
+                    reference: reference.reference,
+                    alternates: reference.alternates,
+                };
+                if tx.send(Ok(message)).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+/// Forwards generated bytes to the response stream in [`CHUNK_SIZE`] pieces.
+struct ChunkSender {
+    tx: mpsc::Sender<Result<proto::GenerateSyntheticResponse, Status>>,
+    buffer: Vec<u8>,
+}
+
+impl Write for ChunkSender {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(data);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let data = std::mem::replace(&mut self.buffer, Vec::with_capacity(CHUNK_SIZE));
+        let message = proto::GenerateSyntheticResponse {
+            payload: Some(Payload::Data(data)),
+        };
+        self.tx
+            .blocking_send(Ok(message))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "client disconnected"))
+    }
+}
+
+fn category_counts(counts: Vec<CategoryCount>) -> Vec<proto::CategoryCount> {
+    counts
+        .into_iter()
+        .map(|entry| proto::CategoryCount {
+            value: entry.value,
+            count: entry.count,
+        })
+        .collect()
+}
+
+fn to_status(err: BiosynthError) -> Status {
+    let code = match &err {
+        BiosynthError::Parse(_)
+        | BiosynthError::InvalidArgument(_)
+        | BiosynthError::Json { .. } => tonic::Code::InvalidArgument,
+        BiosynthError::ReferenceMissing { .. } => tonic::Code::FailedPrecondition,
+        BiosynthError::ChecksumMismatch { .. } => tonic::Code::DataLoss,
+        _ => tonic::Code::Internal,
+    };
+    Status::new(code, format!("{:#}", anyhow::Error::from(err)))
+}
@@ -0,0 +1,45 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use biosynth_grpc::{BiosynthServer, BiosynthService};
+use clap::Parser;
+
+/// gRPC sidecar serving synthetic genotype generation and reference stats.
+#[derive(Parser)]
+#[command(name = "biosynth-grpc", version)]
+struct Args {
+    /// Address to listen on.
+    #[arg(long, env = "BVS_GRPC_LISTEN", default_value = "0.0.0.0:50051")]
+    listen: SocketAddr,
+    /// Path to the genostats SQLite database.
+    #[arg(long, env = "BVS_SQLITE", default_value = "data/genostats.sqlite")]
+    sqlite: PathBuf,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    if !args.sqlite.exists() {
+        anyhow::bail!(
+            "Reference database not found at {:?}; run `bvs fetch-reference --dest {}` first",
+            args.sqlite,
+            args.sqlite.display()
+        );
+    }
+    let service = BiosynthService::new(args.sqlite.clone())?;
+
+    println!(
+        "🛰️  Serving biosynth gRPC on {} ({})",
+        args.listen,
+        args.sqlite.display()
+    );
+    tonic::transport::Server::builder()
+        .add_service(BiosynthServer::new(service))
+        .serve_with_shutdown(args.listen, async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+        .with_context(|| format!("Serve gRPC on {}", args.listen))?;
+    Ok(())
+}
@@ -0,0 +1,165 @@
+//! Python bindings: `import biosynth`.
+
+// `#[pyfunction]` expands to an `Into<PyErr>` call that clippy flags on every function.
+#![allow(clippy::useless_conversion)]
+
+use std::path::PathBuf;
+
+use anyhow::bail;
+use biosynth_core::stats::{CategoryCount, StatsStore};
+use biosynth_core::synthetic::{parse_overlay_specs, write_single_file, Sex};
+use biosynth_core::GenotypeReader;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+/// Parses a genotype file into a pandas DataFrame with columns
+/// `rsid`, `chromosome`, `position`, `genotype`. Skipped rows are counted in
+/// `df.attrs["skipped_rows"]`.
+#[pyfunction]
+fn read_genotypes(py: Python<'_>, path: PathBuf) -> PyResult<PyObject> {
+    let (columns, skipped) = py.allow_threads(|| -> anyhow::Result<_> {
+        let mut reader = GenotypeReader::open(&path)?;
+        let mut columns = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for record in reader.by_ref() {
+            let record = record?;
+            columns.0.push(record.rsid.to_string());
+            columns.1.push(record.chromosome.to_string());
+            columns.2.push(record.position);
+            columns.3.push(record.genotype);
+        }
+        Ok((columns, reader.summary().skipped_rows))
+    })?;
+
+    let data = PyDict::new_bound(py);
+    data.set_item("rsid", columns.0)?;
+    data.set_item("chromosome", columns.1)?;
+    data.set_item("position", columns.2)?;
+    data.set_item("genotype", columns.3)?;
+    let frame = dataframe(py, &data)?;
+    frame.getattr("attrs")?.set_item("skipped_rows", skipped)?;
+    Ok(frame.unbind())
+}
+
+/// Loads reference variants from a genostats database as a pandas DataFrame.
+#[pyfunction]
+#[pyo3(signature = (sqlite, limit=None))]
+fn references(py: Python<'_>, sqlite: PathBuf, limit: Option<usize>) -> PyResult<PyObject> {
+    let references = py.allow_threads(|| -> anyhow::Result<_> {
+        Ok(StatsStore::connect(&sqlite)?.all_references(limit)?)
+    })?;
+
+    let data = PyDict::new_bound(py);
+    data.set_item(
+        "rsid",
+        references.iter().map(|r| r.rsid).collect::<Vec<_>>(),
+    )?;
+    data.set_item(
+        "chromosome",
+        references.iter().map(|r| &r.chromosome).collect::<Vec<_>>(),
+    )?;
+    data.set_item(
+        "position",
+        references.iter().map(|r| r.position).collect::<Vec<_>>(),
+    )?;
+    data.set_item(
+        "reference",
+        references.iter().map(|r| &r.reference).collect::<Vec<_>>(),
+    )?;
+    data.set_item(
+        "alternates",
+        references.iter().map(|r| &r.alternates).collect::<Vec<_>>(),
+    )?;
+    Ok(dataframe(py, &data)?.unbind())
+}
+
+/// Summary statistics for a genostats database, as a dict.
+#[pyfunction]
+fn summary(py: Python<'_>, sqlite: PathBuf) -> PyResult<PyObject> {
+    let report =
+        py.allow_threads(|| -> anyhow::Result<_> { Ok(StatsStore::connect(&sqlite)?.summary()?) })?;
+
+    let result = PyDict::new_bound(py);
+    result.set_item("total_variants", report.total_variants)?;
+    result.set_item("unique_rsids", report.unique_rsids)?;
+    result.set_item("formats_seen", category_counts(py, &report.formats_seen)?)?;
+    result.set_item("builds_seen", category_counts(py, &report.builds_seen)?)?;
+    result.set_item("sqlite_path", report.sqlite_path)?;
+    Ok(result.into_any().unbind())
+}
+
+/// Writes one synthetic genotype file and returns the number of rows written.
+/// `sex` is `"male"` or `"female"`; `overlays` is an overlay JSON document.
+#[pyfunction]
+#[pyo3(signature = (sqlite, output, alt_frequency=0.01, seed=None, limit=None, sex=None, overlays=None))]
+#[allow(clippy::too_many_arguments)]
+fn generate(
+    py: Python<'_>,
+    sqlite: PathBuf,
+    output: PathBuf,
+    alt_frequency: f64,
+    seed: Option<u64>,
+    limit: Option<usize>,
+    sex: Option<&str>,
+    overlays: Option<&str>,
+) -> PyResult<usize> {
+    let sex = match sex {
+        None => None,
+        Some("male") => Some(Sex::Male),
+        Some("female") => Some(Sex::Female),
+        Some(other) => {
+            return Err(pyo3::exceptions::PyValueError::new_err(format!(
+                "sex must be 'male' or 'female', got {:?}",
+                other
+            )))
+        }
+    };
+    let rows = py.allow_threads(|| -> anyhow::Result<usize> {
+        if !(0.0..=1.0).contains(&alt_frequency) {
+            bail!("alt_frequency must be between 0 and 1");
+        }
+        let overlays = match overlays {
+            Some(raw) => parse_overlay_specs(raw)?,
+            None => Vec::new(),
+        };
+        let references = StatsStore::connect(&sqlite)?.all_references(limit)?;
+        if references.is_empty() {
+            bail!("No reference rows found in {}", sqlite.display());
+        }
+        Ok(write_single_file(
+            &output,
+            &references,
+            &overlays,
+            alt_frequency,
+            seed,
+            sex,
+        )?)
+    })?;
+    Ok(rows)
+}
+
+fn dataframe<'py>(py: Python<'py>, data: &Bound<'py, PyDict>) -> PyResult<Bound<'py, PyAny>> {
+    py.import_bound("pandas")?
+        .getattr("DataFrame")?
+        .call1((data,))
+}
+
+fn category_counts<'py>(py: Python<'py>, counts: &[CategoryCount]) -> PyResult<Bound<'py, PyList>> {
+    let list = PyList::empty_bound(py);
+    for entry in counts {
+        let item = PyDict::new_bound(py);
+        item.set_item("value", &entry.value)?;
+        item.set_item("count", entry.count)?;
+        list.append(item)?;
+    }
+    Ok(list)
+}
+
+#[pymodule]
+fn biosynth(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add_function(wrap_pyfunction!(read_genotypes, m)?)?;
+    m.add_function(wrap_pyfunction!(references, m)?)?;
+    m.add_function(wrap_pyfunction!(summary, m)?)?;
+    m.add_function(wrap_pyfunction!(generate, m)?)?;
+    Ok(())
+}
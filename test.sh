@@ -9,17 +9,17 @@ set -euo pipefail
 
 MODE=${1:---fast}
 
-cd cli
+cd "$(dirname "${BASH_SOURCE[0]}")"
 
 echo "==> cargo fmt"
-cargo fmt
+cargo fmt --all
 
 echo "==> cargo clippy"
-cargo clippy --all-targets --all-features -q || true
+cargo clippy --workspace --all-targets --all-features -q || true
 
 run_fast() {
   echo "==> Running fast tests"
-  cargo test
+  cargo test --workspace
 }
 
 case "$MODE" in